@@ -16,7 +16,7 @@ async fn main() {
 async fn create_order(ex: &mut BinanceUsdm, order_market: &Market) {
     let params = CreateOrderParamsBuilder::default()
         .market(order_market.clone())
-        .amount(0.6)
+        .amount("0.6".parse().expect("failed to parse amount"))
         .margin_mode(Some(MarginMode::Cross))
         .order_type(Some(OrderType::TrailingStopMarket))
         .callback_rate(Some(3f64))
@@ -1,28 +1,110 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::io;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use futures_util::{SinkExt, Stream, StreamExt};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use reqwest::header::RETRY_AFTER;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio::time::{Instant, Interval, MissedTickBehavior, Sleep};
 use tokio_tungstenite::{connect_async, MaybeTlsStream, tungstenite, WebSocketStream};
 use tokio_tungstenite::tungstenite::Message;
 
 use crate::{WatchError, WatchResult};
 use crate::error::{Error, Result};
 use crate::exchange::{StreamItem, Unifier};
+use crate::util::channel::Sender;
+use crate::util::rate_limiter::{RateLimit, RateLimiter};
 
 pub(crate) const EMPTY_QUERY: Option<&'static ()> = None;
 pub(crate) const EMPTY_BODY: Option<&String> = None;
 
 
-pub(crate) struct WsClient {
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const RECONNECT_MIN_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// pause after a 429 or 418 response without a `Retry-After` header
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// time to wait for the response to a JSON-RPC request
+const RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// id of the next subscription request, unique across connections
+static NEXT_REQUEST_ID: AtomicI64 = AtomicI64::new(1);
+
+pub(crate) fn next_request_id() -> i64 {
+    NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Message changing the topics of a connection, given its request id.
+pub(crate) type TopicsMessage = fn(i64, &[String]) -> String;
+
+
+/// Keep-alive settings of a `WsClient`.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct Heartbeat {
+    /// sends a ping at this interval, for exchanges that close connections without client messages
+    pub(crate) ping_interval: Option<Duration>,
+    /// text message sent as the ping. a ping frame is sent when not set
+    pub(crate) ping_message: Option<String>,
+    /// text message answering `ping_message`, which is not passed to the parser
+    pub(crate) pong_message: Option<String>,
+    /// the connection is re-established when nothing, control frames included, is received for
+    /// this long
+    pub(crate) idle_timeout: Option<Duration>,
+}
+
+
+/// Items yielded by a `WsClient` besides the parsed messages.
+pub(crate) trait WsItem {
+    /// emitted once the connection is re-established and the subscriptions are replayed
+    fn reconnected() -> Self;
+}
+
+impl WsItem for StreamItem {
+    fn reconnected() -> Self {
+        StreamItem::Reconnected
+    }
+}
+
+/// for parsers that turn a single message into several items
+impl WsItem for Vec<StreamItem> {
+    fn reconnected() -> Self {
+        vec![StreamItem::Reconnected]
+    }
+}
+
+
+pub(crate) struct WsClient<T = StreamItem> {
     endpoint: String,
-    parser: fn(&[u8], &Unifier) -> WatchResult<StreamItem>,
+    parser: fn(&[u8], &Unifier) -> WatchResult<T>,
     unifier: Unifier,
 
-    stream: Option<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+    stream: Option<WsStream>,
+
+    /// messages replayed in order after a reconnection
+    subscriptions: Vec<String>,
+    /// topics replayed after the messages, in a message rendered with a new request id
+    topics: Option<(TopicsMessage, Vec<String>)>,
+    reconnection: Option<Pin<Box<dyn Future<Output=Result<WsStream>> + Send>>>,
+    reconnect_attempts: u32,
+
+    heartbeat: Heartbeat,
+    /// timers of the current connection
+    ping: Option<Interval>,
+    idle: Option<Pin<Box<Sleep>>>,
+    ping_due: bool,
+    flushing: bool,
 }
 
 impl From<io::Error> for Error {
@@ -32,42 +114,188 @@ impl From<io::Error> for Error {
 }
 
 
-impl Stream for WsClient {
-    type Item = WatchResult<StreamItem>;
+impl<T: WsItem> Stream for WsClient<T> {
+    type Item = WatchResult<T>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match self.stream.as_mut().unwrap().poll_next_unpin(cx) {
-            Poll::Ready(Some(Ok(x))) => {
-                let resp = (self.parser)(x.into_data().as_slice(), &self.unifier);
-                Poll::Ready(Some(resp))
+        loop {
+            if let Some(reconnection) = self.reconnection.as_mut() {
+                match reconnection.as_mut().poll(cx) {
+                    Poll::Ready(Ok(stream)) => {
+                        self.stream = Some(stream);
+                        self.reconnection = None;
+                        self.reconnect_attempts = 0;
+                        self.start_heartbeat();
+                        return Poll::Ready(Some(Ok(T::reconnected())));
+                    }
+                    Poll::Ready(Err(_)) => {
+                        self.reconnect();
+                        continue;
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            if self.stream.is_none() {
+                return Poll::Ready(None); // never connected
+            }
+            if self.idle.as_mut().is_some_and(|idle| idle.as_mut().poll(cx).is_ready()) {
+                self.stream = None;
+                self.reconnect();
+                return Poll::Ready(Some(Err(WatchError::IdleTimeout(self.heartbeat.idle_timeout.unwrap_or_default()))));
+            }
+            if self.poll_ping(cx).is_err() {
+                self.stream = None;
+                self.reconnect();
+                continue;
+            }
+
+            match self.stream.as_mut().unwrap().poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(x))) => {
+                    let idle_timeout = self.heartbeat.idle_timeout;
+                    if let (Some(idle), Some(timeout)) = (self.idle.as_mut(), idle_timeout) {
+                        idle.as_mut().reset(Instant::now() + timeout);
+                    }
+                    match x {
+                        Message::Text(text) if self.heartbeat.pong_message.as_ref() == Some(&text) => continue,
+                        Message::Text(_) | Message::Binary(_) => {
+                            let resp = (self.parser)(x.into_data().as_slice(), &self.unifier);
+                            return Poll::Ready(Some(resp));
+                        }
+                        // pings are answered by tungstenite, and a close ends the stream next
+                        Message::Ping(_) | Message::Pong(_) | Message::Close(_) | Message::Frame(_) => continue,
+                    }
+                }
+                // the connection is unusable after an error, so both cases end up reconnecting
+                Poll::Ready(Some(Err(_))) | Poll::Ready(None) => {
+                    self.stream = None;
+                    self.reconnect();
+                }
+                Poll::Pending => return Poll::Pending,
             }
-            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(WatchError::WebsocketError(format!("{}", e))))),
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
         }
     }
 }
 
-impl WsClient {
-    pub fn new(endpoint: &str, parser: fn(&[u8], &Unifier) -> WatchResult<StreamItem>, unifier: Unifier) -> Self {
+impl<T: WsItem> WsClient<T> {
+    pub fn new(endpoint: &str, parser: fn(&[u8], &Unifier) -> WatchResult<T>, unifier: Unifier) -> Self {
         Self {
             endpoint: endpoint.to_string(),
             parser,
             unifier,
             stream: None,
+            subscriptions: vec![],
+            topics: None,
+            reconnection: None,
+            reconnect_attempts: 0,
+            heartbeat: Heartbeat::default(),
+            ping: None,
+            idle: None,
+            ping_due: false,
+            flushing: false,
         }
     }
 
+    pub(crate) fn heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Sends a subscription message, connecting first if needed. The message is remembered and
+    /// sent again whenever the connection is re-established.
     pub(crate) async fn send(&mut self, msg: String) -> Result<impl Stream + '_> {
+        self.connect().await?;
+        self.stream.as_mut().unwrap().send(Message::Text(msg.clone())).await?;
+        self.subscriptions.push(msg);
+        Ok(self)
+    }
+
+    /// Sends a message on the current connection, failing while it is being re-established. The
+    /// message is only replayed after a reconnection when `replay` is set.
+    pub(crate) async fn send_message(&mut self, msg: String, replay: bool) -> Result<()> {
+        let stream = self.stream.as_mut().ok_or_else(|| Error::WebsocketError("not connected".into()))?;
+        stream.send(Message::Text(msg.clone())).await?;
+        if replay {
+            self.subscriptions.push(msg);
+        }
+        Ok(())
+    }
+
+    /// Replaces the topics replayed after a reconnection, with the message subscribing to them
+    /// given a request id.
+    pub(crate) fn set_topics(&mut self, message: TopicsMessage, topics: Vec<String>) {
+        self.topics = Some((message, topics)).filter(|(_, topics)| !topics.is_empty());
+    }
+
+    /// Connects without sending anything, for endpoints that do not need a subscription.
+    pub(crate) async fn connect(&mut self) -> Result<()> {
         if self.stream.is_none() {
-            let (stream, response) = connect_async(self.endpoint.as_str()).await.expect("Failed to connect");
-            if response.status() != tungstenite::http::StatusCode::SWITCHING_PROTOCOLS {
-                return Err(Error::WebsocketError(format!("Invalid status code: {}", response.status())));
+            self.stream = Some(connect(self.endpoint.as_str()).await?);
+            self.start_heartbeat();
+        }
+        Ok(())
+    }
+
+    /// Starts the timers of the heartbeat for a new connection.
+    fn start_heartbeat(&mut self) {
+        self.ping = self.heartbeat.ping_interval.map(|period| {
+            let mut ping = tokio::time::interval_at(Instant::now() + period, period);
+            ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ping
+        });
+        self.idle = self.heartbeat.idle_timeout.map(|timeout| Box::pin(tokio::time::sleep(timeout)));
+        self.ping_due = false;
+        self.flushing = false;
+    }
+
+    /// Sends a ping when one is due, without waiting for the connection to be ready to send.
+    fn poll_ping(&mut self, cx: &mut Context<'_>) -> Result<()> {
+        if self.ping.as_mut().is_some_and(|ping| ping.poll_tick(cx).is_ready()) {
+            self.ping_due = true;
+        }
+        let stream = self.stream.as_mut().unwrap();
+        if self.ping_due {
+            if let Poll::Ready(ready) = stream.poll_ready_unpin(cx) {
+                ready?;
+                let message = match &self.heartbeat.ping_message {
+                    Some(message) => Message::Text(message.clone()),
+                    None => Message::Ping(vec![]),
+                };
+                stream.start_send_unpin(message)?;
+                self.ping_due = false;
+                self.flushing = true;
             }
-            self.stream = Some(stream);
         }
-        self.stream.as_mut().unwrap().send(Message::Text(msg)).await?;
-        Ok(self)
+        if self.flushing {
+            if let Poll::Ready(flushed) = stream.poll_flush_unpin(cx) {
+                flushed?;
+                self.flushing = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Schedules a new connection, backing off exponentially while attempts keep failing.
+    fn reconnect(&mut self) {
+        let delay = RECONNECT_MIN_DELAY
+            .saturating_mul(2u32.saturating_pow(self.reconnect_attempts))
+            .min(RECONNECT_MAX_DELAY);
+        self.reconnect_attempts = self.reconnect_attempts.saturating_add(1);
+
+        let endpoint = self.endpoint.clone();
+        let subscriptions = self.subscriptions.clone();
+        let topics = self.topics.clone();
+        self.reconnection = Some(Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            let mut stream = connect(endpoint.as_str()).await?;
+            for msg in subscriptions {
+                stream.send(Message::Text(msg)).await?;
+            }
+            if let Some((message, topics)) = topics {
+                stream.send(Message::Text(message(next_request_id(), &topics))).await?;
+            }
+            Ok(stream)
+        }));
     }
 
     // pub(crate) async fn connect(&mut self) -> Result<&Self> {
@@ -123,20 +351,437 @@ impl WsClient {
 }
 
 
+/// Messages of a `WsClient` left unparsed, for clients that dispatch them themselves.
+enum RawMessage {
+    Data(Vec<u8>),
+    Reconnected,
+}
+
+impl WsItem for RawMessage {
+    fn reconnected() -> Self {
+        RawMessage::Reconnected
+    }
+}
+
+struct RpcRequest {
+    id: u64,
+    message: String,
+    replay: bool,
+    response: oneshot::Sender<Result<serde_json::Value>>,
+}
+
+#[derive(Serialize)]
+struct RpcRequestMessage<'a, P: Serialize> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: &'a P,
+}
+
+#[derive(serde::Deserialize)]
+struct RpcResponseMessage {
+    id: Option<u64>,
+    result: Option<serde_json::Value>,
+    error: Option<serde_json::Value>,
+}
+
+/// JSON-RPC 2.0 client over a `WsClient`. Responses are matched to their requests by id, and the
+/// other messages are notifications, such as the data of subscriptions.
+pub(crate) struct WsRpcClient {
+    requests: flume::Sender<RpcRequest>,
+    next_id: AtomicU64,
+    connection: Arc<AtomicU64>,
+}
+
+impl WsRpcClient {
+    /// Connects and returns the client along with its notifications, parsed by `parser`. Failed
+    /// responses are mapped by `error_parser`, given the `error` member of the response.
+    pub(crate) async fn connect<T: WsItem + Send + 'static>(endpoint: &str, parser: fn(&[u8], &Unifier) -> WatchResult<T>, error_parser: fn(String) -> Error, unifier: Unifier, heartbeat: Heartbeat) -> Result<(Self, flume::Receiver<WatchResult<T>>)> {
+        let mut client = WsClient::new(endpoint, |message, _| Ok(RawMessage::Data(message.to_vec())), unifier.clone())
+            .heartbeat(heartbeat);
+        client.connect().await?;
+
+        let (requests, requests_rx) = flume::unbounded();
+        let (notifications, notifications_rx) = flume::unbounded();
+        let connection = Arc::new(AtomicU64::new(0));
+        let dispatcher = RpcDispatcher { parser, error_parser, unifier, connection: connection.clone(), pending: HashMap::new() };
+        tokio::spawn(dispatcher.run(client, requests_rx, notifications));
+        Ok((Self { requests, next_id: AtomicU64::new(1), connection }, notifications_rx))
+    }
+
+    /// Number of the current connection, incremented whenever the connection is re-established.
+    /// State tied to a connection, such as an authentication, has to be set up again when it
+    /// changes.
+    pub(crate) fn connection(&self) -> u64 {
+        self.connection.load(Ordering::Acquire)
+    }
+
+    pub(crate) async fn request<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: &P) -> Result<R> {
+        self.call(method, params, false).await
+    }
+
+    /// Sends a request that is sent again whenever the connection is re-established, such as a
+    /// subscription.
+    pub(crate) async fn subscribe<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: &P) -> Result<R> {
+        self.call(method, params, true).await
+    }
+
+    async fn call<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: &P, replay: bool) -> Result<R> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = serde_json::to_string(&RpcRequestMessage { jsonrpc: "2.0", id, method, params })
+            .map_err(|e| Error::InvalidParameters(format!("{}", e)))?;
+        let (response, response_rx) = oneshot::channel();
+        self.requests.send_async(RpcRequest { id, message, replay, response }).await
+            .map_err(|_| Error::WebsocketError("connection closed".into()))?;
+        let result = match tokio::time::timeout(RPC_TIMEOUT, response_rx).await {
+            Ok(Ok(result)) => result?,
+            Ok(Err(_)) => return Err(Error::WebsocketError("connection closed".into())),
+            Err(_) => return Err(Error::WebsocketError(format!("{} timed out", method))),
+        };
+        serde_json::from_value(result).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+    }
+}
+
+enum RpcEvent {
+    Request(Option<RpcRequest>),
+    Message(Option<WatchResult<RawMessage>>),
+}
+
+/// Sends the requests of a `WsRpcClient` and routes the messages of the connection.
+struct RpcDispatcher<T> {
+    parser: fn(&[u8], &Unifier) -> WatchResult<T>,
+    error_parser: fn(String) -> Error,
+    unifier: Unifier,
+    connection: Arc<AtomicU64>,
+    pending: HashMap<u64, oneshot::Sender<Result<serde_json::Value>>>,
+}
+
+impl<T: WsItem> RpcDispatcher<T> {
+    /// Runs until the client is dropped. Notifications that are not received are dropped, as the
+    /// client may only be used for requests.
+    async fn run(mut self, mut client: WsClient<RawMessage>, requests: flume::Receiver<RpcRequest>, notifications: flume::Sender<WatchResult<T>>) {
+        loop {
+            let event = tokio::select! {
+                request = requests.recv_async() => RpcEvent::Request(request.ok()),
+                message = client.next() => RpcEvent::Message(message),
+            };
+            match event {
+                RpcEvent::Request(Some(request)) => match client.send_message(request.message, request.replay).await {
+                    Ok(()) => {
+                        self.pending.insert(request.id, request.response);
+                    }
+                    Err(e) => {
+                        let _ = request.response.send(Err(e));
+                    }
+                },
+                RpcEvent::Message(Some(Ok(RawMessage::Data(data)))) => {
+                    if let Some(notification) = self.dispatch(&data) {
+                        let _ = notifications.send_async(notification).await;
+                    }
+                }
+                RpcEvent::Message(Some(Ok(RawMessage::Reconnected))) => {
+                    // the responses to the pending requests were lost with the connection
+                    self.connection.fetch_add(1, Ordering::AcqRel);
+                    for (_, response) in self.pending.drain() {
+                        let _ = response.send(Err(Error::WebsocketError("connection lost".into())));
+                    }
+                    let _ = notifications.send_async(Ok(T::reconnected())).await;
+                }
+                RpcEvent::Message(Some(Err(e))) => {
+                    let _ = notifications.send_async(Err(e)).await;
+                }
+                RpcEvent::Request(None) | RpcEvent::Message(None) => return,
+            }
+        }
+    }
+
+    /// Completes the request a response belongs to, or parses a notification. Responses to
+    /// requests replayed after a reconnection are ignored.
+    fn dispatch(&mut self, data: &[u8]) -> Option<WatchResult<T>> {
+        match serde_json::from_slice::<RpcResponseMessage>(data) {
+            Ok(RpcResponseMessage { id: Some(id), result, error }) => {
+                if let Some(response) = self.pending.remove(&id) {
+                    let result = match error {
+                        Some(error) => Err((self.error_parser)(error.to_string())),
+                        None => Ok(result.unwrap_or_default()),
+                    };
+                    let _ = response.send(result);
+                }
+                None
+            }
+            _ => Some((self.parser)(data, &self.unifier)),
+        }
+    }
+}
+
+
+/// Subscription protocol of an exchange whose topics can be added to and dropped from live
+/// connections.
+#[derive(Clone)]
+pub(crate) struct SubscriptionSpec {
+    pub(crate) endpoint: String,
+    pub(crate) parser: fn(&[u8], &Unifier) -> WatchResult<StreamItem>,
+    pub(crate) unifier: Unifier,
+    pub(crate) heartbeat: Heartbeat,
+    /// topic of a symbol id, e.g. `btcusdt@bookTicker`
+    pub(crate) topic: Arc<dyn Fn(&str) -> String + Send + Sync>,
+    /// messages subscribing to and unsubscribing from topics, given a request id. the exchange
+    /// acknowledges them with a `StreamItem::Subscribed` of the id
+    pub(crate) subscribe_message: TopicsMessage,
+    pub(crate) unsubscribe_message: TopicsMessage,
+    /// topics per connection
+    pub(crate) max_topics: usize,
+    /// pause before opening another connection
+    pub(crate) connect_interval: Duration,
+}
+
+pub(crate) enum SubscriptionCommand {
+    Subscribe(Vec<String>, oneshot::Sender<Result<()>>),
+    Unsubscribe(Vec<String>, oneshot::Sender<Result<()>>),
+}
+
+/// Requests sent for a command, and the topics of the requests that could not be sent.
+#[derive(Default)]
+struct SubscriptionRequests {
+    ids: HashSet<i64>,
+    failed: Vec<String>,
+}
+
+enum SubscriptionEvent {
+    Command(Option<SubscriptionCommand>),
+    Item(Option<Box<WatchResult<StreamItem>>>),
+}
+
+/// Owns the connections of a receiver, changing their topics on command and forwarding their
+/// items.
+pub(crate) struct SubscriptionDispatcher {
+    spec: SubscriptionSpec,
+    /// connections along with their topics
+    connections: Vec<(WsClient, Vec<String>)>,
+    /// ids of the requests of a command that are not acknowledged yet
+    pending: Vec<(HashSet<i64>, oneshot::Sender<Result<()>>)>,
+    /// connection polled first, so that a busy one does not starve the others
+    next: usize,
+}
+
+impl SubscriptionDispatcher {
+    /// Opens the connections of the topics without waiting for their acknowledgements, and runs
+    /// the dispatcher, forwarding the items into `items`, until the returned channel or the
+    /// receiver of the items is dropped.
+    pub(crate) async fn start(spec: SubscriptionSpec, topics: Vec<String>, items: Sender) -> Result<flume::Sender<SubscriptionCommand>> {
+        let mut dispatcher = Self { spec, connections: vec![], pending: vec![], next: 0 };
+        let requests = dispatcher.subscribe(topics).await;
+        if !requests.failed.is_empty() {
+            return Err(Error::SubscriptionFailed(requests.failed));
+        }
+        let (commands, commands_rx) = flume::unbounded();
+        tokio::spawn(dispatcher.run(commands_rx, items));
+        Ok(commands)
+    }
+
+    async fn run(mut self, commands: flume::Receiver<SubscriptionCommand>, items: Sender) {
+        loop {
+            let event = tokio::select! {
+                command = commands.recv_async() => SubscriptionEvent::Command(command.ok()),
+                item = futures_util::future::poll_fn(|cx| self.poll_items(cx)) => SubscriptionEvent::Item(item.map(Box::new)),
+            };
+            match event {
+                SubscriptionEvent::Command(Some(SubscriptionCommand::Subscribe(topics, ack))) => {
+                    let requests = self.subscribe(topics).await;
+                    self.wait_for(requests, ack);
+                }
+                SubscriptionEvent::Command(Some(SubscriptionCommand::Unsubscribe(topics, ack))) => {
+                    let requests = self.unsubscribe(topics).await;
+                    self.wait_for(requests, ack);
+                }
+                SubscriptionEvent::Item(Some(item)) => {
+                    match item.as_ref() {
+                        Ok(StreamItem::Subscribed(id)) => self.acknowledge(*id),
+                        Err(WatchError::SubscriptionRejected(id, e)) => self.reject(*id, e),
+                        _ => {}
+                    }
+                    if items.send(*item).await.is_err() { // the receiver is dropped
+                        return;
+                    }
+                }
+                SubscriptionEvent::Command(None) | SubscriptionEvent::Item(None) => return,
+            }
+        }
+    }
+
+    /// Completes the command once all of its requests are acknowledged, or fails it at once with
+    /// the topics whose requests could not be sent.
+    fn wait_for(&mut self, requests: SubscriptionRequests, ack: oneshot::Sender<Result<()>>) {
+        if !requests.failed.is_empty() {
+            let _ = ack.send(Err(Error::SubscriptionFailed(requests.failed)));
+        } else if requests.ids.is_empty() {
+            let _ = ack.send(Ok(()));
+        } else {
+            self.pending.push((requests.ids, ack));
+        }
+    }
+
+    fn acknowledge(&mut self, id: i64) {
+        for (ids, _) in self.pending.iter_mut() {
+            ids.remove(&id);
+        }
+        // commands given up by their caller are dropped as well
+        let (done, pending): (Vec<_>, Vec<_>) = self.pending.drain(..).partition(|(ids, ack)| ids.is_empty() || ack.is_closed());
+        self.pending = pending;
+        for (_, ack) in done {
+            let _ = ack.send(Ok(()));
+        }
+    }
+
+    /// Fails the command of the request at once with the error reply of the exchange.
+    fn reject(&mut self, id: i64, message: &str) {
+        if let Some(index) = self.pending.iter().position(|(ids, _)| ids.contains(&id)) {
+            let (_, ack) = self.pending.remove(index);
+            let _ = ack.send(Err(Error::SubscriptionRejected(id, message.to_string())));
+        }
+    }
+
+    /// Adds the topics that are not subscribed yet to the connections with room left, opening
+    /// new connections for the rest. Returns the ids of the requests sent, along with the topics
+    /// of the connections that could not be opened.
+    async fn subscribe(&mut self, topics: Vec<String>) -> SubscriptionRequests {
+        let mut remaining: Vec<String> = vec![];
+        for topic in topics {
+            if !remaining.contains(&topic) && !self.connections.iter().any(|(_, subscribed)| subscribed.contains(&topic)) {
+                remaining.push(topic);
+            }
+        }
+
+        let mut requests = SubscriptionRequests::default();
+        for (client, subscribed) in self.connections.iter_mut() {
+            let room = self.spec.max_topics.saturating_sub(subscribed.len()).min(remaining.len());
+            if room == 0 {
+                continue;
+            }
+            let id = next_request_id();
+            // a connection being re-established is skipped
+            if client.send_message((self.spec.subscribe_message)(id, &remaining[..room]), false).await.is_err() {
+                continue;
+            }
+            subscribed.extend(remaining.drain(..room));
+            client.set_topics(self.spec.subscribe_message, subscribed.clone());
+            requests.ids.insert(id);
+        }
+
+        for topics in remaining.chunks(self.spec.max_topics.max(1)) {
+            if !self.connections.is_empty() {
+                tokio::time::sleep(self.spec.connect_interval).await;
+            }
+            let mut client = WsClient::new(self.spec.endpoint.as_str(), self.spec.parser, self.spec.unifier.clone())
+                .heartbeat(self.spec.heartbeat.clone());
+            let id = next_request_id();
+            if client.connect().await.is_err() || client.send_message((self.spec.subscribe_message)(id, topics), false).await.is_err() {
+                requests.failed.extend_from_slice(topics);
+                continue;
+            }
+            client.set_topics(self.spec.subscribe_message, topics.to_vec());
+            self.connections.push((client, topics.to_vec()));
+            requests.ids.insert(id);
+        }
+        requests
+    }
+
+    /// Drops the topics from the connections subscribed to them. Connections are kept open
+    /// without topics, for later subscriptions. Returns the ids of the requests sent, along with
+    /// the topics that stay subscribed.
+    async fn unsubscribe(&mut self, topics: Vec<String>) -> SubscriptionRequests {
+        let mut requests = SubscriptionRequests::default();
+        for (client, subscribed) in self.connections.iter_mut() {
+            let dropped: Vec<String> = subscribed.iter().filter(|topic| topics.contains(topic)).cloned().collect();
+            if dropped.is_empty() {
+                continue;
+            }
+            // the topics stay subscribed, and replayed, when the request cannot be sent
+            let id = next_request_id();
+            if client.send_message((self.spec.unsubscribe_message)(id, &dropped), false).await.is_err() {
+                requests.failed.extend(dropped);
+                continue;
+            }
+            subscribed.retain(|topic| !dropped.contains(topic));
+            client.set_topics(self.spec.subscribe_message, subscribed.clone());
+            requests.ids.insert(id);
+        }
+        requests
+    }
+
+    fn poll_items(&mut self, cx: &mut Context<'_>) -> Poll<Option<WatchResult<StreamItem>>> {
+        let count = self.connections.len();
+        if count == 0 {
+            return Poll::Ready(None);
+        }
+        for i in 0..count {
+            let index = (self.next + i) % count;
+            if let Poll::Ready(Some(item)) = self.connections[index].0.poll_next_unpin(cx) {
+                self.next = (index + 1) % count;
+                return Poll::Ready(Some(item));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+
+async fn connect(endpoint: &str) -> Result<WsStream> {
+    let (stream, response) = connect_async(endpoint).await?;
+    if response.status() != tungstenite::http::StatusCode::SWITCHING_PROTOCOLS {
+        return Err(Error::WebsocketError(format!("Invalid status code: {}", response.status())));
+    }
+    Ok(stream)
+}
+
+
 impl From<tokio_tungstenite::tungstenite::Error> for Error {
     fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
         Error::WebsocketError(format!("{}", e))
     }
 }
 
+/// Weight of a request, given its method, path and query string.
+pub(crate) type WeightCalculator = fn(&Method, &str, &str) -> u32;
+
+#[derive(Clone)]
 pub struct HttpClient {
     host: String,
     port: u16,
     client: reqwest::Client,
     error_parser: fn(message: String) -> Error,
+    rate_limiter: RateLimiter,
+    weight_calculator: WeightCalculator,
 }
 
 impl HttpClient {
+    /// The limiter is shared by all clones of the client.
+    pub(crate) fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    async fn execute<T: DeserializeOwned>(&self, builder: RequestBuilder) -> Result<T> {
+        let request = builder.build()?;
+        let weight = (self.weight_calculator)(request.method(), request.url().path(), request.url().query().unwrap_or(""));
+        self.rate_limiter.acquire(weight).await;
+
+        let response = self.client.execute(request).await?;
+        self.rate_limiter.update(response.headers());
+        let code = response.status();
+        if code == StatusCode::TOO_MANY_REQUESTS || code == StatusCode::IM_A_TEAPOT {
+            let retry_after = response.headers().get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_RETRY_AFTER);
+            self.rate_limiter.block(retry_after);
+        }
+        if !code.is_success() {
+            return Err((self.error_parser)(response.text().await?));
+        }
+        Ok(response.json::<T>().await?)
+    }
+
     /// query: `&[("foo", "a"), ("foo", "b")])` makes `"foo=a&foo=b"`
     pub(crate) async fn get<Q: Serialize + ?Sized, T: DeserializeOwned>(&self, endpoint: &str, headers: Option<Vec<(&str, &str)>>, query: Option<&Q>) -> Result<T> {
         let mut builder = self.client.get(format!("{}:{}{}", self.host, self.port, endpoint));
@@ -150,12 +795,37 @@ impl HttpClient {
             }
         }
 
-        let response = builder.send().await?;
-        let code = response.status();
-        if !code.is_success() {
-            return Err((self.error_parser)(response.text().await?));
+        self.execute(builder).await
+    }
+
+    pub(crate) async fn delete<Q: Serialize + ?Sized, T: DeserializeOwned>(&self, endpoint: &str, headers: Option<Vec<(&str, &str)>>, query: Option<&Q>) -> Result<T> {
+        let mut builder = self.client.delete(format!("{}:{}{}", self.host, self.port, endpoint));
+        if let Some(query) = query {
+            builder = builder.query(query);
         }
-        Ok(response.json::<T>().await?)
+
+        if let Some(headers) = headers {
+            for (k, v) in headers {
+                builder = builder.header(k, v);
+            }
+        }
+
+        self.execute(builder).await
+    }
+
+    pub(crate) async fn put<Q: Serialize + ?Sized, T: DeserializeOwned>(&self, endpoint: &str, headers: Option<Vec<(&str, &str)>>, query: Option<&Q>) -> Result<T> {
+        let mut builder = self.client.put(format!("{}:{}{}", self.host, self.port, endpoint));
+        if let Some(query) = query {
+            builder = builder.query(query);
+        }
+
+        if let Some(headers) = headers {
+            for (k, v) in headers {
+                builder = builder.header(k, v);
+            }
+        }
+
+        self.execute(builder).await
     }
 
     pub(crate) async fn post<Q: Serialize + ?Sized, B: AsRef<str>, T: DeserializeOwned>(&self, endpoint: &str, headers: Option<Vec<(&str, &str)>>, query: Option<&Q>, body: Option<&B>) -> Result<T> {
@@ -173,12 +843,7 @@ impl HttpClient {
         if let Some(body) = body {
             builder = builder.body(body.as_ref().to_owned());
         }
-        let response = builder.send().await?;
-        let code = response.status();
-        if !code.is_success() {
-            return Err((self.error_parser)(response.text().await?));
-        }
-        Ok(response.json::<T>().await?)
+        self.execute(builder).await
     }
 }
 
@@ -187,6 +852,8 @@ pub struct HttpClientBuilder {
     host: String,
     port: u16,
     error_parser: Option<fn(message: String) -> Error>,
+    rate_limits: Vec<RateLimit>,
+    weight_calculator: Option<WeightCalculator>,
 }
 
 impl HttpClientBuilder {
@@ -195,6 +862,8 @@ impl HttpClientBuilder {
             host: "".to_string(),
             port: 0,
             error_parser: None,
+            rate_limits: vec![],
+            weight_calculator: None,
         }
     }
 
@@ -213,6 +882,18 @@ impl HttpClientBuilder {
         self
     }
 
+    /// limits applied until the exchange announces its own
+    pub(crate) fn rate_limits(mut self, rate_limits: Vec<RateLimit>) -> Self {
+        self.rate_limits = rate_limits;
+        self
+    }
+
+    /// every request weighs 1 when it is not set
+    pub(crate) fn weight_calculator(mut self, weight_calculator: Option<WeightCalculator>) -> Self {
+        self.weight_calculator = weight_calculator;
+        self
+    }
+
     pub(crate) fn build(self) -> Result<HttpClient> {
         let result = reqwest::Client::builder().build();
         Ok(HttpClient {
@@ -220,6 +901,8 @@ impl HttpClientBuilder {
             host: self.host,
             port: self.port,
             error_parser: self.error_parser.unwrap_or(|x| Error::HttpError(x)),
+            rate_limiter: RateLimiter::new(self.rate_limits),
+            weight_calculator: self.weight_calculator.unwrap_or(|_, _, _| 1),
         })
     }
 }
@@ -227,10 +910,259 @@ impl HttpClientBuilder {
 
 #[cfg(test)]
 mod test {
-    use futures_util::StreamExt;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
-    use crate::client::WsClient;
+    use crate::client::{Heartbeat, SubscriptionSpec, WsClient, WsRpcClient};
+    use crate::error::Error;
     use crate::exchange::{StreamItem, Unifier};
+    use crate::model::Market;
+    use crate::util::channel::ChannelConfig;
+    use crate::{Receiver, WatchError};
+
+    #[tokio::test]
+    async fn test_ws_client_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut received = vec![];
+            // the first connection is closed right after the subscription
+            for _ in 0..2 {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                let msg = ws.next().await.unwrap().unwrap();
+                received.push(msg.to_text().unwrap().to_string());
+                if received.len() == 1 {
+                    ws.close(None).await.unwrap();
+                } else {
+                    ws.send(Message::Text("data".to_string())).await.unwrap();
+                }
+            }
+            received
+        });
+
+        let parser = |x: &[u8], _: &Unifier| Ok(StreamItem::Unknown(String::from_utf8_lossy(x).to_string()));
+        let mut client = WsClient::new(endpoint.as_str(), parser, Unifier::new());
+        let _ = client.send("subscribe".to_string()).await.unwrap();
+
+        assert!(matches!(client.next().await.unwrap().unwrap(), StreamItem::Reconnected));
+        assert!(matches!(client.next().await.unwrap().unwrap(), StreamItem::Unknown(x) if x == "data"));
+        assert_eq!(server.await.unwrap(), vec!["subscribe", "subscribe"]);
+    }
+
+    #[tokio::test]
+    async fn test_ws_client_replay_topics() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut received = vec![];
+            for _ in 0..2 {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                let msg = ws.next().await.unwrap().unwrap();
+                received.push(msg.to_text().unwrap().to_string());
+                if received.len() == 1 {
+                    ws.close(None).await.unwrap();
+                } else {
+                    ws.send(Message::Text("data".to_string())).await.unwrap();
+                }
+            }
+            received
+        });
+
+        let parser = |x: &[u8], _: &Unifier| Ok(StreamItem::Unknown(String::from_utf8_lossy(x).to_string()));
+        let message = |id: i64, topics: &[String]| format!("{} {}", id, topics.join(","));
+        let topics = vec!["btcusdt".to_string(), "ethusdt".to_string()];
+        let mut client = WsClient::new(endpoint.as_str(), parser, Unifier::new());
+        client.connect().await.unwrap();
+        client.send_message(message(0, &topics), false).await.unwrap();
+        client.set_topics(message, topics);
+
+        assert!(matches!(client.next().await.unwrap().unwrap(), StreamItem::Reconnected));
+        assert!(matches!(client.next().await.unwrap().unwrap(), StreamItem::Unknown(x) if x == "data"));
+        // the replayed request has an id of its own
+        let received = server.await.unwrap();
+        assert_eq!(received[0], "0 btcusdt,ethusdt");
+        assert_ne!(received[1], received[0]);
+        assert!(received[1].ends_with(" btcusdt,ethusdt"));
+    }
+
+    #[tokio::test]
+    async fn test_ws_client_heartbeat() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut received = vec![];
+            // the first connection goes silent after answering a ping
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            for _ in 0..2 {
+                received.push(ws.next().await.unwrap().unwrap().to_text().unwrap().to_string());
+            }
+            ws.send(Message::Text("pong".to_string())).await.unwrap();
+            ws.send(Message::Ping(vec![])).await.unwrap();
+            tokio::spawn(async move { while let Some(Ok(_)) = ws.next().await {} });
+
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            received.push(ws.next().await.unwrap().unwrap().to_text().unwrap().to_string());
+            ws.send(Message::Text("data".to_string())).await.unwrap();
+            received
+        });
+
+        let parser = |x: &[u8], _: &Unifier| Ok(StreamItem::Unknown(String::from_utf8_lossy(x).to_string()));
+        let heartbeat = Heartbeat {
+            ping_interval: Some(Duration::from_millis(100)),
+            ping_message: Some("ping".to_string()),
+            pong_message: Some("pong".to_string()),
+            idle_timeout: Some(Duration::from_millis(300)),
+        };
+        let mut client = WsClient::new(endpoint.as_str(), parser, Unifier::new()).heartbeat(heartbeat);
+        let _ = client.send("subscribe".to_string()).await.unwrap();
+
+        // neither the pong nor the ping frame reach the parser
+        assert!(matches!(client.next().await.unwrap(), Err(WatchError::IdleTimeout(_))));
+        assert!(matches!(client.next().await.unwrap().unwrap(), StreamItem::Reconnected));
+        assert!(matches!(client.next().await.unwrap().unwrap(), StreamItem::Unknown(x) if x == "data"));
+        assert_eq!(server.await.unwrap(), vec!["subscribe", "ping", "subscribe"]);
+    }
+
+    #[tokio::test]
+    async fn test_receiver_subscriptions() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        // requests received by each connection
+        let requests: Arc<Mutex<Vec<Vec<serde_json::Value>>>> = Arc::new(Mutex::new(vec![]));
+        tokio::spawn({
+            let requests = requests.clone();
+            async move {
+                loop {
+                    let (tcp, _) = listener.accept().await.unwrap();
+                    let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                    let index = {
+                        let mut requests = requests.lock().unwrap();
+                        requests.push(vec![]);
+                        requests.len() - 1
+                    };
+                    let requests = requests.clone();
+                    tokio::spawn(async move {
+                        while let Some(Ok(Message::Text(msg))) = ws.next().await {
+                            let request: serde_json::Value = serde_json::from_str(&msg).unwrap();
+                            let ack = match msg.contains("dogeusdt") {
+                                true => serde_json::json!({"code": 2, "msg": "Invalid symbol", "id": request["id"]}),
+                                false => serde_json::json!({"result": null, "id": request["id"]}),
+                            };
+                            requests.lock().unwrap()[index].push(request);
+                            ws.send(Message::Text(ack.to_string())).await.unwrap();
+                        }
+                    });
+                }
+            }
+        });
+
+        let mut unifier = Unifier::new();
+        let markets: Vec<Market> = ["BTC", "ETH", "XRP", "DOGE"].iter()
+            .map(|base| Market { base: base.to_string(), quote: "USDT".into(), ..Default::default() })
+            .collect();
+        for market in &markets {
+            unifier.insert_market_symbol_id(market, &format!("{}USDT", market.base));
+        }
+        let spec = SubscriptionSpec {
+            endpoint,
+            parser: |x, _| {
+                let message = serde_json::from_slice::<serde_json::Value>(x).unwrap();
+                match (message["id"].as_i64(), message["msg"].as_str()) {
+                    (Some(id), Some(msg)) => Err(WatchError::SubscriptionRejected(id, msg.to_string())),
+                    (Some(id), None) => Ok(StreamItem::Subscribed(id)),
+                    _ => Ok(StreamItem::Unknown(String::from_utf8_lossy(x).to_string())),
+                }
+            },
+            unifier,
+            heartbeat: Heartbeat::default(),
+            topic: Arc::new(|symbol_id| symbol_id.to_lowercase()),
+            subscribe_message: |id, topics| serde_json::json!({"method": "SUBSCRIBE", "params": topics, "id": id}).to_string(),
+            unsubscribe_message: |id, topics| serde_json::json!({"method": "UNSUBSCRIBE", "params": topics, "id": id}).to_string(),
+            max_topics: 2,
+            connect_interval: Duration::ZERO,
+        };
+        let unreachable = SubscriptionSpec { endpoint: "ws://127.0.0.1:1".into(), ..spec.clone() };
+        let mut receiver = Receiver::subscribed(spec, &["BTCUSDT".to_string(), "ETHUSDT".to_string()], &ChannelConfig::default()).await.unwrap();
+        assert!(matches!(receiver.receive().await.unwrap(), StreamItem::Subscribed(_)));
+
+        // the first connection is full, then has room again
+        receiver.subscribe(&markets[2..3]).await.unwrap();
+        receiver.unsubscribe(&markets[..1]).await.unwrap();
+        receiver.subscribe(&markets[..1]).await.unwrap();
+
+        let requests = requests.lock().unwrap().clone();
+        let summary: Vec<Vec<String>> = requests.iter()
+            .map(|requests| requests.iter().map(|r| format!("{} {}", r["method"].as_str().unwrap(), r["params"])).collect())
+            .collect();
+        assert_eq!(summary, vec![
+            vec![r#"SUBSCRIBE ["btcusdt","ethusdt"]"#, r#"UNSUBSCRIBE ["btcusdt"]"#, r#"SUBSCRIBE ["btcusdt"]"#],
+            vec![r#"SUBSCRIBE ["xrpusdt"]"#],
+        ]);
+        let mut ids: Vec<i64> = requests.iter().flatten().map(|r| r["id"].as_i64().unwrap()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 4);
+
+        // an error reply fails the subscription without waiting for the timeout
+        let rejected = tokio::time::timeout(Duration::from_secs(1), receiver.subscribe(&markets[3..])).await.unwrap();
+        assert!(matches!(rejected, Err(WatchError::SubscriptionRejected(_, msg)) if msg == "Invalid symbol"));
+
+        // the topics of a connection that cannot be opened are reported
+        let failed = Receiver::subscribed(unreachable, &["BTCUSDT".to_string()], &ChannelConfig::default()).await;
+        assert!(matches!(failed, Err(Error::SubscriptionFailed(topics)) if topics == vec!["btcusdt".to_string()]));
+
+        let (_, receiver) = ChannelConfig::default().bounded();
+        assert!(matches!(receiver.subscribe(&markets).await, Err(WatchError::NotImplemented)));
+    }
+
+    #[tokio::test]
+    async fn test_ws_rpc_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let mut requests = vec![];
+            for _ in 0..2 {
+                let msg = ws.next().await.unwrap().unwrap();
+                requests.push(serde_json::from_str::<serde_json::Value>(msg.to_text().unwrap()).unwrap());
+            }
+            ws.send(Message::Text(r#"{"method": "subscription", "params": {"channel": "test"}}"#.to_string())).await.unwrap();
+            // answered out of order
+            for request in requests.iter().rev() {
+                let response = match request["method"].as_str().unwrap() {
+                    "echo" => serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": request["params"]}),
+                    _ => serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "error": {"code": 1, "message": "failure"}}),
+                };
+                ws.send(Message::Text(response.to_string())).await.unwrap();
+            }
+            // keeps the connection open until the client is dropped
+            while let Some(Ok(_)) = ws.next().await {}
+        });
+
+        let parser = |x: &[u8], _: &Unifier| Ok(StreamItem::Unknown(String::from_utf8_lossy(x).to_string()));
+        let error_parser = |x: String| Error::HttpError(x);
+        let (client, notifications) = WsRpcClient::connect(endpoint.as_str(), parser, error_parser, Unifier::new(), Heartbeat::default()).await.unwrap();
+        let (echo, fail) = tokio::join!(
+            client.request::<_, Vec<i64>>("echo", &[1, 2]),
+            client.request::<_, serde_json::Value>("fail", &()),
+        );
+        assert_eq!(echo.unwrap(), vec![1, 2]);
+        assert!(matches!(fail, Err(Error::HttpError(x)) if x.contains("failure")));
+        assert!(matches!(notifications.recv_async().await.unwrap().unwrap(), StreamItem::Unknown(x) if x.contains("subscription")));
+        assert_eq!(client.connection(), 0);
+
+        drop(client);
+        server.await.unwrap();
+    }
 
     #[tokio::test]
     async fn test_ws_client() {
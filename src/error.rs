@@ -1,5 +1,6 @@
 use std::num::{ParseFloatError, ParseIntError};
 use std::sync::PoisonError;
+use std::time::Duration;
 
 use hmac::digest::InvalidLength;
 use thiserror::Error;
@@ -35,14 +36,22 @@ pub(crate) enum Error {
     UnsupportedOrderStatus(String),
     UnsupportedTimeInForce(String),
     UnsupportedWorkingType(String),
+    UnsupportedTimeframe(String),
     CredentialsError(String),
 
     InvalidOrderBook(String),
     InvalidResponse(String),
     StreamError(String),
+    /// id of the subscription request and the error reply of the exchange
+    SubscriptionRejected(i64, String),
+    /// topics whose subscription request could not be sent
+    SubscriptionFailed(Vec<String>),
     SynchronizationError,
 
     InsufficientMargin(String),
+    OrderNotFound(String),
+    TimestampOutsideRecvWindow(String),
+    UnsupportedInSandbox(String),
 }
 
 
@@ -58,6 +67,13 @@ impl From<ParseFloatError> for Error {
     }
 }
 
+#[cfg(feature = "decimal")]
+impl From<rust_decimal::Error> for Error {
+    fn from(e: rust_decimal::Error) -> Self {
+        Error::ParseError(format!("{}", e))
+    }
+}
+
 impl From<ParseIntError> for Error {
     fn from(e: ParseIntError) -> Self {
         Error::ParseError(format!("{}", e))
@@ -133,6 +149,14 @@ pub enum CommonError {
     #[error("invalid timestamp {0}")]
     InvalidTimestamp(i64),
 
+    /// the exchange has no testnet
+    #[error("unsupported in sandbox {0}")]
+    UnsupportedInSandbox(String),
+
+    /// no exchange has the id
+    #[error("unsupported exchange {0}")]
+    UnsupportedExchange(String),
+
     #[error("parse error {0}")]
     ParseFloatError(#[from] ParseFloatError),
 }
@@ -170,6 +194,7 @@ impl From<Error> for CommonError {
             Error::InvalidParameters(e) => CommonError::InvalidPrice(e),
             Error::InvalidMarket => CommonError::InvalidMarket,
             Error::InvalidTimestamp(ts) => CommonError::InvalidTimestamp(ts),
+            Error::UnsupportedInSandbox(e) => CommonError::UnsupportedInSandbox(e),
             _ => CommonError::NotImplemented,
         }
     }
@@ -206,6 +231,167 @@ pub type TradeResult<T> = std::result::Result<T, TradeError>;
 pub enum TradeError {
     #[error("unknown error {0}")]
     UnknownError(String),
+    /// error of the stream the item was expected from
+    #[error("watch error {0}")]
+    WatchError(WatchError),
+}
+
+impl From<Error> for TradeError {
+    fn from(err: Error) -> Self {
+        TradeError::UnknownError(format!("{:?}", err))
+    }
+}
+
+impl From<WatchError> for TradeError {
+    fn from(err: WatchError) -> Self {
+        TradeError::WatchError(err)
+    }
+}
+
+
+pub type TickerResult<T> = std::result::Result<T, TickerError>;
+
+
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum TickerError {
+    #[error("unknown error {0}")]
+    UnknownError(String),
+    /// error of the stream the item was expected from
+    #[error("watch error {0}")]
+    WatchError(WatchError),
+}
+
+impl From<Error> for TickerError {
+    fn from(err: Error) -> Self {
+        TickerError::UnknownError(format!("{:?}", err))
+    }
+}
+
+impl From<WatchError> for TickerError {
+    fn from(err: WatchError) -> Self {
+        TickerError::WatchError(err)
+    }
+}
+
+pub type OrderResult<T> = std::result::Result<T, OrderError>;
+
+
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum OrderError {
+    #[error("parse error {0}")]
+    ParseError(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+    /// error of the stream the item was expected from
+    #[error("watch error {0}")]
+    WatchError(WatchError),
+}
+
+impl From<Error> for OrderError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::ParseError(e) => OrderError::ParseError(e),
+            _ => OrderError::UnknownError(format!("{:?}", err)),
+        }
+    }
+}
+
+impl From<WatchError> for OrderError {
+    fn from(err: WatchError) -> Self {
+        OrderError::WatchError(err)
+    }
+}
+
+pub type BalanceResult<T> = std::result::Result<T, BalanceError>;
+
+
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum BalanceError {
+    #[error("parse error {0}")]
+    ParseError(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+    /// error of the stream the item was expected from
+    #[error("watch error {0}")]
+    WatchError(WatchError),
+}
+
+impl From<Error> for BalanceError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::ParseError(e) => BalanceError::ParseError(e),
+            _ => BalanceError::UnknownError(format!("{:?}", err)),
+        }
+    }
+}
+
+impl From<WatchError> for BalanceError {
+    fn from(err: WatchError) -> Self {
+        BalanceError::WatchError(err)
+    }
+}
+
+pub type PositionResult<T> = std::result::Result<T, PositionError>;
+
+
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum PositionError {
+    #[error("parse error {0}")]
+    ParseError(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+    /// error of the stream the item was expected from
+    #[error("watch error {0}")]
+    WatchError(WatchError),
+}
+
+impl From<Error> for PositionError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::ParseError(e) => PositionError::ParseError(e),
+            _ => PositionError::UnknownError(format!("{:?}", err)),
+        }
+    }
+}
+
+impl From<WatchError> for PositionError {
+    fn from(err: WatchError) -> Self {
+        PositionError::WatchError(err)
+    }
+}
+
+pub type OhlcvResult<T> = std::result::Result<T, OhlcvError>;
+
+
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum OhlcvError {
+    #[error("parse error {0}")]
+    ParseError(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+    /// error of the stream the item was expected from
+    #[error("watch error {0}")]
+    WatchError(WatchError),
+}
+
+impl From<Error> for OhlcvError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::ParseError(e) => OhlcvError::ParseError(e),
+            _ => OhlcvError::UnknownError(format!("{:?}", err)),
+        }
+    }
+}
+
+impl From<WatchError> for OhlcvError {
+    fn from(err: WatchError) -> Self {
+        OhlcvError::WatchError(err)
+    }
 }
 
 pub type OrderBookResult<T> = std::result::Result<T, OrderBookError>;
@@ -225,17 +411,27 @@ pub enum OrderBookError {
     SynchronizationError(Market),
     #[error("unknown error {0}")]
     UnknownError(String),
+    /// error of the stream the item was expected from
+    #[error("watch error {0}")]
+    WatchError(WatchError),
 }
 
 
 impl From<Error> for OrderBookError {
     fn from(err: Error) -> Self {
         match err {
+            Error::ParseError(e) => OrderBookError::ParseError(e),
             _ => OrderBookError::UnknownError(format!("{:?}", err)),
         }
     }
 }
 
+impl From<WatchError> for OrderBookError {
+    fn from(err: WatchError) -> Self {
+        OrderBookError::WatchError(err)
+    }
+}
+
 impl From<ParseFloatError> for OrderBookError {
     fn from(e: ParseFloatError) -> Self {
         OrderBookError::ParseError(format!("{}", e))
@@ -249,6 +445,30 @@ pub type WatchOrderBookError = WatchError;
 
 pub type WatchTradesResult<T> = WatchResult<T>;
 pub type WatchTradesError = WatchError;
+
+
+pub type WatchTickersResult<T> = WatchResult<T>;
+pub type WatchTickersError = WatchError;
+
+
+pub type WatchOhlcvResult<T> = WatchResult<T>;
+pub type WatchOhlcvError = WatchError;
+
+
+pub type WatchOrdersResult<T> = WatchResult<T>;
+pub type WatchOrdersError = WatchError;
+
+
+pub type WatchMyTradesResult<T> = WatchResult<T>;
+pub type WatchMyTradesError = WatchError;
+
+
+pub type WatchBalanceResult<T> = WatchResult<T>;
+pub type WatchBalanceError = WatchError;
+
+
+pub type WatchPositionsResult<T> = WatchResult<T>;
+pub type WatchPositionsError = WatchError;
 pub type WatchResult<T> = std::result::Result<T, WatchError>;
 
 #[derive(Error, Debug, Clone)]
@@ -264,6 +484,12 @@ pub enum WatchError {
     NotConnected,
     #[error("disconnected")]
     Disconnected,
+    /// nothing was received for the idle timeout. the connection is re-established next
+    #[error("no message received for {0:?}")]
+    IdleTimeout(Duration),
+    /// the connection was re-established, and items sent meanwhile were missed
+    #[error("reconnected")]
+    Reconnected,
     #[error("error response {0}")]
     ErrorResponse(String),
     #[error("invalid response {0}")]
@@ -274,6 +500,16 @@ pub enum WatchError {
     ParseError(String),
     #[error("stream error {0}")]
     StreamError(String),
+    /// the exchange answered the subscription request of the id with an error
+    #[error("subscription {0} rejected {1}")]
+    SubscriptionRejected(i64, String),
+    /// the requests changing the subscriptions of the topics could not be sent, so that they
+    /// keep their former state
+    #[error("subscription of {0:?} failed")]
+    SubscriptionFailed(Vec<String>),
+    /// the method is not available on the testnet of the exchange
+    #[error("unsupported in sandbox {0}")]
+    UnsupportedInSandbox(String),
     #[error("unknown error {0}")]
     UnknownError(String),
 }
@@ -284,6 +520,10 @@ impl From<Error> for WatchError {
         match err {
             Error::DeserializeJsonBody(e) => WatchError::InvalidResponse(e),
             Error::InvalidResponse(e) => WatchError::InvalidResponse(e),
+            Error::UnsupportedInSandbox(e) => WatchError::UnsupportedInSandbox(e),
+            Error::WebsocketError(e) => WatchError::WebsocketError(e),
+            Error::SubscriptionRejected(id, e) => WatchError::SubscriptionRejected(id, e),
+            Error::SubscriptionFailed(topics) => WatchError::SubscriptionFailed(topics),
             _ => WatchError::UnknownError(format!("{:?}", err)),
         }
     }
@@ -327,14 +567,26 @@ pub enum CreateOrderError {
     InsufficientMargin(String),
     #[error("invalid price {0}")]
     InvalidPrice(String),
+    #[error("invalid amount {0}")]
+    InvalidAmount(String),
+    /// price * amount is outside the notional limits of the market
+    #[error("invalid cost {0}")]
+    InvalidCost(String),
     #[error("invalid credentials")]
     InvalidCredentials,
     #[error("unsupported order type {0}")]
     UnsupportedOrderType(String),
     #[error("symbol not found {0}")]
     SymbolNotFound(String),
+    /// the request reached the exchange outside of its receive window. enable time sync or widen
+    /// the window through `Properties`
+    #[error("timestamp outside recv window {0}")]
+    TimestampOutsideRecvWindow(String),
     #[error("not implemented")]
     NotImplemented,
+    /// the method is not available on the testnet of the exchange
+    #[error("unsupported in sandbox {0}")]
+    UnsupportedInSandbox(String),
     #[error("unknown error {0}")]
     UnknownError(String),
 }
@@ -344,15 +596,100 @@ impl From<Error> for CreateOrderError {
         match e {
             Error::InsufficientMargin(s) => CreateOrderError::InsufficientMargin(s),
             Error::InvalidPrice(s) => CreateOrderError::InvalidPrice(s),
+            Error::InvalidAmount(s) => CreateOrderError::InvalidAmount(s),
             Error::InvalidCredentials => CreateOrderError::InvalidCredentials,
             Error::UnsupportedOrderType(s) => CreateOrderError::UnsupportedOrderType(s),
             Error::SymbolNotFound(s) => CreateOrderError::SymbolNotFound(s),
             Error::NotImplemented => CreateOrderError::NotImplemented,
+            Error::TimestampOutsideRecvWindow(s) => CreateOrderError::TimestampOutsideRecvWindow(s),
+            Error::UnsupportedInSandbox(s) => CreateOrderError::UnsupportedInSandbox(s),
             _ => CreateOrderError::UnknownError(format!("{:?}", e)),
         }
     }
 }
 
+pub type CancelOrderResult<T> = std::result::Result<T, CancelOrderError>;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum CancelOrderError {
+    #[error("order not found {0}")]
+    OrderNotFound(String),
+    #[error("invalid credentials")]
+    InvalidCredentials,
+    #[error("invalid parameters {0}")]
+    InvalidParameters(String),
+    #[error("symbol not found {0}")]
+    SymbolNotFound(String),
+    /// the request reached the exchange outside of its receive window. enable time sync or widen
+    /// the window through `Properties`
+    #[error("timestamp outside recv window {0}")]
+    TimestampOutsideRecvWindow(String),
+    #[error("not implemented")]
+    NotImplemented,
+    /// the method is not available on the testnet of the exchange
+    #[error("unsupported in sandbox {0}")]
+    UnsupportedInSandbox(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+}
+
+impl From<Error> for CancelOrderError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::OrderNotFound(s) => CancelOrderError::OrderNotFound(s),
+            Error::InvalidCredentials => CancelOrderError::InvalidCredentials,
+            Error::InvalidParameters(s) => CancelOrderError::InvalidParameters(s),
+            Error::SymbolNotFound(s) => CancelOrderError::SymbolNotFound(s),
+            Error::NotImplemented => CancelOrderError::NotImplemented,
+            Error::TimestampOutsideRecvWindow(s) => CancelOrderError::TimestampOutsideRecvWindow(s),
+            Error::UnsupportedInSandbox(s) => CancelOrderError::UnsupportedInSandbox(s),
+            _ => CancelOrderError::UnknownError(format!("{:?}", e)),
+        }
+    }
+}
+
+pub type FetchOrdersResult<T> = std::result::Result<T, FetchOrdersError>;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum FetchOrdersError {
+    #[error("order not found {0}")]
+    OrderNotFound(String),
+    #[error("invalid credentials")]
+    InvalidCredentials,
+    #[error("invalid parameters {0}")]
+    InvalidParameters(String),
+    #[error("symbol not found {0}")]
+    SymbolNotFound(String),
+    /// the request reached the exchange outside of its receive window. enable time sync or widen
+    /// the window through `Properties`
+    #[error("timestamp outside recv window {0}")]
+    TimestampOutsideRecvWindow(String),
+    #[error("not implemented")]
+    NotImplemented,
+    /// the method is not available on the testnet of the exchange
+    #[error("unsupported in sandbox {0}")]
+    UnsupportedInSandbox(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+}
+
+impl From<Error> for FetchOrdersError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::OrderNotFound(s) => FetchOrdersError::OrderNotFound(s),
+            Error::InvalidCredentials => FetchOrdersError::InvalidCredentials,
+            Error::InvalidParameters(s) => FetchOrdersError::InvalidParameters(s),
+            Error::SymbolNotFound(s) => FetchOrdersError::SymbolNotFound(s),
+            Error::NotImplemented => FetchOrdersError::NotImplemented,
+            Error::TimestampOutsideRecvWindow(s) => FetchOrdersError::TimestampOutsideRecvWindow(s),
+            Error::UnsupportedInSandbox(s) => FetchOrdersError::UnsupportedInSandbox(s),
+            _ => FetchOrdersError::UnknownError(format!("{:?}", e)),
+        }
+    }
+}
+
 pub type LoadMarketResult<T> = std::result::Result<T, LoadMarketError>;
 
 #[derive(Error, Debug)]
@@ -409,6 +746,13 @@ pub type FetchBalanceResult<T> = std::result::Result<T, FetchBalanceError>;
 pub enum FetchBalanceError {
     #[error("not implemented")]
     NotImplemented,
+    /// the request reached the exchange outside of its receive window. enable time sync or widen
+    /// the window through `Properties`
+    #[error("timestamp outside recv window {0}")]
+    TimestampOutsideRecvWindow(String),
+    /// the method is not available on the testnet of the exchange
+    #[error("unsupported in sandbox {0}")]
+    UnsupportedInSandbox(String),
     #[error("unknown error {0}")]
     UnknownError(String),
 }
@@ -417,6 +761,8 @@ pub enum FetchBalanceError {
 impl From<Error> for FetchBalanceError {
     fn from(e: Error) -> Self {
         match e {
+            Error::TimestampOutsideRecvWindow(s) => FetchBalanceError::TimestampOutsideRecvWindow(s),
+            Error::UnsupportedInSandbox(s) => FetchBalanceError::UnsupportedInSandbox(s),
             _ => FetchBalanceError::UnknownError(format!("{:?}", e)),
         }
     }
@@ -429,6 +775,10 @@ pub type FetchPositionsResult<T> = std::result::Result<T, FetchPositionsError>;
 pub enum FetchPositionsError {
     #[error("not implemented")]
     NotImplemented,
+    /// the request reached the exchange outside of its receive window. enable time sync or widen
+    /// the window through `Properties`
+    #[error("timestamp outside recv window {0}")]
+    TimestampOutsideRecvWindow(String),
     #[error("unknown error {0}")]
     UnknownError(String),
 }
@@ -437,6 +787,7 @@ pub enum FetchPositionsError {
 impl From<Error> for FetchPositionsError {
     fn from(e: Error) -> Self {
         match e {
+            Error::TimestampOutsideRecvWindow(s) => FetchPositionsError::TimestampOutsideRecvWindow(s),
             _ => FetchPositionsError::UnknownError(format!("{:?}", e)),
         }
     }
@@ -470,6 +821,109 @@ impl From<ParseFloatError> for FetchTradesError {
     }
 }
 
+pub type FetchOrderBookResult<T> = std::result::Result<T, FetchOrderBookError>;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum FetchOrderBookError {
+    #[error("not implemented")]
+    NotImplemented,
+    #[error("symbol not found {0}")]
+    SymbolNotFound(String),
+    #[error("parse error {0}")]
+    ParseError(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+}
+
+
+impl From<Error> for FetchOrderBookError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::SymbolNotFound(s) => FetchOrderBookError::SymbolNotFound(s),
+            Error::ParseError(s) => FetchOrderBookError::ParseError(s),
+            _ => FetchOrderBookError::UnknownError(format!("{:?}", e)),
+        }
+    }
+}
+
+pub type FetchGreeksResult<T> = std::result::Result<T, FetchGreeksError>;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum FetchGreeksError {
+    #[error("not implemented")]
+    NotImplemented,
+    #[error("symbol not found {0}")]
+    SymbolNotFound(String),
+    #[error("parse error {0}")]
+    ParseError(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+}
+
+
+impl From<Error> for FetchGreeksError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::SymbolNotFound(s) => FetchGreeksError::SymbolNotFound(s),
+            Error::ParseError(s) => FetchGreeksError::ParseError(s),
+            _ => FetchGreeksError::UnknownError(format!("{:?}", e)),
+        }
+    }
+}
+
+pub type FetchOptionChainResult<T> = std::result::Result<T, FetchOptionChainError>;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum FetchOptionChainError {
+    #[error("not implemented")]
+    NotImplemented,
+    #[error("no options listed for {0}")]
+    UnderlyingNotFound(String),
+    #[error("parse error {0}")]
+    ParseError(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+}
+
+
+impl From<Error> for FetchOptionChainError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::ParseError(s) => FetchOptionChainError::ParseError(s),
+            _ => FetchOptionChainError::UnknownError(format!("{:?}", e)),
+        }
+    }
+}
+
+pub type FetchOhlcvResult<T> = std::result::Result<T, FetchOhlcvError>;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum FetchOhlcvError {
+    #[error("not implemented")]
+    NotImplemented,
+    #[error("parse error {0}")]
+    ParseError(String),
+    #[error("unknown error {0}")]
+    UnknownError(String),
+}
+
+
+impl From<Error> for FetchOhlcvError {
+    fn from(e: Error) -> Self {
+        FetchOhlcvError::UnknownError(format!("{:?}", e))
+    }
+}
+
+impl From<ParseFloatError> for FetchOhlcvError {
+    fn from(e: ParseFloatError) -> Self {
+        FetchOhlcvError::ParseError(format!("{}", e))
+    }
+}
+
 pub type FetchTickersResult<T> = std::result::Result<T, FetchTickersError>;
 
 #[derive(Error, Debug)]
@@ -1,39 +1,90 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
-use chrono::Utc;
+use futures_util::stream::SelectAll;
+use futures_util::StreamExt;
 use hmac::{Hmac, Mac};
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 
-use crate::client::EMPTY_BODY;
+use crate::client::{EMPTY_BODY, next_request_id, WsItem};
 use crate::error::*;
 use crate::exchange::*;
-use crate::util::{into_precision, parse_float64};
-use crate::util::channel::Receiver;
+use crate::util::{into_precision, parse_float64, parse_number, ZERO};
+use crate::util::channel::{Receiver, Sender};
+use crate::util::order_book::LocalOrderBook;
 
 use super::util;
+use super::util::StreamChannel;
+
+const ORDER_BOOK_SNAPSHOT_LIMIT: &str = "1000";
+const ORDER_BOOK_SNAPSHOT_ATTEMPTS: usize = 3;
 
 pub struct Binance {
     exchange_base: ExchangeBase,
     api_key: Option<String>,
     secret: Option<String>,
+    recv_window: String,
+    sandbox: bool,
+}
+
+/// Request weights of the endpoints in use. sapi endpoints are limited separately by the exchange
+/// and count with the default weight.
+fn request_weight(method: &Method, path: &str, query: &str) -> u32 {
+    match path {
+        "/api/v3/exchangeInfo" => 20,
+        "/api/v3/depth" => match util::get_query_value(query, "limit").and_then(|l| l.parse::<u32>().ok()).unwrap_or(100) {
+            0..=100 => 5,
+            101..=500 => 25,
+            501..=1000 => 50,
+            _ => 250,
+        },
+        "/api/v3/ticker/24hr" => match (util::get_query_value(query, "symbol"), util::get_query_value(query, "symbols")) {
+            (Some(_), _) => 2,
+            // symbols is a json array of quoted symbol ids
+            (None, Some(symbols)) => match symbols.matches("%22").count() / 2 {
+                0..=20 => 2,
+                21..=100 => 40,
+                _ => 80,
+            },
+            (None, None) => 80,
+        },
+        "/api/v3/klines" => 2,
+        "/api/v3/order" if method == Method::GET => 4,
+        "/api/v3/allOrders" => 20,
+        "/api/v3/openOrders" => match util::get_query_value(query, "symbol") {
+            Some(_) => 6,
+            None => 80,
+        },
+        "/api/v3/account" => 20,
+        "/api/v3/userDataStream" => 2,
+        _ => 1,
+    }
 }
 
 impl Binance {
     pub fn new(props: Properties) -> CommonResult<Self> {
+        let (host, ws_endpoint) = match props.sandbox {
+            true => ("https://testnet.binance.vision", "wss://testnet.binance.vision/ws"),
+            false => ("https://api.binance.com", "wss://stream.binance.com:9443/ws"),
+        };
         let base_props = BasePropertiesBuilder::default()
-            .host(props.host.or(Some("https://api.binance.com".to_string())))
+            .host(props.host.or(Some(host.to_string())))
             .port(props.port.or(Some(443)))
-            .ws_endpoint(props.ws_endpoint.or(Some("wss://stream.binance.com:9443/ws".to_string())))
+            .ws_endpoint(props.ws_endpoint.or(Some(ws_endpoint.to_string())))
             .error_parser(Some(|message| {
                 match serde_json::from_str::<ErrorResponse>(&message) {
                     Ok(error) => {
                         match error.code {
                             -3045 => Error::InsufficientMargin(error.msg), // The system doesn't have enough asset now.
                             -1013 => Error::InvalidAmount(error.msg), // Invalid amount
-                            -1021 => Error::HttpError(error.msg), // Timestamp for this request is outside of the recvWindow
+                            -1021 => Error::TimestampOutsideRecvWindow(error.msg), // Timestamp for this request is outside of the recvWindow
                             -1022 => Error::InvalidSignature(error.msg), // Signature for this request is not valid
                             -1100 => Error::InvalidParameters(error.msg), // Illegal characters found in a parameter
                             -1101 => Error::InvalidParameters(error.msg), // Too many parameters sent for this endpoint
+                            -2011 => Error::OrderNotFound(error.msg), // Unknown order sent
+                            -2013 => Error::OrderNotFound(error.msg), // Order does not exist
                             _ => Error::HttpError(error.msg),
                         }
                     }
@@ -41,9 +92,15 @@ impl Binance {
                 }
             }))
             .stream_parser(Some(|message, unifier| {
+                let (stream, message) = util::split_combined_stream(message);
                 let common_message = WatchCommonResponse::try_from(message.to_vec())?;
-                if common_message.code.is_some() && common_message.msg.is_some() { // error message
-                    return Err(Error::StreamError(format!("code={}, msg={}", common_message.code.unwrap(), common_message.msg.unwrap())))?;
+                if let (Some(code), Some(msg)) = (&common_message.code, &common_message.msg) { // error message
+                    let message = format!("code={}, msg={}", code, msg);
+                    // a rejected subscription request carries its id
+                    return match common_message.id {
+                        Some(id) => Err(Error::SubscriptionRejected(id, message).into()),
+                        None => Err(Error::StreamError(message).into()),
+                    };
                 }
 
                 if common_message.id.is_some() { // subscription result
@@ -51,69 +108,118 @@ impl Binance {
                     return Ok(StreamItem::Subscribed(id))
                 }
 
-                // best bid and ask stream
-                if let (Some(order_book_update_id), Some(symbol), Some(bid_price), Some(bid_quantity), Some(ask_price), Some(ask_quantity)) = (common_message.order_book_update_id, common_message.symbol, common_message.bid_price, common_message.bid_quantity, common_message.ask_price, common_message.ask_quantity) {
-                    let market = unifier.get_market(&symbol);
-                    if market.is_none() {
-                        return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                            format!("Unknown market. symbol={}", symbol), None,
-                        ))))
-                    }
-                    let market = market.unwrap();
-                    let bid_price = bid_price.parse::<f64>();
-                    if bid_price.is_err() {
-                        return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                            format!("Invalid bid price. symbol={}, price={}", symbol, bid_price.unwrap_err()), None,
-                        ))))
-                    }
-                    let bid_price = bid_price.unwrap();
-                    let bid_amount = bid_quantity.parse::<f64>();
-                    if bid_amount.is_err() {
-                        return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                            format!("Invalid bid amount. symbol={}, amount={}", symbol, bid_amount.unwrap_err()), None,
-                        ))))
-                    }
-                    let bid_amount = bid_amount.unwrap();
-                    let ask_price = ask_price.parse::<f64>();
-                    if ask_price.is_err() {
-                        return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                            format!("Invalid ask price. symbol={}, price={}", symbol, ask_price.unwrap_err()), None,
-                        ))))
-                    }
-                    let ask_price = ask_price.unwrap();
-                    let ask_amount = ask_quantity.parse::<f64>();
-                    if ask_amount.is_err() {
-                        return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                            format!("Invalid ask amount. symbol={}, amount={}", symbol, ask_amount.unwrap_err()), None,
-                        ))))
+                let channel = match stream {
+                    Some(stream) => util::stream_channel(&stream),
+                    None => util::event_channel(common_message.event_type.as_deref(), common_message.order_book_update_id),
+                };
+                match channel {
+                    StreamChannel::BookTicker => { // best bid and ask stream
+                        let WatchBookTickerResponse { order_book_update_id, symbol, bid_price, bid_quantity, ask_price, ask_quantity } = WatchBookTickerResponse::try_from(message.to_vec())?;
+                        let market = unifier.get_market(&symbol);
+                        if market.is_none() {
+                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                                format!("Unknown market. symbol={}", symbol), None,
+                            ))))
+                        }
+                        let market = market.unwrap();
+                        let bid_price = bid_price.parse::<Number>();
+                        if bid_price.is_err() {
+                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                                format!("Invalid bid price. symbol={}, price={}", symbol, bid_price.unwrap_err()), None,
+                            ))))
+                        }
+                        let bid_price = bid_price.unwrap();
+                        let bid_amount = bid_quantity.parse::<Number>();
+                        if bid_amount.is_err() {
+                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                                format!("Invalid bid amount. symbol={}, amount={}", symbol, bid_amount.unwrap_err()), None,
+                            ))))
+                        }
+                        let bid_amount = bid_amount.unwrap();
+                        let ask_price = ask_price.parse::<Number>();
+                        if ask_price.is_err() {
+                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                                format!("Invalid ask price. symbol={}, price={}", symbol, ask_price.unwrap_err()), None,
+                            ))))
+                        }
+                        let ask_price = ask_price.unwrap();
+                        let ask_amount = ask_quantity.parse::<Number>();
+                        if ask_amount.is_err() {
+                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                                format!("Invalid ask amount. symbol={}, amount={}", symbol, ask_amount.unwrap_err()), None,
+                            ))))
+                        }
+                        let ask_amount = ask_amount.unwrap();
+                        let book = OrderBook::new(vec![(bid_price, bid_amount).into()], vec![(ask_price, ask_amount).into()], market, None, Some(order_book_update_id));
+                        Ok(StreamItem::OrderBook(Ok(book)))
                     }
-                    let ask_amount = ask_amount.unwrap();
-                    let book = OrderBook::new(vec![(bid_price, bid_amount).into()], vec![(ask_price, ask_amount).into()], market, None, Some(order_book_update_id));
-                    return Ok(StreamItem::OrderBook(Ok(book)))
-                }
-
-
-                return match common_message.event_type {
-                    Some(event_type) if event_type == "depthUpdate" => {
+                    StreamChannel::Depth => {
                         // diff order book
-                        Ok(StreamItem::OrderBook(Err(OrderBookError::NotImplemented)))
+                        let resp = WatchDiffOrderBookResponse::try_from(message.to_vec())?;
+                        let market = unifier.get_market(&resp.symbol);
+                        if market.is_none() {
+                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                                format!("Unknown market. symbol={}", resp.symbol), None,
+                            ))))
+                        }
+                        let market = market.unwrap();
+                        let book = match resp.units() {
+                            Ok((bids, asks)) => Ok(OrderBook::new(bids, asks, market, Some(resp.event_time), Some(resp.final_update_id))),
+                            Err(e) => Err(OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market))),
+                        };
+                        Ok(StreamItem::OrderBook(book))
+                    }
+                    StreamChannel::Kline => {
+                        let resp = util::WatchOhlcvResponse::try_from(message.to_vec())?;
+                        Ok(StreamItem::Ohlcv(resp.to_ohlcv(unifier)))
                     }
                     _ => {
-                        let message = String::from_utf8_lossy(&message);
+                        let message = String::from_utf8_lossy(message);
                         Ok(StreamItem::Unknown(message.to_string()))
                     },
-                };
+                }
             }))
             .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            .rate_limits(util::get_request_weight_limit("REQUEST_WEIGHT", "MINUTE", 1, 6000).map(|limit| vec![limit]))
+            .weight_calculator(Some(request_weight))
             .build()?;
 
         Ok(Self {
             exchange_base: ExchangeBase::new(&base_props)?,
             api_key: props.api_key.clone(),
             secret: props.secret.clone(),
+            recv_window: props.recv_window.unwrap_or(util::DEFAULT_RECV_WINDOW).to_string(),
+            sandbox: props.sandbox,
         })
     }
 
+    /// The testnet has no margin endpoints.
+    fn check_margin_mode(&self, margin_mode: &Option<MarginMode>) -> Result<()> {
+        match (self.sandbox, margin_mode) {
+            (true, Some(margin_mode)) => Err(Error::UnsupportedInSandbox(format!("{:?} margin is not available on the spot testnet", margin_mode))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Order endpoint of the spot account without a margin mode, or of the margin account with
+    /// its `isIsolated` flag.
+    fn order_endpoint(&self, path: &str, margin_mode: &Option<MarginMode>) -> Result<(String, Option<&'static str>)> {
+        self.check_margin_mode(margin_mode)?;
+        match margin_mode {
+            Some(margin_mode) => Ok((format!("/sapi/v1/margin/{}", path), Some(util::get_exchange_is_isolated(margin_mode)))),
+            None => Ok((format!("/api/v3/{}", path), None)),
+        }
+    }
+
+    /// Timestamp for signed requests, on the exchange clock when time sync is enabled. The clock
+    /// is synced in the background from `load_markets`.
+    fn timestamp(&self) -> i64 {
+        self.exchange_base.clock.timestamp()
+    }
+
     fn auth(&self, request: &String) -> Result<String> {
         if self.api_key.is_none() || self.secret.is_none() {
             return Err(Error::InvalidCredentials);
@@ -138,14 +244,143 @@ impl Binance {
             None => Ok(self.auth(&"".to_string())?),
         }
     }
+
+    async fn watch_full_order_book(&self, symbol_ids: &[String]) -> WatchOrderBookResult<Receiver> {
+        let mut clients = vec![];
+        for symbol_ids in symbol_ids.chunks(util::STREAMS_PER_CONNECTION) {
+            if !clients.is_empty() {
+                tokio::time::sleep(util::CONNECT_INTERVAL).await;
+            }
+            let streams: Vec<String> = symbol_ids.iter()
+                .map(|s| format!("{}@depth@100ms", s.to_lowercase()))
+                .collect();
+            let stream_name = util::get_subscribe_request(next_request_id(), &streams);
+            let endpoint = util::combined_endpoint(self.exchange_base.ws_endpoint.as_ref().unwrap());
+            let mut ws_client = self.exchange_base.ws_client(endpoint.as_str(), parse_diff_order_book, self.exchange_base.unifier.clone());
+            let _ = ws_client.send(stream_name).await?;
+            clients.push(ws_client);
+        }
+
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(synchronize_order_books(
+            futures_util::stream::select_all(clients),
+            self.exchange_base.http_client.clone(),
+            self.exchange_base.unifier.clone(),
+            tx,
+        ));
+        Ok(receiver)
+    }
+
+    /// Opens the user data stream of the spot, cross margin or isolated margin account and
+    /// forwards the events accepted by `filter`.
+    async fn watch_user_data(&self, margin_mode: Option<MarginMode>, market: Option<&Market>, filter: fn(&StreamItem) -> bool) -> Result<Receiver> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized);
+        }
+        self.check_margin_mode(&margin_mode)?;
+
+        let (path, query) = match margin_mode {
+            None => ("/api/v3/userDataStream", vec![]),
+            Some(MarginMode::Cross) => ("/sapi/v1/userDataStream", vec![]),
+            Some(MarginMode::Isolated) => {
+                let market = market.ok_or(Error::InvalidParameters("market is required for isolated margin".into()))?;
+                let symbol_id = self.exchange_base.unifier.get_symbol_id(market).ok_or(Error::SymbolNotFound(format!("{}", market)))?;
+                ("/sapi/v1/userDataStream/isolated", vec![("symbol", symbol_id)])
+            }
+        };
+        let endpoint = util::ListenKeyEndpoint {
+            http_client: self.exchange_base.http_client.clone(),
+            path: path.to_string(),
+            query,
+            api_key: self.api_key.clone().unwrap(),
+            keyed: true,
+        };
+        let listen_key = endpoint.create().await?;
+
+        let ws_endpoint = self.exchange_base.ws_endpoint.clone().unwrap();
+        let ws_client_factory = self.exchange_base.ws_client_factory(parse_user_data);
+        let connect = move |listen_key: &str| ws_client_factory(&format!("{}/{}", ws_endpoint, listen_key));
+        let mut ws_client = connect(&listen_key);
+        ws_client.connect().await?;
+
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(util::run_user_data_stream(ws_client, listen_key, endpoint, connect, filter, tx));
+        Ok(receiver)
+    }
+
+    /// Fetches orders from `allOrders` or `openOrders` of the spot or margin account.
+    async fn fetch_order_list(&self, path: &str, params: &FetchOrdersParams, ranged: bool) -> Result<Vec<Order>> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized);
+        }
+
+        let symbol_id = match &params.market {
+            Some(market) => Some(self.exchange_base.unifier.get_symbol_id(market).ok_or(Error::SymbolNotFound(format!("{}", market)))?),
+            None => None,
+        };
+        let since = params.since.map(|since| since.to_string());
+        let until = params.until.map(|until| until.to_string());
+        if let Some(limit) = params.limit.filter(|_| ranged) {
+            if limit > 1000 {
+                return Err(Error::InvalidParameters("limit should be less than or equal to 1000".into()));
+            }
+        }
+        let limit = params.limit.map(|limit| limit.to_string());
+        let (endpoint, is_isolated) = self.order_endpoint(path, &params.margin_mode)?;
+        let timestamp = self.timestamp().to_string();
+
+        let mut queries = vec![];
+        if let Some(symbol_id) = &symbol_id {
+            queries.push(("symbol", symbol_id.as_str()));
+        }
+        if let Some(is_isolated) = is_isolated {
+            queries.push(("isIsolated", is_isolated));
+        }
+        if ranged {
+            if let Some(since) = &since {
+                queries.push(("startTime", since.as_str()));
+            }
+            if let Some(until) = &until {
+                queries.push(("endTime", until.as_str()));
+            }
+            if let Some(limit) = &limit {
+                queries.push(("limit", limit.as_str()));
+            }
+        }
+        queries.push(("recvWindow", self.recv_window.as_str()));
+        queries.push(("timestamp", timestamp.as_str()));
+
+        let signature = self.auth_map(Some(&queries))?;
+        queries.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let responses: Vec<FetchOrderResponse> = self.exchange_base.http_client.get(endpoint.as_str(), Some(headers), Some(&queries)).await?;
+
+        let mut orders = vec![];
+        for response in responses {
+            if let Some(market) = self.exchange_base.unifier.get_market(&response.symbol) {
+                let mut order: Order = response.try_into()?;
+                order.market = market;
+                orders.push(order);
+            }
+        }
+        Ok(orders)
+    }
 }
 
 #[async_trait]
 impl Exchange for Binance {
     async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        util::start_time_sync(&self.exchange_base.clock, &self.exchange_base.http_client, "/api/v3/time".into()).await;
         if self.exchange_base.markets.is_empty() {
             self.exchange_base.unifier.reset();
             let result = self.exchange_base.http_client.get::<(), FetchMarketsResponse>("/api/v3/exchangeInfo", None, None).await?;
+            result.update_rate_limits(&self.exchange_base.http_client);
             let mut markets = vec![];
             for s in result.symbols {
                 if let Ok(market) = (&s).into() {
@@ -160,6 +395,7 @@ impl Exchange for Binance {
 
     async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
         let result = self.exchange_base.http_client.get::<(), FetchMarketsResponse>("/api/v3/exchangeInfo", None, None).await?;
+        result.update_rate_limits(&self.exchange_base.http_client);
         let mut markets = vec![];
         for s in result.symbols {
             let market: Result<Market> = (&s).into();
@@ -227,12 +463,68 @@ impl Exchange for Binance {
                     vwap: item.weighted_avg_price.parse::<f64>()?,
                 });
             }
-            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
         }
 
         Ok(tickers)
     }
 
+    async fn fetch_ohlcv(&self, params: FetchOhlcvParams) -> FetchOhlcvResult<Vec<Ohlcv>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or_else(|| Error::SymbolNotFound(format!("{}", params.market)))?;
+        let mut query = vec![
+            ("symbol", symbol_id),
+            ("interval", util::get_exchange_timeframe(&params.timeframe).to_string()),
+        ];
+        if let Some(since) = params.since {
+            query.push(("startTime", format!("{}", since)));
+        }
+        if let Some(until) = params.until {
+            query.push(("endTime", format!("{}", until)));
+        }
+        if let Some(limit) = params.limit {
+            if limit > 1000 {
+                return Err(Error::InvalidParameters("limit should be less than or equal to 1000".into()).into());
+            }
+            query.push(("limit", format!("{}", limit)));
+        }
+        let result: Vec<util::FetchOhlcvResponse> = self.exchange_base.http_client.get("/api/v3/klines", None, Some(&query)).await?;
+
+        let mut ret = vec![];
+        for item in result {
+            let mut ohlcv: Ohlcv = item.try_into()?;
+            ohlcv.market = params.market.clone();
+            ohlcv.timeframe = params.timeframe;
+            ret.push(ohlcv);
+        }
+        Ok(ret)
+    }
+
+    async fn watch_ohlcv(&self, params: WatchOhlcvParams) -> WatchOhlcvResult<Receiver> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let markets = &params.markets;
+        if markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut symbol_ids: Vec<String> = Vec::new();
+        for m in markets {
+            match self.exchange_base.unifier.get_symbol_id(m) {
+                Some(symbol_id) => symbol_ids.push(symbol_id),
+                None => return Err(WatchError::SymbolNotFound(format!("{:?}", m))),
+            }
+        }
+
+        let interval = util::get_exchange_timeframe(&params.timeframe);
+        let spec = util::subscription_spec(&self.exchange_base, move |s| format!("{}@kline_{}", s.to_lowercase(), interval));
+        Ok(Receiver::subscribed(spec, &symbol_ids, &self.exchange_base.channel).await?)
+    }
+
     async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
         if self.exchange_base.markets.is_empty() {
             return Err(Error::MarketNotInitialized.into());
@@ -253,19 +545,12 @@ impl Exchange for Binance {
             }
         }
 
-        let mut clients = vec![];
-        for symbol_ids in symbol_ids.chunks(100) {
-            let params = symbol_ids.iter()
-                    .map(|s| format!("\"{}@bookTicker\"", s.to_lowercase()))
-                    .collect::<Vec<String>>()
-                    .join(",");
-                let stream_name = format!("{{\"method\": \"SUBSCRIBE\", \"params\": [{params}], \"id\": 1}}");
-                let mut ws_client = WsClient::new(self.exchange_base.ws_endpoint.as_ref().unwrap().as_str(), self.exchange_base.stream_parser, self.exchange_base.unifier.clone());
-                let _ = ws_client.send(stream_name).await?;
-                clients.push(ws_client);
-            tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
+        if params.full_depth {
+            return self.watch_full_order_book(&symbol_ids).await;
         }
-        Ok(Receiver::new(clients))
+
+        let spec = util::subscription_spec(&self.exchange_base, |s| format!("{}@bookTicker", s.to_lowercase()));
+        Ok(Receiver::subscribed(spec, &symbol_ids, &self.exchange_base.channel).await?)
     }
 
 
@@ -277,8 +562,10 @@ impl Exchange for Binance {
             return Err(Error::MarketNotInitialized)?;
         }
 
-        let ts = Utc::now().timestamp_millis().to_string();
+        self.check_margin_mode(&params.margin_mode)?;
+        let ts = self.timestamp().to_string();
         let mut query = vec![];
+        query.push(("recvWindow", self.recv_window.as_str()));
         query.push(("timestamp", ts.as_str()));
         let signature = self.auth_map(Some(&query))?;
         query.push(("signature", signature.as_str()));
@@ -354,53 +641,374 @@ impl Exchange for Binance {
         }
 
         let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or(Error::SymbolNotFound(format!("{}", params.market)))?;
-        let timestamp = Utc::now().timestamp_millis();
-
-        let amount = params.amount.to_string();
-        let timestamp = timestamp.to_string();
-
-        let side_effect_type = match (params.margin_mode, params.reduce_only) {
-            (Some(_), true) => "REDUCE_ONLY",
-            (Some(_), false) => "MARGIN_BUY",
-            (None, _) => "NO_SIDE_EFFECT",
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.exchange_base.unifier.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
         };
+        let timestamp = self.timestamp();
 
-        let is_isolated = match matches!(params.margin_mode, Some(MarginMode::Isolated)) {
-            true => "TRUE",
-            false => "FALSE",
-        };
+        let amount = amount.to_string();
+        let timestamp = timestamp.to_string();
 
         let mut queries = vec![
             ("symbol", symbol_id.as_str()),
-            ("isIsolated", is_isolated),
             ("side", util::get_exchange_order_side(&params.order_side)),
             ("type", util::get_exchange_order_type(&order_type)?),
             ("quantity", amount.as_str()),
-            ("recvWindow", "5000"),
+            ("recvWindow", self.recv_window.as_str()),
             ("timestamp", timestamp.as_str()),
-            ("sideEffectType", side_effect_type),
         ];
 
+        let (endpoint, is_isolated) = self.order_endpoint("order", &params.margin_mode)?;
+        if let Some(is_isolated) = is_isolated {
+            queries.push(("isIsolated", is_isolated));
+            queries.push(("sideEffectType", if params.reduce_only { "REDUCE_ONLY" } else { "MARGIN_BUY" }));
+        }
+
         if order_type != OrderType::Market {
             queries.push(("timeInForce", util::get_exchange_time_in_force(&params.time_in_force.unwrap_or(TimeInForce::GTC))));
         }
 
-        let price = params.price.map(|p| p.to_string());
-        if order_type != OrderType::Market && params.price.is_some() {
-            queries.push(("price", price.as_ref().unwrap().as_str()));
+        let price = price.map(|p| p.to_string());
+        if let (true, Some(price)) = (order_type != OrderType::Market, price.as_ref()) {
+            queries.push(("price", price.as_str()));
         }
 
         let signature = self.auth_map(Some(&queries))?;
         queries.push(("signature", signature.as_str()));
         let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
-        let response: CreateOrderResponse = self.exchange_base.http_client.post("/sapi/v1/margin/order", Some(headers), Some(&queries), EMPTY_BODY).await?;
+        let response: CreateOrderResponse = self.exchange_base.http_client.post(endpoint.as_str(), Some(headers), Some(&queries), EMPTY_BODY).await?;
         let mut order: Order = response.try_into()?;
         order.market = params.market.clone();
         order.order_type = order_type;
         Ok(order)
     }
+
+    async fn cancel_order(&self, params: CancelOrderParams) -> CancelOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or(Error::SymbolNotFound(format!("{}", params.market)))?;
+        let (endpoint, is_isolated) = self.order_endpoint("order", &params.margin_mode)?;
+        let timestamp = self.timestamp().to_string();
+
+        let mut queries = vec![
+            ("symbol", symbol_id.as_str()),
+            util::get_exchange_order_id(&params.order_id, &params.client_order_id)?,
+        ];
+        if let Some(is_isolated) = is_isolated {
+            queries.push(("isIsolated", is_isolated));
+        }
+        queries.push(("recvWindow", self.recv_window.as_str()));
+        queries.push(("timestamp", timestamp.as_str()));
+
+        let signature = self.auth_map(Some(&queries))?;
+        queries.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let response: FetchOrderResponse = self.exchange_base.http_client.delete(endpoint.as_str(), Some(headers), Some(&queries)).await?;
+        let mut order: Order = response.try_into()?;
+        order.market = params.market.clone();
+        Ok(order)
+    }
+
+    async fn fetch_order(&self, params: FetchOrderParams) -> FetchOrdersResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or(Error::SymbolNotFound(format!("{}", params.market)))?;
+        let (endpoint, is_isolated) = self.order_endpoint("order", &params.margin_mode)?;
+        let timestamp = self.timestamp().to_string();
+
+        let mut queries = vec![
+            ("symbol", symbol_id.as_str()),
+            util::get_exchange_order_id(&params.order_id, &params.client_order_id)?,
+        ];
+        if let Some(is_isolated) = is_isolated {
+            queries.push(("isIsolated", is_isolated));
+        }
+        queries.push(("recvWindow", self.recv_window.as_str()));
+        queries.push(("timestamp", timestamp.as_str()));
+
+        let signature = self.auth_map(Some(&queries))?;
+        queries.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let response: FetchOrderResponse = self.exchange_base.http_client.get(endpoint.as_str(), Some(headers), Some(&queries)).await?;
+        let mut order: Order = response.try_into()?;
+        order.market = params.market.clone();
+        Ok(order)
+    }
+
+    async fn fetch_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        if params.market.is_none() {
+            return Err(Error::InvalidParameters("market is required".into()).into());
+        }
+        Ok(self.fetch_order_list("allOrders", &params, true).await?)
+    }
+
+    async fn fetch_open_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        Ok(self.fetch_order_list("openOrders", &params, false).await?)
+    }
+
+    async fn fetch_closed_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        let mut orders = self.fetch_orders(params).await?;
+        orders.retain(|order| order.status != OrderStatus::Open);
+        Ok(orders)
+    }
+
+    async fn watch_balance(&self, params: WatchBalanceParams) -> WatchBalanceResult<Receiver> {
+        Ok(self.watch_user_data(params.margin_mode, params.market.as_ref(), |item| matches!(item, StreamItem::Balance(_))).await?)
+    }
+
+    async fn watch_my_trades(&self, params: WatchMyTradesParams) -> WatchMyTradesResult<Receiver> {
+        Ok(self.watch_user_data(params.margin_mode, params.market.as_ref(), |item| matches!(item, StreamItem::MyTrade(_))).await?)
+    }
+
+    async fn watch_orders(&self, params: WatchOrdersParams) -> WatchOrdersResult<Receiver> {
+        Ok(self.watch_user_data(params.margin_mode, params.market.as_ref(), |item| matches!(item, StreamItem::Order(_))).await?)
+    }
+}
+
+fn parse_user_data(message: &[u8], unifier: &Unifier) -> WatchResult<Vec<StreamItem>> {
+    let common_message = WatchCommonResponse::try_from(message.to_vec())?;
+    match common_message.event_type.as_deref() {
+        Some("executionReport") => {
+            let resp = WatchOrderResponse::try_from(message.to_vec())?;
+            let market = match unifier.get_market(&resp.symbol) {
+                Some(market) => market,
+                None => return Ok(vec![StreamItem::Order(Err(OrderError::UnknownError(format!("Unknown market. symbol={}", resp.symbol))))]),
+            };
+            let mut items = vec![StreamItem::Order(resp.to_order(&market).map_err(OrderError::from))];
+            if resp.execution_type == "TRADE" {
+                items.push(StreamItem::MyTrade(resp.to_trade(&market).map_err(TradeError::from)));
+            }
+            Ok(items)
+        }
+        Some("outboundAccountPosition") => {
+            let resp = WatchBalanceResponse::try_from(message.to_vec())?;
+            Ok(vec![StreamItem::Balance(resp.to_balance().map_err(BalanceError::from))])
+        }
+        Some("listenKeyExpired") => Err(WatchError::StreamError(util::LISTEN_KEY_EXPIRED.into())),
+        _ => Ok(vec![StreamItem::Unknown(String::from_utf8_lossy(message).to_string())]),
+    }
+}
+
+enum DiffOrderBookItem {
+    Update(WatchDiffOrderBookResponse),
+    Reconnected,
+    Other,
+}
+
+impl WsItem for DiffOrderBookItem {
+    fn reconnected() -> Self {
+        DiffOrderBookItem::Reconnected
+    }
+}
+
+fn parse_diff_order_book(message: &[u8], _: &Unifier) -> WatchResult<DiffOrderBookItem> {
+    let (_, message) = util::split_combined_stream(message);
+    let common_message = WatchCommonResponse::try_from(message.to_vec())?;
+    if let (Some(code), Some(msg)) = (common_message.code, common_message.msg) { // error message
+        return Err(Error::StreamError(format!("code={}, msg={}", code, msg)).into());
+    }
+    match common_message.event_type {
+        Some(event_type) if event_type == "depthUpdate" => Ok(DiffOrderBookItem::Update(WatchDiffOrderBookResponse::try_from(message.to_vec())?)),
+        _ => Ok(DiffOrderBookItem::Other),
+    }
+}
+
+/// Keeps a local full-depth book per symbol up to date from diff events and forwards the complete
+/// books to the receiver until it is dropped. Snapshots are fetched in the background, so that a
+/// slow snapshot of one symbol does not hold back the diffs of the others.
+async fn synchronize_order_books(mut clients: SelectAll<WsClient<DiffOrderBookItem>>, http_client: HttpClient, unifier: Unifier, tx: Sender) {
+    let mut books: HashMap<String, SymbolBook> = HashMap::new();
+    let (snapshots_tx, snapshots) = flume::unbounded::<Snapshot>();
+    loop {
+        let (symbol, items, fetch) = tokio::select! {
+            item = clients.next() => {
+                let diff = match item {
+                    Some(Ok(DiffOrderBookItem::Update(diff))) => diff,
+                    Some(Ok(DiffOrderBookItem::Reconnected)) => {
+                        // updates were missed while disconnected
+                        books.values_mut().for_each(|book| book.invalidate());
+                        if tx.send(Ok(StreamItem::Reconnected)).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                    Some(Ok(DiffOrderBookItem::Other)) => continue,
+                    Some(Err(e)) => {
+                        if tx.send(Err(e)).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                    None => return,
+                };
+                let book = match books.get_mut(&diff.symbol) {
+                    Some(book) => book,
+                    None => match unifier.get_market(&diff.symbol) {
+                        Some(market) => books.entry(diff.symbol.clone()).or_insert_with(|| SymbolBook::new(market)),
+                        None => {
+                            let error = OrderBookError::InvalidOrderBook(format!("Unknown market. symbol={}", diff.symbol), None);
+                            if tx.send(Ok(StreamItem::OrderBook(Err(error)))).await.is_err() {
+                                return;
+                            }
+                            continue;
+                        }
+                    },
+                };
+                let symbol = diff.symbol.clone();
+                let (items, fetch) = book.on_diff(diff);
+                (symbol, items, fetch)
+            }
+            Ok((symbol, generation, snapshot)) = snapshots.recv_async() => {
+                match books.get_mut(&symbol) {
+                    // requested before the book was invalidated
+                    Some(book) if book.generation == generation => {
+                        let (items, fetch) = book.on_snapshot(snapshot);
+                        (symbol, items, fetch)
+                    }
+                    _ => continue,
+                }
+            }
+        };
+        if fetch {
+            let generation = books[&symbol].generation;
+            let (http_client, snapshots_tx) = (http_client.clone(), snapshots_tx.clone());
+            tokio::spawn(async move {
+                let snapshot = fetch_order_book_snapshot(&http_client, &symbol).await;
+                let _ = snapshots_tx.send_async((symbol, generation, snapshot)).await;
+            });
+        }
+        for item in items {
+            if tx.send(Ok(StreamItem::OrderBook(item))).await.is_err() {
+                return;
+            }
+        }
+    }
 }
 
+/// snapshot of a symbol, along with the generation of the book it was requested for
+type Snapshot = (String, u64, Result<WatchPartialOrderBookResponse>);
+
+/// Local book of a symbol and the diffs received while its snapshot is fetched.
+struct SymbolBook {
+    book: LocalOrderBook,
+    /// diffs buffered until the snapshot arrives. None while no snapshot is fetched
+    buffer: Option<Vec<WatchDiffOrderBookResponse>>,
+    /// snapshots fetched since the book was last synchronized
+    attempts: usize,
+    /// incremented when the book is invalidated, so that snapshots requested before are ignored
+    generation: u64,
+}
+
+impl SymbolBook {
+    fn new(market: Market) -> Self {
+        Self { book: LocalOrderBook::new(market), buffer: None, attempts: 0, generation: 0 }
+    }
+
+    fn invalidate(&mut self) {
+        self.book.invalidate();
+        self.buffer = None;
+        self.generation += 1;
+    }
+
+    /// Applies a diff event, or buffers it while the snapshot is fetched. The book is rebuilt from
+    /// a snapshot on its first event and after a sequence gap, which is reported as
+    /// `OrderBookError::SynchronizationError`. Returns the items to forward and whether a snapshot
+    /// has to be fetched.
+    fn on_diff(&mut self, diff: WatchDiffOrderBookResponse) -> (Vec<OrderBookResult<OrderBook>>, bool) {
+        if let Some(buffer) = self.buffer.as_mut() {
+            buffer.push(diff);
+            return (vec![], false);
+        }
+        let mut items = vec![];
+        if self.book.is_synchronized() {
+            match apply_diff(&mut self.book, &diff) {
+                Ok(true) => return (vec![Ok(self.book.to_order_book())], false),
+                Ok(false) => return (vec![], false),
+                Err(_) => {
+                    self.invalidate();
+                    items.push(Err(OrderBookError::SynchronizationError(self.book.market().clone())));
+                }
+            }
+        }
+        self.buffer = Some(vec![diff]);
+        self.attempts = 0;
+        (items, true)
+    }
+
+    /// Rebuilds the book from the snapshot and the buffered diffs. The snapshot may be older than
+    /// the buffered diffs, in which case another one is fetched. Returns the items to forward and
+    /// whether a snapshot has to be fetched.
+    fn on_snapshot(&mut self, snapshot: Result<WatchPartialOrderBookResponse>) -> (Vec<OrderBookResult<OrderBook>>, bool) {
+        let buffer = match self.buffer.take() {
+            Some(buffer) => buffer,
+            None => return (vec![], false),
+        };
+        self.attempts += 1;
+        let book = &mut self.book;
+        let result = snapshot.and_then(|snapshot| {
+            let (bids, asks) = snapshot.units()?;
+            book.reset(&bids, &asks, snapshot.last_update_id);
+            buffer.iter().try_for_each(|diff| apply_diff(book, diff).map(|_| ()))
+        });
+        match result {
+            Ok(()) => (vec![Ok(self.book.to_order_book())], false),
+            Err(Error::SynchronizationError) if self.attempts < ORDER_BOOK_SNAPSHOT_ATTEMPTS => {
+                self.book.invalidate();
+                self.buffer = Some(buffer);
+                (vec![], true)
+            }
+            Err(Error::SynchronizationError) => {
+                self.invalidate();
+                (vec![Err(OrderBookError::SynchronizationError(self.book.market().clone()))], false)
+            }
+            Err(e) => {
+                self.invalidate();
+                (vec![Err(e.into())], false)
+            }
+        }
+    }
+}
+
+async fn fetch_order_book_snapshot(http_client: &HttpClient, symbol_id: &str) -> Result<WatchPartialOrderBookResponse> {
+    let query = vec![("symbol", symbol_id), ("limit", ORDER_BOOK_SNAPSHOT_LIMIT)];
+    http_client.get("/api/v3/depth", None, Some(&query)).await
+}
+
+/// Applies a diff event following the `U`/`u` sequencing rules of the spot diff depth stream.
+///
+/// Returns `Ok(false)` if the event is already contained in the book, and
+/// `Err(Error::SynchronizationError)` if events are missing between the book and the event.
+fn apply_diff(book: &mut LocalOrderBook, diff: &WatchDiffOrderBookResponse) -> Result<bool> {
+    let last_update_id = book.last_update_id().ok_or(Error::SynchronizationError)?;
+    if diff.final_update_id <= last_update_id {
+        return Ok(false);
+    }
+    if diff.first_update_id > last_update_id + 1 {
+        return Err(Error::SynchronizationError);
+    }
+    let (bids, asks) = diff.units()?;
+    book.update(&bids, &asks, diff.final_update_id, Some(diff.event_time));
+    Ok(true)
+}
+
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct CreateOrderResponse {
@@ -447,7 +1055,7 @@ impl TryFrom<CreateOrderResponse> for Order {
             status: order_status,
             time_in_force: Some(util::get_unified_time_in_force(&resp.time_in_force)?),
             side: Some(util::get_unified_order_side(&resp.side)?),
-            price: Some(resp.price.parse()?),
+            price: Some(resp.price.parse()?).filter(|price| *price > ZERO),
             amount: resp.original_quantity.parse()?,
             remaining,
             ..Default::default()
@@ -456,6 +1064,234 @@ impl TryFrom<CreateOrderResponse> for Order {
 }
 
 
+/// Order returned by the query and cancel endpoints of both the spot and margin accounts.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchOrderResponse {
+    symbol: String,
+    order_id: i64,
+    client_order_id: String,
+    /// set when canceling, where `client_order_id` identifies the cancel request instead
+    orig_client_order_id: Option<String>,
+    price: String,
+    orig_qty: String,
+    executed_qty: String,
+    cummulative_quote_qty: String,
+    status: String,
+    time_in_force: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    side: String,
+    time: Option<i64>,
+    transact_time: Option<i64>,
+    update_time: Option<i64>,
+    is_isolated: Option<bool>,
+}
+
+impl TryFrom<FetchOrderResponse> for Order {
+    type Error = Error;
+
+    fn try_from(resp: FetchOrderResponse) -> std::result::Result<Self, Self::Error> {
+        let amount: Number = resp.orig_qty.parse()?;
+        let filled: Number = resp.executed_qty.parse()?;
+        let cost: Number = resp.cummulative_quote_qty.parse()?;
+        let price: Number = resp.price.parse()?;
+        let margin_mode = match resp.is_isolated {
+            Some(true) => MarginMode::Isolated,
+            Some(false) => MarginMode::Cross,
+            None => MarginMode::default(),
+        };
+        Ok(Order {
+            id: Some(resp.order_id.to_string()),
+            client_order_id: Some(resp.orig_client_order_id.unwrap_or(resp.client_order_id)),
+            timestamp: resp.time.or(resp.transact_time).or(resp.update_time).unwrap_or_default(),
+            status: util::get_unified_order_status(&resp.status)?,
+            order_type: util::get_unified_order_type(&resp.order_type)?,
+            time_in_force: util::get_unified_time_in_force(&resp.time_in_force).ok(),
+            side: Some(util::get_unified_order_side(&resp.side)?),
+            price: Some(price).filter(|price| *price > ZERO),
+            average: (filled > ZERO).then(|| cost / filled),
+            amount,
+            filled: Some(filled),
+            remaining: Some(amount - filled),
+            cost: Some(cost),
+            margin_mode,
+            ..Default::default()
+        })
+    }
+}
+
+
+
+/// `executionReport` event of the user data stream
+#[derive(Serialize, Deserialize)]
+struct WatchOrderResponse {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "c")]
+    client_order_id: String,
+    /// client order id of the canceled order, empty otherwise
+    #[serde(rename = "C")]
+    original_client_order_id: String,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "o")]
+    order_type: String,
+    #[serde(rename = "f")]
+    time_in_force: String,
+    #[serde(rename = "q")]
+    quantity: String,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "x")]
+    execution_type: String,
+    #[serde(rename = "X")]
+    status: String,
+    #[serde(rename = "i")]
+    order_id: i64,
+    #[serde(rename = "l")]
+    last_executed_quantity: String,
+    #[serde(rename = "z")]
+    cumulative_filled_quantity: String,
+    #[serde(rename = "L")]
+    last_executed_price: String,
+    #[serde(rename = "n")]
+    commission: String,
+    #[serde(rename = "N")]
+    commission_asset: Option<String>,
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    #[serde(rename = "t")]
+    trade_id: i64,
+    #[serde(rename = "m")]
+    is_maker: bool,
+    #[serde(rename = "O")]
+    order_creation_time: i64,
+    #[serde(rename = "Z")]
+    cumulative_quote_quantity: String,
+}
+
+impl TryFrom<Vec<u8>> for WatchOrderResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(message.as_slice());
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+impl WatchOrderResponse {
+    fn to_order(&self, market: &Market) -> Result<Order> {
+        let amount: Number = self.quantity.parse()?;
+        let filled: Number = self.cumulative_filled_quantity.parse()?;
+        let cost: Number = self.cumulative_quote_quantity.parse()?;
+        let price: Number = self.price.parse()?;
+        let client_order_id = match self.original_client_order_id.is_empty() {
+            true => self.client_order_id.clone(),
+            false => self.original_client_order_id.clone(),
+        };
+        Ok(Order {
+            id: Some(self.order_id.to_string()),
+            client_order_id: Some(client_order_id),
+            timestamp: self.order_creation_time,
+            last_trade_timestamp: Some(self.transaction_time).filter(|_| self.execution_type == "TRADE"),
+            status: util::get_unified_order_status(&self.status)?,
+            market: market.clone(),
+            order_type: util::get_unified_order_type(&self.order_type)?,
+            time_in_force: util::get_unified_time_in_force(&self.time_in_force).ok(),
+            side: Some(util::get_unified_order_side(&self.side)?),
+            price: Some(price).filter(|price| *price > ZERO),
+            average: (filled > ZERO).then(|| cost / filled),
+            amount,
+            filled: Some(filled),
+            remaining: Some(amount - filled),
+            cost: Some(cost),
+            ..Default::default()
+        })
+    }
+
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price: Number = self.last_executed_price.parse()?;
+        let amount: Number = self.last_executed_quantity.parse()?;
+        let fee = match &self.commission_asset {
+            Some(currency) => Some(OrderFee {
+                currency: util::to_unified_asset(currency),
+                cost: self.commission.parse()?,
+                rate: None,
+            }),
+            None => None,
+        };
+        Ok(Trade::new(
+            self.trade_id.to_string(),
+            self.transaction_time,
+            market.clone(),
+            Some(self.order_id.to_string()),
+            Some(util::get_unified_order_type(&self.order_type)?),
+            Some(util::get_unified_order_side(&self.side)?),
+            Some(self.is_maker),
+            price, amount,
+            price * amount,
+            fee, None,
+        ))
+    }
+}
+
+
+/// `outboundAccountPosition` event of the user data stream
+#[derive(Serialize, Deserialize)]
+struct WatchBalanceResponse {
+    #[serde(rename = "E")]
+    event_time: i64,
+    #[serde(rename = "B")]
+    balances: Vec<WatchBalanceAssetResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchBalanceAssetResponse {
+    #[serde(rename = "a")]
+    asset: String,
+    #[serde(rename = "f")]
+    free: String,
+    #[serde(rename = "l")]
+    locked: String,
+}
+
+impl TryFrom<Vec<u8>> for WatchBalanceResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(message.as_slice());
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+impl WatchBalanceResponse {
+    fn to_balance(&self) -> Result<Balance> {
+        let mut items = vec![];
+        for balance in &self.balances {
+            let free = parse_float64(&balance.free)?;
+            let used = parse_float64(&balance.locked)?;
+            items.push(BalanceItem {
+                currency: util::to_unified_asset(&balance.asset),
+                market: None,
+                free,
+                used,
+                total: free + used,
+                debt: 0.0,
+            });
+        }
+        Ok(Balance {
+            timestamp: Some(self.event_time),
+            items,
+        })
+    }
+}
+
+
 #[derive(Serialize, Deserialize)]
 struct WatchCommonResponse {
     result: Option<String>,
@@ -468,19 +1304,37 @@ struct WatchCommonResponse {
     /// for best bid and ask stream
     #[serde(rename = "u")]
     order_book_update_id: Option<i64>,
+}
+
+impl TryFrom<Vec<u8>> for WatchCommonResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(message.as_slice());
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct WatchBookTickerResponse {
+    #[serde(rename = "u")]
+    order_book_update_id: i64,
     #[serde(rename = "s")]
-    symbol: Option<String>,
+    symbol: String,
     #[serde(rename = "b")]
-    bid_price: Option<String>,
+    bid_price: String,
     #[serde(rename = "B")]
-    bid_quantity: Option<String>,
+    bid_quantity: String,
     #[serde(rename = "a")]
-    ask_price: Option<String>,
+    ask_price: String,
     #[serde(rename = "A")]
-    ask_quantity: Option<String>,
+    ask_quantity: String,
 }
 
-impl TryFrom<Vec<u8>> for WatchCommonResponse {
+impl TryFrom<Vec<u8>> for WatchBookTickerResponse {
     type Error = Error;
 
     fn try_from(message: Vec<u8>) -> Result<Self> {
@@ -506,6 +1360,12 @@ impl From<Vec<u8>> for WatchPartialOrderBookResponse {
     }
 }
 
+impl WatchPartialOrderBookResponse {
+    fn units(&self) -> Result<(Vec<OrderBookUnit>, Vec<OrderBookUnit>)> {
+        Ok((to_order_book_units(&self.bids)?, to_order_book_units(&self.asks)?))
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct WatchDiffOrderBookResponse {
     #[serde(rename = "e")]
@@ -525,12 +1385,33 @@ struct WatchDiffOrderBookResponse {
 }
 
 
-impl From<Vec<u8>> for WatchDiffOrderBookResponse {
-    fn from(message: Vec<u8>) -> Self {
-        serde_json::from_slice(&message).unwrap()
+impl TryFrom<Vec<u8>> for WatchDiffOrderBookResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(message.as_slice());
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
     }
 }
 
+impl WatchDiffOrderBookResponse {
+    fn units(&self) -> Result<(Vec<OrderBookUnit>, Vec<OrderBookUnit>)> {
+        Ok((to_order_book_units(&self.bids)?, to_order_book_units(&self.asks)?))
+    }
+}
+
+/// Parses `[price, quantity]` levels.
+fn to_order_book_units<L: AsRef<[String]>>(levels: &[L]) -> Result<Vec<OrderBookUnit>> {
+    levels.iter()
+        .map(|level| match level.as_ref() {
+            [price, amount, ..] => Ok((parse_number(price)?, parse_number(amount)?).into()),
+            _ => Err(Error::InvalidOrderBook(format!("Invalid level {:?}", level.as_ref()))),
+        })
+        .collect()
+}
+
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct FetchIsolatedAccountAssetResponse {
@@ -632,6 +1513,19 @@ struct FetchMarketsResponse {
     pub symbols: Vec<FetchMarketsSymbolResponse>,
 }
 
+impl FetchMarketsResponse {
+    /// Applies the announced request weight limits to the client. The defaults are kept when none
+    /// is announced.
+    fn update_rate_limits(&self, http_client: &HttpClient) {
+        let rate_limits: Vec<_> = self.rate_limits.iter()
+            .filter_map(|r| util::get_request_weight_limit(&r.rate_limit_type, &r.interval, r.interval_num, r.limit))
+            .collect();
+        if !rate_limits.is_empty() {
+            http_client.rate_limiter().set_rate_limits(rate_limits);
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct FetchTickersResponse {
     pub symbol: String,
@@ -739,6 +1633,7 @@ impl Into<Result<Market>> for &FetchMarketsSymbolResponse {
             price: Some(self.base_asset_precision),
             cost: None,
         };
+        let (mut tick_size, mut step_size) = (None, None);
 
         if let Some(filters) = &self.filters {
             for filter in filters.iter() {
@@ -747,15 +1642,17 @@ impl Into<Result<Market>> for &FetchMarketsSymbolResponse {
                         let min = filter.min_price.as_ref().ok_or_else(|| Error::MissingField("min_price".into()))?.parse::<f64>()?;
                         let max = filter.max_price.as_ref().ok_or_else(|| Error::MissingField("max_price".into()))?.parse::<f64>()?;
                         limit.price = Some(Range { min, max });
-                        let tick_size = filter.tick_size.as_ref().ok_or_else(|| Error::MissingField("tick_size".into()))?;
-                        precision.price = Some(into_precision(tick_size.clone())?);
+                        let tick = filter.tick_size.as_ref().ok_or_else(|| Error::MissingField("tick_size".into()))?;
+                        precision.price = Some(into_precision(tick.clone())?);
+                        tick_size = Some(parse_number(tick)?);
                     }
                     "LOT_SIZE" => {
                         let min = filter.min_qty.as_ref().ok_or_else(|| Error::MissingField("min_qty".into()))?.parse::<f64>()?;
                         let max = filter.max_qty.as_ref().ok_or_else(|| Error::MissingField("max_qty".into()))?.parse::<f64>()?;
                         limit.amount = Some(Range { min, max });
-                        let step_size = filter.step_size.as_ref().ok_or_else(|| Error::MissingField("step_size".into()))?;
-                        precision.amount = Some(into_precision(step_size.clone())?);
+                        let step = filter.step_size.as_ref().ok_or_else(|| Error::MissingField("step_size".into()))?;
+                        precision.amount = Some(into_precision(step.clone())?);
+                        step_size = Some(parse_number(step)?);
                     }
                     "MIN_NOTIONAL" => {
                         let min = filter.min_notional.as_ref().ok_or_else(|| Error::MissingField("min_notional".into()))?.parse::<f64>()?;
@@ -781,6 +1678,8 @@ impl Into<Result<Market>> for &FetchMarketsSymbolResponse {
             active,
             market_type,
             precision: Some(precision),
+            tick_size,
+            step_size,
             limit: Some(limit),
             ..Default::default()
         })
@@ -867,9 +1766,290 @@ struct ErrorResponse {
 
 #[cfg(test)]
 mod test {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
     use crate::{Binance, Exchange, FetchTickersParamsBuilder, PropertiesBuilder};
+    use crate::client::{HttpClientBuilder, WsClient};
+    use crate::error::{Error, OrderBookError};
     use crate::exchange::params::FetchBalanceParamsBuilder;
-    use crate::model::MarginMode;
+    use crate::exchange::{StreamItem, Unifier};
+    use crate::model::{MarginMode, Market, Ohlcv, Order, OrderSide, OrderStatus, OrderType};
+    use crate::util::channel::ChannelConfig;
+    use crate::util::number;
+    use crate::util::order_book::LocalOrderBook;
+
+    use super::{apply_diff, parse_user_data, util, CreateOrderResponse, FetchOrderResponse, SymbolBook, WatchDiffOrderBookResponse, WatchPartialOrderBookResponse};
+
+    fn diff(first_update_id: i64, final_update_id: i64, bid: (&str, &str)) -> WatchDiffOrderBookResponse {
+        WatchDiffOrderBookResponse {
+            event_type: "depthUpdate".to_string(),
+            event_time: final_update_id,
+            symbol: "BTCUSDT".to_string(),
+            first_update_id,
+            final_update_id,
+            bids: vec![[bid.0.to_string(), bid.1.to_string()]],
+            asks: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse_ohlcv() {
+        let message = r#"[[1499040000000, "0.01634790", "0.80000000", "0.01575800", "0.01577100", "148976.11427815", 1499644799999, "2434.19055334", 308, "1756.87402397", "28.46694368", "0"]]"#;
+        let rows: Vec<util::FetchOhlcvResponse> = serde_json::from_str(message).unwrap();
+        let ohlcv: Ohlcv = rows[0].clone().try_into().unwrap();
+        assert_eq!(ohlcv.timestamp, 1499040000000);
+        assert_eq!(ohlcv.open, 0.0163479);
+        assert_eq!(ohlcv.high, 0.8);
+        assert_eq!(ohlcv.low, 0.015758);
+        assert_eq!(ohlcv.close, 0.015771);
+        assert_eq!(ohlcv.volume, 148976.11427815);
+        assert!(ohlcv.is_closed);
+    }
+
+    #[test]
+    fn test_parse_combined_stream() {
+        let market = Market { base: "BNB".into(), quote: "USDT".into(), ..Default::default() };
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"BNBUSDT".to_string());
+        let exchange = Binance::new(PropertiesBuilder::default().build().unwrap()).unwrap();
+        let parse = exchange.exchange_base.stream_parser;
+
+        // the raw endpoint is still supported for custom endpoints
+        let book_ticker = r#"{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+        let combined = format!(r#"{{"stream":"bnbusdt@bookTicker","data":{}}}"#, book_ticker);
+        for message in [book_ticker.to_string(), combined] {
+            match parse(message.as_bytes(), &unifier).unwrap() {
+                StreamItem::OrderBook(Ok(book)) => {
+                    assert_eq!(book.market.base, "BNB");
+                    assert_eq!(book.bids[0].price, number("25.3519"));
+                    assert_eq!(book.last_update_id, Some(400900217));
+                }
+                item => panic!("unexpected item: {:?}", item),
+            }
+        }
+
+        let kline = r#"{"stream":"bnbusdt@kline_1m","data":{"e":"kline","E":1672515782136,"s":"BNBUSDT","k":{"t":1672515780000,"T":1672515839999,"s":"BNBUSDT","i":"1m","o":"25.1","c":"25.2","h":"25.3","l":"25.0","v":"1000","x":false}}}"#;
+        match parse(kline.as_bytes(), &unifier).unwrap() {
+            StreamItem::Ohlcv(Ok(ohlcv)) => {
+                assert_eq!(ohlcv.timestamp, 1672515780000);
+                assert!(!ohlcv.is_closed);
+            }
+            item => panic!("unexpected item: {:?}", item),
+        }
+        assert!(matches!(parse(br#"{"result":null,"id":7}"#, &unifier), Ok(StreamItem::Subscribed(7))));
+        assert!(matches!(parse(br#"{"code":2,"msg":"Invalid request","id":8}"#, &unifier), Err(crate::WatchError::SubscriptionRejected(8, _))));
+        assert_eq!(util::combined_endpoint("wss://stream.binance.com:9443/ws"), "wss://stream.binance.com:9443/stream");
+    }
+
+    #[test]
+    fn test_sandbox_margin_mode() {
+        let props = PropertiesBuilder::default().sandbox(true).build().unwrap();
+        let exchange = Binance::new(props).unwrap();
+        assert!(exchange.check_margin_mode(&None).is_ok());
+        assert!(matches!(exchange.check_margin_mode(&Some(MarginMode::Cross)), Err(Error::UnsupportedInSandbox(_))));
+
+        let exchange = Binance::new(PropertiesBuilder::default().build().unwrap()).unwrap();
+        assert!(exchange.check_margin_mode(&Some(MarginMode::Isolated)).is_ok());
+    }
+
+    #[test]
+    fn test_order_endpoint() {
+        let exchange = Binance::new(PropertiesBuilder::default().build().unwrap()).unwrap();
+        // create_order, cancel_order and fetch_order use `order`, the order lists their own path
+        for path in ["order", "allOrders", "openOrders"] {
+            assert_eq!(exchange.order_endpoint(path, &None).unwrap(), (format!("/api/v3/{}", path), None));
+            assert_eq!(exchange.order_endpoint(path, &Some(MarginMode::Cross)).unwrap(), (format!("/sapi/v1/margin/{}", path), Some("FALSE")));
+            assert_eq!(exchange.order_endpoint(path, &Some(MarginMode::Isolated)).unwrap(), (format!("/sapi/v1/margin/{}", path), Some("TRUE")));
+        }
+
+        let exchange = Binance::new(PropertiesBuilder::default().sandbox(true).build().unwrap()).unwrap();
+        assert_eq!(exchange.order_endpoint("order", &None).unwrap(), ("/api/v3/order".to_string(), None));
+        assert!(matches!(exchange.order_endpoint("order", &Some(MarginMode::Cross)), Err(Error::UnsupportedInSandbox(_))));
+    }
+
+    #[test]
+    fn test_parse_order_status() {
+        for (status, expected) in [("PENDING_NEW", OrderStatus::Open), ("EXPIRED_IN_MATCH", OrderStatus::Expired)] {
+            let message = format!(r#"{{"symbol": "BNBBTC", "orderId": 28, "clientOrderId": "6gCrw2kRUAF9CvJDGP16IP", "price": "1.00000000", "origQty": "10.00000000", "executedQty": "1.00000000", "cummulativeQuoteQty": "1.00000000", "status": "{}", "timeInForce": "GTC", "type": "LIMIT", "side": "SELL"}}"#, status);
+            let resp: FetchOrderResponse = serde_json::from_str(&message).unwrap();
+            let order: Order = resp.try_into().unwrap();
+            assert_eq!(order.status, expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_canceled_order() {
+        let message = r#"{"symbol": "BNBBTC", "isIsolated": true, "orderId": 28, "origClientOrderId": "msXkySR3u5uYwpvRMFsi3u", "clientOrderId": "6gCrw2kRUAF9CvJDGP16IP", "price": "1.00000000", "origQty": "10.00000000", "executedQty": "8.00000000", "cummulativeQuoteQty": "8.00000000", "status": "CANCELED", "timeInForce": "GTC", "type": "LIMIT", "side": "SELL"}"#;
+        let resp: FetchOrderResponse = serde_json::from_str(message).unwrap();
+        let order: Order = resp.try_into().unwrap();
+        assert_eq!(order.id, Some("28".to_string()));
+        assert_eq!(order.client_order_id, Some("msXkySR3u5uYwpvRMFsi3u".to_string()));
+        assert_eq!(order.status, OrderStatus::Canceled);
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.margin_mode, MarginMode::Isolated);
+        assert_eq!(order.filled, Some(number("8")));
+        assert_eq!(order.remaining, Some(number("2")));
+        assert_eq!(order.average, Some(number("1")));
+    }
+
+    #[tokio::test]
+    async fn test_renew_listen_key() {
+        let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_port = http.local_addr().unwrap().port();
+        let http_server = tokio::spawn(async move {
+            let (mut tcp, _) = http.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = tcp.read(&mut buf).await.unwrap();
+            let body = r#"{"listenKey":"key2"}"#;
+            let resp = format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", body.len(), body);
+            tcp.write_all(resp.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).lines().next().unwrap().to_string()
+        });
+
+        let ws = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_endpoint = format!("ws://{}", ws.local_addr().unwrap());
+        let ws_server = tokio::spawn(async move {
+            let mut paths = vec![];
+            for message in [r#"{"e":"listenKeyExpired","E":1576653824250,"listenKey":"key1"}"#, r#"{"e":"unknown"}"#] {
+                let (tcp, _) = ws.accept().await.unwrap();
+                // the request line of the handshake names the listen key
+                let mut buf = vec![0; 4096];
+                let n = tcp.peek(&mut buf).await.unwrap();
+                paths.push(String::from_utf8_lossy(&buf[..n]).split(' ').nth(1).unwrap().to_string());
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                ws.send(Message::Text(message.to_string())).await.unwrap();
+                tokio::spawn(async move { while let Some(Ok(_)) = ws.next().await {} });
+            }
+            paths
+        });
+
+        let endpoint = util::ListenKeyEndpoint {
+            http_client: HttpClientBuilder::new().host("http://127.0.0.1".into()).port(http_port).build().unwrap(),
+            path: "/api/v3/userDataStream".into(),
+            query: vec![],
+            api_key: "key".into(),
+            keyed: true,
+        };
+        let connect = move |listen_key: &str| WsClient::new(&format!("{}/{}", ws_endpoint, listen_key), parse_user_data, Unifier::new());
+        let mut client = connect("key1");
+        client.connect().await.unwrap();
+        let (tx, mut receiver) = ChannelConfig::new(Some(10), None).bounded();
+        tokio::spawn(util::run_user_data_stream(client, "key1".into(), endpoint, connect, |_| true, tx));
+
+        // the expiry is not forwarded, the stream continues on the new key
+        assert!(matches!(receiver.receive().await, Ok(StreamItem::Reconnected)));
+        assert!(matches!(receiver.receive().await, Ok(StreamItem::Unknown(_))));
+        assert_eq!(http_server.await.unwrap(), "POST /api/v3/userDataStream HTTP/1.1");
+        assert_eq!(ws_server.await.unwrap(), vec!["/key1", "/key2"]);
+    }
+
+    #[test]
+    fn test_parse_market_order() {
+        let message = r#"{"symbol": "BTCUSDT", "orderId": 28, "clientOrderId": "6gCrw2kRUAF9CvJDGP16IP", "transactTime": 1507725176595, "price": "0.00000000", "origQty": "10.00000000", "executedQty": "10.00000000", "cummulativeQuoteQty": "10.00000000", "status": "FILLED", "timeInForce": "GTC", "type": "MARKET", "isIsolated": false, "side": "SELL", "selfTradePreventionMode": "NONE"}"#;
+        let resp: CreateOrderResponse = serde_json::from_str(message).unwrap();
+        let order: Order = resp.try_into().unwrap();
+        assert_eq!(order.price, None);
+    }
+
+    #[test]
+    fn test_parse_unfilled_order() {
+        let message = r#"{"symbol": "BNBBTC", "orderId": 28, "clientOrderId": "6gCrw2kRUAF9CvJDGP16IP", "price": "1.00000000", "origQty": "10.00000000", "executedQty": "0", "cummulativeQuoteQty": "0", "status": "NEW", "timeInForce": "GTC", "type": "LIMIT", "side": "SELL"}"#;
+        let resp: FetchOrderResponse = serde_json::from_str(message).unwrap();
+        let order: Order = resp.try_into().unwrap();
+        assert_eq!(order.filled, Some(number("0")));
+        assert_eq!(order.average, None);
+
+        let market = Market { base: "ETH".into(), quote: "BTC".into(), ..Default::default() };
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"ETHBTC".to_string());
+        let message = r#"{"e": "executionReport", "E": 1499405658658, "s": "ETHBTC", "c": "mUvoqJxFIILMdfAW5iGSOW", "S": "BUY", "o": "LIMIT", "f": "GTC", "q": "1.00000000", "p": "0.10264410", "P": "0.00000000", "F": "0.00000000", "g": -1, "C": "", "x": "NEW", "X": "NEW", "r": "NONE", "i": 4293153, "l": "0", "z": "0", "L": "0", "n": "0", "N": null, "T": 1499405658657, "t": -1, "I": 8641984, "w": true, "m": false, "M": false, "O": 1499405658600, "Z": "0", "Y": "0", "Q": "0"}"#;
+        match &parse_user_data(message.as_bytes(), &unifier).unwrap()[0] {
+            StreamItem::Order(Ok(order)) => assert_eq!(order.average, None),
+            item => panic!("unexpected item {:?}", item),
+        }
+    }
+
+    #[test]
+    fn test_parse_execution_report() {
+        let market = Market { base: "ETH".into(), quote: "BTC".into(), ..Default::default() };
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"ETHBTC".to_string());
+
+        let message = r#"{"e": "executionReport", "E": 1499405658658, "s": "ETHBTC", "c": "mUvoqJxFIILMdfAW5iGSOW", "S": "BUY", "o": "LIMIT", "f": "GTC", "q": "1.00000000", "p": "0.10264410", "P": "0.00000000", "F": "0.00000000", "g": -1, "C": "", "x": "TRADE", "X": "PARTIALLY_FILLED", "r": "NONE", "i": 4293153, "l": "0.40000000", "z": "0.40000000", "L": "0.10000000", "n": "0.00040000", "N": "ETH", "T": 1499405658657, "t": 718, "I": 8641984, "w": false, "m": true, "M": false, "O": 1499405658600, "Z": "0.04000000", "Y": "0.04000000", "Q": "0.00000000"}"#;
+        let items = parse_user_data(message.as_bytes(), &unifier).unwrap();
+        assert_eq!(items.len(), 2);
+        match &items[0] {
+            StreamItem::Order(Ok(order)) => {
+                assert_eq!(order.id, Some("4293153".to_string()));
+                assert_eq!(order.status, OrderStatus::Open);
+                assert_eq!(order.timestamp, 1499405658600);
+                assert_eq!(order.filled, Some(number("0.4")));
+                assert_eq!(order.cost, Some(number("0.04")));
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+        match &items[1] {
+            StreamItem::MyTrade(Ok(trade)) => {
+                assert_eq!(trade.id, "718");
+                assert_eq!(trade.side, Some(OrderSide::Buy));
+                assert_eq!(trade.price, number("0.1"));
+                assert_eq!(trade.amount, number("0.4"));
+                assert_eq!(trade.fee.as_ref().map(|fee| fee.cost), Some(number("0.0004")));
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+    }
+
+    #[test]
+    fn test_apply_diff() {
+        let mut book = LocalOrderBook::new(Market::default());
+        assert_eq!(apply_diff(&mut book, &diff(1, 2, ("100", "1"))), Err(Error::SynchronizationError));
+
+        book.reset(&[(number("100"), number("1")).into()], &[(number("101"), number("1")).into()], 10);
+        // already contained in the snapshot
+        assert_eq!(apply_diff(&mut book, &diff(5, 10, ("100", "5"))), Ok(false));
+        // first event straddles the snapshot
+        assert_eq!(apply_diff(&mut book, &diff(8, 12, ("100", "2"))), Ok(true));
+        assert_eq!(apply_diff(&mut book, &diff(13, 15, ("99", "3"))), Ok(true));
+        // 16 is missing
+        assert_eq!(apply_diff(&mut book, &diff(17, 18, ("98", "1"))), Err(Error::SynchronizationError));
+
+        let order_book = book.to_order_book();
+        assert_eq!(order_book.last_update_id, Some(15));
+        assert_eq!(order_book.bids, vec![(number("100"), number("2")).into(), (number("99"), number("3")).into()]);
+    }
+
+    #[test]
+    fn test_symbol_book() {
+        let snapshot = |last_update_id: i64| Ok(WatchPartialOrderBookResponse {
+            last_update_id,
+            bids: vec![vec!["100".to_string(), "1".to_string()]],
+            asks: vec![vec!["101".to_string(), "1".to_string()]],
+        });
+        let mut book = SymbolBook::new(Market::default());
+        let (items, fetch) = book.on_diff(diff(11, 12, ("100", "2")));
+        assert!(items.is_empty() && fetch);
+        // buffered while the snapshot is fetched
+        assert!(!book.on_diff(diff(13, 14, ("99", "3"))).1);
+
+        // older than the buffered diffs
+        let (items, fetch) = book.on_snapshot(snapshot(5));
+        assert!(items.is_empty() && fetch);
+        let (items, fetch) = book.on_snapshot(snapshot(11));
+        assert!(!fetch);
+        let order_book = items[0].as_ref().unwrap();
+        assert_eq!(order_book.last_update_id, Some(14));
+        assert_eq!(order_book.bids, vec![(number("100"), number("2")).into(), (number("99"), number("3")).into()]);
+
+        // a gap invalidates the book, and snapshots requested before are ignored
+        let generation = book.generation;
+        let (items, fetch) = book.on_diff(diff(20, 21, ("98", "1")));
+        assert!(matches!(items[..], [Err(OrderBookError::SynchronizationError(_))]) && fetch);
+        assert_ne!(book.generation, generation);
+    }
 
     #[tokio::test]
     async fn test_fetch_balance() {
@@ -0,0 +1,327 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::parse_float64;
+use crate::util::channel::Receiver;
+
+use super::futures::{BinanceFutures, FuturesProduct, LeverageBracket};
+use super::util;
+
+pub struct BinanceCoinm {
+    futures: BinanceFutures,
+}
+
+static COINM: FuturesProduct = FuturesProduct {
+    host: "https://dapi.binance.com",
+    ws_endpoint: "wss://dstream.binance.com/ws",
+    sandbox_host: "https://testnet.binancefuture.com",
+    sandbox_ws_endpoint: "wss://dstream.binancefuture.com/ws",
+    prefix: "/dapi",
+    account_path: "/v1/account",
+    leverage_bracket_path: "/v2/leverageBracket",
+    contract_type: ContractType::Inverse,
+    weight_calculator: request_weight,
+};
+
+
+/// Request weights of the endpoints in use.
+fn request_weight(_: &Method, path: &str, query: &str) -> u32 {
+    let limit = util::get_query_value(query, "limit").and_then(|l| l.parse::<u32>().ok());
+    match path {
+        "/dapi/v1/depth" => match limit.unwrap_or(500) {
+            0..=50 => 2,
+            51..=100 => 5,
+            101..=500 => 10,
+            _ => 20,
+        },
+        "/dapi/v1/ticker/24hr" | "/dapi/v1/openOrders" => match util::get_query_value(query, "symbol") {
+            Some(_) => 1,
+            None => 40,
+        },
+        "/dapi/v1/klines" => match limit.unwrap_or(500) {
+            0..=99 => 1,
+            100..=499 => 2,
+            500..=1000 => 5,
+            _ => 10,
+        },
+        "/dapi/v1/aggTrades" | "/dapi/v1/allOrders" => 20,
+        "/dapi/v1/account" => 5,
+        _ => 1,
+    }
+}
+
+impl BinanceCoinm {
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        Ok(Self {
+            futures: BinanceFutures::new(props, &COINM)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Exchange for BinanceCoinm {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        self.futures.load_markets().await
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        self.futures.fetch_markets().await
+    }
+
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        self.futures.fetch_tickers(params).await
+    }
+
+    async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        self.futures.fetch_trades(params).await
+    }
+
+    async fn fetch_ohlcv(&self, params: FetchOhlcvParams) -> FetchOhlcvResult<Vec<Ohlcv>> {
+        self.futures.fetch_ohlcv(params).await
+    }
+
+    async fn watch_ohlcv(&self, params: WatchOhlcvParams) -> WatchOhlcvResult<Receiver> {
+        self.futures.watch_ohlcv(params).await
+    }
+
+    async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
+        self.futures.watch_trades(params).await
+    }
+
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        self.futures.watch_order_book(params).await
+    }
+
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        self.futures.create_order(params).await
+    }
+
+    async fn cancel_order(&self, params: CancelOrderParams) -> CancelOrderResult<Order> {
+        self.futures.cancel_order(params).await
+    }
+
+    async fn fetch_order(&self, params: FetchOrderParams) -> FetchOrdersResult<Order> {
+        self.futures.fetch_order(params).await
+    }
+
+    async fn fetch_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        self.futures.fetch_orders(params).await
+    }
+
+    async fn fetch_open_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        self.futures.fetch_open_orders(params).await
+    }
+
+    async fn fetch_closed_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        self.futures.fetch_closed_orders(params).await
+    }
+
+    async fn watch_balance(&self, params: WatchBalanceParams) -> WatchBalanceResult<Receiver> {
+        self.futures.watch_balance(params).await
+    }
+
+    async fn watch_my_trades(&self, params: WatchMyTradesParams) -> WatchMyTradesResult<Receiver> {
+        self.futures.watch_my_trades(params).await
+    }
+
+    async fn watch_orders(&self, params: WatchOrdersParams) -> WatchOrdersResult<Receiver> {
+        self.futures.watch_orders(params).await
+    }
+
+    async fn watch_positions(&self, params: WatchPositionsParams) -> WatchPositionsResult<Receiver> {
+        self.futures.watch_positions(params).await
+    }
+
+    async fn fetch_balance(&self, params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        self.futures.fetch_balance(params).await
+    }
+
+    async fn fetch_positions(&self, _params: FetchPositionsParams) -> FetchPositionsResult<Vec<Position>> {
+        if self.futures.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let items: Vec<FetchPositionsResponse> = self.futures.get_signed("/dapi/v1/positionRisk").await?;
+
+        let mut ret = vec![];
+        for item in items {
+            let market = match self.futures.exchange_base.unifier.get_market(&item.symbol) {
+                Some(market) => market,
+                None => continue,
+            };
+            let brackets = self.futures.leverage_brackets.as_ref().and_then(|leverage_brackets| leverage_brackets.get(&market));
+            if let Some(position) = to_position(item, market, brackets)? {
+                ret.push(position);
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Builds a position from the position risk of the exchange. Contracts are worth a fixed amount of
+/// the quote currency, so the notional, pnl and margins are computed in the base currency, which
+/// the contracts are settled in. Returns `None` for empty positions.
+fn to_position(item: FetchPositionsResponse, market: Market, brackets: Option<&Vec<LeverageBracket>>) -> Result<Option<Position>> {
+    let amount = parse_float64(&item.position_amt)?;
+    let (side, sign) = match amount {
+        a if a > 0.0 => (PositionSide::Long, 1f64),
+        a if a < 0.0 => (PositionSide::Short, -1f64),
+        _ => return Ok(None),
+    };
+    let contracts = amount.abs();
+    let entry_price = parse_float64(&item.entry_price)?;
+    let mark_price = parse_float64(&item.mark_price)?;
+    let liquidation_price = parse_float64(&item.liquidation_price)?;
+    let leverage = parse_float64(&item.leverage)?;
+    if entry_price <= 0.0 || mark_price <= 0.0 {
+        return Err(Error::InvalidResponse(format!("invalid prices of position {}", item.symbol)));
+    }
+
+    // value of the position in the quote currency
+    let size = contracts * market.contract_size.unwrap_or(1.0);
+    let notional = size / mark_price;
+    let unrealized_pnl = sign * size * (1f64 / entry_price - 1f64 / mark_price);
+
+    // the tiers are bounded by the position size in base currency
+    let maintenance_margin_percent = brackets
+        .and_then(|brackets| brackets.iter().rev().find(|b| notional >= b.floor))
+        .map(|b| b.maintenance_margin_ratio)
+        .ok_or_else(|| Error::InvalidResponse("maintenance margin ratio is not found".into()))?;
+    let maintenance_margin = notional * maintenance_margin_percent;
+
+    let margin_mode = match item.margin_type.as_str() {
+        "cross" => MarginMode::Cross,
+        _ => MarginMode::Isolated,
+    };
+    let collateral = match margin_mode {
+        // at the liquidation price the wallet balance plus the pnl equals the maintenance margin
+        MarginMode::Cross if liquidation_price > 0.0 => {
+            maintenance_margin_percent * size / liquidation_price - sign * size * (1f64 / entry_price - 1f64 / liquidation_price)
+        }
+        MarginMode::Cross => 0.0,
+        MarginMode::Isolated => parse_float64(&item.isolated_margin)?,
+    };
+
+    let initial_margin_percent = 1f64 / leverage;
+    let initial_margin = notional * initial_margin_percent;
+    let margin_ratio = match collateral > 0.0 {
+        true => maintenance_margin / collateral + 5e-5,
+        false => 0.0,
+    };
+
+    Ok(Some(Position {
+        contract_size: market.contract_size,
+        market,
+        side,
+        contracts,
+        unrealized_pnl,
+        leverage,
+        liquidation_price,
+        collateral,
+        notional,
+        mark_price,
+        entry_price,
+        timestamp: item.update_time,
+
+        initial_margin,
+        initial_margin_percent,
+        maintenance_margin_percent,
+        maintenance_margin,
+
+        margin_ratio,
+        margin_mode,
+        is_hedged: item.position_side != "BOTH",
+        percentage: unrealized_pnl / initial_margin * 100f64,
+        ..Default::default()
+    }))
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchPositionsResponse {
+    pub symbol: String,
+    pub position_amt: String,
+    pub entry_price: String,
+    pub mark_price: String,
+    pub un_realized_profit: String,
+    pub liquidation_price: String,
+    pub leverage: String,
+    pub max_qty: Option<String>,
+    pub margin_type: String,
+    pub isolated_margin: String,
+    pub is_auto_add_margin: String,
+    pub position_side: String,
+    pub notional_value: Option<String>,
+    pub update_time: i64,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BinanceCoinm, PropertiesBuilder};
+    use crate::model::{Market, MarketType, PositionSide};
+
+    use super::{FetchPositionsResponse, LeverageBracket, to_position};
+
+    #[test]
+    fn test_to_position() {
+        let market = Market { base: "BTC".into(), quote: "USD".into(), settle: Some("BTC".into()), market_type: MarketType::Swap, contract_size: Some(100.0), ..Default::default() };
+        let brackets = vec![
+            LeverageBracket { floor: 0.0, maintenance_margin_ratio: 0.004 },
+            LeverageBracket { floor: 5.0, maintenance_margin_ratio: 0.005 },
+        ];
+        let item = FetchPositionsResponse {
+            symbol: "BTCUSD_PERP".into(),
+            position_amt: "10".into(),
+            entry_price: "50000".into(),
+            mark_price: "60000".into(),
+            liquidation_price: "40000".into(),
+            leverage: "20".into(),
+            margin_type: "cross".into(),
+            position_side: "BOTH".into(),
+            ..Default::default()
+        };
+
+        // 10 contracts of 100 USD are worth 1000 / 60000 BTC at the mark price
+        let position = to_position(item.clone(), market.clone(), Some(&brackets)).unwrap().unwrap();
+        assert_eq!(position.side, PositionSide::Long);
+        assert!((position.notional - 1000.0 / 60000.0).abs() < 1e-12);
+        assert!((position.unrealized_pnl - 1000.0 * (1.0 / 50000.0 - 1.0 / 60000.0)).abs() < 1e-12);
+        assert_eq!(position.maintenance_margin_percent, 0.004);
+        assert!((position.initial_margin - 1000.0 / 60000.0 / 20.0).abs() < 1e-12);
+        assert!((position.collateral - 0.0051).abs() < 1e-12);
+
+        let short = FetchPositionsResponse { position_amt: "-10".into(), ..item.clone() };
+        let position = to_position(short, market.clone(), Some(&brackets)).unwrap().unwrap();
+        assert_eq!(position.side, PositionSide::Short);
+        assert!((position.unrealized_pnl + 1000.0 * (1.0 / 50000.0 - 1.0 / 60000.0)).abs() < 1e-12);
+
+        let empty = FetchPositionsResponse { position_amt: "0".into(), ..item };
+        assert!(to_position(empty, market, Some(&brackets)).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_auth() {
+        let api_key = "dbefbc809e3e83c283a984c3a1459732ea7db1360ca80c5c2c8867408d28cc83";
+        let secret = "2b5eb11e18796d12d88f13dc27dbbd02c2cc51ff7059765ed9821957d82bb4d9";
+
+        let props = PropertiesBuilder::default().api_key(Some(api_key.to_string())).secret(Some(secret.to_string())).build().expect("failed to create properties");
+        let exchange = BinanceCoinm::new(props).expect("failed to create exchange");
+        let params = vec![
+            ("symbol", "BTCUSD_PERP"),
+            ("side", "BUY"),
+            ("type", "LIMIT"),
+            ("quantity", "1"),
+            ("price", "9000"),
+            ("timeInForce", "GTC"),
+            ("recvWindow", "5000"),
+            ("timestamp", "1591702613943"),
+        ];
+        let result = exchange.futures.auth_map(Some(&params));
+        assert_eq!(result.unwrap(), "51a6c74c94676e083fbac605386ab8e550efce842129d9ac144c52b570054ed3");
+    }
+}
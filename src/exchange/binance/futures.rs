@@ -0,0 +1,1627 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+
+use crate::client::{EMPTY_QUERY, WeightCalculator};
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{f64_to_number, into_precision, parse_float64, parse_number, ZERO};
+use crate::util::channel::Receiver;
+
+use super::util;
+use super::util::StreamChannel;
+
+/// Futures API served by `BinanceFutures`. The USDⓈ-M and COIN-M APIs only differ in their hosts,
+/// the prefix of their paths and the contract type of their markets.
+pub(in super) struct FuturesProduct {
+    pub(in super) host: &'static str,
+    pub(in super) ws_endpoint: &'static str,
+    pub(in super) sandbox_host: &'static str,
+    pub(in super) sandbox_ws_endpoint: &'static str,
+    /// prefix of the rest paths, such as `/fapi`
+    pub(in super) prefix: &'static str,
+    /// account endpoint, without the prefix
+    pub(in super) account_path: &'static str,
+    /// leverage bracket endpoint, without the prefix
+    pub(in super) leverage_bracket_path: &'static str,
+    pub(in super) contract_type: ContractType,
+    pub(in super) weight_calculator: WeightCalculator,
+}
+
+/// Implementation shared by `BinanceUsdm` and `BinanceCoinm`.
+pub(in super) struct BinanceFutures {
+    pub(in super) exchange_base: ExchangeBase,
+    api_key: Option<String>,
+    secret: Option<String>,
+    recv_window: String,
+    pub(in super) leverage_brackets: Option<HashMap<Market, Vec<LeverageBracket>>>,
+    product: &'static FuturesProduct,
+}
+
+/// Maintenance margin tier. USDⓈ-M tiers are bounded by the notional value, COIN-M tiers by the
+/// position size in base currency.
+pub(in super) struct LeverageBracket {
+    pub(in super) floor: f64,
+    pub(in super) maintenance_margin_ratio: f64,
+}
+
+fn parse_error(message: String) -> Error {
+    match serde_json::from_str::<ErrorResponse>(&message) {
+        Ok(error) => {
+            match error.code {
+                -2019 => Error::InsufficientMargin(error.msg), // Margin is insufficient
+                -1013 => Error::InvalidAmount(error.msg), // Invalid amount
+                -1021 => Error::TimestampOutsideRecvWindow(error.msg), // Timestamp for this request is outside of the recvWindow
+                -1022 => Error::InvalidSignature(error.msg), // Signature for this request is not valid
+                -1100 => Error::InvalidParameters(error.msg), // Illegal characters found in a parameter
+                -1101 => Error::InvalidParameters(error.msg), // Too many parameters sent for this endpoint
+                -2011 => Error::OrderNotFound(error.msg), // Unknown order sent
+                -2013 => Error::OrderNotFound(error.msg), // Order does not exist
+                _ => Error::HttpError(error.msg),
+            }
+        }
+        Err(_) => Error::DeserializeJsonBody(message),
+    }
+}
+
+fn parse_stream(message: &[u8], unifier: &Unifier) -> WatchResult<StreamItem> {
+    let (stream, message) = util::split_combined_stream(message);
+    let common_message = WatchCommonResponse::try_from(message.to_vec())?;
+    if let (Some(code), Some(msg)) = (&common_message.code, &common_message.msg) { // error message
+        let message = format!("code={}, msg={}", code, msg);
+        // a rejected subscription request carries its id
+        return match common_message.id {
+            Some(id) => Err(Error::SubscriptionRejected(id, message).into()),
+            None => Err(Error::InvalidResponse(message).into()),
+        };
+    }
+    if common_message.id.is_some() { // subscription response
+        let id = common_message.id.ok_or(Error::InvalidResponse("id is not found".into()))?;
+        return Ok(StreamItem::Subscribed(id));
+    }
+    let channel = match stream {
+        Some(stream) => util::stream_channel(&stream),
+        None => util::event_channel(common_message.event_type.as_deref(), None),
+    };
+    match channel {
+        StreamChannel::AggTrade => {
+            let resp = WatchTradesResponse::try_from(message.to_vec())?;
+            let market = match unifier.get_market(&resp.symbol) {
+                Some(market) => market,
+                None => return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                    format!("Unknown market {}", resp.symbol), None,
+                )))),
+            };
+            let order_side = match resp.is_buyer_market_maker {
+                true => OrderSide::Sell,
+                false => OrderSide::Buy,
+            };
+            let price = resp.price.parse::<Number>().map_err(|_| WatchError::ParseError(resp.price.clone()))?;
+            let amount = resp.quantity.parse::<Number>().map_err(|_| WatchError::ParseError(resp.quantity.clone()))?;
+            let cost = contract_cost(&market, price, amount);
+            let trade = Trade::new(
+                resp.aggregate_trade_id.to_string(),
+                resp.trade_time,
+                market,
+                None,
+                None,
+                Some(order_side),
+                Some(resp.is_buyer_market_maker),
+                price, amount,
+                cost,
+                None, None,
+            );
+            Ok(StreamItem::Trade(Ok(trade)))
+        }
+        StreamChannel::Depth => {
+            let resp = WatchOrderBookResponse::try_from(message.to_vec())?;
+            let market = unifier.get_market(&resp.symbol);
+            if market.is_none() {
+                return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                    format!("Unknown market {}", resp.symbol), None,
+                ))));
+            }
+            let market = market.unwrap();
+            let bids = resp.bids.iter().map(OrderBookUnit::try_from).collect::<OrderBookResult<Vec<OrderBookUnit>>>();
+            if bids.is_err() {
+                return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                    format!("Invalid bid {:?}", resp.bids), Some(market),
+                ))));
+            }
+            let asks = resp.asks.iter().map(OrderBookUnit::try_from).collect::<OrderBookResult<Vec<OrderBookUnit>>>();
+            if asks.is_err() {
+                return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
+                    format!("Invalid ask {:?}", resp.asks), Some(market),
+                ))));
+            }
+            let book = OrderBook::new(
+                bids.unwrap(),
+                asks.unwrap(),
+                market,
+                Some(resp.event_time),
+                None,
+            );
+            Ok(StreamItem::OrderBook(Ok(book)))
+        }
+        StreamChannel::Kline => {
+            let resp = util::WatchOhlcvResponse::try_from(message.to_vec())?;
+            Ok(StreamItem::Ohlcv(resp.to_ohlcv(unifier)))
+        }
+        _ => {
+            let message = String::from_utf8_lossy(message);
+            Ok(StreamItem::Unknown(message.to_string()))
+        }
+    }
+}
+
+impl BinanceFutures {
+    pub(in super) fn new(props: Properties, product: &'static FuturesProduct) -> CommonResult<Self> {
+        let (host, ws_endpoint) = match props.sandbox {
+            true => (product.sandbox_host, product.sandbox_ws_endpoint),
+            false => (product.host, product.ws_endpoint),
+        };
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some(host.to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some(ws_endpoint.to_string())))
+            .error_parser(Some(parse_error))
+            .stream_parser(Some(parse_stream))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            .rate_limits(util::get_request_weight_limit("REQUEST_WEIGHT", "MINUTE", 1, 2400).map(|limit| vec![limit]))
+            .weight_calculator(Some(product.weight_calculator))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            recv_window: props.recv_window.unwrap_or(util::DEFAULT_RECV_WINDOW).to_string(),
+            leverage_brackets: None,
+            product,
+        })
+    }
+
+    /// Rest path of the product.
+    fn path(&self, path: &str) -> String {
+        format!("{}{}", self.product.prefix, path)
+    }
+
+    /// Timestamp for signed requests, on the exchange clock when time sync is enabled. The clock
+    /// is synced in the background from `load_markets`.
+    fn timestamp(&self) -> i64 {
+        self.exchange_base.clock.timestamp()
+    }
+
+    fn auth(&self, request: &String) -> Result<String> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(request.as_bytes());
+        Ok(hex::encode(signed_key.finalize().into_bytes()))
+    }
+
+    pub(in super) fn auth_map(&self, params: Option<&Vec<(&str, &str)>>) -> Result<String> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        match params {
+            Some(params) => {
+                let params = params.iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<String>>()
+                    .join("&");
+                Ok(self.auth(&params)?)
+            }
+            None => Ok(self.auth(&"".to_string())?),
+        }
+    }
+
+    /// Signed request to an account endpoint without parameters.
+    pub(in super) async fn get_signed<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let mut query = vec![];
+        let ts = self.timestamp().to_string();
+        query.push(("recvWindow", self.recv_window.as_str()));
+        query.push(("timestamp", ts.as_str()));
+        let signature = self.auth_map(Some(&query))?;
+        query.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        self.exchange_base.http_client.get(path, Some(headers), Some(&query)).await
+    }
+
+    /// Opens the user data stream of the futures account and forwards the events accepted by
+    /// `filter`.
+    async fn watch_user_data(&self, filter: fn(&StreamItem) -> bool) -> Result<Receiver> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized);
+        }
+
+        let endpoint = util::ListenKeyEndpoint {
+            http_client: self.exchange_base.http_client.clone(),
+            path: self.path("/v1/listenKey"),
+            query: vec![],
+            api_key: self.api_key.clone().unwrap(),
+            keyed: false,
+        };
+        let listen_key = endpoint.create().await?;
+
+        let ws_endpoint = self.exchange_base.ws_endpoint.clone().unwrap();
+        let ws_client_factory = self.exchange_base.ws_client_factory(parse_user_data);
+        let connect = move |listen_key: &str| ws_client_factory(&format!("{}/{}", ws_endpoint, listen_key));
+        let mut ws_client = connect(&listen_key);
+        ws_client.connect().await?;
+
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(util::run_user_data_stream(ws_client, listen_key, endpoint, connect, filter, tx));
+        Ok(receiver)
+    }
+
+    /// Fetches orders from `allOrders` or `openOrders`.
+    async fn fetch_order_list(&self, endpoint: &str, params: &FetchOrdersParams, ranged: bool) -> Result<Vec<Order>> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized);
+        }
+
+        let symbol_id = match &params.market {
+            Some(market) => Some(self.exchange_base.unifier.get_symbol_id(market).ok_or(Error::SymbolNotFound(format!("{}", market)))?),
+            None => None,
+        };
+        let since = params.since.map(|since| since.to_string());
+        let until = params.until.map(|until| until.to_string());
+        let limit = params.limit.map(|limit| limit.to_string());
+        let timestamp = self.timestamp().to_string();
+
+        let mut queries = vec![];
+        if let Some(symbol_id) = &symbol_id {
+            queries.push(("symbol", symbol_id.as_str()));
+        }
+        if ranged {
+            if let Some(since) = &since {
+                queries.push(("startTime", since.as_str()));
+            }
+            if let Some(until) = &until {
+                queries.push(("endTime", until.as_str()));
+            }
+            if let Some(limit) = &limit {
+                queries.push(("limit", limit.as_str()));
+            }
+        }
+        queries.push(("recvWindow", self.recv_window.as_str()));
+        queries.push(("timestamp", timestamp.as_str()));
+
+        let signature = self.auth_map(Some(&queries))?;
+        queries.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let responses: Vec<FetchOrderResponse> = self.exchange_base.http_client.get(&self.path(endpoint), Some(headers), Some(&queries)).await?;
+
+        let mut orders = vec![];
+        for response in responses {
+            if let Some(market) = self.exchange_base.unifier.get_market(&response.symbol) {
+                let mut order: Order = response.try_into()?;
+                order.market = market;
+                orders.push(order);
+            }
+        }
+        Ok(orders)
+    }
+
+    pub(in super) async fn load_leverage_brackets(&mut self) -> Result<()> {
+        let result: Vec<FetchLeverageResponse> = self.get_signed(&self.path(self.product.leverage_bracket_path)).await?;
+        let mut leverage_brackets = HashMap::new();
+        for resp in result {
+            let market = match self.exchange_base.unifier.get_market(&resp.symbol) {
+                Some(market) => market,
+                None => continue,
+            };
+            for b in &resp.brackets {
+                let floor = b.notional_floor.or(b.qty_floor).ok_or_else(|| Error::MissingField("notionalFloor".into()))?;
+                leverage_brackets.entry(market.clone()).or_insert(vec![]).push(LeverageBracket {
+                    floor,
+                    maintenance_margin_ratio: b.maint_margin_ratio,
+                });
+            }
+        }
+        self.leverage_brackets = Some(leverage_brackets);
+        Ok(())
+    }
+
+    pub(in super) async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        util::start_time_sync(&self.exchange_base.clock, &self.exchange_base.http_client, self.path("/v1/time")).await;
+        if self.exchange_base.markets.is_empty() {
+            let result = self.exchange_base.http_client.get::<(), FetchMarketsResponse>(&self.path("/v1/exchangeInfo"), None, None).await?;
+            result.update_rate_limits(&self.exchange_base.http_client);
+            self.exchange_base.unifier.reset();
+            let mut markets = vec![];
+            for s in result.symbols {
+                if s.symbol.is_none() {
+                    continue;
+                }
+                let market = s.to_market(self.product.contract_type)?;
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &(s.symbol.unwrap()));
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        if self.api_key.is_some() && self.secret.is_some() && self.leverage_brackets.is_none() {
+            self.load_leverage_brackets().await?;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    pub(in super) async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.exchange_base.http_client.get::<(), FetchMarketsResponse>(&self.path("/v1/exchangeInfo"), None, None).await?;
+        result.update_rate_limits(&self.exchange_base.http_client);
+        let mut markets = vec![];
+        for s in result.symbols {
+            if s.symbol.is_none() {
+                continue;
+            }
+            markets.push(s.to_market(self.product.contract_type)?);
+        }
+        Ok(markets)
+    }
+
+    pub(in super) async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let mut result: Vec<FetchTickersResponse>;
+        if let Some(markets) = params.markets.as_ref() {
+            result = vec![];
+            for market in markets {
+                let symbol_id = self.exchange_base.unifier.get_symbol_id(market).ok_or(Error::SymbolNotFound(format!("{}", market)))?;
+                let query = Some(vec![("symbol", symbol_id)]);
+                let item: FetchTickerResponse = self.exchange_base.http_client.get(&self.path("/v1/ticker/24hr"), None, query.as_ref()).await?;
+                match item {
+                    FetchTickerResponse::Single(item) => result.push(*item),
+                    FetchTickerResponse::List(items) => result.extend(items),
+                }
+            }
+        } else {
+            result = self.exchange_base.http_client.get(&self.path("/v1/ticker/24hr"), None, EMPTY_QUERY).await?;
+        }
+
+        let mut tickers = vec![];
+        for item in result {
+            let market = self.exchange_base.unifier.get_market(&item.symbol);
+            if market.is_none() {
+                continue;
+            }
+            let market = market.unwrap();
+            let timestamp = item.close_time;
+
+            let volume = item.volume.parse::<f64>()?;
+            let (base_volume, quote_volume) = match market.contract_type {
+                // the volume is counted in contracts
+                Some(ContractType::Inverse) => {
+                    let base_volume = item.base_volume.as_deref().ok_or_else(|| Error::MissingField("baseVolume".into()))?;
+                    (base_volume.parse::<f64>()?, volume * market.contract_size.unwrap_or(1.0))
+                }
+                _ => {
+                    let quote_volume = item.quote_volume.as_deref().ok_or_else(|| Error::MissingField("quoteVolume".into()))?;
+                    (volume, quote_volume.parse::<f64>()?)
+                }
+            };
+            let last = item.last_price.parse::<f64>()?;
+            let open = item.open_price.parse::<f64>()?;
+            tickers.push(Ticker {
+                base_volume,
+                change: item.price_change.parse::<f64>()?,
+                close: last,
+                high: item.high_price.parse::<f64>()?,
+                last,
+                low: item.low_price.parse::<f64>()?,
+                open,
+                percentage: item.price_change_percent.parse::<f64>()?,
+                previous_close: None,
+                quote_volume,
+                average: (open + last) / 2f64,
+                market,
+                timestamp,
+                vwap: item.weighted_avg_price.parse::<f64>()?,
+                ..Default::default()
+            });
+        }
+        Ok(tickers)
+    }
+
+    pub(in super) async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or_else(|| Error::SymbolNotFound(format!("{}", params.market)))?;
+        let mut query = vec![("symbol", symbol_id.clone())];
+        if let Some(since) = params.since {
+            query.push(("startTime", format!("{}", since)));
+        }
+        if let Some(until) = params.until {
+            query.push(("endTime", format!("{}", until)));
+        }
+        if let Some(limit) = params.limit {
+            if limit > 1000 {
+                return Err(Error::InvalidParameters("limit should be less than or equal to 1000".into()).into());
+            }
+            query.push(("limit", format!("{}", limit)));
+        }
+        let trades: Vec<FetchTradesResponse> = self.exchange_base.http_client.get(&self.path("/v1/aggTrades"), None, Some(&query)).await?;
+        let market = self.exchange_base.unifier.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+
+        let mut ret = vec![];
+        for trade in trades {
+            let order_side = match trade.is_buyer_market_maker {
+                true => OrderSide::Sell,
+                false => OrderSide::Buy,
+            };
+            let price = parse_number(&trade.price)?;
+            let amount = parse_number(&trade.quantity)?;
+            let trade = Trade::new(
+                trade.aggregate_trade_id.to_string(),
+                trade.timestamp,
+                params.market.clone(),
+                None,
+                None,
+                Some(order_side),
+                Some(trade.is_buyer_market_maker),
+                price, amount,
+                contract_cost(&market, price, amount),
+                None, None,
+            );
+            ret.push(trade);
+        }
+        Ok(ret)
+    }
+
+    pub(in super) async fn fetch_ohlcv(&self, params: FetchOhlcvParams) -> FetchOhlcvResult<Vec<Ohlcv>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or_else(|| Error::SymbolNotFound(format!("{}", params.market)))?;
+        let mut query = vec![
+            ("symbol", symbol_id),
+            ("interval", util::get_exchange_timeframe(&params.timeframe).to_string()),
+        ];
+        if let Some(since) = params.since {
+            query.push(("startTime", format!("{}", since)));
+        }
+        if let Some(until) = params.until {
+            query.push(("endTime", format!("{}", until)));
+        }
+        if let Some(limit) = params.limit {
+            if limit > 1500 {
+                return Err(Error::InvalidParameters("limit should be less than or equal to 1500".into()).into());
+            }
+            query.push(("limit", format!("{}", limit)));
+        }
+        let result: Vec<util::FetchOhlcvResponse> = self.exchange_base.http_client.get(&self.path("/v1/klines"), None, Some(&query)).await?;
+
+        let mut ret = vec![];
+        for item in result {
+            let mut ohlcv: Ohlcv = item.try_into()?;
+            ohlcv.market = params.market.clone();
+            ohlcv.timeframe = params.timeframe;
+            ret.push(ohlcv);
+        }
+        Ok(ret)
+    }
+
+    /// Symbol ids of the markets to subscribe to.
+    fn watch_symbol_ids(&self, markets: &[Market]) -> WatchResult<Vec<String>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if markets.is_empty() {
+            return Err(Error::InvalidParameters("markets is empty".into()).into());
+        }
+        if self.exchange_base.ws_endpoint.is_none() {
+            return Err(Error::InvalidParameters("ws endpoint is empty".into()).into());
+        }
+
+        let mut symbol_ids: Vec<String> = Vec::new();
+        for m in markets {
+            match self.exchange_base.unifier.get_symbol_id(m) {
+                Some(symbol_id) => symbol_ids.push(symbol_id),
+                None => return Err(WatchError::SymbolNotFound(format!("{:?}", m))),
+            }
+        }
+        Ok(symbol_ids)
+    }
+
+    pub(in super) async fn watch_ohlcv(&self, params: WatchOhlcvParams) -> WatchOhlcvResult<Receiver> {
+        let symbol_ids = self.watch_symbol_ids(&params.markets)?;
+        let interval = util::get_exchange_timeframe(&params.timeframe);
+        let spec = util::subscription_spec(&self.exchange_base, move |s| format!("{}@kline_{}", s.to_lowercase(), interval));
+        Ok(Receiver::subscribed(spec, &symbol_ids, &self.exchange_base.channel).await?)
+    }
+
+    pub(in super) async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
+        let symbol_ids = self.watch_symbol_ids(&params.markets)?;
+        let spec = util::subscription_spec(&self.exchange_base, |s| format!("{}@aggTrade", s.to_lowercase()));
+        Ok(Receiver::subscribed(spec, &symbol_ids, &self.exchange_base.channel).await?)
+    }
+
+    pub(in super) async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        let symbol_ids = self.watch_symbol_ids(&params.markets)?;
+        let spec = util::subscription_spec(&self.exchange_base, |s| format!("{}@depth5@100ms", s.to_lowercase()));
+        Ok(Receiver::subscribed(spec, &symbol_ids, &self.exchange_base.channel).await?)
+    }
+
+    pub(in super) async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or(Error::SymbolNotFound(format!("{}", params.market)))?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.exchange_base.unifier.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+        let timestamp = self.timestamp();
+        let mut body = format!("symbol={}&side={}&type={}&quantity={}&recvWindow={}&timestamp={}",
+                               symbol_id,
+                               util::get_exchange_order_side(&params.order_side),
+                               util::get_exchange_order_type(&order_type)?,
+                               amount,
+                               self.recv_window,
+                               timestamp);
+        if let (true, Some(price)) = (order_type != OrderType::Market, price) {
+            body = format!("{}&price={}", body, price);
+        }
+        if order_type != OrderType::Market {
+            body = format!("{}&timeInForce={}", body, util::get_exchange_time_in_force(&params.time_in_force.unwrap_or(TimeInForce::GTC)))
+        }
+
+        let working_type = params.working_type.unwrap_or(WorkingType::ContractPrice);
+
+        match (order_type, params.callback_rate) {
+            (OrderType::TrailingStopMarket, Some(callback_rate)) => {
+                body = format!("{}&callbackRate={}&workingType={}", body, callback_rate, util::get_exchange_working_type(&working_type)?);
+            }
+            (OrderType::TrailingStopMarket, None) => {
+                return Err(Error::InvalidParameters("callback rate is required for trailing stop market orders".into()).into());
+            }
+            _ => {}
+        }
+
+        let signature = self.auth(&body)?;
+        let body = format!("{}&signature={}", body, signature);
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let response: CreateOrderResponse = self.exchange_base.http_client.post(&self.path("/v1/order"), Some(headers), EMPTY_QUERY, Some(&body)).await?;
+        let mut order: Order = response.try_into()?;
+        order.market = params.market.clone();
+        order.order_type = order_type;
+        Ok(order)
+    }
+
+    pub(in super) async fn cancel_order(&self, params: CancelOrderParams) -> CancelOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or(Error::SymbolNotFound(format!("{}", params.market)))?;
+        let timestamp = self.timestamp().to_string();
+        let mut queries = vec![
+            ("symbol", symbol_id.as_str()),
+            util::get_exchange_order_id(&params.order_id, &params.client_order_id)?,
+            ("recvWindow", self.recv_window.as_str()),
+            ("timestamp", timestamp.as_str()),
+        ];
+        let signature = self.auth_map(Some(&queries))?;
+        queries.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let response: FetchOrderResponse = self.exchange_base.http_client.delete(&self.path("/v1/order"), Some(headers), Some(&queries)).await?;
+        let mut order: Order = response.try_into()?;
+        order.market = params.market.clone();
+        Ok(order)
+    }
+
+    pub(in super) async fn fetch_order(&self, params: FetchOrderParams) -> FetchOrdersResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or(Error::SymbolNotFound(format!("{}", params.market)))?;
+        let timestamp = self.timestamp().to_string();
+        let mut queries = vec![
+            ("symbol", symbol_id.as_str()),
+            util::get_exchange_order_id(&params.order_id, &params.client_order_id)?,
+            ("recvWindow", self.recv_window.as_str()),
+            ("timestamp", timestamp.as_str()),
+        ];
+        let signature = self.auth_map(Some(&queries))?;
+        queries.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let response: FetchOrderResponse = self.exchange_base.http_client.get(&self.path("/v1/order"), Some(headers), Some(&queries)).await?;
+        let mut order: Order = response.try_into()?;
+        order.market = params.market.clone();
+        Ok(order)
+    }
+
+    pub(in super) async fn fetch_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        if params.market.is_none() {
+            return Err(Error::InvalidParameters("market is required".into()).into());
+        }
+        if let Some(limit) = params.limit {
+            if limit > 1000 {
+                return Err(Error::InvalidParameters("limit should be less than or equal to 1000".into()).into());
+            }
+        }
+        Ok(self.fetch_order_list("/v1/allOrders", &params, true).await?)
+    }
+
+    pub(in super) async fn fetch_open_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        Ok(self.fetch_order_list("/v1/openOrders", &params, false).await?)
+    }
+
+    pub(in super) async fn fetch_closed_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        let mut orders = self.fetch_orders(params).await?;
+        orders.retain(|order| order.status != OrderStatus::Open);
+        Ok(orders)
+    }
+
+    pub(in super) async fn watch_balance(&self, _params: WatchBalanceParams) -> WatchBalanceResult<Receiver> {
+        Ok(self.watch_user_data(|item| matches!(item, StreamItem::Balance(_))).await?)
+    }
+
+    pub(in super) async fn watch_my_trades(&self, _params: WatchMyTradesParams) -> WatchMyTradesResult<Receiver> {
+        Ok(self.watch_user_data(|item| matches!(item, StreamItem::MyTrade(_))).await?)
+    }
+
+    pub(in super) async fn watch_orders(&self, _params: WatchOrdersParams) -> WatchOrdersResult<Receiver> {
+        Ok(self.watch_user_data(|item| matches!(item, StreamItem::Order(_))).await?)
+    }
+
+    pub(in super) async fn watch_positions(&self, _params: WatchPositionsParams) -> WatchPositionsResult<Receiver> {
+        Ok(self.watch_user_data(|item| matches!(item, StreamItem::Position(_))).await?)
+    }
+
+    pub(in super) async fn fetch_balance(&self, params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        if params.margin_mode.is_some() && params.margin_mode.unwrap() != MarginMode::Cross {
+            return Err(Error::InvalidParameters("margin mode is not supported".into()).into());
+        }
+
+        let resp: FetchBalanceResponse = self.get_signed(&self.path(self.product.account_path)).await?;
+        let mut bal = Balance::default();
+
+        for asset in resp.assets {
+            let free = parse_float64(&asset.available_balance)?;
+            let used = parse_float64(&asset.initial_margin)?;
+            let total = parse_float64(&asset.margin_balance)?;
+            let item = BalanceItem {
+                currency: util::to_unified_asset(&asset.asset),
+                market: None,
+                total,
+                free,
+                used,
+                debt: 0.0,
+            };
+            bal.items.push(item);
+        }
+        Ok(bal)
+    }
+}
+
+fn parse_user_data(message: &[u8], unifier: &Unifier) -> WatchResult<Vec<StreamItem>> {
+    let common_message = WatchCommonResponse::try_from(message.to_vec())?;
+    match common_message.event_type.as_deref() {
+        Some("ORDER_TRADE_UPDATE") => {
+            let resp = WatchOrderResponse::try_from(message.to_vec())?;
+            let market = match unifier.get_market(&resp.order.symbol) {
+                Some(market) => market,
+                None => return Ok(vec![StreamItem::Order(Err(OrderError::UnknownError(format!("Unknown market. symbol={}", resp.order.symbol))))]),
+            };
+            let mut items = vec![StreamItem::Order(resp.order.to_order(&market).map_err(OrderError::from))];
+            if resp.order.execution_type == "TRADE" {
+                items.push(StreamItem::MyTrade(resp.order.to_trade(&market).map_err(TradeError::from)));
+            }
+            Ok(items)
+        }
+        Some("ACCOUNT_UPDATE") => {
+            let resp = WatchAccountResponse::try_from(message.to_vec())?;
+            let mut items = vec![StreamItem::Balance(resp.to_balance().map_err(BalanceError::from))];
+            for position in &resp.account.positions {
+                if let Some(market) = unifier.get_market(&position.symbol) {
+                    items.push(StreamItem::Position(position.to_position(market, resp.transaction_time).map_err(PositionError::from)));
+                }
+            }
+            Ok(items)
+        }
+        Some("listenKeyExpired") => Err(WatchError::StreamError(util::LISTEN_KEY_EXPIRED.into())),
+        _ => Ok(vec![StreamItem::Unknown(String::from_utf8_lossy(message).to_string())]),
+    }
+}
+
+/// Value in the settlement currency of `amount` contracts at `price`. Inverse contracts are worth a
+/// fixed amount of the quote currency each.
+fn contract_cost(market: &Market, price: Number, amount: Number) -> Number {
+    match market.contract_type {
+        Some(ContractType::Inverse) if price == ZERO => ZERO,
+        Some(ContractType::Inverse) => amount * f64_to_number(market.contract_size.unwrap_or(1.0)) / price,
+        _ => price * amount,
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct WatchTradesResponse {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "a")]
+    pub aggregate_trade_id: i64,
+    #[serde(rename = "p")]
+    pub price: String,
+    #[serde(rename = "q")]
+    pub quantity: String,
+    #[serde(rename = "f")]
+    pub first_trade_id: i64,
+    #[serde(rename = "l")]
+    pub last_trade_id: i64,
+    #[serde(rename = "T")]
+    pub trade_time: i64,
+    #[serde(rename = "m")]
+    pub is_buyer_market_maker: bool,
+}
+
+
+impl TryFrom<Vec<u8>> for WatchTradesResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct WatchOrderBookResponse {
+    #[serde(rename = "e")]
+    event_type: String,
+    #[serde(rename = "E")]
+    event_time: i64,
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "U")]
+    first_update_id: i64,
+    #[serde(rename = "u")]
+    final_update_id: i64,
+    #[serde(rename = "b")]
+    bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    asks: Vec<[String; 2]>,
+    #[serde(rename = "pu")]
+    previous_final_update_id: i64,
+}
+
+impl TryFrom<Vec<u8>> for WatchOrderBookResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchCommonResponse {
+    result: Option<String>,
+    id: Option<i64>,
+    code: Option<i64>,
+    msg: Option<String>,
+    #[serde(rename = "e")]
+    event_type: Option<String>,
+}
+
+impl TryFrom<Vec<u8>> for WatchCommonResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+
+
+/// `ORDER_TRADE_UPDATE` event of the user data stream
+#[derive(Serialize, Deserialize)]
+struct WatchOrderResponse {
+    #[serde(rename = "o")]
+    order: WatchOrderUpdateResponse,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchOrderUpdateResponse {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "c")]
+    client_order_id: String,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "ot")]
+    original_order_type: String,
+    #[serde(rename = "f")]
+    time_in_force: String,
+    #[serde(rename = "q")]
+    quantity: String,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "ap")]
+    average_price: String,
+    #[serde(rename = "x")]
+    execution_type: String,
+    #[serde(rename = "X")]
+    status: String,
+    #[serde(rename = "i")]
+    order_id: i64,
+    #[serde(rename = "l")]
+    last_filled_quantity: String,
+    #[serde(rename = "z")]
+    cumulative_filled_quantity: String,
+    #[serde(rename = "L")]
+    last_filled_price: String,
+    #[serde(rename = "N")]
+    commission_asset: Option<String>,
+    #[serde(rename = "n")]
+    commission: Option<String>,
+    #[serde(rename = "T")]
+    trade_time: i64,
+    #[serde(rename = "t")]
+    trade_id: i64,
+    #[serde(rename = "m")]
+    is_maker: bool,
+}
+
+impl TryFrom<Vec<u8>> for WatchOrderResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+impl WatchOrderUpdateResponse {
+    fn to_order(&self, market: &Market) -> Result<Order> {
+        let amount: Number = self.quantity.parse()?;
+        let filled: Number = self.cumulative_filled_quantity.parse()?;
+        let price: Number = self.price.parse()?;
+        let average: Number = self.average_price.parse()?;
+        Ok(Order {
+            id: Some(self.order_id.to_string()),
+            client_order_id: Some(self.client_order_id.clone()),
+            timestamp: self.trade_time,
+            last_trade_timestamp: Some(self.trade_time).filter(|_| self.execution_type == "TRADE"),
+            status: util::get_unified_order_status(&self.status)?,
+            market: market.clone(),
+            order_type: util::get_unified_order_type(&self.original_order_type)?,
+            time_in_force: util::get_unified_time_in_force(&self.time_in_force).ok(),
+            side: Some(util::get_unified_order_side(&self.side)?),
+            price: Some(price).filter(|price| *price > ZERO),
+            average: Some(average).filter(|average| *average > ZERO),
+            amount,
+            filled: Some(filled),
+            remaining: Some(amount - filled),
+            cost: Some(contract_cost(market, average, filled)),
+            margin_mode: MarginMode::Cross,
+            ..Default::default()
+        })
+    }
+
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price: Number = self.last_filled_price.parse()?;
+        let amount: Number = self.last_filled_quantity.parse()?;
+        let fee = match (&self.commission_asset, &self.commission) {
+            (Some(currency), Some(commission)) => Some(OrderFee {
+                currency: util::to_unified_asset(currency),
+                cost: commission.parse()?,
+                rate: None,
+            }),
+            _ => None,
+        };
+        Ok(Trade::new(
+            self.trade_id.to_string(),
+            self.trade_time,
+            market.clone(),
+            Some(self.order_id.to_string()),
+            Some(util::get_unified_order_type(&self.original_order_type)?),
+            Some(util::get_unified_order_side(&self.side)?),
+            Some(self.is_maker),
+            price, amount,
+            contract_cost(market, price, amount),
+            fee, None,
+        ))
+    }
+}
+
+
+/// `ACCOUNT_UPDATE` event of the user data stream
+#[derive(Serialize, Deserialize)]
+struct WatchAccountResponse {
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    #[serde(rename = "a")]
+    account: WatchAccountUpdateResponse,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchAccountUpdateResponse {
+    #[serde(rename = "B")]
+    balances: Vec<WatchAccountBalanceResponse>,
+    #[serde(rename = "P")]
+    positions: Vec<WatchAccountPositionResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchAccountBalanceResponse {
+    #[serde(rename = "a")]
+    asset: String,
+    #[serde(rename = "wb")]
+    wallet_balance: String,
+    #[serde(rename = "cw")]
+    cross_wallet_balance: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchAccountPositionResponse {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "pa")]
+    position_amount: String,
+    #[serde(rename = "ep")]
+    entry_price: String,
+    #[serde(rename = "up")]
+    unrealized_pnl: String,
+    #[serde(rename = "mt")]
+    margin_type: String,
+    #[serde(rename = "iw")]
+    isolated_wallet: String,
+    #[serde(rename = "ps")]
+    position_side: String,
+}
+
+impl TryFrom<Vec<u8>> for WatchAccountResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+impl WatchAccountResponse {
+    fn to_balance(&self) -> Result<Balance> {
+        let mut items = vec![];
+        for balance in &self.account.balances {
+            let total = parse_float64(&balance.wallet_balance)?;
+            let free = parse_float64(&balance.cross_wallet_balance)?;
+            items.push(BalanceItem {
+                currency: util::to_unified_asset(&balance.asset),
+                market: None,
+                total,
+                free,
+                used: total - free,
+                debt: 0.0,
+            });
+        }
+        Ok(Balance {
+            timestamp: Some(self.transaction_time),
+            items,
+        })
+    }
+}
+
+impl WatchAccountPositionResponse {
+    fn to_position(&self, market: Market, timestamp: i64) -> Result<Position> {
+        let amount = parse_float64(&self.position_amount)?;
+        let margin_mode = match self.margin_type.as_str() {
+            "cross" => MarginMode::Cross,
+            _ => MarginMode::Isolated,
+        };
+        let side = match self.position_side.as_str() {
+            "LONG" => PositionSide::Long,
+            "SHORT" => PositionSide::Short,
+            _ if amount < 0.0 => PositionSide::Short,
+            _ => PositionSide::Long,
+        };
+        let collateral = match margin_mode {
+            MarginMode::Isolated => parse_float64(&self.isolated_wallet)?,
+            MarginMode::Cross => 0.0,
+        };
+        Ok(Position {
+            contract_size: market.contract_size,
+            market,
+            timestamp,
+            is_hedged: self.position_side != "BOTH",
+            side,
+            contracts: amount.abs(),
+            entry_price: parse_float64(&self.entry_price)?,
+            unrealized_pnl: parse_float64(&self.unrealized_pnl)?,
+            collateral,
+            margin_mode,
+            ..Default::default()
+        })
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct ErrorResponse {
+    code: i64,
+    msg: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderResponse {
+    client_order_id: String,
+    /// USDⓈ-M only
+    cum_quote: Option<String>,
+    /// COIN-M only
+    cum_qty: Option<String>,
+    /// COIN-M only
+    cum_base: Option<String>,
+    executed_qty: String,
+    order_id: i64,
+    avg_price: String,
+    orig_qty: String,
+    price: String,
+    reduce_only: bool,
+    side: String,
+    position_side: String,
+    status: String,
+    stop_price: String,
+    close_position: bool,
+    symbol: String,
+    time_in_force: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    orig_type: String,
+    activate_price: Option<String>,
+    price_rate: Option<String>,
+    update_time: i64,
+    working_type: String,
+    price_protect: bool,
+}
+
+impl TryFrom<CreateOrderResponse> for Order {
+    type Error = Error;
+
+    fn try_from(resp: CreateOrderResponse) -> std::result::Result<Self, Self::Error> {
+        let order_status = util::get_unified_order_status(&resp.status)?;
+        let amount = resp.orig_qty.parse()?;
+        let remaining = match order_status {
+            OrderStatus::Open => Some(amount),
+            _ => None,
+        };
+        Ok(Order {
+            id: Some(resp.order_id.to_string()),
+            client_order_id: Some(resp.client_order_id),
+            timestamp: resp.update_time,
+            status: order_status,
+            time_in_force: Some(util::get_unified_time_in_force(&resp.time_in_force)?),
+            side: Some(util::get_unified_order_side(&resp.side)?),
+            price: Some(resp.price.parse()?),
+            average: Some(resp.avg_price.parse()?),
+            amount: resp.orig_qty.parse()?,
+            remaining,
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Order returned by the query and cancel endpoints.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchOrderResponse {
+    avg_price: String,
+    client_order_id: String,
+    /// cost in quote currency, USDⓈ-M only
+    cum_quote: Option<String>,
+    /// cost in base currency, COIN-M only
+    cum_base: Option<String>,
+    executed_qty: String,
+    order_id: i64,
+    orig_qty: String,
+    orig_type: String,
+    price: String,
+    reduce_only: bool,
+    side: String,
+    position_side: String,
+    status: String,
+    stop_price: String,
+    symbol: String,
+    /// not set when canceling
+    time: Option<i64>,
+    time_in_force: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    update_time: i64,
+    working_type: String,
+}
+
+impl TryFrom<FetchOrderResponse> for Order {
+    type Error = Error;
+
+    fn try_from(resp: FetchOrderResponse) -> std::result::Result<Self, Self::Error> {
+        let amount: Number = resp.orig_qty.parse()?;
+        let filled: Number = resp.executed_qty.parse()?;
+        let price: Number = resp.price.parse()?;
+        let average: Number = resp.avg_price.parse()?;
+        let cost = resp.cum_quote.as_deref().or(resp.cum_base.as_deref()).map(parse_number).transpose()?;
+        Ok(Order {
+            id: Some(resp.order_id.to_string()),
+            client_order_id: Some(resp.client_order_id),
+            timestamp: resp.time.unwrap_or(resp.update_time),
+            status: util::get_unified_order_status(&resp.status)?,
+            order_type: util::get_unified_order_type(&resp.orig_type)?,
+            time_in_force: util::get_unified_time_in_force(&resp.time_in_force).ok(),
+            side: Some(util::get_unified_order_side(&resp.side)?),
+            price: Some(price).filter(|price| *price > ZERO),
+            average: Some(average).filter(|average| *average > ZERO),
+            amount,
+            filled: Some(filled),
+            remaining: Some(amount - filled),
+            cost,
+            margin_mode: MarginMode::Cross,
+            ..Default::default()
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchLeverageBracketResponse {
+    pub bracket: f64,
+    pub initial_leverage: f64,
+    /// USDⓈ-M only
+    pub notional_cap: Option<f64>,
+    /// USDⓈ-M only
+    pub notional_floor: Option<f64>,
+    /// COIN-M only
+    pub qty_cap: Option<f64>,
+    /// COIN-M only
+    pub qty_floor: Option<f64>,
+    pub maint_margin_ratio: f64,
+    pub cum: f64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchLeverageResponse {
+    pub symbol: String,
+    #[serde(rename = "notionalCoef")]
+    pub notional_coef: Option<f64>,
+    pub brackets: Vec<FetchLeverageBracketResponse>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsResponse {
+    pub exchange_filters: Option<Vec<String>>,
+    pub rate_limits: Vec<FetchMarketsRateLimitResponse>,
+    pub server_time: i64,
+    pub assets: Option<Vec<FetchMarketsAssetResponse>>,
+    pub symbols: Vec<FetchMarketsSymbolResponse>,
+    pub timezone: String,
+}
+
+impl FetchMarketsResponse {
+    /// Applies the announced request weight limits to the client. The defaults are kept when none
+    /// is announced.
+    fn update_rate_limits(&self, http_client: &HttpClient) {
+        let rate_limits: Vec<_> = self.rate_limits.iter()
+            .filter_map(|r| util::get_request_weight_limit(&r.rate_limit_type, &r.interval, r.interval_num, r.limit))
+            .collect();
+        if !rate_limits.is_empty() {
+            http_client.rate_limiter().set_rate_limits(rate_limits);
+        }
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsRateLimitResponse {
+    pub interval: String,
+    pub interval_num: i64,
+    pub limit: i64,
+    pub rate_limit_type: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsAssetResponse {
+    pub asset: String,
+    pub margin_available: bool,
+    pub auto_asset_exchange: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsSymbolResponse {
+    pub symbol: Option<String>,
+    pub pair: Option<String>,
+    pub contract_type: Option<String>,
+    pub delivery_date: Option<i64>,
+    pub onboard_date: Option<i64>,
+    /// USDⓈ-M only
+    pub status: Option<String>,
+    /// COIN-M only
+    pub contract_status: Option<String>,
+    /// COIN-M only, USDⓈ-M contracts are worth one unit of the base currency
+    pub contract_size: Option<f64>,
+    pub maint_margin_percent: Option<String>,
+    pub required_margin_percent: Option<String>,
+    pub base_asset: Option<String>,
+    pub quote_asset: Option<String>,
+    pub margin_asset: Option<String>,
+    pub price_precision: Option<isize>,
+    pub quantity_precision: Option<isize>,
+    pub base_asset_precision: Option<isize>,
+    pub quote_precision: Option<isize>,
+    pub underlying_type: Option<String>,
+    pub underlying_sub_type: Option<Vec<String>>,
+    pub settle_plan: Option<i64>,
+    pub equal_qty_precision: Option<isize>,
+    pub trigger_protect: Option<String>,
+    pub filters: Option<Vec<FetchMarketsFilterResponse>>,
+    pub order_type: Option<Vec<String>>,
+    pub time_in_force: Option<Vec<String>>,
+    pub liquidation_fee: Option<String>,
+    pub market_take_bound: Option<String>,
+}
+
+impl FetchMarketsSymbolResponse {
+    fn to_market(&self, contract_type: ContractType) -> Result<Market> {
+        let base_id = self.base_asset.clone().ok_or_else(|| Error::MissingField("base_asset".into()))?;
+        let quote_id = self.quote_asset.clone().ok_or_else(|| Error::MissingField("quote_asset".into()))?;
+        let settle_id = self.margin_asset.clone();
+
+
+        let base = util::to_unified_asset(&base_id);
+        let quote = util::to_unified_asset(&quote_id);
+        let settle = settle_id.as_ref().map(|s| util::to_unified_asset(s));
+
+        let market_type = match self.contract_type {
+            Some(ref s) if s == "PERPETUAL" => MarketType::Swap,
+            Some(ref s) if s == "CURRENT_QUARTER" => MarketType::Future,
+            Some(ref s) if s == "NEXT_QUARTER" => MarketType::Future,
+            _ => MarketType::Unknown,
+        };
+
+        let active = util::is_active(self.status.clone().or(self.contract_status.clone()));
+
+        let mut limit = MarketLimit {
+            amount: None,
+            price: None,
+            cost: None,
+            leverage: None,
+        };
+
+        let mut precision = Precision {
+            amount: self.quantity_precision,
+            price: self.price_precision,
+            cost: None,
+        };
+        let (mut tick_size, mut step_size) = (None, None);
+
+        for filter in self.filters.iter().flatten() {
+            match filter.filter_type.as_str() {
+                "PRICE_FILTER" => {
+                    let min = filter.min_price.as_ref().ok_or_else(|| Error::MissingField("min_price".into()))?.parse::<f64>()?;
+                    let max = filter.max_price.as_ref().ok_or_else(|| Error::MissingField("max_price".into()))?.parse::<f64>()?;
+                    limit.price = Some(Range { min, max });
+                    let tick = filter.tick_size.as_ref().ok_or_else(|| Error::MissingField("tick_size".into()))?;
+                    precision.price = Some(into_precision(tick.clone())?);
+                    tick_size = Some(parse_number(tick)?);
+                }
+                "LOT_SIZE" => {
+                    let min = filter.min_qty.as_ref().ok_or_else(|| Error::MissingField("min_qty".into()))?.parse::<f64>()?;
+                    let max = filter.max_qty.as_ref().ok_or_else(|| Error::MissingField("max_qty".into()))?.parse::<f64>()?;
+                    limit.amount = Some(Range { min, max });
+                    step_size = filter.step_size.as_deref().map(parse_number).transpose()?;
+                }
+                "MIN_NOTIONAL" => {
+                    let min = filter.notional.as_ref().ok_or_else(|| Error::MissingField("notional".into()))?.parse::<f64>()?;
+                    limit.cost = Some(Range { min, max: f64::MAX });
+                }
+                // "MARKET_LOT_SIZE" => {},
+                // "MAX_NUM_ORDERS" => {},
+                // "MAX_NUM_ALGO_ORDERS" => {},
+                // "PERCENT_PRICE" => {},
+                _ => {}
+            }
+        }
+        Ok(Market {
+            base,
+            quote,
+            active,
+            market_type,
+            settle,
+            contract_size: self.contract_size.or(Some(1.0)),
+            contract_type: Some(contract_type),
+            // perpetual contracts carry a delivery date far in the future
+            expiry: self.delivery_date.filter(|_| market_type == MarketType::Future),
+            precision: Some(precision),
+            tick_size,
+            step_size,
+            limit: Some(limit),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsFilterResponse {
+    pub filter_type: String,
+    pub max_price: Option<String>,
+    pub min_price: Option<String>,
+    pub tick_size: Option<String>,
+    pub max_qty: Option<String>,
+    pub min_qty: Option<String>,
+    pub step_size: Option<String>,
+    pub limit: Option<i64>,
+    pub notional: Option<String>,
+    pub multiplier_up: Option<String>,
+    pub multiplier_down: Option<String>,
+    pub multiplier_decimal: Option<String>,
+}
+
+
+/// Fields of the account response shared by both products.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FetchBalanceAssetResponse {
+    pub asset: String,
+    pub wallet_balance: String,
+    pub unrealized_profit: String,
+    pub margin_balance: String,
+    pub maint_margin: String,
+    pub initial_margin: String,
+    pub position_initial_margin: String,
+    pub open_order_initial_margin: String,
+    pub cross_wallet_balance: String,
+    pub cross_un_pnl: String,
+    pub available_balance: String,
+    pub max_withdraw_amount: String,
+    pub update_time: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FetchBalanceResponse {
+    pub fee_tier: i64,
+    pub can_trade: bool,
+    pub can_deposit: bool,
+    pub can_withdraw: bool,
+    pub update_time: i64,
+    pub assets: Vec<FetchBalanceAssetResponse>,
+}
+
+
+/// Ticker of a single symbol. COIN-M returns a list even for a single symbol.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FetchTickerResponse {
+    Single(Box<FetchTickersResponse>),
+    List(Vec<FetchTickersResponse>),
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchTickersResponse {
+    pub symbol: String,
+    pub price_change: String,
+    pub price_change_percent: String,
+    pub weighted_avg_price: String,
+    pub last_price: String,
+    pub last_qty: String,
+    pub open_price: String,
+    pub high_price: String,
+    pub low_price: String,
+    /// in base currency for USDⓈ-M, in contracts for COIN-M
+    pub volume: String,
+    /// USDⓈ-M only
+    pub quote_volume: Option<String>,
+    /// COIN-M only
+    pub base_volume: Option<String>,
+    pub open_time: i64,
+    pub close_time: i64,
+    pub first_id: i64,
+    pub last_id: i64,
+    pub count: i64,
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchTradesResponse {
+    #[serde(rename = "a")]
+    pub aggregate_trade_id: i64,
+    #[serde(rename = "p")]
+    pub price: String,
+    #[serde(rename = "q")]
+    pub quantity: String,
+    #[serde(rename = "f")]
+    pub first_trade_id: i64,
+    #[serde(rename = "l")]
+    pub last_trade_id: i64,
+    #[serde(rename = "T")]
+    pub timestamp: i64,
+    #[serde(rename = "m")]
+    pub is_buyer_market_maker: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::exchange::{StreamItem, Unifier};
+    use crate::model::{ContractType, MarginMode, Market, MarketType, PositionSide};
+    use crate::util::number;
+
+    use super::{FetchMarketsSymbolResponse, parse_stream, parse_user_data};
+
+    #[test]
+    fn test_parse_combined_stream() {
+        let market = Market { base: "BTC".into(), quote: "USDT".into(), ..Default::default() };
+        let inverse = Market { base: "BTC".into(), quote: "USD".into(), contract_type: Some(ContractType::Inverse), contract_size: Some(100.0), ..Default::default() };
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"BTCUSDT".to_string());
+        unifier.insert_market_symbol_id(&inverse, &"BTCUSD_PERP".to_string());
+
+        let trade = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":123456789,"s":"BTCUSDT","a":5933014,"p":"0.001","q":"100","f":100,"l":105,"T":123456785,"m":true}}"#;
+        match parse_stream(trade.as_bytes(), &unifier).unwrap() {
+            StreamItem::Trade(Ok(trade)) => assert_eq!(trade.id, "5933014"),
+            item => panic!("unexpected item: {:?}", item),
+        }
+        // 10 contracts of 100 USD are worth 0.02 BTC at 50000
+        let trade = r#"{"stream":"btcusd_perp@aggTrade","data":{"e":"aggTrade","E":123456789,"s":"BTCUSD_PERP","a":5933015,"p":"50000","q":"10","f":100,"l":105,"T":123456785,"m":true}}"#;
+        match parse_stream(trade.as_bytes(), &unifier).unwrap() {
+            StreamItem::Trade(Ok(trade)) => assert_eq!(trade.cost, number("0.02")),
+            item => panic!("unexpected item: {:?}", item),
+        }
+        let depth = r#"{"stream":"btcusdt@depth5@100ms","data":{"e":"depthUpdate","E":1571889248277,"T":1571889248276,"s":"BTCUSDT","U":390497796,"u":390497878,"pu":390497794,"b":[["7403.89","0.002"]],"a":[["7405.96","3.340"]]}}"#;
+        match parse_stream(depth.as_bytes(), &unifier).unwrap() {
+            StreamItem::OrderBook(Ok(book)) => assert_eq!(book.asks.len(), 1),
+            item => panic!("unexpected item: {:?}", item),
+        }
+        assert!(matches!(parse_stream(br#"{"stream":"btcusdt@markPrice","data":{"e":"markPriceUpdate"}}"#, &unifier), Ok(StreamItem::Unknown(_))));
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"symbol": "BTCUSD_240628", "pair": "BTCUSD", "contractType": "CURRENT_QUARTER", "deliveryDate": 1719561600000, "onboardDate": 1703836800000, "contractStatus": "TRADING", "contractSize": 100, "marginAsset": "BTC", "baseAsset": "BTC", "quoteAsset": "USD", "pricePrecision": 1, "quantityPrecision": 0, "filters": [{"filterType": "PRICE_FILTER", "minPrice": "1000", "maxPrice": "4520958", "tickSize": "0.1"}, {"filterType": "LOT_SIZE", "stepSize": "1", "maxQty": "1000", "minQty": "1"}]}"#;
+        let resp: FetchMarketsSymbolResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(ContractType::Inverse).unwrap();
+        assert_eq!(market.market_type, MarketType::Future);
+        assert_eq!(market.contract_type, Some(ContractType::Inverse));
+        assert_eq!(market.contract_size, Some(100.0));
+        assert!(market.active);
+        assert_eq!(format!("{}", market), "BTC/USD:BTC-240628");
+        assert_eq!(market.tick_size, Some(number("0.1")));
+        assert_eq!(market.step_size, Some(number("1")));
+
+        let message = r#"{"symbol": "BTCUSD_PERP", "pair": "BTCUSD", "contractType": "PERPETUAL", "deliveryDate": 4133404800000, "contractStatus": "TRADING", "contractSize": 100, "marginAsset": "BTC", "baseAsset": "BTC", "quoteAsset": "USD"}"#;
+        let resp: FetchMarketsSymbolResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(ContractType::Inverse).unwrap();
+        assert_eq!(market.expiry, None);
+        assert_eq!(format!("{}", market), "BTC/USD:BTC");
+
+        let message = r#"{"symbol": "BTCUSDT", "pair": "BTCUSDT", "contractType": "PERPETUAL", "deliveryDate": 4133404800000, "status": "TRADING", "marginAsset": "USDT", "baseAsset": "BTC", "quoteAsset": "USDT"}"#;
+        let resp: FetchMarketsSymbolResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(ContractType::Linear).unwrap();
+        assert!(market.active);
+        assert_eq!(market.contract_size, Some(1.0));
+        assert_eq!(format!("{}", market), "BTC/USDT:USDT");
+    }
+
+    #[test]
+    fn test_parse_account_update() {
+        let market = Market { base: "BTC".into(), quote: "USDT".into(), ..Default::default() };
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"BTCUSDT".to_string());
+
+        let message = r#"{"e": "ACCOUNT_UPDATE", "E": 1564745798939, "T": 1564745798938, "a": {"m": "ORDER", "B": [{"a": "USDT", "wb": "122624.12345678", "cw": "100.12345678", "bc": "50.12345678"}], "P": [{"s": "BTCUSDT", "pa": "-20", "ep": "6563.66500", "bep": "0", "cr": "0", "up": "2850.21200", "mt": "isolated", "iw": "13200.70726908", "ps": "BOTH"}]}}"#;
+        let items = parse_user_data(message.as_bytes(), &unifier).unwrap();
+        assert_eq!(items.len(), 2);
+        match &items[0] {
+            StreamItem::Balance(Ok(balance)) => {
+                assert_eq!(balance.timestamp, Some(1564745798938));
+                assert_eq!(balance.items[0].currency, "USDT");
+                assert_eq!(balance.items[0].free, 100.12345678);
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+        match &items[1] {
+            StreamItem::Position(Ok(position)) => {
+                assert_eq!(position.side, PositionSide::Short);
+                assert_eq!(position.contracts, 20.0);
+                assert_eq!(position.margin_mode, MarginMode::Isolated);
+                assert_eq!(position.collateral, 13200.70726908);
+                assert!(!position.is_hedged);
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+    }
+
+    #[test]
+    fn test_parse_inverse_account_update() {
+        let market = Market { base: "BTC".into(), quote: "USD".into(), ..Default::default() };
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"BTCUSD_PERP".to_string());
+
+        let message = r#"{"e": "ACCOUNT_UPDATE", "E": 1564745798939, "T": 1564745798938, "i": "SfsR", "a": {"m": "ORDER", "B": [{"a": "BTC", "wb": "0.12345678", "cw": "0.10000000", "bc": "0"}], "P": [{"s": "BTCUSD_PERP", "pa": "-20", "ep": "6563.6", "cr": "0", "up": "0.0001", "mt": "isolated", "iw": "0.05", "ps": "BOTH"}]}}"#;
+        let items = parse_user_data(message.as_bytes(), &unifier).unwrap();
+        assert_eq!(items.len(), 2);
+        match &items[0] {
+            StreamItem::Balance(Ok(balance)) => {
+                assert_eq!(balance.items[0].currency, "BTC");
+                assert_eq!(balance.items[0].free, 0.1);
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+        match &items[1] {
+            StreamItem::Position(Ok(position)) => {
+                assert_eq!(position.side, PositionSide::Short);
+                assert_eq!(position.contracts, 20.0);
+                assert_eq!(position.margin_mode, MarginMode::Isolated);
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+    }
+}
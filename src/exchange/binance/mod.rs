@@ -1,7 +1,13 @@
 pub use binance::Binance;
 pub use usdm::BinanceUsdm;
+pub use coinm::BinanceCoinm;
+pub use options::BinanceOptions;
 
 mod usdm;
+mod coinm;
+mod futures;
+mod options;
+#[allow(clippy::module_inception)]
 mod binance;
 mod util;
 
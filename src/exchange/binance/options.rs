@@ -0,0 +1,728 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::client::EMPTY_QUERY;
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{into_precision, parse_float64, parse_number};
+
+use super::util;
+
+/// European options on `eapi.binance.com`. Options are settled in USDT and quoted per unit of
+/// the underlying.
+pub struct BinanceOptions {
+    exchange_base: ExchangeBase,
+    api_key: Option<String>,
+    secret: Option<String>,
+    recv_window: String,
+}
+
+
+/// Request weights of the endpoints in use.
+fn request_weight(_: &Method, path: &str, query: &str) -> u32 {
+    let limit = util::get_query_value(query, "limit").and_then(|l| l.parse::<u32>().ok());
+    match path {
+        "/eapi/v1/depth" => match limit.unwrap_or(100) {
+            0..=100 => 2,
+            101..=500 => 5,
+            _ => 10,
+        },
+        "/eapi/v1/mark" | "/eapi/v1/position" => 5,
+        _ => 1,
+    }
+}
+
+impl BinanceOptions {
+    /// There is no testnet for options, so `sandbox` is only accepted along with a `host`.
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        if props.sandbox && props.host.is_none() {
+            return Err(Error::UnsupportedInSandbox("options have no testnet".into()).into());
+        }
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some("https://eapi.binance.com".to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some("wss://nbstream.binance.com/eoptions/ws".to_string())))
+            .error_parser(Some(|message| {
+                match serde_json::from_str::<ErrorResponse>(&message) {
+                    Ok(error) => {
+                        match error.code {
+                            -2018 => Error::InsufficientMargin(error.msg), // Balance is insufficient
+                            -1021 => Error::TimestampOutsideRecvWindow(error.msg), // Timestamp for this request is outside of the recvWindow
+                            -1022 => Error::InvalidSignature(error.msg), // Signature for this request is not valid
+                            -1102..=-1100 => Error::InvalidParameters(error.msg), // Illegal, too many or mandatory parameters
+                            -1121 => Error::SymbolNotFound(error.msg), // Invalid symbol
+                            -2011 | -2013 => Error::OrderNotFound(error.msg), // Unknown order sent / Order does not exist
+                            _ => Error::HttpError(error.msg),
+                        }
+                    }
+                    Err(_) => Error::DeserializeJsonBody(message),
+                }
+            }))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            .rate_limits(util::get_request_weight_limit("REQUEST_WEIGHT", "MINUTE", 1, 400).map(|limit| vec![limit]))
+            .weight_calculator(Some(request_weight))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            recv_window: props.recv_window.unwrap_or(util::DEFAULT_RECV_WINDOW).to_string(),
+        })
+    }
+
+    /// Timestamp for signed requests, on the exchange clock when time sync is enabled. The clock
+    /// is synced in the background from `load_markets`.
+    fn timestamp(&self) -> i64 {
+        self.exchange_base.clock.timestamp()
+    }
+
+    fn auth(&self, request: &String) -> Result<String> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(request.as_bytes());
+        Ok(hex::encode(signed_key.finalize().into_bytes()))
+    }
+
+    fn auth_map(&self, params: &[(&str, &str)]) -> Result<String> {
+        let params = params.iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<String>>()
+            .join("&");
+        self.auth(&params)
+    }
+
+    fn get_symbol_id(&self, market: &Market) -> Result<String> {
+        self.exchange_base.unifier.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))
+    }
+
+    async fn fetch_exchange_info(&self) -> Result<Vec<(String, Market)>> {
+        let result: FetchMarketsResponse = self.exchange_base.http_client.get("/eapi/v1/exchangeInfo", None, EMPTY_QUERY).await?;
+        result.update_rate_limits(&self.exchange_base.http_client);
+        result.option_symbols.iter()
+            .map(|s| Ok((s.symbol.clone(), Result::<Market>::from(s)?)))
+            .collect()
+    }
+
+    /// Index price of an underlying, e.g. `BTCUSDT`, with its timestamp.
+    async fn fetch_index(&self, underlying: &str) -> Result<IndexResponse> {
+        let query = vec![("underlying", underlying)];
+        self.exchange_base.http_client.get("/eapi/v1/index", None, Some(&query)).await
+    }
+}
+
+#[async_trait]
+impl Exchange for BinanceOptions {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        util::start_time_sync(&self.exchange_base.clock, &self.exchange_base.http_client, "/eapi/v1/time".into()).await;
+        if self.exchange_base.markets.is_empty() {
+            let result = self.fetch_exchange_info().await?;
+            self.exchange_base.unifier.reset();
+            let mut markets = vec![];
+            for (symbol_id, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &symbol_id);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.fetch_exchange_info().await?;
+        Ok(result.into_iter().map(|(_, market)| market).collect())
+    }
+
+    async fn fetch_order_book(&self, params: FetchOrderBookParams) -> FetchOrderBookResult<OrderBook> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = vec![("symbol", symbol_id.as_str())];
+        if let Some(limit) = &limit {
+            query.push(("limit", limit.as_str()));
+        }
+        let result: FetchOrderBookResponse = self.exchange_base.http_client.get("/eapi/v1/depth", None, Some(&query)).await?;
+        let bids = result.bids.iter().map(OrderBookUnit::try_from).collect::<OrderBookResult<Vec<OrderBookUnit>>>()
+            .map_err(|e| Error::InvalidOrderBook(format!("{:?}", e)))?;
+        let asks = result.asks.iter().map(OrderBookUnit::try_from).collect::<OrderBookResult<Vec<OrderBookUnit>>>()
+            .map_err(|e| Error::InvalidOrderBook(format!("{:?}", e)))?;
+        Ok(OrderBook::new(bids, asks, params.market, Some(result.transaction_time), Some(result.update_id)))
+    }
+
+    async fn fetch_greeks(&self, params: FetchGreeksParams) -> FetchGreeksResult<Greeks> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let query = vec![("symbol", symbol_id.as_str())];
+        let result: Vec<MarkPriceResponse> = self.exchange_base.http_client.get("/eapi/v1/mark", None, Some(&query)).await?;
+        let mark = result.first().ok_or_else(|| Error::SymbolNotFound(symbol_id.clone()))?;
+        let index = self.fetch_index(&get_underlying(&params.market)).await?;
+        Ok(mark.to_greeks(params.market, &index)?)
+    }
+
+    async fn fetch_option_chain(&self, params: FetchOptionChainParams) -> FetchOptionChainResult<OptionChain> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let base = util::to_unified_asset(&params.base);
+        let quote = match self.exchange_base.markets.iter().find(|market| market.base == base) {
+            Some(market) => market.quote.clone(),
+            None => return Err(FetchOptionChainError::UnderlyingNotFound(base)),
+        };
+        let index = self.fetch_index(&format!("{}{}", base, quote)).await?;
+
+        // marks are only listed per symbol or for every option at once
+        let result: Vec<MarkPriceResponse> = self.exchange_base.http_client.get("/eapi/v1/mark", None, EMPTY_QUERY).await?;
+        let mut options = vec![];
+        for mark in result {
+            let market = match self.exchange_base.unifier.get_market(&mark.symbol) {
+                Some(market) if market.base == base => market,
+                _ => continue,
+            };
+            if params.expiry.is_some_and(|expiry| market.expiry != Some(expiry)) {
+                continue;
+            }
+            options.push(mark.to_greeks(market, &index)?);
+        }
+        options.sort_by(|a, b| {
+            a.market.expiry.cmp(&b.market.expiry)
+                .then(a.market.strike.unwrap_or_default().total_cmp(&b.market.strike.unwrap_or_default()))
+                .then((a.market.option_type == Some(OptionType::Put)).cmp(&(b.market.option_type == Some(OptionType::Put))))
+        });
+        Ok(OptionChain {
+            base,
+            timestamp: index.time,
+            underlying_price: Some(parse_float64(&index.index_price)?),
+            options,
+        })
+    }
+
+    async fn fetch_positions(&self, _params: FetchPositionsParams) -> FetchPositionsResult<Vec<Position>> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let timestamp = self.timestamp().to_string();
+        let mut queries = vec![
+            ("recvWindow", self.recv_window.as_str()),
+            ("timestamp", timestamp.as_str()),
+        ];
+        let signature = self.auth_map(&queries)?;
+        queries.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let result: Vec<FetchPositionsResponse> = self.exchange_base.http_client.get("/eapi/v1/position", Some(headers), Some(&queries)).await?;
+        let now = Utc::now().timestamp_millis();
+        let mut positions = vec![];
+        for item in result {
+            if let Some(market) = self.exchange_base.unifier.get_market(&item.symbol) {
+                if let Some(position) = item.to_position(market, now)? {
+                    positions.push(position);
+                }
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Only limit orders are accepted for options. `TimeInForce::PO` places a post-only order.
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if order_type != OrderType::Limit {
+            return Err(Error::UnsupportedOrderType(order_type.to_string()).into());
+        }
+        let price = params.price.ok_or_else(|| Error::InvalidPrice("price is required for limit orders".into()))?;
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.exchange_base.unifier.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+                let amount = market.amount_to_precision(params.amount);
+                let price = market.price_to_precision(price);
+                market.validate_order(amount, Some(price))?;
+                (amount, price)
+            }
+            false => (params.amount, price),
+        };
+        let time_in_force = params.time_in_force.unwrap_or(TimeInForce::GTC);
+        let timestamp = self.timestamp();
+        let mut body = format!("symbol={}&side={}&type=LIMIT&quantity={}&price={}&timeInForce={}&recvWindow={}&timestamp={}",
+                               symbol_id,
+                               util::get_exchange_order_side(&params.order_side),
+                               amount,
+                               price,
+                               match time_in_force {
+                                   TimeInForce::PO => "GTC",
+                                   _ => util::get_exchange_time_in_force(&time_in_force),
+                               },
+                               self.recv_window,
+                               timestamp);
+        if time_in_force == TimeInForce::PO {
+            body = format!("{}&postOnly=true", body);
+        }
+        if params.reduce_only {
+            body = format!("{}&reduceOnly=true", body);
+        }
+
+        let signature = self.auth(&body)?;
+        let body = format!("{}&signature={}", body, signature);
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let response: OrderResponse = self.exchange_base.http_client.post("/eapi/v1/order", Some(headers), EMPTY_QUERY, Some(&body)).await?;
+        Ok(response.to_order(params.market)?)
+    }
+
+    async fn cancel_order(&self, params: CancelOrderParams) -> CancelOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let timestamp = self.timestamp().to_string();
+        let mut queries = vec![
+            ("symbol", symbol_id.as_str()),
+            util::get_exchange_order_id(&params.order_id, &params.client_order_id)?,
+            ("recvWindow", self.recv_window.as_str()),
+            ("timestamp", timestamp.as_str()),
+        ];
+        let signature = self.auth_map(&queries)?;
+        queries.push(("signature", signature.as_str()));
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
+        let response: OrderResponse = self.exchange_base.http_client.delete("/eapi/v1/order", Some(headers), Some(&queries)).await?;
+        Ok(response.to_order(params.market)?)
+    }
+}
+
+
+/// Underlying id of an option market, e.g. `BTCUSDT`.
+fn get_underlying(market: &Market) -> String {
+    format!("{}{}", market.base, market.quote)
+}
+
+fn get_unified_order_status(exchange_order_status: &str) -> Result<OrderStatus> {
+    match exchange_order_status {
+        "ACCEPTED" | "PARTIALLY_FILLED" => Ok(OrderStatus::Open),
+        "FILLED" => Ok(OrderStatus::Closed),
+        "CANCELLED" => Ok(OrderStatus::Canceled),
+        "REJECTED" => Ok(OrderStatus::Rejected),
+        _ => Err(Error::UnsupportedOrderStatus(exchange_order_status.to_string())),
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ErrorResponse {
+    code: i64,
+    msg: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsResponse {
+    rate_limits: Vec<FetchMarketsRateLimitResponse>,
+    server_time: i64,
+    option_symbols: Vec<FetchMarketsSymbolResponse>,
+}
+
+impl FetchMarketsResponse {
+    /// Applies the announced request weight limits to the client. The defaults are kept when none
+    /// is announced.
+    fn update_rate_limits(&self, http_client: &HttpClient) {
+        let rate_limits: Vec<_> = self.rate_limits.iter()
+            .filter_map(|r| util::get_request_weight_limit(&r.rate_limit_type, &r.interval, r.interval_num, r.limit))
+            .collect();
+        if !rate_limits.is_empty() {
+            http_client.rate_limiter().set_rate_limits(rate_limits);
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsRateLimitResponse {
+    interval: String,
+    interval_num: i64,
+    limit: i64,
+    rate_limit_type: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsSymbolResponse {
+    /// e.g. `BTC-240628-60000-C`
+    symbol: String,
+    expiry_date: i64,
+    /// `CALL` or `PUT`
+    side: String,
+    strike_price: String,
+    /// e.g. `BTCUSDT`
+    underlying: String,
+    /// amount of the underlying per contract
+    unit: f64,
+    quote_asset: String,
+    status: Option<String>,
+    filters: Vec<FetchMarketsFilterResponse>,
+}
+
+impl From<&FetchMarketsSymbolResponse> for Result<Market> {
+    fn from(resp: &FetchMarketsSymbolResponse) -> Self {
+        let base_id = resp.symbol.split('-').next().unwrap_or_default();
+        let quote = util::to_unified_asset(&resp.quote_asset);
+        let option_type = match resp.side.as_str() {
+            "CALL" => OptionType::Call,
+            "PUT" => OptionType::Put,
+            side => return Err(Error::InvalidResponse(format!("unknown option side {}", side))),
+        };
+
+        let mut limit = MarketLimit { amount: None, price: None, cost: None, leverage: None };
+        let mut precision = Precision { amount: None, price: None, cost: None };
+        for filter in &resp.filters {
+            match filter.filter_type.as_str() {
+                "PRICE_FILTER" => {
+                    if let (Some(min), Some(max)) = (&filter.min_price, &filter.max_price) {
+                        limit.price = Some(Range { min: parse_float64(min)?, max: parse_float64(max)? });
+                    }
+                    if let Some(tick_size) = &filter.tick_size {
+                        precision.price = Some(into_precision(tick_size.clone())?);
+                    }
+                }
+                "LOT_SIZE" => {
+                    if let (Some(min), Some(max)) = (&filter.min_qty, &filter.max_qty) {
+                        limit.amount = Some(Range { min: parse_float64(min)?, max: parse_float64(max)? });
+                    }
+                    if let Some(step_size) = &filter.step_size {
+                        precision.amount = Some(into_precision(step_size.clone())?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Market {
+            base: util::to_unified_asset(base_id),
+            settle: Some(quote.clone()),
+            quote,
+            // listed options are tradable unless the status says otherwise
+            active: resp.status.as_ref().map(|status| status == "TRADING").unwrap_or(true),
+            market_type: MarketType::Option,
+            contract_size: Some(resp.unit),
+            contract_type: Some(ContractType::Linear),
+            expiry: Some(resp.expiry_date),
+            strike: Some(parse_float64(&resp.strike_price)?),
+            option_type: Some(option_type),
+            precision: Some(precision),
+            limit: Some(limit),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsFilterResponse {
+    filter_type: String,
+    max_price: Option<String>,
+    min_price: Option<String>,
+    tick_size: Option<String>,
+    max_qty: Option<String>,
+    min_qty: Option<String>,
+    step_size: Option<String>,
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchOrderBookResponse {
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    #[serde(rename = "u")]
+    update_id: i64,
+    bids: Vec<[String; 2]>,
+    asks: Vec<[String; 2]>,
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexResponse {
+    time: i64,
+    index_price: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MarkPriceResponse {
+    symbol: String,
+    mark_price: String,
+    #[serde(rename = "bidIV")]
+    bid_iv: String,
+    #[serde(rename = "askIV")]
+    ask_iv: String,
+    #[serde(rename = "markIV")]
+    mark_iv: String,
+    delta: String,
+    theta: String,
+    gamma: String,
+    vega: String,
+}
+
+impl MarkPriceResponse {
+    /// Implied volatilities are reported as zero when there is no bid or ask.
+    fn to_greeks(&self, market: Market, index: &IndexResponse) -> Result<Greeks> {
+        let bid_iv = parse_float64(&self.bid_iv)?;
+        let ask_iv = parse_float64(&self.ask_iv)?;
+        Ok(Greeks {
+            market,
+            timestamp: index.time,
+            delta: parse_float64(&self.delta)?,
+            gamma: parse_float64(&self.gamma)?,
+            theta: parse_float64(&self.theta)?,
+            vega: parse_float64(&self.vega)?,
+            mark_price: parse_float64(&self.mark_price)?,
+            mark_implied_volatility: parse_float64(&self.mark_iv)?,
+            bid_implied_volatility: Some(bid_iv).filter(|iv| *iv > 0.0),
+            ask_implied_volatility: Some(ask_iv).filter(|iv| *iv > 0.0),
+            underlying_price: Some(parse_float64(&index.index_price)?),
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchPositionsResponse {
+    symbol: String,
+    /// `LONG` or `SHORT`
+    side: String,
+    entry_price: String,
+    /// in contracts, negative for short positions
+    quantity: String,
+    mark_price: String,
+    mark_value: String,
+    /// cost of the position
+    position_cost: String,
+    unrealized_p_n_l: String,
+    /// return on the cost, as a fraction
+    ror: String,
+}
+
+impl FetchPositionsResponse {
+    /// Returns `None` for empty positions. The exchange does not report when a position changed,
+    /// so `timestamp` is used.
+    fn to_position(&self, market: Market, timestamp: i64) -> Result<Option<Position>> {
+        let contracts = parse_float64(&self.quantity)?.abs();
+        if contracts == 0.0 {
+            return Ok(None);
+        }
+        let side = match self.side.as_str() {
+            "SHORT" => PositionSide::Short,
+            _ => PositionSide::Long,
+        };
+        let collateral = parse_float64(&self.position_cost)?.abs();
+        Ok(Some(Position {
+            contract_size: market.contract_size,
+            market,
+            timestamp,
+            side,
+            contracts,
+            entry_price: parse_float64(&self.entry_price)?,
+            mark_price: parse_float64(&self.mark_price)?,
+            notional: parse_float64(&self.mark_value)?.abs(),
+            leverage: 1.0,
+            collateral,
+            initial_margin: collateral,
+            unrealized_pnl: parse_float64(&self.unrealized_p_n_l)?,
+            margin_mode: MarginMode::Cross,
+            percentage: parse_float64(&self.ror)? * 100f64,
+            ..Default::default()
+        }))
+    }
+}
+
+
+/// Order returned by the create and cancel endpoints.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderResponse {
+    order_id: i64,
+    client_order_id: Option<String>,
+    price: String,
+    quantity: String,
+    executed_qty: String,
+    side: String,
+    time_in_force: String,
+    post_only: bool,
+    create_time: i64,
+    status: String,
+    avg_price: String,
+}
+
+impl OrderResponse {
+    fn to_order(&self, market: Market) -> Result<Order> {
+        let status = get_unified_order_status(&self.status)?;
+        let amount = parse_number(&self.quantity)?;
+        let filled = parse_number(&self.executed_qty)?;
+        let time_in_force = match self.post_only {
+            true => TimeInForce::PO,
+            false => util::get_unified_time_in_force(&self.time_in_force)?,
+        };
+        Ok(Order {
+            id: Some(self.order_id.to_string()),
+            client_order_id: self.client_order_id.clone().filter(|id| !id.is_empty()),
+            timestamp: self.create_time,
+            status,
+            market,
+            order_type: OrderType::Limit,
+            time_in_force: Some(time_in_force),
+            side: Some(util::get_unified_order_side(&self.side)?),
+            price: Some(parse_number(&self.price)?),
+            average: Some(parse_number(&self.avg_price)?),
+            amount,
+            filled: Some(filled),
+            remaining: Some(amount - filled).filter(|_| status == OrderStatus::Open),
+            margin_mode: MarginMode::Cross,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BinanceOptions, PropertiesBuilder};
+    use crate::model::{ContractType, Market, MarketType, OptionType, OrderStatus, PositionSide, TimeInForce};
+
+    use super::{FetchMarketsSymbolResponse, FetchPositionsResponse, IndexResponse, MarkPriceResponse, OrderResponse};
+
+    fn option_market() -> Market {
+        Market {
+            base: "BTC".into(),
+            quote: "USDT".into(),
+            settle: Some("USDT".into()),
+            market_type: MarketType::Option,
+            contract_size: Some(1.0),
+            expiry: Some(1719561600000),
+            strike: Some(60000.0),
+            option_type: Some(OptionType::Call),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"id": 1, "contractId": 2, "underlying": "BTCUSDT", "quoteAsset": "USDT", "symbol": "BTC-240628-60000-C", "unit": 1, "minQty": "0.01", "maxQty": "500", "priceScale": 0, "quantityScale": 2, "side": "CALL", "strikePrice": "60000", "expiryDate": 1719561600000, "filters": [{"filterType": "PRICE_FILTER", "minPrice": "5", "maxPrice": "100000", "tickSize": "5"}, {"filterType": "LOT_SIZE", "minQty": "0.01", "maxQty": "500", "stepSize": "0.01"}]}"#;
+        let resp: FetchMarketsSymbolResponse = serde_json::from_str(message).unwrap();
+        let market: crate::error::Result<Market> = (&resp).into();
+        let market = market.unwrap();
+        assert_eq!(market.market_type, MarketType::Option);
+        assert_eq!(market.contract_type, Some(ContractType::Linear));
+        assert_eq!(market.strike, Some(60000.0));
+        assert_eq!(market.option_type, Some(OptionType::Call));
+        assert!(market.active);
+        assert_eq!(market, option_market());
+        assert_eq!(format!("{}", market), "BTC/USDT:USDT-240628-60000-C");
+
+        let message = r#"{"quoteAsset": "USDT", "symbol": "ETH-240628-3000-P", "unit": 1, "side": "PUT", "strikePrice": "3000", "underlying": "ETHUSDT", "expiryDate": 1719561600000, "filters": []}"#;
+        let resp: FetchMarketsSymbolResponse = serde_json::from_str(message).unwrap();
+        let market: crate::error::Result<Market> = (&resp).into();
+        assert_eq!(format!("{}", market.unwrap()), "ETH/USDT:USDT-240628-3000-P");
+    }
+
+    #[test]
+    fn test_to_greeks() {
+        let message = r#"{"symbol": "BTC-240628-60000-C", "markPrice": "2150", "bidIV": "0.52", "askIV": "0", "markIV": "0.54", "delta": "0.48", "theta": "-35.2", "gamma": "0.00003", "vega": "88.1", "highPriceLimit": "4000", "lowPriceLimit": "5", "riskFreeInterest": "0.1"}"#;
+        let mark: MarkPriceResponse = serde_json::from_str(message).unwrap();
+        let index = IndexResponse { time: 1719000000000, index_price: "61000.5".into() };
+        let greeks = mark.to_greeks(option_market(), &index).unwrap();
+        assert_eq!(greeks.timestamp, 1719000000000);
+        assert_eq!(greeks.delta, 0.48);
+        assert_eq!(greeks.theta, -35.2);
+        assert_eq!(greeks.mark_price, 2150.0);
+        assert_eq!(greeks.mark_implied_volatility, 0.54);
+        assert_eq!(greeks.bid_implied_volatility, Some(0.52));
+        assert_eq!(greeks.ask_implied_volatility, None);
+        assert_eq!(greeks.underlying_price, Some(61000.5));
+    }
+
+    #[test]
+    fn test_to_order() {
+        let message = r#"{"orderId": 4611875134427365377, "symbol": "BTC-240628-60000-C", "price": "2000", "quantity": "0.1", "executedQty": "0.04", "fee": "0", "side": "BUY", "type": "LIMIT", "timeInForce": "GTC", "reduceOnly": false, "postOnly": true, "createTime": 1592465880683, "updateTime": 1566818724722, "status": "PARTIALLY_FILLED", "avgPrice": "2000", "clientOrderId": "", "priceScale": 0, "quantityScale": 2, "optionSide": "CALL", "quoteAsset": "USDT", "mmp": false}"#;
+        let resp: OrderResponse = serde_json::from_str(message).unwrap();
+        let order = resp.to_order(option_market()).unwrap();
+        assert_eq!(order.id, Some("4611875134427365377".into()));
+        assert_eq!(order.client_order_id, None);
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.time_in_force, Some(TimeInForce::PO));
+        assert_eq!(order.timestamp, 1592465880683);
+    }
+
+    #[test]
+    fn test_to_position() {
+        let item = FetchPositionsResponse {
+            symbol: "BTC-240628-60000-C".into(),
+            side: "SHORT".into(),
+            entry_price: "2000".into(),
+            quantity: "-0.5".into(),
+            mark_price: "2150".into(),
+            mark_value: "-1075".into(),
+            position_cost: "-1000".into(),
+            unrealized_p_n_l: "-75".into(),
+            ror: "-0.075".into(),
+        };
+        let position = item.to_position(option_market(), 1719000000000).unwrap().unwrap();
+        assert_eq!(position.side, PositionSide::Short);
+        assert_eq!(position.contracts, 0.5);
+        assert_eq!(position.notional, 1075.0);
+        assert_eq!(position.unrealized_pnl, -75.0);
+        assert_eq!(position.percentage, -7.5);
+
+        let empty = FetchPositionsResponse { quantity: "0".into(), ..item };
+        assert!(empty.to_position(option_market(), 1719000000000).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_auth() {
+        let api_key = "dbefbc809e3e83c283a984c3a1459732ea7db1360ca80c5c2c8867408d28cc83";
+        let secret = "2b5eb11e18796d12d88f13dc27dbbd02c2cc51ff7059765ed9821957d82bb4d9";
+
+        let props = PropertiesBuilder::default().api_key(Some(api_key.to_string())).secret(Some(secret.to_string())).build().expect("failed to create properties");
+        let exchange = BinanceOptions::new(props).expect("failed to create exchange");
+        let params = vec![
+            ("symbol", "BTC-240628-60000-C"),
+            ("side", "BUY"),
+            ("type", "LIMIT"),
+            ("quantity", "0.1"),
+            ("price", "2000"),
+            ("timeInForce", "GTC"),
+            ("recvWindow", "5000"),
+            ("timestamp", "1591702613943"),
+        ];
+        let result = exchange.auth_map(&params);
+        assert_eq!(result.unwrap(), "80439f48a71ad667ef6822a1081d97c85744ef2192398f41d6add3c56f49180f");
+
+        let props = PropertiesBuilder::default().sandbox(true).build().expect("failed to create properties");
+        assert!(BinanceOptions::new(props).is_err());
+    }
+}
@@ -1,504 +1,154 @@
-use std::collections::HashMap;
 use std::fmt::Debug;
 
 use async_trait::async_trait;
-use chrono::Utc;
-use hmac::{Hmac, Mac};
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
 
-use crate::client::EMPTY_QUERY;
 use crate::error::*;
 use crate::exchange::*;
-use crate::util::{into_precision, parse_float64};
 use crate::util::channel::Receiver;
 
+use super::futures::{BinanceFutures, FuturesProduct};
 use super::util;
 
 pub struct BinanceUsdm {
-    exchange_base: ExchangeBase,
-    api_key: Option<String>,
-    secret: Option<String>,
-    leverage_brackets: Option<HashMap<Market, Vec<LeverageBracket>>>,
+    futures: BinanceFutures,
 }
 
+static USDM: FuturesProduct = FuturesProduct {
+    host: "https://fapi.binance.com",
+    ws_endpoint: "wss://fstream.binance.com/ws",
+    sandbox_host: "https://testnet.binancefuture.com",
+    sandbox_ws_endpoint: "wss://stream.binancefuture.com/ws",
+    prefix: "/fapi",
+    account_path: "/v2/account",
+    leverage_bracket_path: "/v1/leverageBracket",
+    contract_type: ContractType::Linear,
+    weight_calculator: request_weight,
+};
+
+
+/// Request weights of the endpoints in use.
+fn request_weight(_: &Method, path: &str, query: &str) -> u32 {
+    let limit = util::get_query_value(query, "limit").and_then(|l| l.parse::<u32>().ok());
+    match path {
+        "/fapi/v1/depth" => match limit.unwrap_or(500) {
+            0..=50 => 2,
+            51..=100 => 5,
+            101..=500 => 10,
+            _ => 20,
+        },
+        "/fapi/v1/ticker/24hr" | "/fapi/v1/openOrders" => match util::get_query_value(query, "symbol") {
+            Some(_) => 1,
+            None => 40,
+        },
+        "/fapi/v1/klines" => match limit.unwrap_or(500) {
+            0..=99 => 1,
+            100..=499 => 2,
+            500..=1000 => 5,
+            _ => 10,
+        },
+        "/fapi/v1/aggTrades" => 20,
+        "/fapi/v1/allOrders" | "/fapi/v2/account" | "/fapi/v2/positionRisk" => 5,
+        _ => 1,
+    }
+}
 
 impl BinanceUsdm {
     pub fn new(props: Properties) -> CommonResult<Self> {
-        let base_props = BasePropertiesBuilder::default()
-            .host(props.host.or(Some("https://fapi.binance.com".to_string())))
-            .port(props.port.or(Some(443)))
-            .ws_endpoint(props.ws_endpoint.or(Some("wss://fstream.binance.com/ws".to_string())))
-            .error_parser(Some(|message| {
-                match serde_json::from_str::<ErrorResponse>(&message) {
-                    Ok(error) => {
-                        match error.code {
-                            -2019 => Error::InsufficientMargin(error.msg), // Margin is insufficient
-                            -1013 => Error::InvalidAmount(error.msg), // Invalid amount
-                            -1021 => Error::HttpError(error.msg), // Timestamp for this request is outside of the recvWindow
-                            -1022 => Error::InvalidSignature(error.msg), // Signature for this request is not valid
-                            -1100 => Error::InvalidParameters(error.msg), // Illegal characters found in a parameter
-                            -1101 => Error::InvalidParameters(error.msg), // Too many parameters sent for this endpoint
-                            _ => Error::HttpError(error.msg),
-                        }
-                    }
-                    Err(_) => Error::DeserializeJsonBody(message),
-                }
-            }))
-            .stream_parser(Some(|message, unifier| {
-                let common_message = WatchCommonResponse::try_from(message.to_vec())?;
-                if common_message.code.is_some() && common_message.msg.is_some() { // error message
-                    return Err(Error::InvalidResponse(format!("code={}, msg={}", common_message.code.unwrap(), common_message.msg.unwrap())))?;
-                }
-                if common_message.id.is_some() { // subscription response
-                    let id = common_message.id.ok_or(Error::InvalidResponse("id is not found".into()))?;
-                    return Ok(StreamItem::Subscribed(id));
-                }
-                match common_message.event_type {
-                    Some(event_type) if event_type == "aggTrade" => {
-                        let resp = WatchTradesResponse::try_from(message.to_vec())?;
-                        let market = unifier.get_market(&resp.symbol);
-                        if market.is_none() {
-                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                                format!("Unknown market {}", resp.symbol), None,
-                            ))));
-                        }
-                        let order_side = match resp.is_buyer_market_maker {
-                            true => OrderSide::Sell,
-                            false => OrderSide::Buy,
-                        };
-                        let price = resp.price.parse::<f64>().map_err(|_| WatchError::ParseError(resp.price.clone()))?;
-                        let amount = resp.quantity.parse::<f64>().map_err(|_| WatchError::ParseError(resp.quantity.clone()))?;
-                        let trade = Trade::new(
-                            resp.aggregate_trade_id.to_string(),
-                            resp.trade_time,
-                            market.unwrap(),
-                            None,
-                            None,
-                            Some(order_side),
-                            Some(resp.is_buyer_market_maker),
-                            price, amount,
-                            price * amount,
-                            None, None,
-                        );
-                        Ok(StreamItem::Trade(Ok(trade)))
-                    }
-                    Some(event_type) if event_type == "depthUpdate" => {
-                        let resp = WatchOrderBookResponse::try_from(message.to_vec())?;
-                        let market = unifier.get_market(&resp.symbol);
-                        if market.is_none() {
-                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                                format!("Unknown market {}", resp.symbol), None,
-                            ))));
-                        }
-                        let market = market.unwrap();
-                        let bids = resp.bids.iter().map(|b| b.try_into()).collect::<OrderBookResult<Vec<OrderBookUnit>>>();
-                        if bids.is_err() {
-                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                                format!("Invalid bid {:?}", resp.bids), Some(market),
-                            ))));
-                        }
-                        let asks = resp.asks.iter().map(|b| b.try_into()).collect::<OrderBookResult<Vec<OrderBookUnit>>>();
-                        if asks.is_err() {
-                            return Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(
-                                format!("Invalid ask {:?}", resp.asks), Some(market),
-                            ))));
-                        }
-                        let book = OrderBook::new(
-                            bids.unwrap(),
-                            asks.unwrap(),
-                            market,
-                            Some(resp.event_time),
-                            None,
-                        );
-                        Ok(StreamItem::OrderBook(Ok(book)))
-                    }
-                    _ => {
-                        let message = String::from_utf8_lossy(&message);
-                        Ok(StreamItem::Unknown(message.to_string()))
-                    }
-                }
-            }))
-            .channel_capacity(props.channel_capacity)
-            .build()?;
-
         Ok(Self {
-            exchange_base: ExchangeBase::new(&base_props)?,
-            api_key: props.api_key.clone(),
-            secret: props.secret.clone(),
-            leverage_brackets: None,
+            futures: BinanceFutures::new(props, &USDM)?,
         })
     }
-
-    fn auth(&self, request: &String) -> Result<String> {
-        if self.api_key.is_none() || self.secret.is_none() {
-            return Err(Error::InvalidCredentials);
-        }
-        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
-        signed_key.update(request.as_bytes());
-        Ok(hex::encode(signed_key.finalize().into_bytes()))
-    }
-
-    fn auth_map(&self, params: Option<&Vec<(&str, &str)>>) -> Result<String> {
-        if self.api_key.is_none() || self.secret.is_none() {
-            return Err(Error::InvalidCredentials);
-        }
-        match params {
-            Some(params) => {
-                let params = params.iter()
-                    .map(|(k, v)| format!("{}={}", k, v))
-                    .collect::<Vec<String>>()
-                    .join("&");
-                Ok(self.auth(&params)?)
-            }
-            None => Ok(self.auth(&"".to_string())?),
-        }
-    }
-
-
-    async fn load_leverage_brackets(&mut self) -> Result<()> {
-        let mut query: Vec<(&str, &str)> = vec![];
-        let ts = Utc::now().timestamp_millis().to_string();
-        query.push(("timestamp", ts.as_str()));
-        let signature = self.auth_map(Some(&query))?;
-        query.push(("signature", signature.as_str()));
-        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
-
-        self.leverage_brackets = Some(HashMap::new());
-        let result: Vec<FetchLeverageResponse> = self.exchange_base.http_client.get("/fapi/v1/leverageBracket", Some(headers), Some(&query)).await?;
-        for resp in result {
-            let market = self.exchange_base.unifier.get_market(&resp.symbol);
-            match market {
-                Some(market) => {
-                    resp.brackets.iter().for_each(|b| {
-                        self.leverage_brackets.as_mut().unwrap().entry(market.clone()).or_insert(vec![]).push(LeverageBracket {
-                            notional_floor: b.notional_floor,
-                            maintenance_margin_ratio: b.maint_margin_ratio,
-                        });
-                    });
-                }
-                None => continue,
-            }
-        }
-        Ok(())
-    }
 }
 
 #[async_trait]
 impl Exchange for BinanceUsdm {
     async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
-        if self.exchange_base.markets.is_empty() {
-            let result = self.exchange_base.http_client.get::<(), FetchMarketsResponse>("/fapi/v1/exchangeInfo", None, None).await?;
-            self.exchange_base.unifier.reset();
-            let mut markets = vec![];
-            for s in result.symbols {
-                if s.symbol.is_none() {
-                    continue;
-                }
-                let market: Result<Market> = (&s).into();
-                let market = market?;
-                self.exchange_base.unifier.insert_market_symbol_id(&market, &(s.symbol.unwrap()));
-                markets.push(market);
-            }
-            self.exchange_base.markets = markets;
-        }
-        if self.api_key.is_some() && self.secret.is_some() && self.leverage_brackets.is_none() {
-            self.load_leverage_brackets().await?;
-        }
-        Ok(self.exchange_base.markets.clone())
+        self.futures.load_markets().await
     }
 
     async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
-        let result = self.exchange_base.http_client.get::<(), FetchMarketsResponse>("/fapi/v1/exchangeInfo", None, None).await?;
-        let mut markets = vec![];
-        for s in result.symbols {
-            if s.symbol.is_none() {
-                continue;
-            }
-            let market: Result<Market> = (&s).into();
-            let market = market?;
-            markets.push(market);
-        }
-        Ok(markets)
+        self.futures.fetch_markets().await
     }
-    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
-        if self.exchange_base.markets.is_empty() {
-            return Err(Error::MarketNotInitialized.into());
-        }
-
-        let mut result: Vec<FetchTickersResponse>;
-        if let Some(markets) = params.markets.as_ref() {
-            result = vec![];
-            for market in markets.into_iter() {
-                let symbol_id = self.exchange_base.unifier.get_symbol_id(&market).expect("market is not found");
-                let query = Some(vec![("symbol", format!("{}", symbol_id))]);
-                let item: FetchTickersResponse = self.exchange_base.http_client.get("/fapi/v1/ticker/24hr", None, query.as_ref()).await?;
-                result.push(item);
-                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
-            }
-        } else {
-            result = self.exchange_base.http_client.get("/fapi/v1/ticker/24hr", None, EMPTY_QUERY).await?;
-        }
-
-        let mut tickers = vec![];
-        for item in result {
-            let market = self.exchange_base.unifier.get_market(&item.symbol);
-            if market.is_none() {
-                continue;
-            }
-            let market = market.unwrap();
-            let timestamp = item.close_time;
 
-            let last = item.last_price.parse::<f64>()?;
-            let open = item.open_price.parse::<f64>()?;
-            tickers.push(Ticker {
-                base_volume: item.volume.parse::<f64>()?,
-                change: item.price_change.parse::<f64>()?,
-                close: last,
-                high: item.high_price.parse::<f64>()?,
-                last,
-                low: item.low_price.parse::<f64>()?,
-                open: open,
-                percentage: item.price_change_percent.parse::<f64>()?,
-                previous_close: None,
-                quote_volume: item.quote_volume.parse::<f64>()?,
-                average: (open + last) / 2f64,
-                market,
-                timestamp,
-                vwap: item.weighted_avg_price.parse::<f64>()?,
-                ..Default::default()
-            });
-        }
-        Ok(tickers)
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        self.futures.fetch_tickers(params).await
     }
 
     async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
-        if self.exchange_base.markets.is_empty() {
-            return Err(Error::MarketNotInitialized.into());
-        }
-
-        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or_else(|| Error::SymbolNotFound(format!("{}", params.market)))?;
-        let mut query = vec![("symbol", format!("{}", symbol_id))];
-        if let Some(since) = params.since {
-            query.push(("startTime", format!("{}", since)));
-        }
-        if let Some(until) = params.until {
-            query.push(("endTime", format!("{}", until)));
-        }
-        if let Some(limit) = params.limit {
-            if limit > 1000 {
-                return Err(Error::InvalidParameters("limit should be less than or equal to 1000".into()).into());
-            }
-            query.push(("limit", format!("{}", limit)));
-        }
-        let trades: Vec<FetchTradesResponse> = self.exchange_base.http_client.get("/fapi/v1/aggTrades", None, Some(&query)).await?;
+        self.futures.fetch_trades(params).await
+    }
 
+    async fn fetch_ohlcv(&self, params: FetchOhlcvParams) -> FetchOhlcvResult<Vec<Ohlcv>> {
+        self.futures.fetch_ohlcv(params).await
+    }
 
-        let mut ret = vec![];
-        for trade in trades {
-            let order_side = match trade.is_buyer_market_maker {
-                true => OrderSide::Sell,
-                false => OrderSide::Buy,
-            };
-            let price = trade.price.parse::<f64>()?;
-            let amount = trade.quantity.parse::<f64>()?;
-            let trade = Trade::new(
-                trade.aggregate_trade_id.to_string(),
-                trade.timestamp,
-                params.market.clone(),
-                None,
-                None,
-                Some(order_side),
-                Some(trade.is_buyer_market_maker),
-                price, amount,
-                price * amount,
-                None, None,
-            );
-            ret.push(trade);
-        }
-        Ok(ret)
+    async fn watch_ohlcv(&self, params: WatchOhlcvParams) -> WatchOhlcvResult<Receiver> {
+        self.futures.watch_ohlcv(params).await
     }
 
     async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
-        if self.exchange_base.markets.is_empty() {
-            return Err(Error::MarketNotInitialized.into());
-        }
-
-        if self.exchange_base.ws_endpoint.is_none() {
-            return Err(Error::InvalidParameters("ws endpoint is empty".into()).into());
-        }
-
-        let markets = &params.markets;
-        if markets.len() == 0 {
-            return Err(Error::InvalidParameters("markets is empty".into()).into());
-        }
-
-        if self.exchange_base.ws_endpoint.is_none() {
-            return Err(Error::InvalidParameters("ws endpoint is empty".into()).into());
-        }
-
-
-        let mut symbol_ids: Vec<String> = Vec::new();
-        for m in markets {
-            match self.exchange_base.unifier.get_symbol_id(&m) {
-                Some(symbol_id) => symbol_ids.push(symbol_id),
-                None => return Err(WatchError::SymbolNotFound(format!("{:?}", m))),
-            }
-        }
-
-        let mut clients = vec![];
-        for symbol_ids in symbol_ids.chunks(100) {
-            let params = symbol_ids.iter()
-                .map(|s| format!("\"{}@aggTrade\"", s.to_lowercase()))
-                .collect::<Vec<String>>()
-                .join(",");
-            let stream_name = format!("{{\"method\": \"SUBSCRIBE\", \"params\": [{params}], \"id\": 1}}");
-            let mut ws_client = WsClient::new(self.exchange_base.ws_endpoint.as_ref().unwrap().as_str(), self.exchange_base.stream_parser, self.exchange_base.unifier.clone());
-            let _ = ws_client.send(stream_name).await?;
-            clients.push(ws_client);
-            tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
-        }
-        Ok(Receiver::new(clients))
+        self.futures.watch_trades(params).await
     }
 
     async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
-        if self.exchange_base.markets.is_empty() {
-            return Err(Error::MarketNotInitialized.into());
-        }
+        self.futures.watch_order_book(params).await
+    }
 
-        let markets = &params.markets;
-        if markets.len() == 0 {
-            return Err(Error::InvalidParameters("markets is empty".into()).into());
-        }
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        self.futures.create_order(params).await
+    }
 
-        if self.exchange_base.ws_endpoint.is_none() {
-            return Err(Error::InvalidParameters("ws endpoint is empty".into()).into());
-        }
+    async fn cancel_order(&self, params: CancelOrderParams) -> CancelOrderResult<Order> {
+        self.futures.cancel_order(params).await
+    }
 
+    async fn fetch_order(&self, params: FetchOrderParams) -> FetchOrdersResult<Order> {
+        self.futures.fetch_order(params).await
+    }
 
-        let mut symbol_ids: Vec<String> = Vec::new();
-        for m in markets {
-            match self.exchange_base.unifier.get_symbol_id(&m) {
-                Some(symbol_id) => symbol_ids.push(symbol_id),
-                None => return Err(WatchError::SymbolNotFound(format!("{:?}", m))),
-            }
-        }
+    async fn fetch_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        self.futures.fetch_orders(params).await
+    }
 
-        let mut clients = vec![];
-        for symbol_ids in symbol_ids.chunks(100) {
-            let params = symbol_ids.iter()
-                .map(|s| format!("\"{}@depth5@100ms\"", s.to_lowercase()))
-                .collect::<Vec<String>>()
-                .join(",");
-            let stream_name = format!("{{\"method\": \"SUBSCRIBE\", \"params\": [{params}], \"id\": 1}}");
-            let mut ws_client = WsClient::new(self.exchange_base.ws_endpoint.as_ref().unwrap().as_str(), self.exchange_base.stream_parser, self.exchange_base.unifier.clone());
-            let _ = ws_client.send(stream_name).await?;
-            clients.push(ws_client);
-            tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
-        }
-        Ok(Receiver::new(clients))
+    async fn fetch_open_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        self.futures.fetch_open_orders(params).await
     }
 
-    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
-        if self.api_key.is_none() || self.secret.is_none() {
-            return Err(Error::InvalidCredentials.into());
-        }
+    async fn fetch_closed_orders(&self, params: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        self.futures.fetch_closed_orders(params).await
+    }
 
-        let order_type = params.order_type.unwrap_or_default();
-        if params.price.is_none() && order_type == OrderType::Limit {
-            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
-        }
-        if self.exchange_base.markets.is_empty() {
-            return Err(Error::MarketNotInitialized.into());
-        }
-        let symbol_id = self.exchange_base.unifier.get_symbol_id(&params.market).ok_or(Error::SymbolNotFound(format!("{}", params.market)))?;
-        let timestamp = Utc::now().timestamp_millis();
-        let mut body = format!("symbol={}&side={}&type={}&quantity={}&recvWindow=5000&timestamp={}",
-                               symbol_id,
-                               util::get_exchange_order_side(&params.order_side),
-                               util::get_exchange_order_type(&order_type)?,
-                               params.amount,
-                               timestamp);
-        if order_type != OrderType::Market && params.price.is_some() {
-            body = format!("{}&price={}", body, params.price.unwrap());
-        }
-        if order_type != OrderType::Market {
-            body = format!("{}&timeInForce={}", body, util::get_exchange_time_in_force(&params.time_in_force.unwrap_or(TimeInForce::GTC)))
-        }
+    async fn watch_balance(&self, params: WatchBalanceParams) -> WatchBalanceResult<Receiver> {
+        self.futures.watch_balance(params).await
+    }
 
-        let working_type = params.working_type.unwrap_or(WorkingType::ContractPrice);
+    async fn watch_my_trades(&self, params: WatchMyTradesParams) -> WatchMyTradesResult<Receiver> {
+        self.futures.watch_my_trades(params).await
+    }
 
-        match (order_type, params.callback_rate) {
-            (OrderType::TrailingStopMarket, Some(callback_rate)) => {
-                body = format!("{}&callbackRate={}&workingType={}", body, callback_rate, util::get_exchange_working_type(&working_type)?);
-            }
-            (OrderType::TrailingStopMarket, None) => {
-                return Err(Error::InvalidParameters("callback rate is required for trailing stop market orders".into()).into());
-            }
-            _ => {}
-        }
+    async fn watch_orders(&self, params: WatchOrdersParams) -> WatchOrdersResult<Receiver> {
+        self.futures.watch_orders(params).await
+    }
 
-        let signature = self.auth(&body)?;
-        let body = format!("{}&signature={}", body, signature);
-        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
-        let response: CreateOrderResponse = self.exchange_base.http_client.post("/fapi/v1/order", Some(headers), EMPTY_QUERY, Some(&body)).await?;
-        let mut order: Order = response.try_into()?;
-        order.market = params.market.clone();
-        order.order_type = order_type;
-        Ok(order)
+    async fn watch_positions(&self, params: WatchPositionsParams) -> WatchPositionsResult<Receiver> {
+        self.futures.watch_positions(params).await
     }
 
     async fn fetch_balance(&self, params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
-        if self.exchange_base.markets.is_empty() {
-            return Err(Error::MarketNotInitialized.into());
-        }
-
-        if params.margin_mode.is_some() && params.margin_mode.unwrap() != MarginMode::Cross {
-            return Err(Error::InvalidParameters("margin mode is not supported".into()).into());
-        }
-
-        let mut query = vec![];
-        let ts = Utc::now().timestamp_millis().to_string();
-        query.push(("timestamp", ts.as_str()));
-        let signature = self.auth_map(Some(&query))?;
-        query.push(("signature", signature.as_str()));
-        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
-        let resp: FetchBalanceResponse = self.exchange_base.http_client.get("/fapi/v2/account", Some(headers), Some(&query)).await?;
-        let mut bal = Balance::default();
-        bal.timestamp = None;
-
-        for asset in resp.assets {
-            let free = parse_float64(&asset.available_balance)?;
-            let used = parse_float64(&asset.initial_margin)?;
-            let total = parse_float64(&asset.margin_balance)?;
-            let item = BalanceItem {
-                currency: util::to_unified_asset(&asset.asset),
-                market: None,
-                total,
-                free,
-                used,
-                debt: 0.0,
-            };
-            bal.items.push(item);
-        }
-        Ok(bal)
+        self.futures.fetch_balance(params).await
     }
 
     async fn fetch_positions(&self, _params: FetchPositionsParams) -> FetchPositionsResult<Vec<Position>> {
-        if self.exchange_base.markets.is_empty() {
+        if self.futures.exchange_base.markets.is_empty() {
             return Err(Error::MarketNotInitialized.into());
         }
-        let mut query = vec![];
-        let ts = Utc::now().timestamp_millis().to_string();
-        query.push(("timestamp", ts.as_str()));
-        let signature = self.auth_map(Some(&query))?;
-        query.push(("signature", signature.as_str()));
-        let headers = vec![("X-MBX-APIKEY", self.api_key.as_ref().unwrap().as_str())];
-        let items: Vec<FetchPositionsResponse> = self.exchange_base.http_client.get("/fapi/v2/positionRisk", Some(headers), Some(&query)).await?;
+        let items: Vec<FetchPositionsResponse> = self.futures.get_signed("/fapi/v2/positionRisk").await?;
 
         let mut ret = vec![];
         for item in items {
-            let market = self.exchange_base.unifier.get_market(&item.symbol);
+            let market = self.futures.exchange_base.unifier.get_market(&item.symbol);
             if market.is_none() {
                 continue;
             }
@@ -507,10 +157,10 @@ impl Exchange for BinanceUsdm {
             let notional: f64 = item.notional.parse().map_err(|_| Error::ParseError(item.notional))?;
             let abs_notional = notional.abs();
 
-            let maintenance_margin_percent = self.leverage_brackets
+            let maintenance_margin_percent = self.futures.leverage_brackets
                 .as_ref()
                 .and_then(|leverage_brackets| leverage_brackets.get(&market))
-                .and_then(|brackets| brackets.iter().find(|b| abs_notional >= b.notional_floor))
+                .and_then(|brackets| brackets.iter().find(|b| abs_notional >= b.floor))
                 .map(|b| b.maintenance_margin_ratio);
 
             let maintenance_margin_percent = maintenance_margin_percent.ok_or_else(|| Error::InvalidResponse("maintenance margin ratio is not found".into()))?;
@@ -588,454 +238,6 @@ impl Exchange for BinanceUsdm {
     }
 }
 
-struct LeverageBracket {
-    notional_floor: f64,
-    maintenance_margin_ratio: f64,
-}
-
-
-#[derive(Serialize, Deserialize)]
-struct WatchTradesResponse {
-    #[serde(rename = "e")]
-    pub event_type: String,
-    #[serde(rename = "E")]
-    pub event_time: i64,
-    #[serde(rename = "s")]
-    pub symbol: String,
-    #[serde(rename = "a")]
-    pub aggregate_trade_id: i64,
-    #[serde(rename = "p")]
-    pub price: String,
-    #[serde(rename = "q")]
-    pub quantity: String,
-    #[serde(rename = "f")]
-    pub first_trade_id: i64,
-    #[serde(rename = "l")]
-    pub last_trade_id: i64,
-    #[serde(rename = "T")]
-    pub trade_time: i64,
-    #[serde(rename = "m")]
-    pub is_buyer_market_maker: bool,
-}
-
-
-impl TryFrom<Vec<u8>> for WatchTradesResponse {
-    type Error = Error;
-
-    fn try_from(message: Vec<u8>) -> Result<Self> {
-        serde_json::from_slice(&message).map_err(|e| {
-            let message = String::from_utf8_lossy(&message);
-            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
-        })
-    }
-}
-
-#[derive(Serialize, Deserialize)]
-struct WatchOrderBookResponse {
-    #[serde(rename = "e")]
-    event_type: String,
-    #[serde(rename = "E")]
-    event_time: i64,
-    #[serde(rename = "T")]
-    transaction_time: i64,
-    #[serde(rename = "s")]
-    symbol: String,
-    #[serde(rename = "U")]
-    first_update_id: i64,
-    #[serde(rename = "u")]
-    final_update_id: i64,
-    #[serde(rename = "b")]
-    bids: Vec<[String; 2]>,
-    #[serde(rename = "a")]
-    asks: Vec<[String; 2]>,
-    #[serde(rename = "pu")]
-    previous_final_update_id: i64,
-}
-
-impl TryFrom<Vec<u8>> for WatchOrderBookResponse {
-    type Error = Error;
-
-    fn try_from(message: Vec<u8>) -> Result<Self> {
-        serde_json::from_slice(&message).map_err(|e| {
-            let message = String::from_utf8_lossy(&message);
-            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
-        })
-    }
-}
-
-#[derive(Serialize, Deserialize)]
-struct WatchCommonResponse {
-    result: Option<String>,
-    id: Option<i64>,
-    code: Option<i64>,
-    msg: Option<String>,
-    #[serde(rename = "e")]
-    event_type: Option<String>,
-}
-
-impl TryFrom<Vec<u8>> for WatchCommonResponse {
-    type Error = Error;
-
-    fn try_from(message: Vec<u8>) -> Result<Self> {
-        serde_json::from_slice(&message).map_err(|e| {
-            let message = String::from_utf8_lossy(&message);
-            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
-        })
-    }
-}
-
-
-#[derive(Serialize, Deserialize)]
-struct ErrorResponse {
-    code: i64,
-    msg: String,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CreateOrderResponse {
-    client_order_id: String,
-    cum_quote: String,
-    executed_qty: String,
-    order_id: i64,
-    avg_price: String,
-    orig_qty: String,
-    price: String,
-    reduce_only: bool,
-    side: String,
-    position_side: String,
-    status: String,
-    stop_price: String,
-    close_position: bool,
-    symbol: String,
-    time_in_force: String,
-    #[serde(rename = "type")]
-    order_type: String,
-    orig_type: String,
-    activate_price: Option<String>,
-    price_rate: Option<String>,
-    update_time: i64,
-    working_type: String,
-    price_protect: bool,
-}
-
-impl TryFrom<CreateOrderResponse> for Order {
-    type Error = Error;
-
-    fn try_from(resp: CreateOrderResponse) -> std::result::Result<Self, Self::Error> {
-        let order_status = util::get_unified_order_status(&resp.status)?;
-        let amount = resp.orig_qty.parse()?;
-        let remaining = match order_status {
-            OrderStatus::Open => Some(amount),
-            _ => None,
-        };
-        Ok(Order {
-            id: Some(resp.order_id.to_string()),
-            client_order_id: Some(resp.client_order_id),
-            timestamp: resp.update_time,
-            status: order_status,
-            time_in_force: Some(util::get_unified_time_in_force(&resp.time_in_force)?),
-            side: Some(util::get_unified_order_side(&resp.side)?),
-            price: Some(resp.price.parse()?),
-            average: Some(resp.avg_price.parse()?),
-            amount: resp.orig_qty.parse()?,
-            remaining,
-            ..Default::default()
-        })
-    }
-}
-
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-struct FetchLeverageBracketResponse {
-    pub bracket: f64,
-    #[serde(rename = "initialLeverage")]
-    pub initial_leverage: f64,
-    #[serde(rename = "notionalCap")]
-    pub notional_cap: f64,
-    #[serde(rename = "notionalFloor")]
-    pub notional_floor: f64,
-    #[serde(rename = "maintMarginRatio")]
-    pub maint_margin_ratio: f64,
-    pub cum: f64,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-struct FetchLeverageResponse {
-    pub symbol: String,
-    #[serde(rename = "notionalCoef")]
-    pub notional_coef: Option<f64>,
-    pub brackets: Vec<FetchLeverageBracketResponse>,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct FetchMarketsResponse {
-    pub exchange_filters: Option<Vec<String>>,
-    pub rate_limits: Vec<FetchMarketsRateLimitResponse>,
-    pub server_time: i64,
-    pub assets: Option<Vec<FetchMarketsAssetResponse>>,
-    pub symbols: Vec<FetchMarketsSymbolResponse>,
-    pub timezone: String,
-}
-
-impl TryInto<Vec<Market>> for FetchMarketsResponse {
-    type Error = Error;
-
-    fn try_into(self) -> std::result::Result<Vec<Market>, Self::Error> {
-        self.symbols.into_iter()
-            .map(|s| (&s).into())
-            .collect()
-    }
-}
-
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct FetchMarketsRateLimitResponse {
-    pub interval: String,
-    pub interval_num: i64,
-    pub limit: i64,
-    pub rate_limit_type: String,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct FetchMarketsAssetResponse {
-    pub asset: String,
-    pub margin_available: bool,
-    pub auto_asset_exchange: Option<String>,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct FetchMarketsSymbolResponse {
-    pub symbol: Option<String>,
-    pub pair: Option<String>,
-    pub contract_type: Option<String>,
-    pub delivery_date: Option<i64>,
-    pub onboard_date: Option<i64>,
-    pub status: Option<String>,
-    pub maint_margin_percent: Option<String>,
-    pub required_margin_percent: Option<String>,
-    pub base_asset: Option<String>,
-    pub quote_asset: Option<String>,
-    pub margin_asset: Option<String>,
-    pub price_precision: Option<isize>,
-    pub quantity_precision: Option<isize>,
-    pub base_asset_precision: Option<isize>,
-    pub quote_precision: Option<isize>,
-    pub underlying_type: Option<String>,
-    pub underlying_sub_type: Option<Vec<String>>,
-    pub settle_plan: Option<i64>,
-    pub trigger_protect: Option<String>,
-    pub filters: Option<Vec<FetchMarketsFilterResponse>>,
-    pub order_type: Option<Vec<String>>,
-    pub time_in_force: Option<Vec<String>>,
-    pub liquidation_fee: Option<String>,
-    pub market_take_bound: Option<String>,
-}
-
-impl Into<Result<Market>> for &FetchMarketsSymbolResponse {
-    fn into(self) -> Result<Market> {
-        let base_id = self.base_asset.clone().ok_or_else(|| Error::MissingField("base_asset".into()))?;
-        let quote_id = self.quote_asset.clone().ok_or_else(|| Error::MissingField("quote_asset".into()))?;
-        let settle_id = self.margin_asset.clone();
-
-
-        let base = util::to_unified_asset(&base_id);
-        let quote = util::to_unified_asset(&quote_id);
-        let settle = settle_id.as_ref()
-            .and_then(|s| Some(util::to_unified_asset(s)));
-
-        let market_type = match self.contract_type {
-            Some(ref s) if s == "PERPETUAL" => MarketType::Swap,
-            Some(ref s) if s == "CURRENT_QUARTER" => MarketType::Future,
-            Some(ref s) if s == "NEXT_QUARTER" => MarketType::Future,
-            _ => MarketType::Unknown,
-        };
-
-        let active = util::is_active(self.status.clone());
-
-        let mut limit = MarketLimit {
-            amount: None,
-            price: None,
-            cost: None,
-            leverage: None,
-        };
-
-        let mut precision = Precision {
-            amount: self.quantity_precision,
-            price: self.price_precision,
-            cost: None,
-        };
-
-        for filter in self.filters.iter().flatten() {
-            match filter.filter_type.as_str() {
-                "PRICE_FILTER" => {
-                    let min = filter.min_price.as_ref().ok_or_else(|| Error::MissingField("min_price".into()))?.parse::<f64>()?;
-                    let max = filter.max_price.as_ref().ok_or_else(|| Error::MissingField("max_price".into()))?.parse::<f64>()?;
-                    limit.price = Some(Range { min, max });
-                    let tick_size = filter.tick_size.as_ref().ok_or_else(|| Error::MissingField("tick_size".into()))?;
-                    precision.price = Some(into_precision(tick_size.clone())?);
-                }
-                "LOT_SIZE" => {
-                    let min = filter.min_qty.as_ref().ok_or_else(|| Error::MissingField("min_qty".into()))?.parse::<f64>()?;
-                    let max = filter.max_qty.as_ref().ok_or_else(|| Error::MissingField("max_qty".into()))?.parse::<f64>()?;
-                    limit.amount = Some(Range { min, max });
-                }
-                "MIN_NOTIONAL" => {
-                    let min = filter.notional.as_ref().ok_or_else(|| Error::MissingField("notional".into()))?.parse::<f64>()?;
-                    limit.cost = Some(Range { min, max: f64::MAX });
-                }
-                // "MARKET_LOT_SIZE" => {},
-                // "MAX_NUM_ORDERS" => {},
-                // "MAX_NUM_ALGO_ORDERS" => {},
-                // "PERCENT_PRICE" => {},
-                _ => {}
-            }
-        }
-        Ok(Market {
-            base,
-            quote,
-            active,
-            market_type,
-            settle,
-            contract_size: Some(1.0),
-            contract_type: Some(ContractType::Linear),
-            expiry: self.delivery_date,
-            precision: Some(precision),
-            limit: Some(limit),
-            ..Default::default()
-        })
-    }
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct FetchMarketsFilterResponse {
-    pub filter_type: String,
-    pub max_price: Option<String>,
-    pub min_price: Option<String>,
-    pub tick_size: Option<String>,
-    pub max_qty: Option<String>,
-    pub min_qty: Option<String>,
-    pub step_size: Option<String>,
-    pub limit: Option<i64>,
-    pub notional: Option<String>,
-    pub multiplier_up: Option<String>,
-    pub multiplier_down: Option<String>,
-    pub multiplier_decimal: Option<String>,
-}
-
-
-#[derive(Serialize, Deserialize, Debug)]
-struct FetchBalancePositionResponse {
-    pub symbol: String,
-    #[serde(rename = "initialMargin")]
-    pub initial_margin: String,
-    #[serde(rename = "maintMargin")]
-    pub maint_margin: String,
-    #[serde(rename = "unrealizedProfit")]
-    pub unrealized_profit: String,
-    #[serde(rename = "positionInitialMargin")]
-    pub position_initial_margin: String,
-    #[serde(rename = "openOrderInitialMargin")]
-    pub open_order_initial_margin: String,
-    pub leverage: String,
-    pub isolated: bool,
-    #[serde(rename = "entryPrice")]
-    pub entry_price: String,
-    #[serde(rename = "breakEvenPrice")]
-    pub break_even_price: Option<String>,
-    #[serde(rename = "maxNotional")]
-    pub max_notional: String,
-    #[serde(rename = "bidNotional")]
-    pub bid_notional: String,
-    #[serde(rename = "askNotional")]
-    pub ask_notional: String,
-    #[serde(rename = "positionSide")]
-    pub position_side: String,
-    #[serde(rename = "positionAmt")]
-    pub position_amt: String,
-    #[serde(rename = "updateTime")]
-    pub update_time: i64,
-}
-
-
-#[derive(Serialize, Deserialize, Debug)]
-struct FetchBalanceAssetResponse {
-    pub asset: String,
-    #[serde(rename = "walletBalance")]
-    pub wallet_balance: String,
-    #[serde(rename = "unrealizedProfit")]
-    pub unrealized_profit: String,
-    #[serde(rename = "marginBalance")]
-    pub margin_balance: String,
-    #[serde(rename = "maintMargin")]
-    pub maint_margin: String,
-    #[serde(rename = "initialMargin")]
-    pub initial_margin: String,
-    #[serde(rename = "positionInitialMargin")]
-    pub position_initial_margin: String,
-    #[serde(rename = "openOrderInitialMargin")]
-    pub open_order_initial_margin: String,
-    #[serde(rename = "crossWalletBalance")]
-    pub cross_wallet_balance: String,
-    #[serde(rename = "crossUnPnl")]
-    pub cross_un_pnl: String,
-    #[serde(rename = "availableBalance")]
-    pub available_balance: String,
-    #[serde(rename = "maxWithdrawAmount")]
-    pub max_withdraw_amount: String,
-    #[serde(rename = "marginAvailable")]
-    pub margin_available: bool,
-    #[serde(rename = "updateTime")]
-    pub update_time: i64,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct FetchBalanceResponse {
-    #[serde(rename = "feeTier")]
-    pub fee_tier: i64,
-    #[serde(rename = "canTrade")]
-    pub can_trade: bool,
-    #[serde(rename = "canDeposit")]
-    pub can_deposit: bool,
-    #[serde(rename = "canWithdraw")]
-    pub can_withdraw: bool,
-    #[serde(rename = "updateTime")]
-    pub update_time: i64,
-    #[serde(rename = "multiAssetsMargin")]
-    pub multi_assets_margin: bool,
-    #[serde(rename = "tradeGroupId")]
-    pub trade_group_id: i64,
-    #[serde(rename = "totalInitialMargin")]
-    pub total_initial_margin: String,
-    #[serde(rename = "totalMaintMargin")]
-    pub total_maint_margin: String,
-    #[serde(rename = "totalWalletBalance")]
-    pub total_wallet_balance: String,
-    #[serde(rename = "totalUnrealizedProfit")]
-    pub total_unrealized_profit: String,
-    #[serde(rename = "totalMarginBalance")]
-    pub total_margin_balance: String,
-    #[serde(rename = "totalPositionInitialMargin")]
-    pub total_position_initial_margin: String,
-    #[serde(rename = "totalOpenOrderInitialMargin")]
-    pub total_open_order_initial_margin: String,
-    #[serde(rename = "totalCrossWalletBalance")]
-    pub total_cross_wallet_balance: String,
-    #[serde(rename = "totalCrossUnPnl")]
-    pub total_cross_un_pnl: String,
-    #[serde(rename = "availableBalance")]
-    pub available_balance: String,
-    #[serde(rename = "maxWithdrawAmount")]
-    pub max_withdraw_amount: String,
-    pub assets: Vec<FetchBalanceAssetResponse>,
-    pub positions: Vec<FetchBalancePositionResponse>,
-}
-
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FetchPositionsResponse {
@@ -1057,59 +259,6 @@ pub struct FetchPositionsResponse {
     pub update_time: i64,
 }
 
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-struct FetchTickersResponse {
-    pub symbol: String,
-    #[serde(rename = "priceChange")]
-    pub price_change: String,
-    #[serde(rename = "priceChangePercent")]
-    pub price_change_percent: String,
-    #[serde(rename = "weightedAvgPrice")]
-    pub weighted_avg_price: String,
-    #[serde(rename = "lastPrice")]
-    pub last_price: String,
-    #[serde(rename = "lastQty")]
-    pub last_qty: String,
-    #[serde(rename = "openPrice")]
-    pub open_price: String,
-    #[serde(rename = "highPrice")]
-    pub high_price: String,
-    #[serde(rename = "lowPrice")]
-    pub low_price: String,
-    pub volume: String,
-    #[serde(rename = "quoteVolume")]
-    pub quote_volume: String,
-    #[serde(rename = "openTime")]
-    pub open_time: i64,
-    #[serde(rename = "closeTime")]
-    pub close_time: i64,
-    #[serde(rename = "firstId")]
-    pub first_id: i64,
-    #[serde(rename = "lastId")]
-    pub last_id: i64,
-    pub count: i64,
-}
-
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-struct FetchTradesResponse {
-    #[serde(rename = "a")]
-    pub aggregate_trade_id: i64,
-    #[serde(rename = "p")]
-    pub price: String,
-    #[serde(rename = "q")]
-    pub quantity: String,
-    #[serde(rename = "f")]
-    pub first_trade_id: i64,
-    #[serde(rename = "l")]
-    pub last_trade_id: i64,
-    #[serde(rename = "T")]
-    pub timestamp: i64,
-    #[serde(rename = "m")]
-    pub is_buyer_market_maker: bool,
-}
-
 #[cfg(test)]
 mod test {
     use crate::{BinanceUsdm, Exchange, FetchBalanceParamsBuilder, FetchTickersParamsBuilder, PropertiesBuilder};
@@ -1132,7 +281,7 @@ mod test {
         params.push(("timeInForce", "GTC"));
         params.push(("recvWindow", "5000"));
         params.push(("timestamp", "1591702613943"));
-        let result = exchange.auth_map(Some(&params));
+        let result = exchange.futures.auth_map(Some(&params));
         assert_eq!(result.unwrap(), "3c661234138461fcc7a7d8746c6558c9842d4e10870d2ecbedf7777cad694af9");
     }
 
@@ -1143,7 +292,7 @@ mod test {
 
         let props = PropertiesBuilder::default().api_key(Some(api_key)).secret(Some(secret)).build().expect("failed to create properties");
         let mut exchange = BinanceUsdm::new(props).expect("failed to create exchange");
-        let result = exchange.load_leverage_brackets().await;
+        let result = exchange.futures.load_leverage_brackets().await;
         println!("{:?}", result);
         assert!(!result.is_err());
     }
@@ -1,5 +1,19 @@
-use crate::error::{Error, Result};
-use crate::model::{OrderSide, OrderStatus, OrderType, TimeInForce, WorkingType};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use crate::client::{EMPTY_BODY, EMPTY_QUERY, HttpClient, SubscriptionSpec, WsClient};
+use crate::error::{Error, OhlcvError, OhlcvResult, Result, WatchError};
+use crate::exchange::{ExchangeBase, StreamItem, Unifier};
+use crate::util::parse_float64;
+use crate::util::channel::Sender;
+use crate::util::clock::Clock;
+use crate::util::rate_limiter::RateLimit;
+use crate::model::{MarginMode, Ohlcv, OrderSide, OrderStatus, OrderType, TimeInForce, Timeframe, WorkingType};
 
 pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
     exchange_asset.to_uppercase()
@@ -9,12 +23,74 @@ pub(in super) fn is_active(status: Option<String>) -> bool {
     status.map(|status| status == "TRADING").unwrap_or(false)
 }
 
+/// Request weight limit announced in exchangeInfo. The weight spent is reported in the
+/// `X-MBX-USED-WEIGHT-<interval>` response headers. Limits other than request weight are ignored.
+pub(in super) fn get_request_weight_limit(rate_limit_type: &str, interval: &str, interval_num: i64, limit: i64) -> Option<RateLimit> {
+    if rate_limit_type != "REQUEST_WEIGHT" || interval_num <= 0 || limit <= 0 {
+        return None;
+    }
+    let (seconds, letter) = match interval {
+        "SECOND" => (1, "S"),
+        "MINUTE" => (60, "M"),
+        "HOUR" => (3600, "H"),
+        "DAY" => (86400, "D"),
+        _ => return None,
+    };
+    Some(RateLimit {
+        interval: Duration::from_secs(seconds * interval_num as u64),
+        limit: limit as u32,
+        header: Some(format!("X-MBX-USED-WEIGHT-{}{}", interval_num, letter)),
+    })
+}
+
+pub(in super) const DEFAULT_RECV_WINDOW: u64 = 5000;
+const TIME_SYNC_SAMPLES: usize = 3;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerTimeResponse {
+    server_time: i64,
+}
+
+/// Keeps the clock on the server time of `path` in the background. Each measurement samples the
+/// server time a few times and keeps the sample with the shortest round trip. The clock keeps a
+/// zero offset while the server time cannot be fetched.
+pub(in super) async fn start_time_sync(clock: &Clock, http_client: &HttpClient, path: String) {
+    let http_client = http_client.clone();
+    clock.start_sync(move || {
+        let http_client = http_client.clone();
+        let path = path.clone();
+        async move {
+            let mut best: Option<(i64, i64, i64)> = None;
+            for _ in 0..TIME_SYNC_SAMPLES {
+                let sent = Utc::now().timestamp_millis();
+                let response: ServerTimeResponse = http_client.get(&path, None, EMPTY_QUERY).await?;
+                let received = Utc::now().timestamp_millis();
+                match best {
+                    Some((best_sent, _, best_received)) if best_received - best_sent <= received - sent => {}
+                    _ => best = Some((sent, response.server_time, received)),
+                }
+            }
+            Ok(best.unwrap())
+        }
+    }).await
+}
+
+/// Value of `key` in a query string.
+pub(in super) fn get_query_value<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
 pub(in super) fn get_unified_time_in_force(time_in_force: &str) -> Result<TimeInForce> {
     match time_in_force {
         "GTC" => Ok(TimeInForce::GTC),
         "IOC" => Ok(TimeInForce::IOC),
         "FOK" => Ok(TimeInForce::FOK),
         "PO" => Ok(TimeInForce::PO),
+        "GTX" => Ok(TimeInForce::PO),
         _ => Err(Error::UnsupportedTimeInForce(time_in_force.to_string())),
     }
 }
@@ -54,6 +130,34 @@ pub(in super) fn get_exchange_order_type(order_type: &OrderType) -> Result<&'sta
     }
 }
 
+pub(in super) fn get_unified_order_type(exchange_order_type: &str) -> Result<OrderType> {
+    match exchange_order_type {
+        "LIMIT" | "LIMIT_MAKER" => Ok(OrderType::Limit),
+        "MARKET" => Ok(OrderType::Market),
+        "STOP" | "STOP_MARKET" | "STOP_LOSS" | "STOP_LOSS_LIMIT" => Ok(OrderType::StopLoss),
+        "TAKE_PROFIT" | "TAKE_PROFIT_MARKET" | "TAKE_PROFIT_LIMIT" => Ok(OrderType::TakeProfit),
+        "TRAILING_STOP_MARKET" => Ok(OrderType::TrailingStopMarket),
+        _ => Err(Error::UnsupportedOrderType(exchange_order_type.to_string())),
+    }
+}
+
+/// `isIsolated` parameter of the margin endpoints
+pub(in super) fn get_exchange_is_isolated(margin_mode: &MarginMode) -> &'static str {
+    match margin_mode {
+        MarginMode::Isolated => "TRUE",
+        MarginMode::Cross => "FALSE",
+    }
+}
+
+/// Query parameter identifying an existing order. The exchange order id takes precedence.
+pub(in super) fn get_exchange_order_id<'a>(order_id: &'a Option<String>, client_order_id: &'a Option<String>) -> Result<(&'static str, &'a str)> {
+    match (order_id, client_order_id) {
+        (Some(order_id), _) => Ok(("orderId", order_id.as_str())),
+        (None, Some(client_order_id)) => Ok(("origClientOrderId", client_order_id.as_str())),
+        (None, None) => Err(Error::InvalidParameters("order_id or client_order_id is required".into())),
+    }
+}
+
 
 const BUY: &str = "BUY";
 const SELL: &str = "SELL";
@@ -76,12 +180,385 @@ pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<Ord
 pub(in super) fn get_unified_order_status(exchange_order_status: &str) -> Result<OrderStatus> {
     match exchange_order_status {
         "NEW" => Ok(OrderStatus::Open),
+        "PENDING_NEW" => Ok(OrderStatus::Open),
         "PARTIALLY_FILLED" => Ok(OrderStatus::Open),
         "FILLED" => Ok(OrderStatus::Closed),
         "CANCELED" => Ok(OrderStatus::Canceled),
         "PENDING_CANCEL" => Ok(OrderStatus::Canceled),
         "REJECTED" => Ok(OrderStatus::Rejected),
         "EXPIRED" => Ok(OrderStatus::Expired),
+        // canceled by self-trade prevention
+        "EXPIRED_IN_MATCH" => Ok(OrderStatus::Expired),
         _ => Err(Error::UnsupportedOrderStatus(exchange_order_status.to_string())),
     }
-}
\ No newline at end of file
+}
+
+pub(in super) fn get_exchange_timeframe(timeframe: &Timeframe) -> &'static str {
+    match timeframe {
+        Timeframe::Minute1 => "1m",
+        Timeframe::Minute3 => "3m",
+        Timeframe::Minute5 => "5m",
+        Timeframe::Minute15 => "15m",
+        Timeframe::Minute30 => "30m",
+        Timeframe::Hour1 => "1h",
+        Timeframe::Hour2 => "2h",
+        Timeframe::Hour4 => "4h",
+        Timeframe::Hour6 => "6h",
+        Timeframe::Hour8 => "8h",
+        Timeframe::Hour12 => "12h",
+        Timeframe::Day1 => "1d",
+        Timeframe::Day3 => "3d",
+        Timeframe::Week1 => "1w",
+        Timeframe::Month1 => "1M",
+    }
+}
+
+pub(in super) fn get_unified_timeframe(interval: &str) -> Result<Timeframe> {
+    match interval {
+        "1m" => Ok(Timeframe::Minute1),
+        "3m" => Ok(Timeframe::Minute3),
+        "5m" => Ok(Timeframe::Minute5),
+        "15m" => Ok(Timeframe::Minute15),
+        "30m" => Ok(Timeframe::Minute30),
+        "1h" => Ok(Timeframe::Hour1),
+        "2h" => Ok(Timeframe::Hour2),
+        "4h" => Ok(Timeframe::Hour4),
+        "6h" => Ok(Timeframe::Hour6),
+        "8h" => Ok(Timeframe::Hour8),
+        "12h" => Ok(Timeframe::Hour12),
+        "1d" => Ok(Timeframe::Day1),
+        "3d" => Ok(Timeframe::Day3),
+        "1w" => Ok(Timeframe::Week1),
+        "1M" => Ok(Timeframe::Month1),
+        _ => Err(Error::UnsupportedTimeframe(interval.to_string())),
+    }
+}
+
+
+/// streams per connection of the receivers of public streams
+pub(in super) const STREAMS_PER_CONNECTION: usize = 100;
+
+/// pause before opening another connection, as new connections are limited per IP
+pub(in super) const CONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct StreamRequest<'a> {
+    method: &'static str,
+    params: &'a [String],
+    id: i64,
+}
+
+fn get_stream_request(method: &'static str, id: i64, streams: &[String]) -> String {
+    serde_json::to_string(&StreamRequest { method, params: streams, id }).unwrap_or_default()
+}
+
+pub(in super) fn get_subscribe_request(id: i64, streams: &[String]) -> String {
+    get_stream_request("SUBSCRIBE", id, streams)
+}
+
+/// Subscriptions of the public streams named by `stream` after a symbol id, e.g.
+/// `btcusdt@bookTicker`, whose markets can be changed on the receiver.
+pub(in super) fn subscription_spec(exchange_base: &ExchangeBase, stream: impl Fn(&str) -> String + Send + Sync + 'static) -> SubscriptionSpec {
+    SubscriptionSpec {
+        endpoint: combined_endpoint(exchange_base.ws_endpoint.as_deref().unwrap_or_default()),
+        parser: exchange_base.stream_parser,
+        unifier: exchange_base.unifier.clone(),
+        heartbeat: exchange_base.heartbeat.clone(),
+        topic: Arc::new(stream),
+        subscribe_message: get_subscribe_request,
+        unsubscribe_message: |id, streams| get_stream_request("UNSUBSCRIBE", id, streams),
+        max_topics: STREAMS_PER_CONNECTION,
+        connect_interval: CONNECT_INTERVAL,
+    }
+}
+
+
+/// Combined stream endpoint of a raw stream endpoint, e.g. `wss://fstream.binance.com/stream` for
+/// `wss://fstream.binance.com/ws`. Endpoints that do not end with `/ws` are used as is.
+pub(in super) fn combined_endpoint(ws_endpoint: &str) -> String {
+    match ws_endpoint.strip_suffix("/ws") {
+        Some(base) => format!("{}/stream", base),
+        None => ws_endpoint.to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+struct CombinedStreamMessage<'a> {
+    stream: String,
+    #[serde(borrow)]
+    data: &'a RawValue,
+}
+
+/// Splits a payload of the combined stream endpoint into the name of its stream and its data.
+/// Subscription results, errors and payloads of the raw endpoint are returned as is, without a
+/// stream name.
+pub(in super) fn split_combined_stream(message: &[u8]) -> (Option<String>, &[u8]) {
+    match serde_json::from_slice::<CombinedStreamMessage>(message) {
+        Ok(combined) => (Some(combined.stream), combined.data.get().as_bytes()),
+        Err(_) => (None, message),
+    }
+}
+
+/// Channels of the public streams.
+#[derive(Debug, PartialEq)]
+pub(in super) enum StreamChannel {
+    BookTicker,
+    /// diff and partial book depth
+    Depth,
+    Kline,
+    AggTrade,
+    Unknown,
+}
+
+/// Channel of a stream name of the combined endpoint, e.g. `btcusdt@depth5@100ms`.
+pub(in super) fn stream_channel(stream: &str) -> StreamChannel {
+    match stream.split('@').nth(1).unwrap_or_default() {
+        "bookTicker" => StreamChannel::BookTicker,
+        "aggTrade" => StreamChannel::AggTrade,
+        channel if channel.starts_with("depth") => StreamChannel::Depth,
+        channel if channel.starts_with("kline_") => StreamChannel::Kline,
+        _ => StreamChannel::Unknown,
+    }
+}
+
+/// Channel of a payload of the raw endpoint, which does not name its stream. Spot book tickers
+/// have no event type and are told apart by their update id.
+pub(in super) fn event_channel(event_type: Option<&str>, order_book_update_id: Option<i64>) -> StreamChannel {
+    match event_type {
+        Some("bookTicker") => StreamChannel::BookTicker,
+        Some("depthUpdate") => StreamChannel::Depth,
+        Some("kline") => StreamChannel::Kline,
+        Some("aggTrade") => StreamChannel::AggTrade,
+        None if order_book_update_id.is_some() => StreamChannel::BookTicker,
+        _ => StreamChannel::Unknown,
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize)]
+pub(in super) struct WatchOhlcvResponse {
+    #[serde(rename = "E")]
+    event_time: i64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "k")]
+    kline: WatchOhlcvKlineResponse,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(in super) struct WatchOhlcvKlineResponse {
+    #[serde(rename = "t")]
+    start_time: i64,
+    #[serde(rename = "T")]
+    close_time: i64,
+    #[serde(rename = "i")]
+    interval: String,
+    #[serde(rename = "o")]
+    open: String,
+    #[serde(rename = "h")]
+    high: String,
+    #[serde(rename = "l")]
+    low: String,
+    #[serde(rename = "c")]
+    close: String,
+    #[serde(rename = "v")]
+    volume: String,
+    #[serde(rename = "x")]
+    is_closed: bool,
+}
+
+impl TryFrom<Vec<u8>> for WatchOhlcvResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(message.as_slice());
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+impl WatchOhlcvResponse {
+    pub(in super) fn to_ohlcv(&self, unifier: &Unifier) -> OhlcvResult<Ohlcv> {
+        let market = unifier.get_market(&self.symbol)
+            .ok_or_else(|| OhlcvError::UnknownError(format!("Unknown market. symbol={}", self.symbol)))?;
+        Ok(Ohlcv {
+            market,
+            timeframe: get_unified_timeframe(&self.kline.interval)?,
+            timestamp: self.kline.start_time,
+            open: parse_float64(&self.kline.open)?,
+            high: parse_float64(&self.kline.high)?,
+            low: parse_float64(&self.kline.low)?,
+            close: parse_float64(&self.kline.close)?,
+            volume: parse_float64(&self.kline.volume)?,
+            is_closed: self.kline.is_closed,
+        })
+    }
+}
+
+
+/// kline row, serialized by the exchange as an array
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(in super) struct FetchOhlcvResponse {
+    pub open_time: i64,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+    pub close_time: i64,
+    pub quote_volume: String,
+    pub number_of_trades: i64,
+    pub taker_buy_base_volume: String,
+    pub taker_buy_quote_volume: String,
+    pub unused: String,
+}
+
+impl TryFrom<FetchOhlcvResponse> for Ohlcv {
+    type Error = Error;
+
+    fn try_from(resp: FetchOhlcvResponse) -> std::result::Result<Self, Self::Error> {
+        Ok(Ohlcv {
+            timestamp: resp.open_time,
+            open: parse_float64(&resp.open)?,
+            high: parse_float64(&resp.high)?,
+            low: parse_float64(&resp.low)?,
+            close: parse_float64(&resp.close)?,
+            volume: parse_float64(&resp.volume)?,
+            // the latest row is still in progress
+            is_closed: resp.close_time < Utc::now().timestamp_millis(),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// listen keys expire after 60 minutes without a keepalive
+const LISTEN_KEY_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+const LISTEN_KEY_RENEW_ATTEMPTS: u32 = 3;
+
+/// Stream error the user data parsers report on a `listenKeyExpired` event.
+pub(in super) const LISTEN_KEY_EXPIRED: &str = "listen key expired";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenKeyResponse {
+    listen_key: String,
+}
+
+/// Endpoint creating and keeping alive the listen keys of one account.
+pub(in super) struct ListenKeyEndpoint {
+    pub(in super) http_client: HttpClient,
+    pub(in super) path: String,
+    /// account parameters, such as the symbol of an isolated margin account
+    pub(in super) query: Vec<(&'static str, String)>,
+    pub(in super) api_key: String,
+    /// whether the keepalive names the key. the futures endpoints keep the key of the api key alive
+    pub(in super) keyed: bool,
+}
+
+impl ListenKeyEndpoint {
+    /// Creates a listen key, or returns the active one of the account.
+    pub(in super) async fn create(&self) -> Result<String> {
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_str())];
+        let resp: ListenKeyResponse = self.http_client.post(&self.path, Some(headers), Some(&self.query), EMPTY_BODY).await?;
+        Ok(resp.listen_key)
+    }
+
+    async fn keepalive(&self, listen_key: &str) -> Result<()> {
+        let mut query = self.query.clone();
+        if self.keyed {
+            query.push(("listenKey", listen_key.to_string()));
+        }
+        let headers = vec![("X-MBX-APIKEY", self.api_key.as_str())];
+        self.http_client.put::<_, serde_json::Value>(&self.path, Some(headers), Some(&query)).await?;
+        Ok(())
+    }
+
+    /// Creates a new listen key and connects to its stream.
+    async fn renew<F>(&self, connect: &F) -> Result<(String, WsClient<Vec<StreamItem>>)>
+    where
+        F: Fn(&str) -> WsClient<Vec<StreamItem>>,
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = async {
+                let listen_key = self.create().await?;
+                let mut client = connect(&listen_key);
+                client.connect().await?;
+                Ok((listen_key, client))
+            }.await;
+            match result {
+                Err(_) if attempt < LISTEN_KEY_RENEW_ATTEMPTS => tokio::time::sleep(Duration::from_secs(attempt as u64)).await,
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Forwards the user data events accepted by `filter` and keeps the listen key alive until the
+/// receiver is dropped. When the key expires or a keepalive fails, a new key is created and the
+/// stream reconnected with it, which is reported as `StreamItem::Reconnected`.
+pub(in super) async fn run_user_data_stream<F>(
+    mut client: WsClient<Vec<StreamItem>>,
+    mut listen_key: String,
+    endpoint: ListenKeyEndpoint,
+    connect: F,
+    filter: fn(&StreamItem) -> bool,
+    tx: Sender,
+)
+where
+    F: Fn(&str) -> WsClient<Vec<StreamItem>>,
+{
+    let start = tokio::time::Instant::now() + LISTEN_KEY_KEEPALIVE_INTERVAL;
+    let mut keepalive = tokio::time::interval_at(start, LISTEN_KEY_KEEPALIVE_INTERVAL);
+    loop {
+        let expired = tokio::select! {
+            _ = keepalive.tick() => {
+                if tx.is_closed() {
+                    return;
+                }
+                endpoint.keepalive(&listen_key).await.is_err()
+            }
+            items = client.next() => match items {
+                Some(Ok(items)) => {
+                    let items = items.into_iter().filter(|item| matches!(item, StreamItem::Reconnected) || filter(item));
+                    for item in items {
+                        if tx.send(Ok(item)).await.is_err() { // the receiver is dropped
+                            return;
+                        }
+                    }
+                    false
+                }
+                Some(Err(WatchError::StreamError(e))) if e == LISTEN_KEY_EXPIRED => true,
+                Some(Err(e)) => {
+                    if tx.send(Err(e)).await.is_err() {
+                        return;
+                    }
+                    false
+                }
+                None => return,
+            },
+        };
+        if !expired {
+            continue;
+        }
+        // events between the expiry and the new connection are lost
+        let item = match endpoint.renew(&connect).await {
+            Ok((key, renewed)) => {
+                listen_key = key;
+                client = renewed;
+                keepalive.reset();
+                Ok(StreamItem::Reconnected)
+            }
+            // the next keepalive tries again
+            Err(e) => Err(e.into()),
+        };
+        if tx.send(item).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,960 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use futures_util::stream::SelectAll;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+
+use crate::client::EMPTY_QUERY;
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{parse_float64, parse_number};
+use crate::util::channel::{Receiver, Sender};
+use crate::util::rate_limiter::RateLimit;
+
+use super::util;
+use super::util::ProductType;
+
+/// maximum number of channels in a subscription message
+const SUBSCRIPTION_CHUNK_SIZE: usize = 50;
+
+/// connections without a `ping` for two minutes are closed by the exchange
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct Bitget {
+    exchange_base: ExchangeBase,
+    api_key: Option<String>,
+    secret: Option<String>,
+    passphrase: Option<String>,
+
+    /// demo trading is served by the live host and selected with a header
+    demo: bool,
+
+    /// markets by the symbol id of each product type
+    unifiers: HashMap<ProductType, Unifier>,
+}
+
+impl Bitget {
+    /// `passphrase` is required in addition to `api_key` and `secret` for private methods.
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        let ws_endpoint = match props.sandbox {
+            true => "wss://wspap.bitget.com/v2/ws/public",
+            false => "wss://ws.bitget.com/v2/ws/public",
+        };
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some("https://api.bitget.com".to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some(ws_endpoint.to_string())))
+            .error_parser(Some(|message| {
+                match serde_json::from_str::<Response>(&message) {
+                    Ok(response) => get_error(&response.code, response.msg),
+                    Err(_) => Error::HttpError(message),
+                }
+            }))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            .heartbeat(Some(Heartbeat {
+                ping_interval: Some(PING_INTERVAL),
+                ping_message: Some("ping".to_string()),
+                pong_message: Some("pong".to_string()),
+                ..Default::default()
+            }))
+            // most endpoints allow 20 requests per second
+            .rate_limits(Some(vec![RateLimit { interval: Duration::from_secs(1), limit: 20, header: None }]))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            passphrase: props.passphrase.clone(),
+            demo: props.sandbox,
+            unifiers: HashMap::new(),
+        })
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.api_key.is_some() && self.secret.is_some() && self.passphrase.is_some()
+    }
+
+    /// Timestamp for signed requests, on the exchange clock when time sync is enabled.
+    async fn timestamp(&self) -> Result<i64> {
+        let clock = &self.exchange_base.clock;
+        if clock.needs_sync() {
+            let sent = Utc::now().timestamp_millis();
+            let result: ServerTimeResponse = self.get("/api/v2/public/time", &vec![]).await?;
+            let received = Utc::now().timestamp_millis();
+            clock.update(sent, result.server_time.parse()?, received);
+        }
+        Ok(clock.timestamp())
+    }
+
+    /// Signs `timestamp + method + request path + body` and encodes the signature in base64. The
+    /// request path includes the query string.
+    fn auth(&self, timestamp: &str, method: &str, request_path: &str, body: &str) -> Result<String> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials);
+        }
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(timestamp.as_bytes());
+        signed_key.update(method.as_bytes());
+        signed_key.update(request_path.as_bytes());
+        signed_key.update(body.as_bytes());
+        Ok(BASE64.encode(signed_key.finalize().into_bytes()))
+    }
+
+    fn headers<'a>(&'a self, signature: Option<(&'a str, &'a str)>) -> Vec<(&'a str, &'a str)> {
+        let mut headers = vec![];
+        if let Some((timestamp, signature)) = signature {
+            headers.push(("ACCESS-KEY", self.api_key.as_ref().unwrap().as_str()));
+            headers.push(("ACCESS-SIGN", signature));
+            headers.push(("ACCESS-TIMESTAMP", timestamp));
+            headers.push(("ACCESS-PASSPHRASE", self.passphrase.as_ref().unwrap().as_str()));
+        }
+        if self.demo {
+            headers.push(("paptrading", "1"));
+        }
+        headers
+    }
+
+    fn unifier(&self, product_type: ProductType) -> Result<&Unifier> {
+        self.unifiers.get(&product_type).ok_or(Error::MarketNotInitialized)
+    }
+
+    /// Product type and symbol id of a loaded market.
+    fn get_symbol_id(&self, market: &Market) -> Result<(ProductType, String)> {
+        let product_type = ProductType::of(market)?;
+        let symbol_id = self.unifier(product_type)?.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))?;
+        Ok((product_type, symbol_id))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let response: Response = self.exchange_base.http_client.get(path, Some(self.headers(None)), Some(query)).await?;
+        response.into_result()
+    }
+
+    async fn signed_get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let timestamp = self.timestamp().await?.to_string();
+        let request_path = match query.is_empty() {
+            true => path.to_string(),
+            false => {
+                let query = query.iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<String>>()
+                    .join("&");
+                format!("{}?{}", path, query)
+            }
+        };
+        let signature = self.auth(&timestamp, "GET", &request_path, "")?;
+        let headers = self.headers(Some((&timestamp, &signature)));
+        let response: Response = self.exchange_base.http_client.get(path, Some(headers), Some(query)).await?;
+        response.into_result()
+    }
+
+    async fn signed_post<T: DeserializeOwned>(&self, path: &str, body: &String) -> Result<T> {
+        let timestamp = self.timestamp().await?.to_string();
+        let signature = self.auth(&timestamp, "POST", path, body)?;
+        let mut headers = self.headers(Some((&timestamp, &signature)));
+        headers.push(("Content-Type", "application/json"));
+        let response: Response = self.exchange_base.http_client.post(path, Some(headers), EMPTY_QUERY, Some(body)).await?;
+        response.into_result()
+    }
+
+    async fn fetch_product_markets(&self, product_type: ProductType) -> Result<Vec<(String, Market)>> {
+        let mut markets = vec![];
+        match product_type {
+            ProductType::Spot => {
+                let result: Vec<FetchSpotMarketsResponse> = self.get("/api/v2/spot/public/symbols", &vec![]).await?;
+                for symbol in result {
+                    markets.push((symbol.symbol.clone(), symbol.to_market()?));
+                }
+            }
+            _ => {
+                let query = vec![("productType", product_type.as_str())];
+                let result: Vec<FetchContractMarketsResponse> = self.get("/api/v2/mix/market/contracts", &query).await?;
+                for contract in result {
+                    markets.push((contract.symbol.clone(), contract.to_market(product_type)?));
+                }
+            }
+        }
+        Ok(markets)
+    }
+
+    async fn fetch_all_markets(&self) -> Result<Vec<(ProductType, String, Market)>> {
+        let mut markets = vec![];
+        for product_type in ProductType::ALL {
+            for (symbol_id, market) in self.fetch_product_markets(product_type).await? {
+                markets.push((product_type, symbol_id, market));
+            }
+        }
+        Ok(markets)
+    }
+
+    /// Subscribes to a public channel of the markets over a connection per product type, whose
+    /// items are merged into a single receiver.
+    async fn subscribe(&self, channel: &str, markets: &[Market]) -> WatchResult<Receiver> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut symbol_ids: HashMap<ProductType, Vec<String>> = HashMap::new();
+        for market in markets {
+            let (product_type, symbol_id) = self.get_symbol_id(market).map_err(|_| WatchError::SymbolNotFound(format!("{:?}", market)))?;
+            symbol_ids.entry(product_type).or_default().push(symbol_id);
+        }
+
+        let mut clients = vec![];
+        for (product_type, symbol_ids) in symbol_ids {
+            let mut ws_client = self.exchange_base.ws_client(self.exchange_base.ws_endpoint.as_ref().unwrap().as_str(), parse_stream, self.unifier(product_type)?.clone());
+            for symbol_ids in symbol_ids.chunks(SUBSCRIPTION_CHUNK_SIZE) {
+                let request = SubscribeRequest {
+                    op: "subscribe",
+                    args: symbol_ids.iter()
+                        .map(|symbol_id| ChannelArg { inst_type: product_type.as_str().to_string(), channel: channel.to_string(), inst_id: symbol_id.clone() })
+                        .collect(),
+                };
+                let message = serde_json::to_string(&request).map_err(|e| WatchError::UnknownError(format!("{}", e)))?;
+                let _ = ws_client.send(message).await?;
+            }
+            clients.push(ws_client);
+        }
+
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(forward_items(futures_util::stream::select_all(clients), tx));
+        Ok(receiver)
+    }
+}
+
+#[async_trait]
+impl Exchange for Bitget {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        if self.exchange_base.markets.is_empty() {
+            let result = self.fetch_all_markets().await?;
+            self.exchange_base.unifier.reset();
+            self.unifiers.clear();
+            let mut markets = vec![];
+            for (product_type, symbol_id, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &symbol_id);
+                self.unifiers.entry(product_type).or_insert_with(Unifier::new).insert_market_symbol_id(&market, &symbol_id);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.fetch_all_markets().await?;
+        Ok(result.into_iter().map(|(_, _, market)| market).collect())
+    }
+
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let product_types = match params.markets.as_ref() {
+            Some(markets) => {
+                let mut product_types = vec![];
+                for market in markets {
+                    let (product_type, _) = self.get_symbol_id(market)?;
+                    if !product_types.contains(&product_type) {
+                        product_types.push(product_type);
+                    }
+                }
+                product_types
+            }
+            None => ProductType::ALL.to_vec(),
+        };
+
+        let mut tickers = vec![];
+        for product_type in product_types {
+            let unifier = self.unifier(product_type)?;
+            let result: Vec<FetchTickersResponse> = match product_type {
+                ProductType::Spot => self.get("/api/v2/spot/market/tickers", &vec![]).await?,
+                _ => self.get("/api/v2/mix/market/tickers", &vec![("productType", product_type.as_str())]).await?,
+            };
+            for item in result {
+                let market = match unifier.get_market(&item.symbol) {
+                    Some(market) => market,
+                    None => continue,
+                };
+                if let Some(markets) = params.markets.as_ref() {
+                    if !markets.contains(&market) {
+                        continue;
+                    }
+                }
+                tickers.push(item.to_ticker(market)?);
+            }
+        }
+        Ok(tickers)
+    }
+
+    async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let (product_type, symbol_id) = self.get_symbol_id(&params.market)?;
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = vec![("symbol", symbol_id.as_str())];
+        if product_type != ProductType::Spot {
+            query.push(("productType", product_type.as_str()));
+        }
+        if let Some(limit) = &limit {
+            query.push(("limit", limit.as_str()));
+        }
+        let path = match product_type {
+            ProductType::Spot => "/api/v2/spot/market/fills",
+            _ => "/api/v2/mix/market/fills",
+        };
+        let result: Vec<TradeResponse> = self.get(path, &query).await?;
+
+        // only the most recent trades are served, so the range is applied locally
+        let mut trades = vec![];
+        for item in result {
+            let trade = item.to_trade(&params.market)?;
+            if params.since.is_some_and(|since| trade.timestamp < since) || params.until.is_some_and(|until| trade.timestamp > until) {
+                continue;
+            }
+            trades.push(trade);
+        }
+        Ok(trades)
+    }
+
+    /// Streams the best bid and ask. Full-depth books are not supported.
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        if params.full_depth {
+            return Err(WatchError::NotImplemented);
+        }
+        self.subscribe("books1", &params.markets).await
+    }
+
+    /// Trades from before the subscription, which the exchange sends first, are left out.
+    async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
+        self.subscribe("trade", &params.markets).await
+    }
+
+    /// Fetches the spot account.
+    async fn fetch_balance(&self, params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if params.margin_mode.is_some() {
+            return Err(Error::InvalidParameters("margin mode is not supported".into()).into());
+        }
+
+        let result: Vec<FetchBalanceResponse> = self.signed_get("/api/v2/spot/account/assets", &vec![]).await?;
+        let mut balance = Balance::default();
+        for item in result {
+            balance.items.push(item.to_balance_item()?);
+        }
+        Ok(balance)
+    }
+
+    /// Amounts are in the base currency. Spot market buys are sized in the quote currency by the
+    /// exchange, so they require a price to convert the amount.
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let (product_type, symbol_id) = self.get_symbol_id(&params.market)?;
+        let market = self.unifier(product_type)?.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+        let (amount, price) = match params.normalize {
+            true => {
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+
+        let is_market = order_type == OrderType::Market;
+        let size = match (product_type, &params.order_side, is_market, price) {
+            (ProductType::Spot, OrderSide::Buy, true, Some(price)) => amount * price,
+            (ProductType::Spot, OrderSide::Buy, true, None) => {
+                return Err(Error::InvalidPrice("price is required for spot market buys, which are sized in the quote currency".into()).into());
+            }
+            _ => amount,
+        };
+        let is_contract = product_type != ProductType::Spot;
+        let request = CreateOrderRequest {
+            symbol: symbol_id,
+            product_type: Some(product_type.as_str()).filter(|_| is_contract),
+            margin_mode: Some(util::get_exchange_margin_mode(params.margin_mode.as_ref().unwrap_or(&MarginMode::Cross))).filter(|_| is_contract),
+            margin_coin: market.settle.clone().filter(|_| is_contract),
+            side: util::get_exchange_order_side(&params.order_side),
+            order_type: util::get_exchange_order_type(&order_type)?,
+            force: util::get_exchange_force(&params.time_in_force.unwrap_or(TimeInForce::GTC)),
+            size: size.to_string(),
+            price: price.filter(|_| !is_market).map(|price| price.to_string()),
+            reduce_only: Some("YES").filter(|_| params.reduce_only && is_contract),
+        };
+        let body = serde_json::to_string(&request).map_err(|e| Error::InvalidParameters(format!("{}", e)))?;
+        let path = match product_type {
+            ProductType::Spot => "/api/v2/spot/trade/place-order",
+            _ => "/api/v2/mix/order/place-order",
+        };
+        let response: CreateOrderResponse = self.signed_post(path, &body).await?;
+        Ok(Order {
+            id: Some(response.order_id),
+            client_order_id: response.client_oid.filter(|id| !id.is_empty()),
+            timestamp: Utc::now().timestamp_millis(),
+            status: OrderStatus::Open,
+            market: params.market.clone(),
+            order_type,
+            time_in_force: match is_market {
+                true => None,
+                false => Some(params.time_in_force.unwrap_or(TimeInForce::GTC)),
+            },
+            side: Some(params.order_side),
+            price: price.filter(|_| !is_market),
+            amount,
+            margin_mode: params.margin_mode.unwrap_or(MarginMode::Cross),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Maps the error code of a response to an error.
+fn get_error(code: &str, msg: String) -> Error {
+    match code {
+        "40006" | "40012" | "40037" => Error::CredentialsError(msg), // Invalid ACCESS_KEY / apikey or password is incorrect / Apikey does not exist
+        "40008" => Error::TimestampOutsideRecvWindow(msg), // Request timestamp expired
+        "40009" => Error::InvalidSignature(msg), // sign signature error
+        "40017" | "40034" => Error::InvalidParameters(msg), // Parameter verification failed / Parameter does not exist
+        "40309" => Error::SymbolNotFound(msg), // The contract has been removed
+        "43012" | "40762" => Error::InsufficientMargin(msg), // Insufficient balance / The order amount exceeds the balance
+        "43001" | "40768" => Error::OrderNotFound(msg), // The order does not exist
+        _ => Error::HttpError(format!("code={}, msg={}", code, msg)),
+    }
+}
+
+/// Levels are `[price, amount]`.
+fn to_order_book_units(levels: &[[String; 2]]) -> Result<Vec<OrderBookUnit>> {
+    levels.iter()
+        .map(|[price, amount]| Ok(OrderBookUnit { price: parse_number(price)?, amount: parse_number(amount)? }))
+        .collect()
+}
+
+/// Forwards the items of the connections one by one to the receiver until it is dropped.
+async fn forward_items(mut clients: SelectAll<WsClient<Vec<StreamItem>>>, tx: Sender) {
+    loop {
+        let items = match clients.next().await {
+            Some(Ok(items)) => items.into_iter().map(Ok).collect(),
+            Some(Err(e)) => vec![Err(e)],
+            None => return,
+        };
+        for item in items {
+            if tx.send(item).await.is_err() { // the receiver is dropped
+                return;
+            }
+        }
+    }
+}
+
+/// Parses a public channel push into a stream item per element of its data.
+fn parse_stream(message: &[u8], unifier: &Unifier) -> WatchResult<Vec<StreamItem>> {
+    if message == b"pong" {
+        return Ok(vec![]);
+    }
+    let push = WatchPushResponse::try_from(message.to_vec())?;
+    if push.event.as_deref() == Some("error") {
+        return Err(Error::StreamError(format!("code={}, msg={}", push.code.unwrap_or_default(), push.msg.unwrap_or_default())).into());
+    }
+    let (arg, data) = match (push.arg, push.data) {
+        (Some(arg), Some(data)) => (arg, data),
+        _ => return Ok(vec![StreamItem::Unknown(String::from_utf8_lossy(message).to_string())]),
+    };
+    // recent trades are sent as a snapshot on subscription
+    if arg.channel == "trade" && push.action.as_deref() == Some("snapshot") {
+        return Ok(vec![]);
+    }
+    let market = match unifier.get_market(&arg.inst_id) {
+        Some(market) => market,
+        None => return Err(WatchError::SymbolNotFound(arg.inst_id)),
+    };
+
+    let mut items = vec![];
+    for data in data {
+        let item = match arg.channel.as_str() {
+            "books1" => StreamItem::OrderBook(
+                serde_json::from_value::<WatchOrderBookResponse>(data)
+                    .map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+                    .and_then(|book| book.to_order_book(market.clone()))
+                    .map_err(|e| OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market.clone())))
+            ),
+            "trade" => StreamItem::Trade(
+                serde_json::from_value::<TradeResponse>(data)
+                    .map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+                    .and_then(|trade| trade.to_trade(&market))
+                    .map_err(TradeError::from)
+            ),
+            _ => StreamItem::Unknown(String::from_utf8_lossy(message).to_string()),
+        };
+        items.push(item);
+    }
+    Ok(items)
+}
+
+
+/// Envelope of every REST response. `data` is kept raw so that errors are reported from `code`
+/// even when the data does not match the expected type.
+#[derive(Serialize, Deserialize)]
+struct Response {
+    code: String,
+    msg: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+impl Response {
+    fn into_result<T: DeserializeOwned>(self) -> Result<T> {
+        if self.code != "00000" {
+            return Err(get_error(&self.code, self.msg));
+        }
+        serde_json::from_value(self.data).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerTimeResponse {
+    server_time: String,
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FetchSpotMarketsResponse {
+    symbol: String,
+    base_coin: String,
+    quote_coin: String,
+    min_trade_amount: String,
+    max_trade_amount: String,
+    /// number of decimal places
+    price_precision: String,
+    /// number of decimal places
+    quantity_precision: String,
+    status: String,
+}
+
+impl FetchSpotMarketsResponse {
+    fn to_market(&self) -> Result<Market> {
+        Ok(Market {
+            base: util::to_unified_asset(&self.base_coin),
+            quote: util::to_unified_asset(&self.quote_coin),
+            active: util::is_active(&self.status),
+            market_type: MarketType::Spot,
+            precision: Some(Precision {
+                amount: Some(self.quantity_precision.parse()?),
+                price: Some(self.price_precision.parse()?),
+                cost: None,
+            }),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: util::parse_optional_float(&self.min_trade_amount)?, max: util::parse_optional_float(&self.max_trade_amount)? }),
+                price: None,
+                cost: None,
+                leverage: None,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+/// Sizes of contracts are in the base currency.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FetchContractMarketsResponse {
+    symbol: String,
+    base_coin: String,
+    quote_coin: String,
+    min_trade_num: String,
+    max_order_qty: String,
+    /// number of decimal places
+    price_place: String,
+    /// number of decimal places
+    volume_place: String,
+    /// `perpetual` or `delivery`
+    symbol_type: String,
+    symbol_status: String,
+    delivery_time: String,
+    min_lever: String,
+    max_lever: String,
+}
+
+impl FetchContractMarketsResponse {
+    fn to_market(&self, product_type: ProductType) -> Result<Market> {
+        let (contract_type, settle) = match product_type {
+            ProductType::CoinFutures => (ContractType::Inverse, self.base_coin.as_str()),
+            ProductType::UsdcFutures => (ContractType::Linear, "USDC"),
+            _ => (ContractType::Linear, "USDT"),
+        };
+        let (market_type, expiry) = match self.symbol_type.as_str() {
+            "delivery" => (MarketType::Future, Some(self.delivery_time.parse::<i64>()?)),
+            _ => (MarketType::Swap, None),
+        };
+        let leverage = match (self.min_lever.as_str(), self.max_lever.as_str()) {
+            ("", _) | (_, "") => None,
+            (min, max) => Some(Range { min: parse_float64(min)?, max: parse_float64(max)? }),
+        };
+        Ok(Market {
+            base: util::to_unified_asset(&self.base_coin),
+            quote: util::to_unified_asset(&self.quote_coin),
+            active: util::is_active(&self.symbol_status),
+            market_type,
+            settle: Some(util::to_unified_asset(settle)),
+            contract_size: Some(1.0),
+            contract_type: Some(contract_type),
+            expiry,
+            precision: Some(Precision {
+                amount: Some(self.volume_place.parse()?),
+                price: Some(self.price_place.parse()?),
+                cost: None,
+            }),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: util::parse_optional_float(&self.min_trade_num)?, max: util::parse_optional_float(&self.max_order_qty)? }),
+                price: None,
+                cost: None,
+                leverage,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Tickers of spot and contracts, which name the open price of the last 24 hours differently.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FetchTickersResponse {
+    symbol: String,
+    last_pr: String,
+    ask_pr: String,
+    ask_sz: String,
+    bid_pr: String,
+    bid_sz: String,
+    #[serde(alias = "open24h")]
+    open: String,
+    high24h: String,
+    low24h: String,
+    base_volume: String,
+    quote_volume: String,
+    ts: String,
+}
+
+impl FetchTickersResponse {
+    fn to_ticker(&self, market: Market) -> Result<Ticker> {
+        let last = util::parse_optional_float(&self.last_pr)?;
+        let open = util::parse_optional_float(&self.open)?;
+        let base_volume = util::parse_optional_float(&self.base_volume)?;
+        let quote_volume = util::parse_optional_float(&self.quote_volume)?;
+        Ok(Ticker {
+            ask: Some(util::parse_optional_float(&self.ask_pr)?).filter(|ask| *ask > 0.0),
+            ask_volume: util::parse_optional_float(&self.ask_sz)?,
+            bid: Some(util::parse_optional_float(&self.bid_pr)?).filter(|bid| *bid > 0.0),
+            bid_volume: util::parse_optional_float(&self.bid_sz)?,
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume,
+            change: last - open,
+            close: last,
+            high: util::parse_optional_float(&self.high24h)?,
+            low: util::parse_optional_float(&self.low24h)?,
+            last,
+            open,
+            percentage: match open > 0.0 {
+                true => (last - open) / open * 100f64,
+                false => 0.0,
+            },
+            previous_close: None,
+            vwap: match base_volume > 0.0 {
+                true => quote_volume / base_volume,
+                false => 0.0,
+            },
+            market,
+            timestamp: self.ts.parse()?,
+        })
+    }
+}
+
+
+/// Trade of the REST endpoints and of the `trade` channel.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TradeResponse {
+    trade_id: String,
+    price: String,
+    size: String,
+    /// taker side
+    side: String,
+    ts: String,
+}
+
+impl TradeResponse {
+    /// The cost is in the quote currency, sizes being in the base currency.
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = parse_number(&self.price)?;
+        let amount = parse_number(&self.size)?;
+        Ok(Trade::new(
+            self.trade_id.clone(),
+            self.ts.parse()?,
+            market.clone(),
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.side)?),
+            None,
+            price, amount,
+            price * amount,
+            None, None,
+        ))
+    }
+}
+
+
+#[derive(Serialize)]
+struct SubscribeRequest {
+    op: &'static str,
+    args: Vec<ChannelArg>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChannelArg {
+    inst_type: String,
+    channel: String,
+    inst_id: String,
+}
+
+/// Channel push with a `snapshot` or `update` action, or the result of an operation when `event`
+/// is set.
+#[derive(Serialize, Deserialize)]
+struct WatchPushResponse {
+    event: Option<String>,
+    code: Option<serde_json::Value>,
+    msg: Option<String>,
+    action: Option<String>,
+    arg: Option<ChannelArg>,
+    data: Option<Vec<serde_json::Value>>,
+}
+
+impl TryFrom<Vec<u8>> for WatchPushResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct WatchOrderBookResponse {
+    asks: Vec<[String; 2]>,
+    bids: Vec<[String; 2]>,
+    ts: String,
+    seq: Option<i64>,
+}
+
+impl WatchOrderBookResponse {
+    fn to_order_book(&self, market: Market) -> Result<OrderBook> {
+        Ok(OrderBook::new(
+            to_order_book_units(&self.bids)?,
+            to_order_book_units(&self.asks)?,
+            market,
+            Some(self.ts.parse()?),
+            self.seq,
+        ))
+    }
+}
+
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FetchBalanceResponse {
+    coin: String,
+    available: String,
+    /// locked by open orders
+    frozen: String,
+    /// locked by other products, e.g. fiat merchants
+    locked: String,
+}
+
+impl FetchBalanceResponse {
+    fn to_balance_item(&self) -> Result<BalanceItem> {
+        let free = util::parse_optional_float(&self.available)?;
+        let used = util::parse_optional_float(&self.frozen)? + util::parse_optional_float(&self.locked)?;
+        Ok(BalanceItem {
+            currency: util::to_unified_asset(&self.coin),
+            market: None,
+            total: free + used,
+            free,
+            used,
+            debt: 0.0,
+        })
+    }
+}
+
+
+/// Spot and contract orders, the latter with their product type and margin.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderRequest {
+    symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    product_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    margin_mode: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    margin_coin: Option<String>,
+    side: &'static str,
+    order_type: &'static str,
+    force: &'static str,
+    size: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<String>,
+    /// `YES` or `NO`, only applied in one-way position mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reduce_only: Option<&'static str>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderResponse {
+    order_id: String,
+    client_oid: Option<String>,
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Bitget, PropertiesBuilder};
+    use crate::error::Error;
+    use crate::exchange::{StreamItem, Unifier};
+    use crate::model::{ContractType, MarketType, OrderSide};
+
+    use super::{FetchBalanceResponse, FetchContractMarketsResponse, FetchSpotMarketsResponse, FetchTickersResponse, parse_stream, ProductType, Response};
+
+    #[test]
+    fn test_auth() {
+        let props = PropertiesBuilder::default()
+            .api_key(Some("key".into()))
+            .secret(Some("secret".into()))
+            .passphrase(Some("passphrase".into()))
+            .build()
+            .unwrap();
+        let bitget = Bitget::new(props).unwrap();
+        let signature = bitget.auth("1695806875837", "GET", "/api/v2/spot/account/assets?coin=USDT", "").unwrap();
+        assert_eq!(signature, "ICzR5Yk6XY8U1+xOzYAPdUrwQP78leakoeziwH4ij5s=");
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"symbol": "BTCUSDT", "baseCoin": "BTC", "quoteCoin": "USDT", "minTradeAmount": "0", "maxTradeAmount": "10000000000", "takerFeeRate": "0.002", "makerFeeRate": "0.002", "pricePrecision": "2", "quantityPrecision": "6", "quotePrecision": "8", "status": "online", "minTradeUSDT": "1"}"#;
+        let resp: FetchSpotMarketsResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market().unwrap();
+        assert_eq!(market.market_type, MarketType::Spot);
+        assert_eq!(market.precision.as_ref().unwrap().amount, Some(6));
+        assert_eq!(market.precision.as_ref().unwrap().price, Some(2));
+        assert_eq!(format!("{}", market), "BTC/USDT");
+
+        let message = r#"{"symbol": "BTCUSD", "baseCoin": "BTC", "quoteCoin": "USD", "supportMarginCoins": ["BTC"], "minTradeNum": "0.001", "priceEndStep": "1", "volumePlace": "3", "pricePlace": "1", "sizeMultiplier": "0.001", "symbolType": "perpetual", "symbolStatus": "normal", "deliveryTime": "", "minLever": "1", "maxLever": "125", "maxOrderQty": "1200"}"#;
+        let resp: FetchContractMarketsResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(ProductType::CoinFutures).unwrap();
+        assert_eq!(market.market_type, MarketType::Swap);
+        assert_eq!(market.contract_type, Some(ContractType::Inverse));
+        assert_eq!(market.limit.as_ref().unwrap().leverage.as_ref().unwrap().max, 125.0);
+        assert_eq!(ProductType::of(&market).unwrap(), ProductType::CoinFutures);
+        assert_eq!(format!("{}", market), "BTC/USD:BTC");
+
+        let message = r#"{"symbol": "BTCPERP", "baseCoin": "BTC", "quoteCoin": "USDC", "minTradeNum": "0.0001", "volumePlace": "4", "pricePlace": "1", "symbolType": "perpetual", "symbolStatus": "normal", "minLever": "1", "maxLever": "100", "maxOrderQty": "100"}"#;
+        let resp: FetchContractMarketsResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(ProductType::UsdcFutures).unwrap();
+        assert_eq!(ProductType::of(&market).unwrap(), ProductType::UsdcFutures);
+        assert_eq!(format!("{}", market), "BTC/USDC:USDC");
+    }
+
+    #[test]
+    fn test_parse_stream() {
+        let message = r#"{"symbol": "BTCUSDT", "baseCoin": "BTC", "quoteCoin": "USDT", "minTradeAmount": "0", "maxTradeAmount": "10000000000", "pricePrecision": "2", "quantityPrecision": "6", "status": "online"}"#;
+        let resp: FetchSpotMarketsResponse = serde_json::from_str(message).unwrap();
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&resp.to_market().unwrap(), &"BTCUSDT".to_string());
+
+        let message = r#"{"action": "snapshot", "arg": {"instType": "SPOT", "channel": "books1", "instId": "BTCUSDT"}, "data": [{"asks": [["26274.9", "0.0009"]], "bids": [["26274.8", "0.0011"]], "checksum": 0, "ts": "1695710946294", "seq": 1234}], "ts": 1695710946294}"#;
+        match &parse_stream(message.as_bytes(), &unifier).unwrap()[..] {
+            [StreamItem::OrderBook(Ok(order_book))] => {
+                assert_eq!(order_book.asks.len(), 1);
+                assert_eq!(order_book.bids.len(), 1);
+                assert_eq!(order_book.timestamp, Some(1695710946294));
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+
+        let message = r#"{"action": "snapshot", "arg": {"instType": "SPOT", "channel": "trade", "instId": "BTCUSDT"}, "data": [{"ts": "1695709835822", "price": "26293.4", "size": "0.0013", "side": "buy", "tradeId": "1111111111"}], "ts": 1695709835822}"#;
+        assert!(parse_stream(message.as_bytes(), &unifier).unwrap().is_empty());
+
+        let message = r#"{"action": "update", "arg": {"instType": "SPOT", "channel": "trade", "instId": "BTCUSDT"}, "data": [{"ts": "1695709835822", "price": "26293.4", "size": "0.0013", "side": "sell", "tradeId": "1111111112"}], "ts": 1695709835822}"#;
+        match &parse_stream(message.as_bytes(), &unifier).unwrap()[..] {
+            [StreamItem::Trade(Ok(trade))] => {
+                assert_eq!(trade.id, "1111111112");
+                assert_eq!(trade.side, Some(OrderSide::Sell));
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+
+        assert!(parse_stream(b"pong", &unifier).unwrap().is_empty());
+
+        let message = r#"{"event": "error", "code": 30001, "msg": "instType:SPOT,channel:trade,instId:BTCUSD doesn't exist"}"#;
+        assert!(parse_stream(message.as_bytes(), &unifier).is_err());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let response: Response = serde_json::from_str(r#"{"code": "43012", "msg": "Insufficient balance", "requestTime": 1695806875837, "data": null}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::InsufficientMargin(_))));
+
+        let response: Response = serde_json::from_str(r#"{"code": "40009", "msg": "sign signature error", "requestTime": 1695806875837, "data": null}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_to_ticker_and_balance() {
+        let message = r#"{"symbol": "BTCUSDT", "lastPr": "27000", "askPr": "27000.5", "bidPr": "26999.5", "bidSz": "0.1", "askSz": "0.2", "high24h": "28000", "low24h": "26000", "ts": "1695794098184", "change24h": "0.08", "baseVolume": "100", "quoteVolume": "2700000", "usdtVolume": "2700000", "open24h": "25000", "fundingRate": "0.0001"}"#;
+        let resp: FetchTickersResponse = serde_json::from_str(message).unwrap();
+        let ticker = resp.to_ticker(Default::default()).unwrap();
+        assert_eq!(ticker.open, 25000.0);
+        assert_eq!(ticker.percentage, 8.0);
+        assert_eq!(ticker.vwap, 27000.0);
+        assert_eq!(ticker.timestamp, 1695794098184);
+
+        let message = r#"{"coin": "USDT", "available": "100", "limitAvailable": "0", "frozen": "20", "locked": "5", "uTime": "1622697148"}"#;
+        let resp: FetchBalanceResponse = serde_json::from_str(message).unwrap();
+        let item = resp.to_balance_item().unwrap();
+        assert_eq!(item.used, 25.0);
+        assert_eq!(item.total, 125.0);
+    }
+}
@@ -0,0 +1,5 @@
+pub use bitget::Bitget;
+
+#[allow(clippy::module_inception)]
+mod bitget;
+mod util;
@@ -0,0 +1,93 @@
+use crate::error::{Error, Result};
+use crate::model::{ContractType, MarginMode, Market, MarketType, OrderSide, OrderType, TimeInForce};
+
+/// Product line of the v2 API. Symbol ids are only unique within a product type, e.g. `BTCUSDT`
+/// is both a spot and a USDT-M perpetual market.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub(in super) enum ProductType {
+    Spot,
+    UsdtFutures,
+    CoinFutures,
+    UsdcFutures,
+}
+
+impl ProductType {
+    pub(in super) const ALL: [ProductType; 4] = [ProductType::Spot, ProductType::UsdtFutures, ProductType::CoinFutures, ProductType::UsdcFutures];
+
+    /// Also the `instType` of the public streams.
+    pub(in super) fn as_str(&self) -> &'static str {
+        match self {
+            ProductType::Spot => "SPOT",
+            ProductType::UsdtFutures => "USDT-FUTURES",
+            ProductType::CoinFutures => "COIN-FUTURES",
+            ProductType::UsdcFutures => "USDC-FUTURES",
+        }
+    }
+
+    pub(in super) fn of(market: &Market) -> Result<ProductType> {
+        match (market.market_type, market.contract_type, market.settle.as_deref()) {
+            (MarketType::Spot, _, _) => Ok(ProductType::Spot),
+            (MarketType::Swap | MarketType::Future, Some(ContractType::Inverse), _) => Ok(ProductType::CoinFutures),
+            (MarketType::Swap | MarketType::Future, Some(ContractType::Linear), Some("USDC")) => Ok(ProductType::UsdcFutures),
+            (MarketType::Swap | MarketType::Future, Some(ContractType::Linear), _) => Ok(ProductType::UsdtFutures),
+            _ => Err(Error::InvalidParameters(format!("unsupported market {}", market))),
+        }
+    }
+}
+
+pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
+    exchange_asset.to_uppercase()
+}
+
+/// Spot symbols are `online`, contracts are `normal` or only closed to new positions.
+pub(in super) fn is_active(status: &str) -> bool {
+    matches!(status, "online" | "normal" | "limit_open")
+}
+
+/// Parses a decimal field that the exchange leaves empty when it does not apply.
+pub(in super) fn parse_optional_float(s: &str) -> Result<f64> {
+    match s {
+        "" => Ok(0.0),
+        s => Ok(s.parse::<f64>()?),
+    }
+}
+
+pub(in super) fn get_exchange_order_side(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+/// Sides are lowercase, except in some of the spot responses.
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "buy" | "Buy" => Ok(OrderSide::Buy),
+        "sell" | "Sell" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+pub(in super) fn get_exchange_order_type(order_type: &OrderType) -> Result<&'static str> {
+    match order_type {
+        OrderType::Limit => Ok("limit"),
+        OrderType::Market => Ok("market"),
+        _ => Err(Error::UnsupportedOrderType(order_type.to_string())),
+    }
+}
+
+pub(in super) fn get_exchange_force(time_in_force: &TimeInForce) -> &'static str {
+    match time_in_force {
+        TimeInForce::GTC => "gtc",
+        TimeInForce::IOC => "ioc",
+        TimeInForce::FOK => "fok",
+        TimeInForce::PO => "post_only",
+    }
+}
+
+pub(in super) fn get_exchange_margin_mode(margin_mode: &MarginMode) -> &'static str {
+    match margin_mode {
+        MarginMode::Cross => "crossed",
+        MarginMode::Isolated => "isolated",
+    }
+}
@@ -0,0 +1,5 @@
+pub use bithumb::Bithumb;
+
+#[allow(clippy::module_inception)]
+mod bithumb;
+mod util;
@@ -0,0 +1,1115 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::stream::SelectAll;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+
+use crate::client::{EMPTY_QUERY, WsItem};
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{f64_to_number, into_precision, parse_float64, parse_number, ZERO};
+use crate::util::channel::{Receiver, Sender};
+use crate::util::order_book::LocalOrderBook;
+use crate::util::rate_limiter::RateLimit;
+
+use super::util;
+use super::util::Category;
+
+/// maximum number of topics in a subscription message
+const SUBSCRIPTION_CHUNK_SIZE: usize = 10;
+
+/// interval of the pings recommended by the exchange to keep connections alive
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+
+/// page size of the paginated endpoints
+const PAGE_LIMIT: &str = "1000";
+const POSITION_PAGE_LIMIT: &str = "200";
+
+/// linear positions can only be listed per settle coin
+const LINEAR_SETTLE_COINS: [&str; 2] = ["USDT", "USDC"];
+
+pub struct Bybit {
+    exchange_base: ExchangeBase,
+    api_key: Option<String>,
+    secret: Option<String>,
+    recv_window: String,
+
+    /// markets by the symbol id of each category
+    unifiers: HashMap<Category, Unifier>,
+}
+
+impl Bybit {
+    /// `ws_endpoint` is the common prefix of the public streams, which are suffixed with the
+    /// category, e.g. `wss://stream.bybit.com/v5/public/linear`.
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        let (host, ws_endpoint) = match props.sandbox {
+            true => ("https://api-testnet.bybit.com", "wss://stream-testnet.bybit.com/v5/public"),
+            false => ("https://api.bybit.com", "wss://stream.bybit.com/v5/public"),
+        };
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some(host.to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some(ws_endpoint.to_string())))
+            .error_parser(Some(|message| {
+                match serde_json::from_str::<Response>(&message) {
+                    Ok(response) => get_error(response.ret_code, response.ret_msg),
+                    Err(_) => Error::HttpError(message),
+                }
+            }))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            .heartbeat(Some(Heartbeat {
+                ping_interval: Some(PING_INTERVAL),
+                ping_message: Some(r#"{"op": "ping"}"#.to_string()),
+                ..Default::default()
+            }))
+            // requests are limited per IP over a rolling 5 seconds window
+            .rate_limits(Some(vec![RateLimit { interval: Duration::from_secs(5), limit: 600, header: None }]))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            recv_window: props.recv_window.unwrap_or(util::DEFAULT_RECV_WINDOW).to_string(),
+            unifiers: HashMap::new(),
+        })
+    }
+
+    /// Timestamp for signed requests, on the exchange clock when time sync is enabled.
+    async fn timestamp(&self) -> Result<i64> {
+        let clock = &self.exchange_base.clock;
+        if clock.needs_sync() {
+            let sent = Utc::now().timestamp_millis();
+            let response: Response = self.exchange_base.http_client.get("/v5/market/time", None, EMPTY_QUERY).await?;
+            let received = Utc::now().timestamp_millis();
+            let server_time = response.time.ok_or_else(|| Error::MissingField("time".into()))?;
+            clock.update(sent, server_time, received);
+        }
+        Ok(clock.timestamp())
+    }
+
+    /// Signs `timestamp + api key + recv window + payload`, where the payload is the query string
+    /// of GET requests and the json body of POST requests.
+    fn auth(&self, timestamp: &str, payload: &str) -> Result<String> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(timestamp.as_bytes());
+        signed_key.update(self.api_key.as_ref().unwrap().as_bytes());
+        signed_key.update(self.recv_window.as_bytes());
+        signed_key.update(payload.as_bytes());
+        Ok(hex::encode(signed_key.finalize().into_bytes()))
+    }
+
+    fn unifier(&self, category: Category) -> Result<&Unifier> {
+        self.unifiers.get(&category).ok_or(Error::MarketNotInitialized)
+    }
+
+    /// Category and symbol id of a loaded market.
+    fn get_symbol_id(&self, market: &Market) -> Result<(Category, String)> {
+        let category = Category::of(market)?;
+        let symbol_id = self.unifier(category)?.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))?;
+        Ok((category, symbol_id))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let response: Response = self.exchange_base.http_client.get(path, None, Some(query)).await?;
+        response.into_result()
+    }
+
+    async fn signed_get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let timestamp = self.timestamp().await?.to_string();
+        let payload = query.iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<String>>()
+            .join("&");
+        let signature = self.auth(&timestamp, &payload)?;
+        let headers = vec![
+            ("X-BAPI-API-KEY", self.api_key.as_ref().unwrap().as_str()),
+            ("X-BAPI-TIMESTAMP", timestamp.as_str()),
+            ("X-BAPI-RECV-WINDOW", self.recv_window.as_str()),
+            ("X-BAPI-SIGN", signature.as_str()),
+        ];
+        let response: Response = self.exchange_base.http_client.get(path, Some(headers), Some(query)).await?;
+        response.into_result()
+    }
+
+    async fn signed_post<T: DeserializeOwned>(&self, path: &str, body: &String) -> Result<T> {
+        let timestamp = self.timestamp().await?.to_string();
+        let signature = self.auth(&timestamp, body)?;
+        let headers = vec![
+            ("Content-Type", "application/json"),
+            ("X-BAPI-API-KEY", self.api_key.as_ref().unwrap().as_str()),
+            ("X-BAPI-TIMESTAMP", timestamp.as_str()),
+            ("X-BAPI-RECV-WINDOW", self.recv_window.as_str()),
+            ("X-BAPI-SIGN", signature.as_str()),
+        ];
+        let response: Response = self.exchange_base.http_client.post(path, Some(headers), EMPTY_QUERY, Some(body)).await?;
+        response.into_result()
+    }
+
+    async fn fetch_category_markets(&self, category: Category) -> Result<Vec<(String, Market)>> {
+        let mut markets = vec![];
+        let mut cursor = String::new();
+        loop {
+            let mut query = vec![("category", category.as_str()), ("limit", PAGE_LIMIT)];
+            if !cursor.is_empty() {
+                query.push(("cursor", cursor.as_str()));
+            }
+            let page: ListResponse<FetchMarketsSymbolResponse> = self.get("/v5/market/instruments-info", &query).await?;
+            for symbol in &page.list {
+                markets.push((symbol.symbol.clone(), symbol.to_market(category)?));
+            }
+            match page.next_page_cursor {
+                Some(next) if !next.is_empty() && !page.list.is_empty() => cursor = next,
+                _ => break,
+            }
+        }
+        Ok(markets)
+    }
+
+    async fn fetch_all_markets(&self) -> Result<Vec<(Category, String, Market)>> {
+        let mut markets = vec![];
+        for category in Category::ALL {
+            for (symbol_id, market) in self.fetch_category_markets(category).await? {
+                markets.push((category, symbol_id, market));
+            }
+        }
+        Ok(markets)
+    }
+}
+
+#[async_trait]
+impl Exchange for Bybit {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        if self.exchange_base.markets.is_empty() {
+            let result = self.fetch_all_markets().await?;
+            self.exchange_base.unifier.reset();
+            self.unifiers.clear();
+            let mut markets = vec![];
+            for (category, symbol_id, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &symbol_id);
+                self.unifiers.entry(category).or_insert_with(Unifier::new).insert_market_symbol_id(&market, &symbol_id);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.fetch_all_markets().await?;
+        Ok(result.into_iter().map(|(_, _, market)| market).collect())
+    }
+
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let categories = match params.markets.as_ref() {
+            Some(markets) => {
+                let mut categories = vec![];
+                for market in markets {
+                    let (category, _) = self.get_symbol_id(market)?;
+                    if !categories.contains(&category) {
+                        categories.push(category);
+                    }
+                }
+                categories
+            }
+            None => Category::ALL.to_vec(),
+        };
+
+        let mut tickers = vec![];
+        for category in categories {
+            let unifier = self.unifier(category)?;
+            let query = vec![("category", category.as_str())];
+            let response: Response = self.exchange_base.http_client.get("/v5/market/tickers", None, Some(&query)).await?;
+            let timestamp = response.time.unwrap_or_default();
+            let result: ListResponse<FetchTickersResponse> = response.into_result()?;
+            for item in result.list {
+                let market = match unifier.get_market(&item.symbol) {
+                    Some(market) => market,
+                    None => continue,
+                };
+                if let Some(markets) = params.markets.as_ref() {
+                    if !markets.contains(&market) {
+                        continue;
+                    }
+                }
+                tickers.push(item.to_ticker(market, category, timestamp)?);
+            }
+        }
+        Ok(tickers)
+    }
+
+    async fn fetch_order_book(&self, params: FetchOrderBookParams) -> FetchOrderBookResult<OrderBook> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let (category, symbol_id) = self.get_symbol_id(&params.market)?;
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = vec![("category", category.as_str()), ("symbol", symbol_id.as_str())];
+        if let Some(limit) = &limit {
+            query.push(("limit", limit.as_str()));
+        }
+        let result: FetchOrderBookResponse = self.get("/v5/market/orderbook", &query).await?;
+        Ok(OrderBook::new(
+            to_order_book_units(&result.bids)?,
+            to_order_book_units(&result.asks)?,
+            params.market,
+            Some(result.timestamp),
+            Some(result.update_id),
+        ))
+    }
+
+    async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let (category, symbol_id) = self.get_symbol_id(&params.market)?;
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = vec![("category", category.as_str()), ("symbol", symbol_id.as_str())];
+        if let Some(limit) = &limit {
+            query.push(("limit", limit.as_str()));
+        }
+        let result: ListResponse<FetchTradesResponse> = self.get("/v5/market/recent-trade", &query).await?;
+
+        // only the most recent trades are served, so the range is applied locally
+        let mut trades = vec![];
+        for item in result.list {
+            let trade = item.to_trade(&params.market, category)?;
+            if params.since.is_some_and(|since| trade.timestamp < since) || params.until.is_some_and(|until| trade.timestamp > until) {
+                continue;
+            }
+            trades.push(trade);
+        }
+        Ok(trades)
+    }
+
+    /// Streams `orderbook.N` books, with the best bid and ask only (N = 1) unless `full_depth` is
+    /// set, in which case the deepest level of the category is used.
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if params.markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut symbol_ids: HashMap<Category, Vec<String>> = HashMap::new();
+        for market in &params.markets {
+            let (category, symbol_id) = self.get_symbol_id(market).map_err(|_| WatchError::SymbolNotFound(format!("{:?}", market)))?;
+            symbol_ids.entry(category).or_default().push(symbol_id);
+        }
+
+        let mut clients = vec![];
+        for (category, symbol_ids) in symbol_ids {
+            let depth = match (params.full_depth, category) {
+                (false, _) => 1,
+                (true, Category::Spot) => 200,
+                (true, _) => 500,
+            };
+            let endpoint = format!("{}/{}", self.exchange_base.ws_endpoint.as_ref().unwrap(), category.as_str());
+            let mut ws_client = self.exchange_base.ws_client(endpoint.as_str(), parse_order_book, self.unifier(category)?.clone());
+            for symbol_ids in symbol_ids.chunks(SUBSCRIPTION_CHUNK_SIZE) {
+                let args = symbol_ids.iter()
+                    .map(|s| format!("\"orderbook.{}.{}\"", depth, s))
+                    .collect::<Vec<String>>()
+                    .join(",");
+                let message = format!("{{\"op\": \"subscribe\", \"args\": [{args}]}}");
+                let _ = ws_client.send(message).await?;
+            }
+            clients.push(ws_client);
+        }
+
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(synchronize_order_books(futures_util::stream::select_all(clients), tx));
+        Ok(receiver)
+    }
+
+    /// Fetches the unified trading account.
+    async fn fetch_balance(&self, params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if params.margin_mode == Some(MarginMode::Isolated) {
+            return Err(Error::InvalidParameters("margin mode is not supported".into()).into());
+        }
+
+        let query = vec![("accountType", "UNIFIED")];
+        let result: ListResponse<FetchBalanceResponse> = self.signed_get("/v5/account/wallet-balance", &query).await?;
+        let mut balance = Balance::default();
+        for account in result.list {
+            for coin in account.coin {
+                balance.items.push(coin.to_balance_item()?);
+            }
+        }
+        Ok(balance)
+    }
+
+    async fn fetch_positions(&self, _params: FetchPositionsParams) -> FetchPositionsResult<Vec<Position>> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let requests = LINEAR_SETTLE_COINS.iter()
+            .map(|settle_coin| (Category::Linear, Some(*settle_coin)))
+            .chain([(Category::Inverse, None)]);
+        let mut positions = vec![];
+        for (category, settle_coin) in requests {
+            let unifier = self.unifier(category)?;
+            let mut cursor = String::new();
+            loop {
+                let mut query = vec![("category", category.as_str())];
+                if let Some(settle_coin) = settle_coin {
+                    query.push(("settleCoin", settle_coin));
+                }
+                query.push(("limit", POSITION_PAGE_LIMIT));
+                if !cursor.is_empty() {
+                    query.push(("cursor", cursor.as_str()));
+                }
+                let page: ListResponse<FetchPositionsResponse> = self.signed_get("/v5/position/list", &query).await?;
+                for item in &page.list {
+                    if let Some(market) = unifier.get_market(&item.symbol) {
+                        if let Some(position) = item.to_position(market)? {
+                            positions.push(position);
+                        }
+                    }
+                }
+                match page.next_page_cursor {
+                    Some(next) if !next.is_empty() && !page.list.is_empty() => cursor = next,
+                    _ => break,
+                }
+            }
+        }
+        Ok(positions)
+    }
+
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let (category, symbol_id) = self.get_symbol_id(&params.market)?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.unifier(category)?.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+
+        let is_market = order_type == OrderType::Market;
+        let request = CreateOrderRequest {
+            category: category.as_str(),
+            symbol: symbol_id,
+            side: util::get_exchange_order_side(&params.order_side),
+            order_type: util::get_exchange_order_type(&order_type)?,
+            qty: amount.to_string(),
+            price: price.filter(|_| !is_market).map(|price| price.to_string()),
+            time_in_force: match is_market {
+                true => None,
+                false => Some(util::get_exchange_time_in_force(&params.time_in_force.unwrap_or(TimeInForce::GTC))),
+            },
+            // spot market buys are sized in the quote currency unless told otherwise
+            market_unit: Some("baseCoin").filter(|_| is_market && category == Category::Spot),
+            is_leverage: params.margin_mode.filter(|_| category == Category::Spot).map(|_| 1),
+            reduce_only: Some(true).filter(|_| params.reduce_only && category != Category::Spot),
+        };
+        let body = serde_json::to_string(&request).map_err(|e| Error::InvalidParameters(format!("{}", e)))?;
+        let response: CreateOrderResponse = self.signed_post("/v5/order/create", &body).await?;
+        Ok(Order {
+            id: Some(response.order_id),
+            client_order_id: Some(response.order_link_id).filter(|id| !id.is_empty()),
+            timestamp: Utc::now().timestamp_millis(),
+            status: OrderStatus::Open,
+            market: params.market.clone(),
+            order_type,
+            time_in_force: match is_market {
+                true => None,
+                false => Some(params.time_in_force.unwrap_or(TimeInForce::GTC)),
+            },
+            side: Some(params.order_side),
+            price: price.filter(|_| !is_market),
+            amount,
+            margin_mode: params.margin_mode.unwrap_or(MarginMode::Cross),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Maps the `retCode` of a response to an error.
+fn get_error(code: i64, msg: String) -> Error {
+    match code {
+        10001 => Error::InvalidParameters(msg), // Request parameter error
+        10002 => Error::TimestampOutsideRecvWindow(msg), // The request time exceeds the time window range
+        10003 => Error::CredentialsError(msg), // API key is invalid
+        10004 => Error::InvalidSignature(msg), // Error sign
+        110001 => Error::OrderNotFound(msg), // Order does not exist
+        110004 | 110007 | 110012 | 170131 => Error::InsufficientMargin(msg), // Insufficient balance
+        _ => Error::HttpError(format!("code={}, msg={}", code, msg)),
+    }
+}
+
+/// Value in the quote currency of linear and spot trades, and in the base currency of inverse
+/// trades, whose contracts are worth one unit of the quote currency.
+fn get_cost(market: &Market, category: Category, price: Number, amount: Number) -> Number {
+    let contract_size = f64_to_number(market.contract_size.unwrap_or(1.0));
+    match category {
+        Category::Inverse if price == ZERO => ZERO,
+        Category::Inverse => amount * contract_size / price,
+        _ => amount * price * contract_size,
+    }
+}
+
+fn to_order_book_units(levels: &[[String; 2]]) -> Result<Vec<OrderBookUnit>> {
+    levels.iter()
+        .map(|[price, amount]| Ok(OrderBookUnit { price: parse_number(price)?, amount: parse_number(amount)? }))
+        .collect()
+}
+
+
+enum OrderBookItem {
+    Update(Box<(Market, WatchOrderBookResponse)>),
+    Reconnected,
+    Other,
+}
+
+impl WsItem for OrderBookItem {
+    fn reconnected() -> Self {
+        OrderBookItem::Reconnected
+    }
+}
+
+fn parse_order_book(message: &[u8], unifier: &Unifier) -> WatchResult<OrderBookItem> {
+    let common_message = WatchCommonResponse::try_from(message.to_vec())?;
+    if common_message.success == Some(false) { // rejected subscription
+        return Err(Error::StreamError(common_message.ret_msg.unwrap_or_default()).into());
+    }
+    match common_message.topic {
+        Some(topic) if topic.starts_with("orderbook.") => {
+            let resp = WatchOrderBookResponse::try_from(message.to_vec())?;
+            match unifier.get_market(&resp.data.symbol) {
+                Some(market) => Ok(OrderBookItem::Update(Box::new((market, resp)))),
+                None => Err(WatchError::SymbolNotFound(resp.data.symbol)),
+            }
+        }
+        _ => Ok(OrderBookItem::Other),
+    }
+}
+
+/// Keeps a local book per market up to date from snapshot and delta events and forwards the
+/// complete books to the receiver until it is dropped.
+async fn synchronize_order_books(mut clients: SelectAll<WsClient<OrderBookItem>>, tx: Sender) {
+    let mut books: HashMap<Market, LocalOrderBook> = HashMap::new();
+    while let Some(item) = clients.next().await {
+        let item = match item {
+            Ok(OrderBookItem::Update(update)) => {
+                let (market, update) = *update;
+                let book = books.entry(market.clone()).or_insert_with(|| LocalOrderBook::new(market));
+                match apply_update(book, &update) {
+                    Ok(true) => Ok(StreamItem::OrderBook(Ok(book.to_order_book()))),
+                    Ok(false) => continue,
+                    Err(e) => Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(book.market().clone()))))),
+                }
+            }
+            Ok(OrderBookItem::Reconnected) => {
+                // a snapshot follows the replayed subscriptions
+                books.values_mut().for_each(|book| book.invalidate());
+                Ok(StreamItem::Reconnected)
+            }
+            Ok(OrderBookItem::Other) => continue,
+            Err(e) => Err(e),
+        };
+        if tx.send(item).await.is_err() { // the receiver is dropped
+            return;
+        }
+    }
+}
+
+/// Applies a snapshot or delta event. Returns `Ok(false)` for deltas received before the first
+/// snapshot and for stale ones.
+fn apply_update(book: &mut LocalOrderBook, update: &WatchOrderBookResponse) -> Result<bool> {
+    let bids = to_order_book_units(&update.data.bids)?;
+    let asks = to_order_book_units(&update.data.asks)?;
+    // the update id restarts from 1 with a snapshot when the exchange restarts its service
+    if update.message_type == "snapshot" || update.data.update_id == 1 {
+        book.reset(&bids, &asks, update.data.update_id);
+        book.update(&[], &[], update.data.update_id, Some(update.timestamp));
+        return Ok(true);
+    }
+    match book.last_update_id() {
+        Some(last_update_id) if update.data.update_id > last_update_id => {
+            book.update(&bids, &asks, update.data.update_id, Some(update.timestamp));
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+
+/// Envelope of every REST response. `result` is kept raw so that errors are reported from
+/// `retCode` even when the result does not match the expected type.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    ret_code: i64,
+    ret_msg: String,
+    #[serde(default)]
+    result: serde_json::Value,
+    time: Option<i64>,
+}
+
+impl Response {
+    fn into_result<T: DeserializeOwned>(self) -> Result<T> {
+        if self.ret_code != 0 {
+            return Err(get_error(self.ret_code, self.ret_msg));
+        }
+        serde_json::from_value(self.result).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListResponse<T> {
+    list: Vec<T>,
+    next_page_cursor: Option<String>,
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsSymbolResponse {
+    symbol: String,
+    /// not set for spot
+    contract_type: Option<String>,
+    status: String,
+    base_coin: String,
+    quote_coin: String,
+    settle_coin: Option<String>,
+    /// milliseconds, "0" for perpetual contracts
+    delivery_time: Option<String>,
+    leverage_filter: Option<FetchMarketsLeverageFilterResponse>,
+    price_filter: FetchMarketsPriceFilterResponse,
+    lot_size_filter: FetchMarketsLotSizeFilterResponse,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsLeverageFilterResponse {
+    min_leverage: String,
+    max_leverage: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsPriceFilterResponse {
+    min_price: Option<String>,
+    max_price: Option<String>,
+    tick_size: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchMarketsLotSizeFilterResponse {
+    /// amount step of spot markets
+    base_precision: Option<String>,
+    /// amount step of derivatives
+    qty_step: Option<String>,
+    min_order_qty: String,
+    max_order_qty: String,
+    min_order_amt: Option<String>,
+    max_order_amt: Option<String>,
+    min_notional_value: Option<String>,
+}
+
+impl FetchMarketsSymbolResponse {
+    fn to_market(&self, category: Category) -> Result<Market> {
+        let market_type = match (category, self.contract_type.as_deref()) {
+            (Category::Spot, _) => MarketType::Spot,
+            (_, Some("LinearPerpetual" | "InversePerpetual")) => MarketType::Swap,
+            (_, Some("LinearFutures" | "InverseFutures")) => MarketType::Future,
+            _ => MarketType::Unknown,
+        };
+        let (contract_size, contract_type) = match category {
+            Category::Spot => (None, None),
+            Category::Linear => (Some(1.0), Some(ContractType::Linear)),
+            Category::Inverse => (Some(1.0), Some(ContractType::Inverse)),
+        };
+        let expiry = match market_type {
+            MarketType::Future => self.delivery_time.as_ref().map(|t| t.parse::<i64>()).transpose()?.filter(|t| *t > 0),
+            _ => None,
+        };
+
+        let lot_size = &self.lot_size_filter;
+        let amount_step = lot_size.qty_step.as_ref().or(lot_size.base_precision.as_ref());
+        let precision = Precision {
+            amount: amount_step.map(|step| into_precision(step.clone())).transpose()?,
+            price: Some(into_precision(self.price_filter.tick_size.clone())?),
+            cost: None,
+        };
+
+        let price = match (&self.price_filter.min_price, &self.price_filter.max_price) {
+            (Some(min), Some(max)) => Some(Range { min: parse_float64(min)?, max: parse_float64(max)? }),
+            _ => None,
+        };
+        let cost = match (&lot_size.min_order_amt, &lot_size.max_order_amt, &lot_size.min_notional_value) {
+            (Some(min), Some(max), _) => Some(Range { min: parse_float64(min)?, max: parse_float64(max)? }),
+            (_, _, Some(min)) => Some(Range { min: parse_float64(min)?, max: 0.0 }),
+            _ => None,
+        };
+        let leverage = match &self.leverage_filter {
+            Some(filter) => Some(Range { min: parse_float64(&filter.min_leverage)?, max: parse_float64(&filter.max_leverage)? }),
+            None => None,
+        };
+
+        Ok(Market {
+            base: util::to_unified_asset(&self.base_coin),
+            quote: util::to_unified_asset(&self.quote_coin),
+            active: util::is_active(&self.status),
+            market_type,
+            settle: self.settle_coin.as_deref().filter(|_| category != Category::Spot).map(util::to_unified_asset),
+            contract_size,
+            contract_type,
+            expiry,
+            precision: Some(precision),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: parse_float64(&lot_size.min_order_qty)?, max: parse_float64(&lot_size.max_order_qty)? }),
+                price,
+                cost,
+                leverage,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchTickersResponse {
+    symbol: String,
+    last_price: String,
+    prev_price24h: String,
+    price24h_pcnt: String,
+    high_price24h: String,
+    low_price24h: String,
+    /// in contracts for derivatives
+    volume24h: String,
+    /// in the quote currency, or in the base currency for inverse contracts
+    turnover24h: String,
+    bid1_price: String,
+    bid1_size: String,
+    ask1_price: String,
+    ask1_size: String,
+}
+
+impl FetchTickersResponse {
+    fn to_ticker(&self, market: Market, category: Category, timestamp: i64) -> Result<Ticker> {
+        let last = parse_float64(&self.last_price)?;
+        let open = parse_float64(&self.prev_price24h)?;
+        let volume = util::parse_optional_float(&self.volume24h)?;
+        let turnover = util::parse_optional_float(&self.turnover24h)?;
+        let (base_volume, quote_volume) = match category {
+            Category::Inverse => (turnover, volume * market.contract_size.unwrap_or(1.0)),
+            _ => (volume * market.contract_size.unwrap_or(1.0), turnover),
+        };
+        Ok(Ticker {
+            ask: Some(util::parse_optional_float(&self.ask1_price)?).filter(|ask| *ask > 0.0),
+            ask_volume: util::parse_optional_float(&self.ask1_size)?,
+            bid: Some(util::parse_optional_float(&self.bid1_price)?).filter(|bid| *bid > 0.0),
+            bid_volume: util::parse_optional_float(&self.bid1_size)?,
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume,
+            change: last - open,
+            close: last,
+            high: parse_float64(&self.high_price24h)?,
+            low: parse_float64(&self.low_price24h)?,
+            last,
+            open,
+            percentage: parse_float64(&self.price24h_pcnt)? * 100f64,
+            previous_close: None,
+            vwap: match base_volume > 0.0 {
+                true => quote_volume / base_volume,
+                false => 0.0,
+            },
+            market,
+            timestamp,
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchTradesResponse {
+    exec_id: String,
+    price: String,
+    size: String,
+    /// taker side
+    side: String,
+    time: String,
+}
+
+impl FetchTradesResponse {
+    fn to_trade(&self, market: &Market, category: Category) -> Result<Trade> {
+        let price = parse_number(&self.price)?;
+        let amount = parse_number(&self.size)?;
+        Ok(Trade::new(
+            self.exec_id.clone(),
+            self.time.parse()?,
+            market.clone(),
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.side)?),
+            None,
+            price, amount,
+            get_cost(market, category, price, amount),
+            None, None,
+        ))
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchOrderBookResponse {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    asks: Vec<[String; 2]>,
+    #[serde(rename = "ts")]
+    timestamp: i64,
+    #[serde(rename = "u")]
+    update_id: i64,
+}
+
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchCommonResponse {
+    topic: Option<String>,
+    success: Option<bool>,
+    ret_msg: Option<String>,
+}
+
+impl TryFrom<Vec<u8>> for WatchCommonResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+/// `orderbook.N` event
+#[derive(Serialize, Deserialize)]
+struct WatchOrderBookResponse {
+    topic: String,
+    /// `snapshot` or `delta`
+    #[serde(rename = "type")]
+    message_type: String,
+    #[serde(rename = "ts")]
+    timestamp: i64,
+    data: WatchOrderBookDataResponse,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchOrderBookDataResponse {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    asks: Vec<[String; 2]>,
+    #[serde(rename = "u")]
+    update_id: i64,
+}
+
+impl TryFrom<Vec<u8>> for WatchOrderBookResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchBalanceResponse {
+    account_type: String,
+    coin: Vec<FetchBalanceCoinResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchBalanceCoinResponse {
+    coin: String,
+    wallet_balance: String,
+    locked: String,
+    #[serde(rename = "totalOrderIM")]
+    total_order_im: String,
+    #[serde(rename = "totalPositionIM")]
+    total_position_im: String,
+    borrow_amount: String,
+}
+
+impl FetchBalanceCoinResponse {
+    fn to_balance_item(&self) -> Result<BalanceItem> {
+        let total = util::parse_optional_float(&self.wallet_balance)?;
+        let used = util::parse_optional_float(&self.locked)?
+            + util::parse_optional_float(&self.total_order_im)?
+            + util::parse_optional_float(&self.total_position_im)?;
+        Ok(BalanceItem {
+            currency: util::to_unified_asset(&self.coin),
+            market: None,
+            total,
+            free: total - used,
+            used,
+            debt: util::parse_optional_float(&self.borrow_amount)?,
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchPositionsResponse {
+    /// 0 in one-way mode, 1 and 2 for the buy and sell sides in hedge mode
+    position_idx: i64,
+    symbol: String,
+    /// empty when there is no position
+    side: String,
+    size: String,
+    avg_price: String,
+    position_value: String,
+    /// 0 for cross margin, 1 for isolated margin
+    trade_mode: i64,
+    leverage: String,
+    position_balance: String,
+    mark_price: String,
+    liq_price: String,
+    #[serde(rename = "positionIM")]
+    position_im: String,
+    #[serde(rename = "positionMM")]
+    position_mm: String,
+    unrealised_pnl: String,
+    updated_time: String,
+}
+
+impl FetchPositionsResponse {
+    /// Returns `None` for empty positions.
+    fn to_position(&self, market: Market) -> Result<Option<Position>> {
+        let contracts = util::parse_optional_float(&self.size)?;
+        if contracts == 0.0 {
+            return Ok(None);
+        }
+        let side = match util::get_unified_order_side(&self.side)? {
+            OrderSide::Buy => PositionSide::Long,
+            OrderSide::Sell => PositionSide::Short,
+        };
+        let notional = util::parse_optional_float(&self.position_value)?;
+        let initial_margin = util::parse_optional_float(&self.position_im)?;
+        let maintenance_margin = util::parse_optional_float(&self.position_mm)?;
+        let unrealized_pnl = util::parse_optional_float(&self.unrealised_pnl)?;
+        let collateral = match util::parse_optional_float(&self.position_balance)? {
+            balance if balance > 0.0 => balance,
+            _ => initial_margin,
+        };
+        let ratio = |value: f64, total: f64| if total > 0.0 { value / total } else { 0.0 };
+        Ok(Some(Position {
+            contract_size: market.contract_size,
+            market,
+            timestamp: util::parse_optional_float(&self.updated_time)? as i64,
+            is_hedged: self.position_idx != 0,
+            side,
+            contracts,
+            entry_price: util::parse_optional_float(&self.avg_price)?,
+            mark_price: util::parse_optional_float(&self.mark_price)?,
+            notional,
+            leverage: util::parse_optional_float(&self.leverage)?,
+            collateral,
+            initial_margin,
+            maintenance_margin,
+            initial_margin_percent: ratio(initial_margin, notional),
+            maintenance_margin_percent: ratio(maintenance_margin, notional),
+            unrealized_pnl,
+            liquidation_price: util::parse_optional_float(&self.liq_price)?,
+            margin_mode: match self.trade_mode {
+                1 => MarginMode::Isolated,
+                _ => MarginMode::Cross,
+            },
+            margin_ratio: ratio(maintenance_margin, collateral),
+            percentage: ratio(unrealized_pnl, initial_margin) * 100f64,
+            ..Default::default()
+        }))
+    }
+}
+
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderRequest {
+    category: &'static str,
+    symbol: String,
+    side: &'static str,
+    order_type: &'static str,
+    qty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_in_force: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    market_unit: Option<&'static str>,
+    /// 1 to borrow on the spot margin account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_leverage: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reduce_only: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderResponse {
+    order_id: String,
+    order_link_id: String,
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Bybit, PropertiesBuilder};
+    use crate::error::Error;
+    use crate::model::{ContractType, MarginMode, Market, MarketType, PositionSide};
+    use crate::util::order_book::LocalOrderBook;
+
+    use super::{apply_update, Category, FetchMarketsSymbolResponse, FetchPositionsResponse, Response, WatchOrderBookResponse};
+
+    #[test]
+    fn test_auth() {
+        let props = PropertiesBuilder::default()
+            .api_key(Some("key".into()))
+            .secret(Some("secret".into()))
+            .build()
+            .unwrap();
+        let bybit = Bybit::new(props).unwrap();
+        let signature = bybit.auth("1658384314791", "category=option&symbol=BTC-29JUL22-25000-C").unwrap();
+        assert_eq!(signature, "8ee00e5ba4e77198454b6e37e87c0977b9f8af4e1e1722f88fcd538232e43f64");
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"symbol": "BTCUSDT", "baseCoin": "BTC", "quoteCoin": "USDT", "status": "Trading", "lotSizeFilter": {"basePrecision": "0.000001", "quotePrecision": "0.00000001", "minOrderQty": "0.000048", "maxOrderQty": "71.73956243", "minOrderAmt": "1", "maxOrderAmt": "2000000"}, "priceFilter": {"tickSize": "0.01"}}"#;
+        let resp: FetchMarketsSymbolResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(Category::Spot).unwrap();
+        assert_eq!(market.market_type, MarketType::Spot);
+        assert_eq!(market.settle, None);
+        assert_eq!(market.precision.as_ref().unwrap().amount, Some(6));
+        assert_eq!(market.precision.as_ref().unwrap().price, Some(2));
+        assert_eq!(market.limit.as_ref().unwrap().cost.as_ref().unwrap().min, 1.0);
+        assert_eq!(format!("{}", market), "BTC/USDT");
+
+        let message = r#"{"symbol": "BTCUSDT", "contractType": "LinearPerpetual", "status": "Trading", "baseCoin": "BTC", "quoteCoin": "USDT", "settleCoin": "USDT", "deliveryTime": "0", "leverageFilter": {"minLeverage": "1", "maxLeverage": "100.00", "leverageStep": "0.01"}, "priceFilter": {"minPrice": "0.10", "maxPrice": "199999.80", "tickSize": "0.10"}, "lotSizeFilter": {"maxOrderQty": "100.000", "minOrderQty": "0.001", "qtyStep": "0.001", "minNotionalValue": "5"}}"#;
+        let resp: FetchMarketsSymbolResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(Category::Linear).unwrap();
+        assert_eq!(market.market_type, MarketType::Swap);
+        assert_eq!(market.contract_type, Some(ContractType::Linear));
+        assert_eq!(market.expiry, None);
+        assert_eq!(market.precision.as_ref().unwrap().amount, Some(3));
+        assert_eq!(market.limit.as_ref().unwrap().leverage.as_ref().unwrap().max, 100.0);
+        assert_eq!(format!("{}", market), "BTC/USDT:USDT");
+
+        let message = r#"{"symbol": "BTCUSDH25", "contractType": "InverseFutures", "status": "Trading", "baseCoin": "BTC", "quoteCoin": "USD", "settleCoin": "BTC", "deliveryTime": "1743148800000", "priceFilter": {"minPrice": "0.50", "maxPrice": "1999999.00", "tickSize": "0.50"}, "lotSizeFilter": {"maxOrderQty": "100000000", "minOrderQty": "1", "qtyStep": "1"}}"#;
+        let resp: FetchMarketsSymbolResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(Category::Inverse).unwrap();
+        assert_eq!(market.market_type, MarketType::Future);
+        assert_eq!(market.contract_type, Some(ContractType::Inverse));
+        assert_eq!(Category::of(&market).unwrap(), Category::Inverse);
+        assert_eq!(format!("{}", market), "BTC/USD:BTC-250328");
+    }
+
+    #[test]
+    fn test_apply_order_book_update() {
+        let market = Market { base: "BTC".into(), quote: "USDT".into(), market_type: MarketType::Spot, ..Default::default() };
+        let mut book = LocalOrderBook::new(market);
+
+        let delta = r#"{"topic": "orderbook.50.BTCUSDT", "type": "delta", "ts": 1687940967467, "data": {"s": "BTCUSDT", "b": [["30247.20", "0"]], "a": [], "u": 177400508, "seq": 66544703342}}"#;
+        let delta: WatchOrderBookResponse = serde_json::from_str(delta).unwrap();
+        assert!(!apply_update(&mut book, &delta).unwrap());
+
+        let snapshot = r#"{"topic": "orderbook.50.BTCUSDT", "type": "snapshot", "ts": 1687940967466, "data": {"s": "BTCUSDT", "b": [["30247.20", "30.028"], ["30245.40", "0.224"]], "a": [["30248.70", "0.803"]], "u": 177400507, "seq": 66544703342}}"#;
+        let snapshot: WatchOrderBookResponse = serde_json::from_str(snapshot).unwrap();
+        assert!(apply_update(&mut book, &snapshot).unwrap());
+        assert!(apply_update(&mut book, &delta).unwrap());
+        let order_book = book.to_order_book();
+        assert_eq!(order_book.bids.len(), 1);
+        assert_eq!(order_book.asks.len(), 1);
+        assert_eq!(order_book.timestamp, Some(1687940967467));
+
+        // already applied
+        assert!(!apply_update(&mut book, &delta).unwrap());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let response: Response = serde_json::from_str(r#"{"retCode": 110007, "retMsg": "ab not enough for new order", "result": {}, "time": 1672211918471}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::InsufficientMargin(_))));
+
+        let response: Response = serde_json::from_str(r#"{"retCode": 10002, "retMsg": "invalid request, please check your server timestamp or recv_window param", "result": {}, "time": 1672211918471}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::TimestampOutsideRecvWindow(_))));
+    }
+
+    #[test]
+    fn test_to_position() {
+        let message = r#"{"positionIdx": 0, "symbol": "BTCUSDT", "side": "Sell", "size": "0.5", "avgPrice": "60000", "positionValue": "30000", "tradeMode": 0, "leverage": "10", "positionBalance": "", "markPrice": "59000", "liqPrice": "", "positionIM": "3000", "positionMM": "150", "unrealisedPnl": "500", "updatedTime": "1697684980172"}"#;
+        let resp: FetchPositionsResponse = serde_json::from_str(message).unwrap();
+        let market = Market { base: "BTC".into(), quote: "USDT".into(), settle: Some("USDT".into()), market_type: MarketType::Swap, contract_size: Some(1.0), ..Default::default() };
+        let position = resp.to_position(market).unwrap().unwrap();
+        assert_eq!(position.side, PositionSide::Short);
+        assert_eq!(position.margin_mode, MarginMode::Cross);
+        assert_eq!(position.collateral, 3000.0);
+        assert_eq!(position.liquidation_price, 0.0);
+        assert_eq!(position.timestamp, 1697684980172);
+        assert!(!position.is_hedged);
+
+        let empty = FetchPositionsResponse { size: "0".into(), ..resp };
+        let market = Market { base: "BTC".into(), quote: "USDT".into(), ..Default::default() };
+        assert!(empty.to_position(market).unwrap().is_none());
+    }
+}
@@ -0,0 +1,5 @@
+pub use bybit::Bybit;
+
+#[allow(clippy::module_inception)]
+mod bybit;
+mod util;
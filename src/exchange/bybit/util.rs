@@ -0,0 +1,82 @@
+use crate::error::{Error, Result};
+use crate::model::{ContractType, Market, MarketType, OrderSide, OrderType, TimeInForce};
+
+pub(in super) const DEFAULT_RECV_WINDOW: u64 = 5000;
+
+/// Product line of the v5 API. Symbol ids are only unique within a category, e.g. `BTCUSDT` is
+/// both a spot and a linear perpetual market.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub(in super) enum Category {
+    Spot,
+    Linear,
+    Inverse,
+}
+
+impl Category {
+    pub(in super) const ALL: [Category; 3] = [Category::Spot, Category::Linear, Category::Inverse];
+
+    pub(in super) fn as_str(&self) -> &'static str {
+        match self {
+            Category::Spot => "spot",
+            Category::Linear => "linear",
+            Category::Inverse => "inverse",
+        }
+    }
+
+    pub(in super) fn of(market: &Market) -> Result<Category> {
+        match (market.market_type, market.contract_type) {
+            (MarketType::Spot, _) => Ok(Category::Spot),
+            (MarketType::Swap | MarketType::Future, Some(ContractType::Linear)) => Ok(Category::Linear),
+            (MarketType::Swap | MarketType::Future, Some(ContractType::Inverse)) => Ok(Category::Inverse),
+            _ => Err(Error::InvalidParameters(format!("unsupported market {}", market))),
+        }
+    }
+}
+
+pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
+    exchange_asset.to_uppercase()
+}
+
+pub(in super) fn is_active(status: &str) -> bool {
+    status == "Trading"
+}
+
+/// Parses a decimal field that the exchange leaves empty when it does not apply.
+pub(in super) fn parse_optional_float(s: &str) -> Result<f64> {
+    match s {
+        "" => Ok(0.0),
+        s => Ok(s.parse::<f64>()?),
+    }
+}
+
+pub(in super) fn get_exchange_order_side(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "Buy",
+        OrderSide::Sell => "Sell",
+    }
+}
+
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "Buy" => Ok(OrderSide::Buy),
+        "Sell" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+pub(in super) fn get_exchange_order_type(order_type: &OrderType) -> Result<&'static str> {
+    match order_type {
+        OrderType::Limit => Ok("Limit"),
+        OrderType::Market => Ok("Market"),
+        _ => Err(Error::UnsupportedOrderType(order_type.to_string())),
+    }
+}
+
+pub(in super) fn get_exchange_time_in_force(time_in_force: &TimeInForce) -> &'static str {
+    match time_in_force {
+        TimeInForce::GTC => "GTC",
+        TimeInForce::IOC => "IOC",
+        TimeInForce::FOK => "FOK",
+        TimeInForce::PO => "PostOnly",
+    }
+}
@@ -0,0 +1,5 @@
+pub use coinbase::Coinbase;
+
+#[allow(clippy::module_inception)]
+mod coinbase;
+mod util;
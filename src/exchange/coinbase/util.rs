@@ -0,0 +1,72 @@
+use chrono::DateTime;
+
+use crate::error::{Error, Result};
+use crate::model::{OrderSide, OrderStatus, OrderType, Timeframe};
+
+pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
+    exchange_asset.to_uppercase()
+}
+
+pub(in super) fn is_active(status: &str, trading_disabled: bool) -> bool {
+    status == "online" && !trading_disabled
+}
+
+/// Milliseconds of an RFC 3339 time, e.g. `2023-02-09T20:32:50.714964855Z`.
+pub(in super) fn parse_timestamp(time: &str) -> Result<i64> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.timestamp_millis())
+        .map_err(|e| Error::ParseError(format!("{}: {}", time, e)))
+}
+
+pub(in super) fn get_exchange_order_side(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "BUY",
+        OrderSide::Sell => "SELL",
+    }
+}
+
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "BUY" => Ok(OrderSide::Buy),
+        "SELL" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+pub(in super) fn get_unified_order_type(exchange_order_type: &str) -> Result<OrderType> {
+    match exchange_order_type {
+        "MARKET" => Ok(OrderType::Market),
+        "LIMIT" => Ok(OrderType::Limit),
+        "STOP" | "STOP_LIMIT" => Ok(OrderType::StopLoss),
+        "BRACKET" => Ok(OrderType::TakeProfit),
+        _ => Err(Error::UnsupportedOrderType(exchange_order_type.to_string())),
+    }
+}
+
+/// Orders waiting to be canceled are still on the book.
+pub(in super) fn get_unified_order_status(exchange_order_status: &str) -> Result<OrderStatus> {
+    match exchange_order_status {
+        "PENDING" | "QUEUED" | "OPEN" | "CANCEL_QUEUED" => Ok(OrderStatus::Open),
+        "FILLED" => Ok(OrderStatus::Closed),
+        "CANCELLED" => Ok(OrderStatus::Canceled),
+        "EXPIRED" => Ok(OrderStatus::Expired),
+        "FAILED" => Ok(OrderStatus::Rejected),
+        "UNKNOWN_ORDER_STATUS" => Ok(OrderStatus::Unknown),
+        _ => Err(Error::UnsupportedOrderStatus(exchange_order_status.to_string())),
+    }
+}
+
+/// Granularity of candles and its length in seconds.
+pub(in super) fn get_exchange_granularity(timeframe: &Timeframe) -> Result<(&'static str, i64)> {
+    match timeframe {
+        Timeframe::Minute1 => Ok(("ONE_MINUTE", 60)),
+        Timeframe::Minute5 => Ok(("FIVE_MINUTE", 300)),
+        Timeframe::Minute15 => Ok(("FIFTEEN_MINUTE", 900)),
+        Timeframe::Minute30 => Ok(("THIRTY_MINUTE", 1800)),
+        Timeframe::Hour1 => Ok(("ONE_HOUR", 3600)),
+        Timeframe::Hour2 => Ok(("TWO_HOUR", 7200)),
+        Timeframe::Hour6 => Ok(("SIX_HOUR", 21600)),
+        Timeframe::Day1 => Ok(("ONE_DAY", 86400)),
+        _ => Err(Error::UnsupportedTimeframe(timeframe.to_string())),
+    }
+}
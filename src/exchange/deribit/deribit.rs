@@ -0,0 +1,981 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::OnceCell;
+
+use crate::client::{WsItem, WsRpcClient};
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{f64_to_number, into_precision, number_to_f64, ZERO};
+use crate::util::channel::{Receiver, Sender};
+use crate::util::order_book::LocalOrderBook;
+use crate::util::rate_limiter::RateLimit;
+
+use super::util;
+
+/// the request connection is authenticated again this long before the authentication expires,
+/// in milliseconds
+const AUTH_RENEWAL_MARGIN: i64 = 60_000;
+
+/// Every method goes through JSON-RPC over the websocket endpoint. Requests share a connection
+/// opened on first use, and every watch opens its own.
+pub struct Deribit {
+    exchange_base: ExchangeBase,
+    api_key: Option<String>,
+    secret: Option<String>,
+
+    rpc: OnceCell<WsRpcClient>,
+
+    /// connection number and expiry of the authentication of the request connection
+    session: Mutex<Option<(u64, i64)>>,
+}
+
+impl Deribit {
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        let (host, ws_endpoint) = match props.sandbox {
+            true => ("https://test.deribit.com", "wss://test.deribit.com/ws/api/v2"),
+            false => ("https://www.deribit.com", "wss://www.deribit.com/ws/api/v2"),
+        };
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some(host.to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some(ws_endpoint.to_string())))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            // non-matching engine requests are limited to 20 per second
+            .rate_limits(Some(vec![RateLimit { interval: Duration::from_secs(1), limit: 20, header: None }]))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            rpc: OnceCell::new(),
+            session: Mutex::new(None),
+        })
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.api_key.is_some() && self.secret.is_some()
+    }
+
+    /// Signs `timestamp + "\n" + nonce + "\n" + data` with an empty `data`.
+    fn auth(&self, timestamp: i64, nonce: &str) -> Result<AuthRequest> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials);
+        }
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(format!("{}\n{}\n", timestamp, nonce).as_bytes());
+        Ok(AuthRequest {
+            grant_type: "client_signature",
+            client_id: self.api_key.clone().unwrap(),
+            timestamp,
+            signature: hex::encode(signed_key.finalize().into_bytes()),
+            nonce: nonce.to_string(),
+            data: String::new(),
+        })
+    }
+
+    fn get_symbol_id(&self, market: &Market) -> Result<String> {
+        self.exchange_base.unifier.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))
+    }
+
+    fn ws_endpoint(&self) -> &str {
+        self.exchange_base.ws_endpoint.as_ref().unwrap().as_str()
+    }
+
+    /// The request connection. Its notifications are dropped.
+    async fn rpc(&self) -> Result<&WsRpcClient> {
+        self.rpc.get_or_try_init(|| async {
+            let parser = |message: &[u8], _: &Unifier| Ok(StreamItem::Unknown(String::from_utf8_lossy(message).to_string()));
+            let (rpc, _) = WsRpcClient::connect(self.ws_endpoint(), parser, parse_error, self.exchange_base.unifier.clone(), self.exchange_base.heartbeat.clone()).await?;
+            Ok(rpc)
+        }).await
+    }
+
+    /// The request connection, authenticated once per connection and again before the
+    /// authentication expires.
+    async fn private_rpc(&self) -> Result<&WsRpcClient> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials);
+        }
+        let rpc = self.rpc().await?;
+        let connection = rpc.connection();
+        let timestamp = Utc::now().timestamp_millis();
+        let is_authenticated = self.session.lock()?
+            .is_some_and(|(session, expiry)| session == connection && timestamp < expiry - AUTH_RENEWAL_MARGIN);
+        if !is_authenticated {
+            let mut nonce = [0u8; 8];
+            OsRng.fill_bytes(&mut nonce);
+            let request = self.auth(timestamp, &hex::encode(nonce))?;
+            let response: AuthResponse = rpc.request("public/auth", &request).await?;
+            *self.session.lock()? = Some((connection, timestamp + response.expires_in * 1000));
+        }
+        Ok(rpc)
+    }
+
+    async fn fetch_all_markets(&self) -> Result<Vec<(String, Market)>> {
+        let request = GetInstrumentsRequest { currency: "any", expired: false };
+        let result: Vec<InstrumentResponse> = self.rpc().await?.request("public/get_instruments", &request).await?;
+        let mut markets = vec![];
+        for instrument in result {
+            if let Some(market) = instrument.to_market()? {
+                markets.push((instrument.instrument_name, market));
+            }
+        }
+        Ok(markets)
+    }
+
+    /// Opens a connection subscribed to a channel of each market, named by `channel` after the
+    /// instrument.
+    async fn subscribe<T: WsItem + Send + 'static>(&self, markets: &[Market], channel: fn(&str) -> String, parser: fn(&[u8], &Unifier) -> WatchResult<T>) -> WatchResult<(WsRpcClient, flume::Receiver<WatchResult<T>>)> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut channels = vec![];
+        for market in markets {
+            match self.exchange_base.unifier.get_symbol_id(market) {
+                Some(instrument_name) => channels.push(channel(&instrument_name)),
+                None => return Err(WatchError::SymbolNotFound(format!("{:?}", market))),
+            }
+        }
+        let (rpc, notifications) = WsRpcClient::connect(self.ws_endpoint(), parser, parse_error, self.exchange_base.unifier.clone(), self.exchange_base.heartbeat.clone()).await?;
+        let _: Vec<String> = rpc.subscribe("public/subscribe", &SubscribeRequest { channels }).await?;
+        Ok((rpc, notifications))
+    }
+
+    async fn watch(&self, markets: &[Market], channel: fn(&str) -> String) -> WatchResult<Receiver> {
+        let (rpc, notifications) = self.subscribe(markets, channel, parse_stream).await?;
+
+        // a message may carry several items, which are forwarded one by one
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(async move {
+            // the connection is closed once the task ends
+            let _rpc = rpc;
+            while let Ok(items) = notifications.recv_async().await {
+                let items = match items {
+                    Ok(items) => items.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                for item in items {
+                    if tx.send(item).await.is_err() { // the receiver is dropped
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+#[async_trait]
+impl Exchange for Deribit {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        if self.exchange_base.markets.is_empty() {
+            let result = self.fetch_all_markets().await?;
+            self.exchange_base.unifier.reset();
+            let mut markets = vec![];
+            for (instrument_name, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &instrument_name);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    /// Loads the spot, futures and options markets. Combinations are left out.
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.fetch_all_markets().await?;
+        Ok(result.into_iter().map(|(_, market)| market).collect())
+    }
+
+    async fn watch_tickers(&self, params: WatchTickersParams) -> WatchTickersResult<Receiver> {
+        self.watch(&params.markets, |instrument_name| format!("ticker.{}.100ms", instrument_name)).await
+    }
+
+    /// Streams the best bid and ask, or full-depth books maintained from the changes of the
+    /// books when `full_depth` is set.
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        if !params.full_depth {
+            return self.watch(&params.markets, |instrument_name| format!("quote.{}", instrument_name)).await;
+        }
+        let (rpc, notifications) = self.subscribe(&params.markets, book_channel, parse_order_book).await?;
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(synchronize_order_books(rpc, notifications, tx));
+        Ok(receiver)
+    }
+
+    async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
+        self.watch(&params.markets, |instrument_name| format!("trades.{}.100ms", instrument_name)).await
+    }
+
+    /// Fetches the open positions of every currency.
+    async fn fetch_positions(&self, _params: FetchPositionsParams) -> FetchPositionsResult<Vec<Position>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let rpc = self.private_rpc().await?;
+        let result: Vec<PositionResponse> = rpc.request("private/get_positions", &GetPositionsRequest { currency: "any" }).await?;
+        let mut positions = vec![];
+        for item in result {
+            if let Some(market) = self.exchange_base.unifier.get_market(&item.instrument_name) {
+                if let Some(position) = item.to_position(market)? {
+                    positions.push(position);
+                }
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Amounts are in USD for inverse futures and in the base currency otherwise.
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let instrument_name = self.get_symbol_id(&params.market)?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.exchange_base.markets.iter().find(|m| **m == params.market).unwrap_or(&params.market);
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+
+        let is_market = order_type == OrderType::Market;
+        let time_in_force = params.time_in_force.unwrap_or(TimeInForce::GTC);
+        let request = CreateOrderRequest {
+            instrument_name,
+            amount: number_to_f64(amount),
+            order_type: util::get_exchange_order_type(&order_type)?,
+            price: price.filter(|_| !is_market).map(number_to_f64),
+            time_in_force: Some(util::get_exchange_time_in_force(&time_in_force)).filter(|_| !is_market),
+            post_only: Some(true).filter(|_| time_in_force == TimeInForce::PO && !is_market),
+            reduce_only: Some(true).filter(|_| params.reduce_only),
+        };
+        let rpc = self.private_rpc().await?;
+        let response: CreateOrderResponse = rpc.request(util::get_exchange_order_method(&params.order_side), &request).await?;
+        Ok(response.order.to_order(params.market.clone())?)
+    }
+
+    /// Orders can only be canceled by order id.
+    async fn cancel_order(&self, params: CancelOrderParams) -> CancelOrderResult<Order> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        let order_id = params.order_id.clone().ok_or_else(|| Error::InvalidParameters("order id is required".into()))?;
+
+        let rpc = self.private_rpc().await?;
+        let response: OrderResponse = rpc.request("private/cancel", &CancelOrderRequest { order_id }).await?;
+        let market = self.exchange_base.unifier.get_market(&response.instrument_name).unwrap_or_else(|| params.market.clone());
+        Ok(response.to_order(market)?)
+    }
+}
+
+
+/// Maps the `error` member of a failed response.
+fn parse_error(error: String) -> Error {
+    match serde_json::from_str::<ErrorResponse>(&error) {
+        Ok(error) => get_error(error.code, error.message),
+        Err(_) => Error::HttpError(error),
+    }
+}
+
+fn get_error(code: i64, message: String) -> Error {
+    match code {
+        10000 | 13004 | 13009 => Error::CredentialsError(message), // authorization_required, invalid_credentials, unauthorized
+        10002 => Error::InvalidAmount(message), // qty_too_low
+        10004 | 11044 => Error::OrderNotFound(message), // order_not_found, not_open_order
+        10005 | 10007 => Error::InvalidPrice(message), // price_too_low, price_too_high
+        10009 => Error::InsufficientMargin(message), // not_enough_funds
+        11029 | -32602 => Error::InvalidParameters(message), // invalid_arguments, Invalid params
+        _ => Error::HttpError(format!("code={}, message={}", code, message)),
+    }
+}
+
+fn book_channel(instrument_name: &str) -> String {
+    format!("book.{}.100ms", instrument_name)
+}
+
+fn to_order_book_units(levels: &[(f64, f64)]) -> Vec<OrderBookUnit> {
+    levels.iter()
+        .filter(|(price, amount)| *price > 0.0 && *amount > 0.0)
+        .map(|(price, amount)| OrderBookUnit { price: f64_to_number(*price), amount: f64_to_number(*amount) })
+        .collect()
+}
+
+
+fn parse_stream(message: &[u8], unifier: &Unifier) -> WatchResult<Vec<StreamItem>> {
+    let notification = Notification::try_from(message)?;
+    let channel = match &notification.params {
+        Some(params) if notification.method == "subscription" => params.channel.as_str(),
+        _ => return Ok(vec![]),
+    };
+    let data = notification.params.as_ref().unwrap().data.clone();
+    let deserialize_error = |e: serde_json::Error| Error::DeserializeJsonBody(format!("{:?}", e));
+    let get_market = |instrument_name: &String| unifier.get_market(instrument_name)
+        .ok_or_else(|| WatchError::SymbolNotFound(instrument_name.clone()));
+
+    let mut items = vec![];
+    match channel.split('.').next() {
+        Some("trades") => {
+            let trades: Vec<TradeResponse> = serde_json::from_value(data).map_err(deserialize_error)?;
+            for trade in trades {
+                let market = get_market(&trade.instrument_name)?;
+                items.push(StreamItem::Trade(trade.to_trade(market).map_err(TradeError::from)));
+            }
+        }
+        Some("ticker") => {
+            let ticker: TickerResponse = serde_json::from_value(data).map_err(deserialize_error)?;
+            let market = get_market(&ticker.instrument_name)?;
+            items.push(StreamItem::Ticker(Ok(ticker.to_ticker(market))));
+        }
+        Some("quote") => {
+            let quote: QuoteResponse = serde_json::from_value(data).map_err(deserialize_error)?;
+            let market = get_market(&quote.instrument_name)?;
+            items.push(StreamItem::OrderBook(Ok(quote.to_order_book(market))));
+        }
+        _ => items.push(StreamItem::Unknown(String::from_utf8_lossy(message).to_string())),
+    }
+    Ok(items)
+}
+
+
+enum OrderBookItem {
+    Update(Box<(Market, BookResponse)>),
+    Reconnected,
+    Other,
+}
+
+impl WsItem for OrderBookItem {
+    fn reconnected() -> Self {
+        OrderBookItem::Reconnected
+    }
+}
+
+fn parse_order_book(message: &[u8], unifier: &Unifier) -> WatchResult<OrderBookItem> {
+    let notification = Notification::try_from(message)?;
+    let data = match notification.params {
+        Some(params) if notification.method == "subscription" && params.channel.starts_with("book.") => params.data,
+        _ => return Ok(OrderBookItem::Other),
+    };
+    let book: BookResponse = serde_json::from_value(data).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))?;
+    match unifier.get_market(&book.instrument_name) {
+        Some(market) => Ok(OrderBookItem::Update(Box::new((market, book)))),
+        None => Err(WatchError::SymbolNotFound(book.instrument_name)),
+    }
+}
+
+/// Keeps a local book per market up to date from the snapshots and changes of the books and
+/// forwards the complete books to the receiver until it is dropped.
+async fn synchronize_order_books(rpc: WsRpcClient, notifications: flume::Receiver<WatchResult<OrderBookItem>>, tx: Sender) {
+    let mut books: HashMap<Market, LocalOrderBook> = HashMap::new();
+    while let Ok(item) = notifications.recv_async().await {
+        let mut items = vec![];
+        match item {
+            Ok(OrderBookItem::Update(update)) => {
+                let (market, update) = *update;
+                let book = books.entry(market.clone()).or_insert_with(|| LocalOrderBook::new(market.clone()));
+                match apply_update(book, &update) {
+                    Ok(true) => items.push(Ok(StreamItem::OrderBook(Ok(book.to_order_book())))),
+                    Ok(false) => {}
+                    Err(e) => {
+                        book.invalidate();
+                        items.push(Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market))))));
+                        // a snapshot follows a new subscription. the connection is re-established
+                        // when the requests fail, which also brings snapshots
+                        let request = SubscribeRequest { channels: vec![book_channel(&update.instrument_name)] };
+                        let _: Result<Vec<String>> = rpc.request("public/unsubscribe", &request).await;
+                        let _: Result<Vec<String>> = rpc.request("public/subscribe", &request).await;
+                    }
+                }
+            }
+            Ok(OrderBookItem::Reconnected) => {
+                books.values_mut().for_each(|book| book.invalidate());
+                items.push(Ok(StreamItem::Reconnected));
+            }
+            Ok(OrderBookItem::Other) => {}
+            Err(e) => items.push(Err(e)),
+        }
+        for item in items {
+            if tx.send(item).await.is_err() { // the receiver is dropped
+                return;
+            }
+        }
+    }
+}
+
+/// Applies a snapshot or a change. Returns `Ok(false)` for changes received before the first
+/// snapshot, and an error when a change is missed.
+fn apply_update(book: &mut LocalOrderBook, update: &BookResponse) -> Result<bool> {
+    let to_units = |levels: &[(String, f64, f64)]| levels.iter()
+        .map(|(action, price, amount)| OrderBookUnit {
+            price: f64_to_number(*price),
+            amount: match action.as_str() {
+                "delete" => ZERO,
+                _ => f64_to_number(*amount),
+            },
+        })
+        .collect::<Vec<_>>();
+    let bids = to_units(&update.bids);
+    let asks = to_units(&update.asks);
+
+    if update.update_type == "snapshot" {
+        book.reset(&bids, &asks, update.change_id);
+        book.update(&[], &[], update.change_id, Some(update.timestamp));
+        return Ok(true);
+    }
+    match book.last_update_id() {
+        None => Ok(false),
+        Some(last_update_id) if update.prev_change_id == Some(last_update_id) => {
+            book.update(&bids, &asks, update.change_id, Some(update.timestamp));
+            Ok(true)
+        }
+        Some(last_update_id) => Err(Error::InvalidOrderBook(format!("missed changes after {}", last_update_id))),
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct ErrorResponse {
+    code: i64,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct AuthRequest {
+    grant_type: &'static str,
+    client_id: String,
+    timestamp: i64,
+    signature: String,
+    nonce: String,
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AuthResponse {
+    /// in seconds
+    expires_in: i64,
+}
+
+
+#[derive(Serialize)]
+struct GetInstrumentsRequest {
+    currency: &'static str,
+    expired: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct InstrumentResponse {
+    instrument_name: String,
+    /// `spot`, `future`, `option`, `future_combo` or `option_combo`
+    kind: String,
+    base_currency: String,
+    quote_currency: String,
+    settlement_currency: Option<String>,
+    /// `linear` or `reversed`
+    instrument_type: Option<String>,
+    /// `perpetual` for perpetual swaps
+    settlement_period: String,
+    tick_size: f64,
+    min_trade_amount: f64,
+    contract_size: f64,
+    max_leverage: Option<f64>,
+    expiration_timestamp: i64,
+    strike: Option<f64>,
+    option_type: Option<String>,
+    is_active: bool,
+}
+
+impl InstrumentResponse {
+    /// Returns `None` for combinations.
+    fn to_market(&self) -> Result<Option<Market>> {
+        let market_type = match util::get_unified_market_type(&self.kind, &self.settlement_period) {
+            Some(market_type) => market_type,
+            None => return Ok(None),
+        };
+        let is_derivative = market_type != MarketType::Spot;
+        let contract_type = match &self.instrument_type {
+            Some(instrument_type) if is_derivative => Some(util::get_unified_contract_type(instrument_type)?),
+            _ => None,
+        };
+        let option_type = match &self.option_type {
+            Some(option_type) if market_type == MarketType::Option => Some(util::get_unified_option_type(option_type)?),
+            _ => None,
+        };
+
+        Ok(Some(Market {
+            base: util::to_unified_asset(&self.base_currency),
+            quote: util::to_unified_asset(&self.quote_currency),
+            active: self.is_active,
+            market_type,
+            settle: self.settlement_currency.as_deref().map(util::to_unified_asset).filter(|_| is_derivative),
+            contract_size: Some(self.contract_size).filter(|_| is_derivative),
+            contract_type,
+            expiry: Some(self.expiration_timestamp).filter(|_| matches!(market_type, MarketType::Future | MarketType::Option)),
+            strike: self.strike.filter(|_| market_type == MarketType::Option),
+            option_type,
+            precision: Some(Precision {
+                amount: Some(into_precision(self.min_trade_amount.to_string())?),
+                price: Some(into_precision(self.tick_size.to_string())?),
+                cost: None,
+            }),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: self.min_trade_amount, max: f64::MAX }),
+                price: None,
+                cost: None,
+                leverage: self.max_leverage.map(|max| Range { min: 1.0, max }),
+            }),
+            ..Default::default()
+        }))
+    }
+}
+
+
+#[derive(Serialize)]
+struct SubscribeRequest {
+    channels: Vec<String>,
+}
+
+/// Message that is not a response, such as the data of a subscription.
+#[derive(Serialize, Deserialize)]
+struct Notification {
+    #[serde(default)]
+    method: String,
+    params: Option<NotificationParams>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NotificationParams {
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+impl TryFrom<&[u8]> for Notification {
+    type Error = Error;
+
+    fn try_from(message: &[u8]) -> Result<Self> {
+        serde_json::from_slice(message).map_err(|e| {
+            let message = String::from_utf8_lossy(message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TradeResponse {
+    trade_id: String,
+    instrument_name: String,
+    timestamp: i64,
+    price: f64,
+    amount: f64,
+    /// taker side
+    direction: String,
+}
+
+impl TradeResponse {
+    fn to_trade(&self, market: Market) -> Result<Trade> {
+        let price = f64_to_number(self.price);
+        let amount = f64_to_number(self.amount);
+        Ok(Trade::new(
+            self.trade_id.clone(),
+            self.timestamp,
+            market,
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.direction)?),
+            None,
+            price, amount,
+            price * amount,
+            None, None,
+        ))
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TickerResponse {
+    instrument_name: String,
+    timestamp: i64,
+    last_price: Option<f64>,
+    best_bid_price: Option<f64>,
+    best_bid_amount: Option<f64>,
+    best_ask_price: Option<f64>,
+    best_ask_amount: Option<f64>,
+    stats: TickerStatsResponse,
+}
+
+/// Statistics of the last 24 hours.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TickerStatsResponse {
+    high: Option<f64>,
+    low: Option<f64>,
+    /// in the base currency
+    volume: Option<f64>,
+    volume_usd: Option<f64>,
+    /// in percent
+    price_change: Option<f64>,
+}
+
+impl TickerResponse {
+    fn to_ticker(&self, market: Market) -> Ticker {
+        let last = self.last_price.unwrap_or_default();
+        let percentage = self.stats.price_change.unwrap_or_default();
+        let open = last / (1.0 + percentage / 100.0);
+        let base_volume = self.stats.volume.unwrap_or_default();
+        let quote_volume = self.stats.volume_usd.unwrap_or(base_volume * last);
+        Ticker {
+            ask: self.best_ask_price.filter(|ask| *ask > 0.0),
+            ask_volume: self.best_ask_amount.unwrap_or_default(),
+            bid: self.best_bid_price.filter(|bid| *bid > 0.0),
+            bid_volume: self.best_bid_amount.unwrap_or_default(),
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume,
+            change: last - open,
+            close: last,
+            high: self.stats.high.unwrap_or_default(),
+            low: self.stats.low.unwrap_or_default(),
+            last,
+            open,
+            percentage,
+            previous_close: None,
+            vwap: match base_volume > 0.0 {
+                true => quote_volume / base_volume,
+                false => 0.0,
+            },
+            market,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+/// Best bid and ask.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct QuoteResponse {
+    instrument_name: String,
+    timestamp: i64,
+    best_bid_price: Option<f64>,
+    best_bid_amount: Option<f64>,
+    best_ask_price: Option<f64>,
+    best_ask_amount: Option<f64>,
+}
+
+impl QuoteResponse {
+    fn to_order_book(&self, market: Market) -> OrderBook {
+        let level = |price: Option<f64>, amount: Option<f64>| [(price.unwrap_or_default(), amount.unwrap_or_default())];
+        OrderBook::new(
+            to_order_book_units(&level(self.best_bid_price, self.best_bid_amount)),
+            to_order_book_units(&level(self.best_ask_price, self.best_ask_amount)),
+            market,
+            Some(self.timestamp),
+            None,
+        )
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BookResponse {
+    /// `snapshot` or `change`
+    #[serde(rename = "type")]
+    update_type: String,
+    instrument_name: String,
+    timestamp: i64,
+    change_id: i64,
+    /// not set for snapshots
+    prev_change_id: Option<i64>,
+    /// `new`, `change` or `delete`, price and amount
+    bids: Vec<(String, f64, f64)>,
+    asks: Vec<(String, f64, f64)>,
+}
+
+
+#[derive(Serialize)]
+struct GetPositionsRequest {
+    currency: &'static str,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct PositionResponse {
+    instrument_name: String,
+    /// `buy`, `sell` or `zero`
+    direction: String,
+    /// signed, in USD for inverse futures and in the base currency otherwise
+    size: f64,
+    /// signed, in the base currency
+    size_currency: Option<f64>,
+    average_price: f64,
+    mark_price: f64,
+    leverage: Option<f64>,
+    initial_margin: f64,
+    maintenance_margin: f64,
+    floating_profit_loss: f64,
+    estimated_liquidation_price: Option<f64>,
+}
+
+impl PositionResponse {
+    /// Returns `None` for empty positions.
+    fn to_position(&self, market: Market) -> Result<Option<Position>> {
+        if self.size == 0.0 || self.direction == "zero" {
+            return Ok(None);
+        }
+        let side = match self.direction.as_str() {
+            "buy" => PositionSide::Long,
+            "sell" => PositionSide::Short,
+            direction => return Err(Error::InvalidResponse(format!("unknown direction {}", direction))),
+        };
+        let contract_size = market.contract_size.unwrap_or(1.0);
+        let notional = match market.contract_type {
+            Some(ContractType::Inverse) => self.size_currency.unwrap_or_default().abs(),
+            _ => self.size.abs() * self.mark_price,
+        };
+        let ratio = |value: f64, total: f64| if total > 0.0 { value / total } else { 0.0 };
+        Ok(Some(Position {
+            contract_size: market.contract_size,
+            market,
+            timestamp: Utc::now().timestamp_millis(),
+            is_hedged: false,
+            side,
+            contracts: self.size.abs() / contract_size,
+            entry_price: self.average_price,
+            mark_price: self.mark_price,
+            notional,
+            leverage: self.leverage.unwrap_or_default(),
+            collateral: self.initial_margin,
+            initial_margin: self.initial_margin,
+            maintenance_margin: self.maintenance_margin,
+            initial_margin_percent: ratio(self.initial_margin, notional),
+            maintenance_margin_percent: ratio(self.maintenance_margin, notional),
+            unrealized_pnl: self.floating_profit_loss,
+            liquidation_price: self.estimated_liquidation_price.unwrap_or_default(),
+            margin_mode: MarginMode::Cross,
+            margin_ratio: ratio(self.maintenance_margin, self.initial_margin),
+            percentage: ratio(self.floating_profit_loss, self.initial_margin) * 100f64,
+            ..Default::default()
+        }))
+    }
+}
+
+
+#[derive(Serialize)]
+struct CreateOrderRequest {
+    instrument_name: String,
+    amount: f64,
+    #[serde(rename = "type")]
+    order_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_in_force: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reduce_only: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CreateOrderResponse {
+    order: OrderResponse,
+}
+
+#[derive(Serialize)]
+struct CancelOrderRequest {
+    order_id: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct OrderResponse {
+    order_id: String,
+    instrument_name: String,
+    /// `open`, `filled`, `rejected`, `cancelled` or `untriggered`
+    order_state: String,
+    direction: String,
+    order_type: String,
+    time_in_force: String,
+    #[serde(default)]
+    post_only: bool,
+    /// `market_price` for market orders
+    price: serde_json::Value,
+    amount: f64,
+    filled_amount: f64,
+    average_price: Option<f64>,
+    creation_timestamp: i64,
+    last_update_timestamp: i64,
+    /// user defined label, used as the client order id
+    label: Option<String>,
+}
+
+impl OrderResponse {
+    fn to_order(&self, market: Market) -> Result<Order> {
+        let status = util::get_unified_order_status(&self.order_state)?;
+        let amount = f64_to_number(self.amount);
+        let filled = f64_to_number(self.filled_amount);
+        let time_in_force = match self.post_only {
+            true => TimeInForce::PO,
+            false => util::get_unified_time_in_force(&self.time_in_force)?,
+        };
+        Ok(Order {
+            id: Some(self.order_id.clone()),
+            client_order_id: self.label.clone().filter(|label| !label.is_empty()),
+            timestamp: self.creation_timestamp,
+            last_trade_timestamp: Some(self.last_update_timestamp).filter(|_| self.filled_amount > 0.0),
+            status,
+            market,
+            order_type: util::get_unified_order_type(&self.order_type)?,
+            time_in_force: Some(time_in_force),
+            side: Some(util::get_unified_order_side(&self.direction)?),
+            price: self.price.as_f64().map(f64_to_number),
+            average: self.average_price.filter(|average| *average > 0.0).map(f64_to_number),
+            amount,
+            filled: Some(filled),
+            remaining: Some(amount - filled).filter(|_| status == OrderStatus::Open),
+            ..Default::default()
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Deribit, PropertiesBuilder};
+    use crate::error::Error;
+    use crate::exchange::{StreamItem, Unifier};
+    use crate::model::{ContractType, Market, MarketType, OptionType, OrderSide, OrderStatus, OrderType, TimeInForce};
+    use crate::util::number;
+    use crate::util::order_book::LocalOrderBook;
+
+    use super::{apply_update, BookResponse, CreateOrderResponse, InstrumentResponse, parse_error, parse_stream};
+
+    #[test]
+    fn test_auth() {
+        let props = PropertiesBuilder::default()
+            .api_key(Some("client".into()))
+            .secret(Some("secret".into()))
+            .build()
+            .unwrap();
+        let deribit = Deribit::new(props).unwrap();
+        let request = deribit.auth(1700000000000, "abcd").unwrap();
+        assert_eq!(request.client_id, "client");
+        assert_eq!(request.signature, "cd3d93a66ae53f454e10a72ea5160668033756df86970fc1f6ebb2878a28a0ea");
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"tick_size": 0.5, "tick_size_steps": [], "taker_commission": 0.0005, "settlement_period": "perpetual", "settlement_currency": "BTC", "rfq": false, "quote_currency": "USD", "price_index": "btc_usd", "min_trade_amount": 10, "max_liquidation_commission": 0.0075, "max_leverage": 50, "maker_commission": 0, "kind": "future", "is_active": true, "instrument_name": "BTC-PERPETUAL", "instrument_id": 124972, "instrument_type": "reversed", "expiration_timestamp": 32503708800000, "creation_timestamp": 1534242287000, "counter_currency": "USD", "contract_size": 10, "block_trade_tick_size": 0.01, "block_trade_min_trade_amount": 200000, "block_trade_commission": 0.00025, "base_currency": "BTC"}"#;
+        let resp: InstrumentResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market().unwrap().unwrap();
+        assert_eq!(market.market_type, MarketType::Swap);
+        assert_eq!(market.contract_type, Some(ContractType::Inverse));
+        assert_eq!(market.contract_size, Some(10.0));
+        assert_eq!(market.expiry, None);
+        assert_eq!(market.precision.as_ref().unwrap().amount, Some(-1));
+        assert_eq!(format!("{}", market), "BTC/USD:BTC");
+
+        let message = r#"{"tick_size": 0.0005, "taker_commission": 0.0003, "strike": 50000.0, "settlement_period": "month", "settlement_currency": "BTC", "quote_currency": "BTC", "option_type": "call", "min_trade_amount": 0.1, "kind": "option", "is_active": true, "instrument_name": "BTC-27DEC24-50000-C", "instrument_type": "reversed", "expiration_timestamp": 1735286400000, "contract_size": 1.0, "counter_currency": "USD", "base_currency": "BTC"}"#;
+        let resp: InstrumentResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market().unwrap().unwrap();
+        assert_eq!(market.market_type, MarketType::Option);
+        assert_eq!(market.option_type, Some(OptionType::Call));
+        assert_eq!(market.strike, Some(50000.0));
+        assert_eq!(market.expiry, Some(1735286400000));
+        assert_eq!(market.precision.as_ref().unwrap().price, Some(4));
+
+        let message = r#"{"tick_size": 0.5, "settlement_period": "week", "kind": "future_combo", "is_active": true, "instrument_name": "BTC-FS-29DEC23_PERP", "instrument_type": "reversed", "expiration_timestamp": 1703836800000, "contract_size": 10, "quote_currency": "USD", "base_currency": "BTC", "min_trade_amount": 10}"#;
+        let resp: InstrumentResponse = serde_json::from_str(message).unwrap();
+        assert!(resp.to_market().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_stream() {
+        let market = Market { base: "BTC".into(), quote: "USD".into(), market_type: MarketType::Swap, ..Default::default() };
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"BTC-PERPETUAL".to_string());
+
+        let message = r#"{"jsonrpc": "2.0", "method": "subscription", "params": {"channel": "trades.BTC-PERPETUAL.100ms", "data": [{"trade_seq": 30289432, "trade_id": "48079254", "timestamp": 1590484156350, "tick_direction": 0, "price": 8950.0, "mark_price": 8948.9, "instrument_name": "BTC-PERPETUAL", "index_price": 8955.88, "direction": "sell", "amount": 10.0}, {"trade_seq": 30289433, "trade_id": "48079255", "timestamp": 1590484156350, "tick_direction": 1, "price": 8950.0, "mark_price": 8948.9, "instrument_name": "BTC-PERPETUAL", "index_price": 8955.88, "direction": "sell", "amount": 20.0}]}}"#;
+        let items = parse_stream(message.as_bytes(), &unifier).unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(matches!(&items[0], StreamItem::Trade(Ok(trade)) if trade.id == "48079254" && trade.side == Some(OrderSide::Sell)));
+
+        let message = r#"{"jsonrpc": "2.0", "method": "subscription", "params": {"channel": "ticker.BTC-PERPETUAL.100ms", "data": {"timestamp": 1623060194301, "stats": {"volume_usd": 284061480, "volume": 7871.02139035, "price_change": 0.7229, "low": 35213.5, "high": 36824.5}, "state": "open", "settlement_price": 36169.49, "open_interest": 502097590, "min_price": 35898.37, "max_price": 36991.72, "mark_price": 36446.51, "last_price": 36457.5, "instrument_name": "BTC-PERPETUAL", "index_price": 36441.64, "funding_8h": 0.0000211, "estimated_delivery_price": 36441.64, "current_funding": 0, "best_bid_price": 36442.5, "best_bid_amount": 5000, "best_ask_price": 36443, "best_ask_amount": 100}}}"#;
+        let items = parse_stream(message.as_bytes(), &unifier).unwrap();
+        assert!(matches!(&items[0], StreamItem::Ticker(Ok(ticker)) if ticker.bid == Some(36442.5) && ticker.base_volume == 7871.02139035));
+
+        let message = r#"{"jsonrpc": "2.0", "method": "subscription", "params": {"channel": "quote.BTC-PERPETUAL", "data": {"timestamp": 1550658624149, "instrument_name": "BTC-PERPETUAL", "best_bid_price": 3914.97, "best_bid_amount": 40, "best_ask_price": 3996.61, "best_ask_amount": 50}}}"#;
+        let items = parse_stream(message.as_bytes(), &unifier).unwrap();
+        assert!(matches!(&items[0], StreamItem::OrderBook(Ok(book)) if book.bids == vec![(number("3914.97"), number("40")).into()]));
+
+        let message = r#"{"jsonrpc": "2.0", "method": "heartbeat", "params": {"type": "heartbeat"}}"#;
+        assert!(parse_stream(message.as_bytes(), &unifier).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_order_book_update() {
+        let mut book = LocalOrderBook::new(Market::default());
+        let parse = |message: &str| serde_json::from_str::<BookResponse>(message).unwrap();
+
+        let change = parse(r#"{"type": "change", "timestamp": 1554373911330, "prev_change_id": 297217, "instrument_name": "BTC-PERPETUAL", "change_id": 297218, "bids": [["delete", 5042.34, 0]], "asks": [["new", 5043.1, 30]]}"#);
+        assert!(!apply_update(&mut book, &change).unwrap());
+
+        let snapshot = parse(r#"{"type": "snapshot", "timestamp": 1554373962454, "instrument_name": "BTC-PERPETUAL", "change_id": 297217, "bids": [["new", 5042.34, 30], ["new", 5041.94, 20]], "asks": [["new", 5042.64, 40], ["new", 5043.3, 40]]}"#);
+        assert!(apply_update(&mut book, &snapshot).unwrap());
+        assert!(apply_update(&mut book, &change).unwrap());
+        let order_book = book.to_order_book();
+        assert_eq!(order_book.bids, vec![(number("5041.94"), number("20")).into()]);
+        assert_eq!(order_book.asks.len(), 3);
+        assert_eq!(order_book.last_update_id, Some(297218));
+        assert_eq!(order_book.timestamp, Some(1554373911330));
+
+        let missed = parse(r#"{"type": "change", "timestamp": 1554373911400, "prev_change_id": 297220, "instrument_name": "BTC-PERPETUAL", "change_id": 297221, "bids": [], "asks": []}"#);
+        assert!(matches!(apply_update(&mut book, &missed), Err(Error::InvalidOrderBook(_))));
+    }
+
+    #[test]
+    fn test_parse_order() {
+        let message = r#"{"trades": [], "order": {"web": false, "time_in_force": "good_til_cancelled", "replaced": false, "reduce_only": false, "price": 50000.0, "post_only": true, "order_type": "limit", "order_state": "open", "order_id": "ETH-349249", "max_show": 40.0, "last_update_timestamp": 1550657341322, "label": "market0000234", "is_liquidation": false, "instrument_name": "BTC-PERPETUAL", "filled_amount": 10.0, "direction": "buy", "creation_timestamp": 1550657341322, "average_price": 50000.0, "api": true, "amount": 40.0}}"#;
+        let resp: CreateOrderResponse = serde_json::from_str(message).unwrap();
+        let order = resp.order.to_order(Market::default()).unwrap();
+        assert_eq!(order.id.as_deref(), Some("ETH-349249"));
+        assert_eq!(order.client_order_id.as_deref(), Some("market0000234"));
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.time_in_force, Some(TimeInForce::PO));
+        assert_eq!(order.remaining, Some(number("30")));
+
+        let message = r#"{"trades": [], "order": {"time_in_force": "good_til_cancelled", "price": "market_price", "post_only": false, "order_type": "market", "order_state": "filled", "order_id": "4008965646", "last_update_timestamp": 1590486335742, "instrument_name": "ETH-PERPETUAL", "filled_amount": 40.0, "direction": "sell", "creation_timestamp": 1590486335742, "average_price": 203.3, "amount": 40.0}}"#;
+        let resp: CreateOrderResponse = serde_json::from_str(message).unwrap();
+        let order = resp.order.to_order(Market::default()).unwrap();
+        assert_eq!(order.price, None);
+        assert_eq!(order.status, OrderStatus::Closed);
+
+        assert!(matches!(parse_error(r#"{"code": 10009, "message": "not_enough_funds"}"#.into()), Error::InsufficientMargin(_)));
+        assert!(matches!(parse_error(r#"{"code": 13009, "message": "unauthorized"}"#.into()), Error::CredentialsError(_)));
+    }
+}
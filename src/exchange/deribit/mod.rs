@@ -0,0 +1,5 @@
+pub use deribit::Deribit;
+
+#[allow(clippy::module_inception)]
+mod deribit;
+mod util;
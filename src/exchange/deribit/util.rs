@@ -0,0 +1,99 @@
+use crate::error::{Error, Result};
+use crate::model::{ContractType, MarketType, OptionType, OrderSide, OrderStatus, OrderType, TimeInForce};
+
+pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
+    exchange_asset.to_uppercase()
+}
+
+/// Combinations of instruments have no unified market.
+pub(in super) fn get_unified_market_type(kind: &str, settlement_period: &str) -> Option<MarketType> {
+    match (kind, settlement_period) {
+        ("spot", _) => Some(MarketType::Spot),
+        ("future", "perpetual") => Some(MarketType::Swap),
+        ("future", _) => Some(MarketType::Future),
+        ("option", _) => Some(MarketType::Option),
+        _ => None,
+    }
+}
+
+/// Contracts of the exchange are either `linear` or `reversed`.
+pub(in super) fn get_unified_contract_type(instrument_type: &str) -> Result<ContractType> {
+    match instrument_type {
+        "linear" => Ok(ContractType::Linear),
+        "reversed" => Ok(ContractType::Inverse),
+        _ => Err(Error::InvalidResponse(format!("unknown instrument type {}", instrument_type))),
+    }
+}
+
+pub(in super) fn get_unified_option_type(option_type: &str) -> Result<OptionType> {
+    match option_type {
+        "call" => Ok(OptionType::Call),
+        "put" => Ok(OptionType::Put),
+        _ => Err(Error::InvalidResponse(format!("unknown option type {}", option_type))),
+    }
+}
+
+/// Name of the method placing orders on the side.
+pub(in super) fn get_exchange_order_method(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "private/buy",
+        OrderSide::Sell => "private/sell",
+    }
+}
+
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "buy" => Ok(OrderSide::Buy),
+        "sell" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+pub(in super) fn get_exchange_order_type(order_type: &OrderType) -> Result<&'static str> {
+    match order_type {
+        OrderType::Limit => Ok("limit"),
+        OrderType::Market => Ok("market"),
+        _ => Err(Error::UnsupportedOrderType(order_type.to_string())),
+    }
+}
+
+/// Market limit orders are market orders whose unfilled amount rests at the price of the fills.
+pub(in super) fn get_unified_order_type(exchange_order_type: &str) -> Result<OrderType> {
+    match exchange_order_type {
+        "limit" => Ok(OrderType::Limit),
+        "market" | "market_limit" => Ok(OrderType::Market),
+        "stop_limit" | "stop_market" => Ok(OrderType::StopLoss),
+        "take_limit" | "take_market" => Ok(OrderType::TakeProfit),
+        "trailing_stop" => Ok(OrderType::TrailingStopMarket),
+        _ => Err(Error::UnsupportedOrderType(exchange_order_type.to_string())),
+    }
+}
+
+/// Post-only orders are good till canceled and flagged with `post_only`.
+pub(in super) fn get_exchange_time_in_force(time_in_force: &TimeInForce) -> &'static str {
+    match time_in_force {
+        TimeInForce::GTC | TimeInForce::PO => "good_til_cancelled",
+        TimeInForce::IOC => "immediate_or_cancel",
+        TimeInForce::FOK => "fill_or_kill",
+    }
+}
+
+pub(in super) fn get_unified_time_in_force(exchange_time_in_force: &str) -> Result<TimeInForce> {
+    match exchange_time_in_force {
+        "good_til_cancelled" | "good_til_day" => Ok(TimeInForce::GTC),
+        "immediate_or_cancel" => Ok(TimeInForce::IOC),
+        "fill_or_kill" => Ok(TimeInForce::FOK),
+        _ => Err(Error::UnsupportedTimeInForce(exchange_time_in_force.to_string())),
+    }
+}
+
+/// Trigger orders wait untriggered on the book.
+pub(in super) fn get_unified_order_status(exchange_order_status: &str) -> Result<OrderStatus> {
+    match exchange_order_status {
+        "open" | "untriggered" => Ok(OrderStatus::Open),
+        "filled" => Ok(OrderStatus::Closed),
+        "cancelled" => Ok(OrderStatus::Canceled),
+        "rejected" => Ok(OrderStatus::Rejected),
+        _ => Err(Error::UnsupportedOrderStatus(exchange_order_status.to_string())),
+    }
+}
@@ -0,0 +1,1073 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha512};
+
+use crate::client::EMPTY_QUERY;
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{f64_to_number, into_precision, number_to_f64, parse_float64, parse_number};
+use crate::util::channel::Receiver;
+use crate::util::rate_limiter::RateLimit;
+
+use super::util;
+use super::util::Product;
+
+/// prefix of the REST endpoints, which is part of the signed path
+const API_PREFIX: &str = "/api/v4";
+
+/// stream of the USDT perpetual contracts, the spot stream being the `ws_endpoint`
+const FUTURES_WS_ENDPOINT: &str = "wss://fx-ws.gateio.ws/v4/ws/usdt";
+
+/// maximum number of markets in a subscription message
+const SUBSCRIPTION_CHUNK_SIZE: usize = 100;
+
+pub struct Gateio {
+    exchange_base: ExchangeBase,
+    api_key: Option<String>,
+    secret: Option<String>,
+
+    /// markets by the symbol id of each product
+    unifiers: HashMap<Product, Unifier>,
+}
+
+impl Gateio {
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        if props.sandbox {
+            return Err(Error::UnsupportedInSandbox("spot has no testnet".into()).into());
+        }
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some("https://api.gateio.ws".to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some("wss://api.gateio.ws/ws/v4/".to_string())))
+            .error_parser(Some(|message| {
+                match serde_json::from_str::<ErrorResponse>(&message) {
+                    Ok(response) => get_error(&response.label, response.message),
+                    Err(_) => Error::HttpError(message),
+                }
+            }))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            // public endpoints allow 200 requests per 10 seconds
+            .rate_limits(Some(vec![RateLimit { interval: Duration::from_secs(10), limit: 200, header: None }]))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            unifiers: HashMap::new(),
+        })
+    }
+
+    /// Timestamp for signed requests, on the exchange clock when time sync is enabled.
+    async fn timestamp(&self) -> Result<i64> {
+        let clock = &self.exchange_base.clock;
+        if clock.needs_sync() {
+            let sent = Utc::now().timestamp_millis();
+            let result: ServerTimeResponse = self.get("/spot/time", &vec![]).await?;
+            let received = Utc::now().timestamp_millis();
+            clock.update(sent, result.server_time, received);
+        }
+        Ok(clock.timestamp())
+    }
+
+    /// Signs `method\npath\nquery\nhex(sha512(body))\ntimestamp` with HMAC-SHA512 and encodes the
+    /// signature in hex. The timestamp is in seconds and the path includes the API prefix.
+    fn auth(&self, method: &str, path: &str, query: &str, body: &str, timestamp: &str) -> Result<String> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        let body_hash = hex::encode(Sha512::digest(body.as_bytes()));
+        let payload = format!("{}\n{}\n{}\n{}\n{}", method, path, query, body_hash, timestamp);
+        let mut signed_key = Hmac::<Sha512>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(payload.as_bytes());
+        Ok(hex::encode(signed_key.finalize().into_bytes()))
+    }
+
+    fn unifier(&self, product: Product) -> Result<&Unifier> {
+        self.unifiers.get(&product).ok_or(Error::MarketNotInitialized)
+    }
+
+    /// Product and symbol id of a loaded market.
+    fn get_symbol_id(&self, market: &Market) -> Result<(Product, String)> {
+        let product = Product::of(market)?;
+        let symbol_id = self.unifier(product)?.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))?;
+        Ok((product, symbol_id))
+    }
+
+    /// Responses are not enveloped, errors being reported with the HTTP status.
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let path = format!("{}{}", API_PREFIX, path);
+        self.exchange_base.http_client.get(&path, None, Some(query)).await
+    }
+
+    async fn signed_get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let path = format!("{}{}", API_PREFIX, path);
+        let timestamp = (self.timestamp().await? / 1000).to_string();
+        let payload = query.iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<String>>()
+            .join("&");
+        let signature = self.auth("GET", &path, &payload, "", &timestamp)?;
+        let headers = vec![
+            ("KEY", self.api_key.as_ref().unwrap().as_str()),
+            ("Timestamp", timestamp.as_str()),
+            ("SIGN", signature.as_str()),
+        ];
+        self.exchange_base.http_client.get(&path, Some(headers), Some(query)).await
+    }
+
+    async fn signed_post<T: DeserializeOwned>(&self, path: &str, body: &String) -> Result<T> {
+        let path = format!("{}{}", API_PREFIX, path);
+        let timestamp = (self.timestamp().await? / 1000).to_string();
+        let signature = self.auth("POST", &path, "", body, &timestamp)?;
+        let headers = vec![
+            ("Content-Type", "application/json"),
+            ("KEY", self.api_key.as_ref().unwrap().as_str()),
+            ("Timestamp", timestamp.as_str()),
+            ("SIGN", signature.as_str()),
+        ];
+        self.exchange_base.http_client.post(&path, Some(headers), EMPTY_QUERY, Some(body)).await
+    }
+
+    async fn fetch_product_markets(&self, product: Product) -> Result<Vec<(String, Market)>> {
+        let mut markets = vec![];
+        match product {
+            Product::Spot => {
+                let result: Vec<FetchSpotMarketsResponse> = self.get("/spot/currency_pairs", &vec![]).await?;
+                for pair in result {
+                    markets.push((pair.id.clone(), pair.to_market()?));
+                }
+            }
+            Product::Futures => {
+                let result: Vec<FetchFuturesMarketsResponse> = self.get("/futures/usdt/contracts", &vec![]).await?;
+                for contract in result {
+                    markets.push((contract.name.clone(), contract.to_market()?));
+                }
+            }
+        }
+        Ok(markets)
+    }
+
+    async fn fetch_all_markets(&self) -> Result<Vec<(Product, String, Market)>> {
+        let mut markets = vec![];
+        for product in Product::ALL {
+            for (symbol_id, market) in self.fetch_product_markets(product).await? {
+                markets.push((product, symbol_id, market));
+            }
+        }
+        Ok(markets)
+    }
+
+    /// Subscribes to a public channel of the markets, named after the product, e.g.
+    /// `spot.trades` and `futures.trades`. The items of both streams are merged into a single
+    /// receiver.
+    async fn subscribe(&self, channel: &str, markets: &[Market]) -> WatchResult<Receiver> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut symbol_ids: HashMap<Product, Vec<String>> = HashMap::new();
+        for market in markets {
+            let (product, symbol_id) = self.get_symbol_id(market).map_err(|_| WatchError::SymbolNotFound(format!("{:?}", market)))?;
+            symbol_ids.entry(product).or_default().push(symbol_id);
+        }
+
+        let mut clients = vec![];
+        for (product, symbol_ids) in symbol_ids {
+            let (endpoint, prefix) = match product {
+                Product::Spot => (self.exchange_base.ws_endpoint.as_ref().unwrap().as_str(), "spot"),
+                Product::Futures => (FUTURES_WS_ENDPOINT, "futures"),
+            };
+            let mut ws_client = self.exchange_base.ws_client(endpoint, parse_stream, self.unifier(product)?.clone());
+            for symbol_ids in symbol_ids.chunks(SUBSCRIPTION_CHUNK_SIZE) {
+                let request = SubscribeRequest {
+                    time: Utc::now().timestamp(),
+                    channel: format!("{}.{}", prefix, channel),
+                    event: "subscribe",
+                    payload: symbol_ids.to_vec(),
+                };
+                let message = serde_json::to_string(&request).map_err(|e| WatchError::UnknownError(format!("{}", e)))?;
+                let _ = ws_client.send(message).await?;
+            }
+            clients.push(ws_client);
+        }
+
+        // a message may carry several items, which are forwarded one by one
+        let mut clients = futures_util::stream::select_all(clients);
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(async move {
+            while let Some(items) = clients.next().await {
+                let items = match items {
+                    Ok(items) => items.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                for item in items {
+                    if tx.send(item).await.is_err() { // the receiver is dropped
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+#[async_trait]
+impl Exchange for Gateio {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        if self.exchange_base.markets.is_empty() {
+            let result = self.fetch_all_markets().await?;
+            self.exchange_base.unifier.reset();
+            self.unifiers.clear();
+            let mut markets = vec![];
+            for (product, symbol_id, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &symbol_id);
+                self.unifiers.entry(product).or_insert_with(Unifier::new).insert_market_symbol_id(&market, &symbol_id);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.fetch_all_markets().await?;
+        Ok(result.into_iter().map(|(_, _, market)| market).collect())
+    }
+
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let products = match params.markets.as_ref() {
+            Some(markets) => {
+                let mut products = vec![];
+                for market in markets {
+                    let (product, _) = self.get_symbol_id(market)?;
+                    if !products.contains(&product) {
+                        products.push(product);
+                    }
+                }
+                products
+            }
+            None => Product::ALL.to_vec(),
+        };
+
+        let is_requested = |market: &Market| params.markets.as_ref().is_none_or(|markets| markets.contains(market));
+        let timestamp = Utc::now().timestamp_millis();
+        let mut tickers = vec![];
+        for product in products {
+            let unifier = self.unifier(product)?;
+            match product {
+                Product::Spot => {
+                    let result: Vec<FetchSpotTickersResponse> = self.get("/spot/tickers", &vec![]).await?;
+                    for item in result {
+                        match unifier.get_market(&item.currency_pair) {
+                            Some(market) if is_requested(&market) => tickers.push(item.to_ticker(market, timestamp)?),
+                            _ => continue,
+                        }
+                    }
+                }
+                Product::Futures => {
+                    let result: Vec<FetchFuturesTickersResponse> = self.get("/futures/usdt/tickers", &vec![]).await?;
+                    for item in result {
+                        match unifier.get_market(&item.contract) {
+                            Some(market) if is_requested(&market) => tickers.push(item.to_ticker(market, timestamp)?),
+                            _ => continue,
+                        }
+                    }
+                }
+            }
+        }
+        Ok(tickers)
+    }
+
+    async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let (product, symbol_id) = self.get_symbol_id(&params.market)?;
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = match product {
+            Product::Spot => vec![("currency_pair", symbol_id.as_str())],
+            Product::Futures => vec![("contract", symbol_id.as_str())],
+        };
+        if let Some(limit) = &limit {
+            query.push(("limit", limit.as_str()));
+        }
+        let trades = match product {
+            Product::Spot => {
+                let result: Vec<SpotTradeResponse> = self.get("/spot/trades", &query).await?;
+                result.iter().map(|item| item.to_trade(&params.market)).collect::<Result<Vec<Trade>>>()?
+            }
+            Product::Futures => {
+                let result: Vec<FuturesTradeResponse> = self.get("/futures/usdt/trades", &query).await?;
+                result.iter().map(|item| item.to_trade(&params.market)).collect::<Result<Vec<Trade>>>()?
+            }
+        };
+
+        // only the most recent trades are served, so the range is applied locally
+        Ok(trades.into_iter()
+            .filter(|trade| params.since.is_none_or(|since| trade.timestamp >= since) && params.until.is_none_or(|until| trade.timestamp <= until))
+            .collect())
+    }
+
+    /// Streams the best bid and ask. Full-depth books are not supported.
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        if params.full_depth {
+            return Err(WatchError::NotImplemented);
+        }
+        self.subscribe("book_ticker", &params.markets).await
+    }
+
+    async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
+        self.subscribe("trades", &params.markets).await
+    }
+
+    /// Fetches the spot account.
+    async fn fetch_balance(&self, params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if params.margin_mode.is_some() {
+            return Err(Error::InvalidParameters("margin mode is not supported".into()).into());
+        }
+
+        let result: Vec<FetchBalanceResponse> = self.signed_get("/spot/accounts", &vec![]).await?;
+        let mut balance = Balance::default();
+        for item in result {
+            balance.items.push(item.to_balance_item()?);
+        }
+        Ok(balance)
+    }
+
+    /// Amounts of contracts are whole numbers of contracts. Spot market buys are sized in the
+    /// quote currency by the exchange, so they require a price to convert the amount. The margin
+    /// mode of contracts is the one of the position and is not set by orders.
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if !matches!(order_type, OrderType::Limit | OrderType::Market) {
+            return Err(Error::UnsupportedOrderType(order_type.to_string()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let (product, symbol_id) = self.get_symbol_id(&params.market)?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.unifier(product)?.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+
+        let is_market = order_type == OrderType::Market;
+        // market orders are immediate or cancel
+        let time_in_force = match is_market {
+            true => TimeInForce::IOC,
+            false => params.time_in_force.unwrap_or(TimeInForce::GTC),
+        };
+        let (id, text) = match product {
+            Product::Spot => {
+                let amount = match (&params.order_side, is_market, price) {
+                    (OrderSide::Buy, true, Some(price)) => amount * price,
+                    (OrderSide::Buy, true, None) => {
+                        return Err(Error::InvalidPrice("price is required for spot market buys, which are sized in the quote currency".into()).into());
+                    }
+                    _ => amount,
+                };
+                let request = CreateSpotOrderRequest {
+                    currency_pair: symbol_id,
+                    order_type: match is_market {
+                        true => "market",
+                        false => "limit",
+                    },
+                    account: util::get_exchange_account(params.margin_mode.as_ref()),
+                    side: util::get_exchange_order_side(&params.order_side),
+                    amount: amount.to_string(),
+                    price: price.filter(|_| !is_market).map(|price| price.to_string()),
+                    time_in_force: util::get_exchange_time_in_force(&time_in_force),
+                };
+                let body = serde_json::to_string(&request).map_err(|e| Error::InvalidParameters(format!("{}", e)))?;
+                let response: CreateSpotOrderResponse = self.signed_post("/spot/orders", &body).await?;
+                (response.id, response.text)
+            }
+            Product::Futures => {
+                let contracts = number_to_f64(amount);
+                if contracts.fract() != 0.0 {
+                    return Err(Error::InvalidAmount(format!("{} is not a whole number of contracts", amount)).into());
+                }
+                let request = CreateFuturesOrderRequest {
+                    contract: symbol_id,
+                    // sells are negative sizes
+                    size: match params.order_side {
+                        OrderSide::Buy => contracts as i64,
+                        OrderSide::Sell => -(contracts as i64),
+                    },
+                    // market orders have a zero price
+                    price: match is_market {
+                        true => "0".to_string(),
+                        false => price.map(|price| price.to_string()).unwrap_or_default(),
+                    },
+                    tif: util::get_exchange_time_in_force(&time_in_force),
+                    reduce_only: params.reduce_only,
+                };
+                let body = serde_json::to_string(&request).map_err(|e| Error::InvalidParameters(format!("{}", e)))?;
+                let response: CreateFuturesOrderResponse = self.signed_post("/futures/usdt/orders", &body).await?;
+                (response.id.to_string(), response.text)
+            }
+        };
+        Ok(Order {
+            id: Some(id),
+            // custom ids are prefixed with `t-`, the others name the origin of the order
+            client_order_id: Some(text).filter(|text| text.starts_with("t-")),
+            timestamp: Utc::now().timestamp_millis(),
+            status: OrderStatus::Open,
+            market: params.market.clone(),
+            order_type,
+            time_in_force: match is_market {
+                true => None,
+                false => Some(time_in_force),
+            },
+            side: Some(params.order_side),
+            price: price.filter(|_| !is_market),
+            amount,
+            margin_mode: params.margin_mode.unwrap_or(MarginMode::Cross),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Maps the label of an error response to an error.
+fn get_error(label: &str, message: String) -> Error {
+    match label {
+        "INVALID_KEY" | "INVALID_CREDENTIALS" | "FORBIDDEN" | "READ_ONLY" => Error::CredentialsError(message),
+        "INVALID_SIGNATURE" => Error::InvalidSignature(message),
+        "REQUEST_EXPIRED" => Error::TimestampOutsideRecvWindow(message),
+        "INVALID_PARAM_VALUE" | "INVALID_ARGUMENT" | "MISSING_REQUIRED_PARAM" | "INVALID_REQUEST_BODY" => Error::InvalidParameters(message),
+        "INVALID_CURRENCY_PAIR" | "CONTRACT_NOT_FOUND" => Error::SymbolNotFound(message),
+        "BALANCE_NOT_ENOUGH" | "INSUFFICIENT_AVAILABLE" | "MARGIN_BALANCE_NOT_ENOUGH" => Error::InsufficientMargin(message),
+        "ORDER_NOT_FOUND" => Error::OrderNotFound(message),
+        _ => Error::HttpError(format!("label={}, message={}", label, message)),
+    }
+}
+
+/// Open price of a 24 hours change in percent.
+fn get_open(last: f64, change_percentage: f64) -> f64 {
+    match change_percentage {
+        change if change <= -100.0 => 0.0,
+        change => last / (1f64 + change / 100f64),
+    }
+}
+
+/// Book of the best bid and ask.
+fn to_best_order_book(market: Market, bid: (&str, f64), ask: (&str, f64), timestamp: i64, update_id: i64) -> Result<OrderBook> {
+    let unit = |(price, amount): (&str, f64)| -> Result<Vec<OrderBookUnit>> {
+        match price {
+            "" => Ok(vec![]),
+            price => Ok(vec![OrderBookUnit { price: parse_number(price)?, amount: f64_to_number(amount) }]),
+        }
+    };
+    Ok(OrderBook::new(unit(bid)?, unit(ask)?, market, Some(timestamp), Some(update_id)))
+}
+
+/// Parses a channel update into a stream item per element of its result.
+fn parse_stream(message: &[u8], unifier: &Unifier) -> WatchResult<Vec<StreamItem>> {
+    let push = WatchPushResponse::try_from(message.to_vec())?;
+    if let Some(error) = push.error {
+        return Err(Error::StreamError(format!("code={}, message={}", error.code, error.message)).into());
+    }
+    if push.event != "update" {
+        return Ok(vec![StreamItem::Unknown(String::from_utf8_lossy(message).to_string())]);
+    }
+    let get_market = |symbol_id: &String| unifier.get_market(symbol_id).ok_or_else(|| WatchError::SymbolNotFound(symbol_id.clone()));
+    let deserialize_error = |e: serde_json::Error| Error::DeserializeJsonBody(format!("{:?}", e));
+
+    let items = match push.channel.as_str() {
+        "spot.book_ticker" => {
+            let book = serde_json::from_value::<WatchSpotBookTickerResponse>(push.result).map_err(deserialize_error)?;
+            let market = get_market(&book.s)?;
+            let order_book = parse_float64(&book.bid_size)
+                .and_then(|bid_size| Ok((bid_size, parse_float64(&book.ask_size)?)))
+                .and_then(|(bid_size, ask_size)| to_best_order_book(market.clone(), (&book.b, bid_size), (&book.a, ask_size), book.t, book.u))
+                .map_err(|e| OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market)));
+            vec![StreamItem::OrderBook(order_book)]
+        }
+        "futures.book_ticker" => {
+            let book = serde_json::from_value::<WatchFuturesBookTickerResponse>(push.result).map_err(deserialize_error)?;
+            let market = get_market(&book.s)?;
+            let order_book = to_best_order_book(market.clone(), (&book.b, book.bid_size as f64), (&book.a, book.ask_size as f64), book.t, book.u)
+                .map_err(|e| OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market)));
+            vec![StreamItem::OrderBook(order_book)]
+        }
+        "spot.trades" => {
+            let trade = serde_json::from_value::<WatchSpotTradeResponse>(push.result).map_err(deserialize_error)?;
+            let market = get_market(&trade.currency_pair)?;
+            vec![StreamItem::Trade(SpotTradeResponse::from(trade).to_trade(&market).map_err(TradeError::from))]
+        }
+        "futures.trades" => {
+            let trades = serde_json::from_value::<Vec<FuturesTradeResponse>>(push.result).map_err(deserialize_error)?;
+            let mut items = vec![];
+            for trade in trades {
+                let market = get_market(&trade.contract)?;
+                items.push(StreamItem::Trade(trade.to_trade(&market).map_err(TradeError::from)));
+            }
+            items
+        }
+        _ => vec![StreamItem::Unknown(String::from_utf8_lossy(message).to_string())],
+    };
+    Ok(items)
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct ErrorResponse {
+    label: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ServerTimeResponse {
+    server_time: i64,
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FetchSpotMarketsResponse {
+    id: String,
+    base: String,
+    quote: String,
+    min_base_amount: Option<String>,
+    max_base_amount: Option<String>,
+    min_quote_amount: Option<String>,
+    max_quote_amount: Option<String>,
+    /// number of decimal places
+    amount_precision: isize,
+    /// number of decimal places of the price
+    precision: isize,
+    trade_status: String,
+}
+
+impl FetchSpotMarketsResponse {
+    fn to_market(&self) -> Result<Market> {
+        let range = |min: &Option<String>, max: &Option<String>| -> Result<Option<Range>> {
+            match (min, max) {
+                (None, None) => Ok(None),
+                (min, max) => Ok(Some(Range {
+                    min: util::parse_optional_float(min.as_deref().unwrap_or_default())?,
+                    max: match max.as_deref() {
+                        None | Some("") => f64::MAX,
+                        Some(max) => parse_float64(max)?,
+                    },
+                })),
+            }
+        };
+        Ok(Market {
+            base: util::to_unified_asset(&self.base),
+            quote: util::to_unified_asset(&self.quote),
+            active: util::is_active(&self.trade_status),
+            market_type: MarketType::Spot,
+            precision: Some(Precision {
+                amount: Some(self.amount_precision),
+                price: Some(self.precision),
+                cost: None,
+            }),
+            limit: Some(MarketLimit {
+                amount: range(&self.min_base_amount, &self.max_base_amount)?,
+                price: None,
+                cost: range(&self.min_quote_amount, &self.max_quote_amount)?,
+                leverage: None,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+/// Orders are sized in contracts, each worth `quanto_multiplier` of the base currency.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FetchFuturesMarketsResponse {
+    /// e.g. `BTC_USDT`
+    name: String,
+    quanto_multiplier: String,
+    order_price_round: String,
+    order_size_min: i64,
+    order_size_max: i64,
+    leverage_min: String,
+    leverage_max: String,
+    in_delisting: bool,
+}
+
+impl FetchFuturesMarketsResponse {
+    fn to_market(&self) -> Result<Market> {
+        let (base, quote) = self.name.split_once('_').ok_or_else(|| Error::InvalidResponse(format!("invalid contract {}", self.name)))?;
+        let leverage = match (self.leverage_min.as_str(), self.leverage_max.as_str()) {
+            ("", _) | (_, "") => None,
+            (min, max) => Some(Range { min: parse_float64(min)?, max: parse_float64(max)? }),
+        };
+        Ok(Market {
+            base: util::to_unified_asset(base),
+            quote: util::to_unified_asset(quote),
+            active: !self.in_delisting,
+            market_type: MarketType::Swap,
+            settle: Some("USDT".to_string()),
+            contract_size: Some(parse_float64(&self.quanto_multiplier)?),
+            contract_type: Some(ContractType::Linear),
+            precision: Some(Precision {
+                amount: Some(0),
+                price: Some(into_precision(self.order_price_round.clone())?),
+                cost: None,
+            }),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: self.order_size_min as f64, max: self.order_size_max as f64 }),
+                price: None,
+                cost: None,
+                leverage,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FetchSpotTickersResponse {
+    currency_pair: String,
+    last: String,
+    lowest_ask: String,
+    lowest_size: String,
+    highest_bid: String,
+    highest_size: String,
+    /// in percent
+    change_percentage: String,
+    base_volume: String,
+    quote_volume: String,
+    high_24h: String,
+    low_24h: String,
+}
+
+impl FetchSpotTickersResponse {
+    fn to_ticker(&self, market: Market, timestamp: i64) -> Result<Ticker> {
+        let last = util::parse_optional_float(&self.last)?;
+        let open = get_open(last, util::parse_optional_float(&self.change_percentage)?);
+        let base_volume = util::parse_optional_float(&self.base_volume)?;
+        let quote_volume = util::parse_optional_float(&self.quote_volume)?;
+        Ok(Ticker {
+            ask: Some(util::parse_optional_float(&self.lowest_ask)?).filter(|ask| *ask > 0.0),
+            ask_volume: util::parse_optional_float(&self.lowest_size)?,
+            bid: Some(util::parse_optional_float(&self.highest_bid)?).filter(|bid| *bid > 0.0),
+            bid_volume: util::parse_optional_float(&self.highest_size)?,
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume,
+            change: last - open,
+            close: last,
+            high: util::parse_optional_float(&self.high_24h)?,
+            low: util::parse_optional_float(&self.low_24h)?,
+            last,
+            open,
+            percentage: util::parse_optional_float(&self.change_percentage)?,
+            previous_close: None,
+            vwap: match base_volume > 0.0 {
+                true => quote_volume / base_volume,
+                false => 0.0,
+            },
+            market,
+            timestamp,
+        })
+    }
+}
+
+/// Sizes of the best levels are left out, their type having changed over time.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FetchFuturesTickersResponse {
+    contract: String,
+    last: String,
+    lowest_ask: String,
+    highest_bid: String,
+    /// in percent
+    change_percentage: String,
+    volume_24h_base: String,
+    volume_24h_quote: String,
+    high_24h: String,
+    low_24h: String,
+}
+
+impl FetchFuturesTickersResponse {
+    fn to_ticker(&self, market: Market, timestamp: i64) -> Result<Ticker> {
+        let last = util::parse_optional_float(&self.last)?;
+        let open = get_open(last, util::parse_optional_float(&self.change_percentage)?);
+        let base_volume = util::parse_optional_float(&self.volume_24h_base)?;
+        let quote_volume = util::parse_optional_float(&self.volume_24h_quote)?;
+        Ok(Ticker {
+            ask: Some(util::parse_optional_float(&self.lowest_ask)?).filter(|ask| *ask > 0.0),
+            ask_volume: 0.0,
+            bid: Some(util::parse_optional_float(&self.highest_bid)?).filter(|bid| *bid > 0.0),
+            bid_volume: 0.0,
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume,
+            change: last - open,
+            close: last,
+            high: util::parse_optional_float(&self.high_24h)?,
+            low: util::parse_optional_float(&self.low_24h)?,
+            last,
+            open,
+            percentage: util::parse_optional_float(&self.change_percentage)?,
+            previous_close: None,
+            vwap: match base_volume > 0.0 {
+                true => quote_volume / base_volume,
+                false => 0.0,
+            },
+            market,
+            timestamp,
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SpotTradeResponse {
+    id: String,
+    /// milliseconds with a fractional part
+    create_time_ms: String,
+    /// taker side
+    side: String,
+    amount: String,
+    price: String,
+}
+
+impl SpotTradeResponse {
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = parse_number(&self.price)?;
+        let amount = parse_number(&self.amount)?;
+        Ok(Trade::new(
+            self.id.clone(),
+            parse_float64(&self.create_time_ms)? as i64,
+            market.clone(),
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.side)?),
+            None,
+            price, amount,
+            price * amount,
+            None, None,
+        ))
+    }
+}
+
+/// Trade of the `spot.trades` channel, whose id is a number.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct WatchSpotTradeResponse {
+    id: i64,
+    currency_pair: String,
+    create_time_ms: String,
+    side: String,
+    amount: String,
+    price: String,
+}
+
+impl From<WatchSpotTradeResponse> for SpotTradeResponse {
+    fn from(trade: WatchSpotTradeResponse) -> Self {
+        Self {
+            id: trade.id.to_string(),
+            create_time_ms: trade.create_time_ms,
+            side: trade.side,
+            amount: trade.amount,
+            price: trade.price,
+        }
+    }
+}
+
+/// Trade of the REST endpoint and of the `futures.trades` channel.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FuturesTradeResponse {
+    id: i64,
+    contract: String,
+    /// milliseconds, with a fractional part in REST responses
+    create_time_ms: f64,
+    /// in contracts, negative when the taker sold
+    size: i64,
+    price: String,
+}
+
+impl FuturesTradeResponse {
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = parse_number(&self.price)?;
+        let amount = f64_to_number(self.size.abs() as f64);
+        let contract_size = f64_to_number(market.contract_size.unwrap_or(1.0));
+        let side = match self.size < 0 {
+            true => OrderSide::Sell,
+            false => OrderSide::Buy,
+        };
+        Ok(Trade::new(
+            self.id.to_string(),
+            self.create_time_ms as i64,
+            market.clone(),
+            None,
+            None,
+            Some(side),
+            None,
+            price, amount,
+            price * amount * contract_size,
+            None, None,
+        ))
+    }
+}
+
+
+#[derive(Serialize)]
+struct SubscribeRequest {
+    /// seconds
+    time: i64,
+    channel: String,
+    event: &'static str,
+    payload: Vec<String>,
+}
+
+/// Channel update, or the result of a subscription.
+#[derive(Serialize, Deserialize)]
+struct WatchPushResponse {
+    channel: String,
+    /// `subscribe`, `unsubscribe` or `update`
+    event: String,
+    error: Option<WatchErrorResponse>,
+    #[serde(default)]
+    result: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchErrorResponse {
+    code: i64,
+    message: String,
+}
+
+impl TryFrom<Vec<u8>> for WatchPushResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchSpotBookTickerResponse {
+    /// milliseconds
+    t: i64,
+    u: i64,
+    s: String,
+    b: String,
+    #[serde(rename = "B")]
+    bid_size: String,
+    a: String,
+    #[serde(rename = "A")]
+    ask_size: String,
+}
+
+/// Sizes are in contracts.
+#[derive(Serialize, Deserialize)]
+struct WatchFuturesBookTickerResponse {
+    /// milliseconds
+    t: i64,
+    u: i64,
+    s: String,
+    b: String,
+    #[serde(rename = "B")]
+    bid_size: i64,
+    a: String,
+    #[serde(rename = "A")]
+    ask_size: i64,
+}
+
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FetchBalanceResponse {
+    currency: String,
+    available: String,
+    locked: String,
+}
+
+impl FetchBalanceResponse {
+    fn to_balance_item(&self) -> Result<BalanceItem> {
+        let free = util::parse_optional_float(&self.available)?;
+        let used = util::parse_optional_float(&self.locked)?;
+        Ok(BalanceItem {
+            currency: util::to_unified_asset(&self.currency),
+            market: None,
+            total: free + used,
+            free,
+            used,
+            debt: 0.0,
+        })
+    }
+}
+
+
+#[derive(Serialize)]
+struct CreateSpotOrderRequest {
+    currency_pair: String,
+    #[serde(rename = "type")]
+    order_type: &'static str,
+    account: &'static str,
+    side: &'static str,
+    /// in the quote currency for market buys
+    amount: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<String>,
+    time_in_force: &'static str,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CreateSpotOrderResponse {
+    id: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Serialize)]
+struct CreateFuturesOrderRequest {
+    contract: String,
+    size: i64,
+    price: String,
+    tif: &'static str,
+    reduce_only: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CreateFuturesOrderResponse {
+    id: i64,
+    #[serde(default)]
+    text: String,
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Gateio, PropertiesBuilder};
+    use crate::error::Error;
+    use crate::exchange::{StreamItem, Unifier};
+    use crate::model::{ContractType, MarketType, OrderSide};
+    use crate::util::number;
+
+    use super::{FetchFuturesMarketsResponse, FetchSpotMarketsResponse, FetchSpotTickersResponse, get_error, parse_stream, Product};
+
+    #[test]
+    fn test_auth() {
+        let props = PropertiesBuilder::default()
+            .api_key(Some("key".into()))
+            .secret(Some("secret".into()))
+            .build()
+            .unwrap();
+        let gateio = Gateio::new(props).unwrap();
+        let signature = gateio.auth("POST", "/api/v4/spot/orders", "", r#"{"currency_pair":"BTC_USDT"}"#, "1541993715").unwrap();
+        assert_eq!(signature, "26a99e799f760ef3830083385f322990f6813eafebb1581b6b975f083220c5c5fbb0db4e4c13faa5cf482d3e2916d8c34eb4023e436354e7c7824c5227904ac6");
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"id": "ETH_USDT", "base": "ETH", "quote": "USDT", "fee": "0.2", "min_base_amount": "0.001", "min_quote_amount": "1.0", "max_quote_amount": "10000000", "amount_precision": 3, "precision": 2, "trade_status": "tradable", "sell_start": 1516378650, "buy_start": 1516378650}"#;
+        let resp: FetchSpotMarketsResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market().unwrap();
+        assert_eq!(market.market_type, MarketType::Spot);
+        assert!(market.active);
+        assert_eq!(market.precision.as_ref().unwrap().amount, Some(3));
+        assert_eq!(market.limit.as_ref().unwrap().amount.as_ref().unwrap().max, f64::MAX);
+        assert_eq!(market.limit.as_ref().unwrap().cost.as_ref().unwrap().min, 1.0);
+        assert_eq!(format!("{}", market), "ETH/USDT");
+
+        let message = r#"{"name": "BTC_USDT", "type": "direct", "quanto_multiplier": "0.0001", "leverage_min": "1", "leverage_max": "100", "mark_price_round": "0.01", "order_price_round": "0.1", "order_size_min": 1, "order_size_max": 1000000, "in_delisting": false}"#;
+        let resp: FetchFuturesMarketsResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market().unwrap();
+        assert_eq!(market.market_type, MarketType::Swap);
+        assert_eq!(market.contract_type, Some(ContractType::Linear));
+        assert_eq!(market.contract_size, Some(0.0001));
+        assert_eq!(market.precision.as_ref().unwrap().price, Some(1));
+        assert_eq!(Product::of(&market).unwrap(), Product::Futures);
+        assert_eq!(format!("{}", market), "BTC/USDT:USDT");
+    }
+
+    #[test]
+    fn test_parse_stream() {
+        let message = r#"{"name": "BTC_USDT", "quanto_multiplier": "0.0001", "order_price_round": "0.1", "order_size_min": 1, "order_size_max": 1000000}"#;
+        let market = serde_json::from_str::<FetchFuturesMarketsResponse>(message).unwrap().to_market().unwrap();
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"BTC_USDT".to_string());
+
+        let message = r#"{"time": 1615366379, "time_ms": 1615366379123, "channel": "futures.book_ticker", "event": "update", "result": {"t": 1615366379123, "u": 2517661076, "s": "BTC_USDT", "b": "54696.6", "B": 37000, "a": "54696.7", "A": 47061}}"#;
+        match &parse_stream(message.as_bytes(), &unifier).unwrap()[..] {
+            [StreamItem::OrderBook(Ok(order_book))] => {
+                assert_eq!(order_book.bids[0].amount, number("37000"));
+                assert_eq!(order_book.asks[0].price, number("54696.7"));
+                assert_eq!(order_book.timestamp, Some(1615366379123));
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+
+        let message = r#"{"channel": "futures.trades", "event": "update", "time": 1541503698, "result": [{"size": -108, "id": 27753479, "create_time": 1545136464, "create_time_ms": 1545136464123, "price": "96.4", "contract": "BTC_USDT"}]}"#;
+        match &parse_stream(message.as_bytes(), &unifier).unwrap()[..] {
+            [StreamItem::Trade(Ok(trade))] => {
+                assert_eq!(trade.id, "27753479");
+                assert_eq!(trade.side, Some(OrderSide::Sell));
+                assert_eq!(trade.amount, number("108"));
+                assert_eq!(trade.timestamp, 1545136464123);
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+
+        let message = r#"{"time": 1606292218, "channel": "futures.trades", "event": "subscribe", "error": {"code": 2, "message": "unknown contract BTC_USD"}, "result": {"status": "fail"}}"#;
+        assert!(parse_stream(message.as_bytes(), &unifier).is_err());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(matches!(get_error("BALANCE_NOT_ENOUGH", "Not enough balance".into()), Error::InsufficientMargin(_)));
+        assert!(matches!(get_error("INVALID_SIGNATURE", "Signature mismatch".into()), Error::InvalidSignature(_)));
+        assert!(matches!(get_error("SERVER_ERROR", "Internal server error".into()), Error::HttpError(_)));
+    }
+
+    #[test]
+    fn test_to_ticker() {
+        let message = r#"{"currency_pair": "BTC_USDT", "last": "27000", "lowest_ask": "27000.1", "lowest_size": "0.5", "highest_bid": "26999.9", "highest_size": "1.5", "change_percentage": "8", "base_volume": "100", "quote_volume": "2700000", "high_24h": "28000", "low_24h": "24000"}"#;
+        let resp: FetchSpotTickersResponse = serde_json::from_str(message).unwrap();
+        let ticker = resp.to_ticker(Default::default(), 1695794098184).unwrap();
+        assert_eq!(ticker.open, 25000.0);
+        assert_eq!(ticker.change, 2000.0);
+        assert_eq!(ticker.bid_volume, 1.5);
+        assert_eq!(ticker.vwap, 27000.0);
+    }
+}
@@ -0,0 +1,5 @@
+pub use gateio::Gateio;
+
+#[allow(clippy::module_inception)]
+mod gateio;
+mod util;
@@ -0,0 +1,74 @@
+use crate::error::{Error, Result};
+use crate::model::{ContractType, MarginMode, Market, MarketType, OrderSide, TimeInForce};
+
+/// Markets of the v4 API. Symbol ids are only unique within a product, e.g. `BTC_USDT` is both a
+/// spot market and a perpetual contract.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub(in super) enum Product {
+    Spot,
+    /// perpetual contracts settled in USDT
+    Futures,
+}
+
+impl Product {
+    pub(in super) const ALL: [Product; 2] = [Product::Spot, Product::Futures];
+
+    pub(in super) fn of(market: &Market) -> Result<Product> {
+        match (market.market_type, market.contract_type, market.settle.as_deref()) {
+            (MarketType::Spot, _, _) => Ok(Product::Spot),
+            (MarketType::Swap, Some(ContractType::Linear), Some("USDT")) => Ok(Product::Futures),
+            _ => Err(Error::InvalidParameters(format!("unsupported market {}", market))),
+        }
+    }
+}
+
+pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
+    exchange_asset.to_uppercase()
+}
+
+/// Pairs may be open to one side only, e.g. `buyable` before the sell start of a new listing.
+pub(in super) fn is_active(trade_status: &str) -> bool {
+    trade_status == "tradable"
+}
+
+/// Parses a decimal field that the exchange leaves empty when it does not apply.
+pub(in super) fn parse_optional_float(s: &str) -> Result<f64> {
+    match s {
+        "" => Ok(0.0),
+        s => Ok(s.parse::<f64>()?),
+    }
+}
+
+pub(in super) fn get_exchange_order_side(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "buy" => Ok(OrderSide::Buy),
+        "sell" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+/// Post-only orders are pending or cancelled, `poc`.
+pub(in super) fn get_exchange_time_in_force(time_in_force: &TimeInForce) -> &'static str {
+    match time_in_force {
+        TimeInForce::GTC => "gtc",
+        TimeInForce::IOC => "ioc",
+        TimeInForce::FOK => "fok",
+        TimeInForce::PO => "poc",
+    }
+}
+
+/// Account of spot orders, which borrow on the margin accounts when a margin mode is given.
+pub(in super) fn get_exchange_account(margin_mode: Option<&MarginMode>) -> &'static str {
+    match margin_mode {
+        None => "spot",
+        Some(MarginMode::Isolated) => "margin",
+        Some(MarginMode::Cross) => "cross_margin",
+    }
+}
@@ -0,0 +1,973 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::client::{EMPTY_QUERY, WsItem};
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{parse_float64, parse_number};
+use crate::util::channel::{Receiver, Sender};
+use crate::util::order_book::LocalOrderBook;
+use crate::util::rate_limiter::RateLimit;
+
+use super::util;
+use super::util::AssetCodes;
+
+/// number of levels of each side covered by the checksum of book updates
+const CHECKSUM_DEPTH: usize = 10;
+
+pub struct Kraken {
+    exchange_base: ExchangeBase,
+    api_key: Option<String>,
+    secret: Option<String>,
+
+    /// asset codes of the exchange, loaded with the markets
+    assets: AssetCodes,
+
+    /// markets by the pair names of the websocket API, e.g. `XBT/USD`
+    ws_unifier: Unifier,
+
+    /// last nonce sent, nonces have to increase with every private request
+    nonce: AtomicI64,
+}
+
+impl Kraken {
+    /// There is no sandbox for spot, so `sandbox` is only accepted along with a `host`.
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        if props.sandbox && props.host.is_none() {
+            return Err(Error::UnsupportedInSandbox("spot has no sandbox".into()).into());
+        }
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some("https://api.kraken.com".to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some("wss://ws.kraken.com".to_string())))
+            .error_parser(Some(|message| {
+                match serde_json::from_str::<Response>(&message) {
+                    Ok(Response { error, .. }) if !error.is_empty() => get_error(&error[0]),
+                    _ => Error::HttpError(message),
+                }
+            }))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            // public endpoints allow about one request per second
+            .rate_limits(Some(vec![RateLimit { interval: Duration::from_secs(1), limit: 1, header: None }]))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            assets: AssetCodes::default(),
+            ws_unifier: Unifier::new(),
+            nonce: AtomicI64::new(0),
+        })
+    }
+
+    /// Milliseconds since the epoch, bumped when several requests are signed within the same
+    /// millisecond.
+    fn nonce(&self) -> i64 {
+        let now = Utc::now().timestamp_millis();
+        let last = self.nonce.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(now.max(last + 1)));
+        // the closure never fails
+        now.max(last.unwrap_or(now) + 1)
+    }
+
+    /// Signs `path + SHA-256(nonce + post data)` with the base64 decoded secret and encodes the
+    /// signature in base64.
+    fn auth(&self, path: &str, nonce: &str, body: &str) -> Result<String> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials);
+        }
+        let secret = BASE64.decode(self.secret.as_ref().unwrap()).map_err(|e| Error::CredentialsError(format!("{}", e)))?;
+        let mut digest = Sha256::new();
+        digest.update(nonce.as_bytes());
+        digest.update(body.as_bytes());
+        let mut signed_key = Hmac::<Sha512>::new_from_slice(&secret)?;
+        signed_key.update(path.as_bytes());
+        signed_key.update(&digest.finalize());
+        Ok(BASE64.encode(signed_key.finalize().into_bytes()))
+    }
+
+    fn get_symbol_id(&self, market: &Market) -> Result<String> {
+        self.exchange_base.unifier.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let response: Response = self.exchange_base.http_client.get(path, None, Some(query)).await?;
+        response.into_result()
+    }
+
+    /// Posts the parameters as a form, after the nonce.
+    async fn signed_post<T: DeserializeOwned>(&self, path: &str, params: &[(&str, &str)]) -> Result<T> {
+        let nonce = self.nonce().to_string();
+        let body = [("nonce", nonce.as_str())].iter().chain(params.iter())
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<String>>()
+            .join("&");
+        let signature = self.auth(path, &nonce, &body)?;
+        let headers = vec![
+            ("Content-Type", "application/x-www-form-urlencoded"),
+            ("API-Key", self.api_key.as_ref().unwrap().as_str()),
+            ("API-Sign", signature.as_str()),
+        ];
+        let response: Response = self.exchange_base.http_client.post(path, Some(headers), EMPTY_QUERY, Some(&body)).await?;
+        response.into_result()
+    }
+
+    /// Asset codes and markets with their REST and websocket ids, ordered by REST id.
+    async fn fetch_all_markets(&self) -> Result<(AssetCodes, Vec<(String, String, Market)>)> {
+        let assets: HashMap<String, FetchAssetsResponse> = self.get("/0/public/Assets", &vec![]).await?;
+        let assets = AssetCodes::new(assets.iter().map(|(code, asset)| (code, &asset.altname)));
+        let pairs: HashMap<String, FetchMarketsResponse> = self.get("/0/public/AssetPairs", &vec![]).await?;
+        let mut pairs: Vec<_> = pairs.into_iter()
+            .filter(|(_, pair)| pair.wsname.is_some())
+            .collect();
+        pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut markets = vec![];
+        for (symbol_id, pair) in pairs {
+            let market = pair.to_market(&assets)?;
+            markets.push((symbol_id, pair.wsname.unwrap_or_default(), market));
+        }
+        Ok((assets, markets))
+    }
+}
+
+#[async_trait]
+impl Exchange for Kraken {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        if self.exchange_base.markets.is_empty() {
+            let (assets, result) = self.fetch_all_markets().await?;
+            self.assets = assets;
+            self.exchange_base.unifier.reset();
+            self.ws_unifier.reset();
+            let mut markets = vec![];
+            for (symbol_id, ws_name, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &symbol_id);
+                self.ws_unifier.insert_market_symbol_id(&market, &ws_name);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let (_, result) = self.fetch_all_markets().await?;
+        Ok(result.into_iter().map(|(_, _, market)| market).collect())
+    }
+
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_ids = match params.markets.as_ref() {
+            Some(markets) => Some(markets.iter()
+                .map(|market| self.get_symbol_id(market))
+                .collect::<Result<Vec<String>>>()?
+                .join(",")),
+            None => None,
+        };
+        let mut query = vec![];
+        if let Some(symbol_ids) = &symbol_ids {
+            query.push(("pair", symbol_ids.as_str()));
+        }
+        let result: HashMap<String, FetchTickersResponse> = self.get("/0/public/Ticker", &query).await?;
+        let timestamp = Utc::now().timestamp_millis();
+        let mut tickers = vec![];
+        for (symbol_id, item) in result {
+            if let Some(market) = self.exchange_base.unifier.get_market(&symbol_id) {
+                tickers.push(item.to_ticker(market, timestamp)?);
+            }
+        }
+        Ok(tickers)
+    }
+
+    async fn fetch_order_book(&self, params: FetchOrderBookParams) -> FetchOrderBookResult<OrderBook> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = vec![("pair", symbol_id.as_str())];
+        if let Some(limit) = &limit {
+            query.push(("count", limit.as_str()));
+        }
+        let mut result: HashMap<String, FetchOrderBookResponse> = self.get("/0/public/Depth", &query).await?;
+        let book = result.remove(&symbol_id).ok_or_else(|| Error::InvalidResponse(format!("missing order book of {}", symbol_id)))?;
+        Ok(OrderBook::new(
+            to_order_book_units(&book.bids)?,
+            to_order_book_units(&book.asks)?,
+            params.market,
+            None,
+            None,
+        ))
+    }
+
+    /// Serves up to 720 candles from `since`, or the most recent ones.
+    async fn fetch_ohlcv(&self, params: FetchOhlcvParams) -> FetchOhlcvResult<Vec<Ohlcv>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        // in seconds
+        let since = params.since.map(|since| (since / 1000).to_string());
+        let mut query = vec![
+            ("pair", symbol_id.as_str()),
+            ("interval", util::get_exchange_timeframe(&params.timeframe)?),
+        ];
+        if let Some(since) = &since {
+            query.push(("since", since.as_str()));
+        }
+        let mut result: HashMap<String, serde_json::Value> = self.get("/0/public/OHLC", &query).await?;
+        let candles = result.remove(&symbol_id).ok_or_else(|| Error::InvalidResponse(format!("missing candles of {}", symbol_id)))?;
+        let candles: Vec<FetchOhlcvResponse> = serde_json::from_value(candles).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))?;
+
+        let mut ohlcvs = vec![];
+        for candle in candles {
+            let ohlcv = candle.to_ohlcv(&params.market, params.timeframe)?;
+            if params.until.is_some_and(|until| ohlcv.timestamp > until) {
+                continue;
+            }
+            ohlcvs.push(ohlcv);
+        }
+        if let Some(limit) = params.limit {
+            let skip = ohlcvs.len().saturating_sub(limit.max(0) as usize);
+            ohlcvs.drain(..skip);
+        }
+        Ok(ohlcvs)
+    }
+
+    async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        // in nanoseconds
+        let since = params.since.map(|since| (since * 1_000_000).to_string());
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = vec![("pair", symbol_id.as_str())];
+        if let Some(since) = &since {
+            query.push(("since", since.as_str()));
+        }
+        if let Some(limit) = &limit {
+            query.push(("count", limit.as_str()));
+        }
+        let mut result: HashMap<String, serde_json::Value> = self.get("/0/public/Trades", &query).await?;
+        let items = result.remove(&symbol_id).ok_or_else(|| Error::InvalidResponse(format!("missing trades of {}", symbol_id)))?;
+        let items: Vec<FetchTradesResponse> = serde_json::from_value(items).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))?;
+
+        let mut trades = vec![];
+        for item in items {
+            let trade = item.to_trade(&params.market)?;
+            if params.until.is_some_and(|until| trade.timestamp > until) {
+                continue;
+            }
+            trades.push(trade);
+        }
+        Ok(trades)
+    }
+
+    /// Streams books of the best 10 levels of each side, or of 1000 levels when `full_depth` is
+    /// set. Every update is verified against the checksum of the exchange. On a mismatch, an
+    /// error is forwarded and the book is subscribed again for a new snapshot.
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if params.markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut ws_names = vec![];
+        for market in &params.markets {
+            let ws_name = self.ws_unifier.get_symbol_id(market).ok_or_else(|| WatchError::SymbolNotFound(format!("{:?}", market)))?;
+            ws_names.push(ws_name);
+        }
+        let depth = match params.full_depth {
+            true => 1000,
+            false => 10,
+        };
+        let mut ws_client = self.exchange_base.ws_client(self.exchange_base.ws_endpoint.as_ref().unwrap().as_str(), parse_order_book, self.ws_unifier.clone());
+        let _ = ws_client.send(get_book_subscription("subscribe", &ws_names, depth)).await?;
+
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(synchronize_order_books(ws_client, self.ws_unifier.clone(), depth, tx));
+        Ok(receiver)
+    }
+
+    async fn fetch_balance(&self, _params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let result: HashMap<String, FetchBalanceResponse> = self.signed_post("/0/private/BalanceEx", &[]).await?;
+        let mut balance = Balance::default();
+        for (asset, item) in result {
+            let total = parse_float64(&item.balance)?;
+            let used = match &item.hold_trade {
+                Some(hold_trade) => parse_float64(hold_trade)?,
+                None => 0.0,
+            };
+            balance.items.push(BalanceItem {
+                currency: self.assets.to_unified(&asset),
+                market: None,
+                total,
+                free: total - used,
+                used,
+                debt: 0.0,
+            });
+        }
+        Ok(balance)
+    }
+
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.exchange_base.unifier.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+
+        let is_market = order_type == OrderType::Market;
+        let time_in_force = params.time_in_force.unwrap_or(TimeInForce::GTC);
+        let amount_str = amount.to_string();
+        let price_str = price.filter(|_| !is_market).map(|price| price.to_string());
+        let mut body = vec![
+            ("ordertype", util::get_exchange_order_type(&order_type)?),
+            ("type", util::get_exchange_order_side(&params.order_side)),
+            ("volume", amount_str.as_str()),
+            ("pair", symbol_id.as_str()),
+        ];
+        if let Some(price) = &price_str {
+            body.push(("price", price.as_str()));
+        }
+        if !is_market {
+            body.push(("timeinforce", util::get_exchange_time_in_force(&time_in_force)?));
+        }
+        if !is_market && time_in_force == TimeInForce::PO {
+            body.push(("oflags", "post"));
+        }
+        if params.reduce_only {
+            body.push(("reduce_only", "true"));
+        }
+        let response: CreateOrderResponse = self.signed_post("/0/private/AddOrder", &body).await?;
+        Ok(Order {
+            id: response.txid.first().cloned(),
+            timestamp: Utc::now().timestamp_millis(),
+            status: OrderStatus::Open,
+            market: params.market.clone(),
+            order_type,
+            time_in_force: match is_market {
+                true => None,
+                false => Some(time_in_force),
+            },
+            side: Some(params.order_side),
+            price: price.filter(|_| !is_market),
+            amount,
+            ..Default::default()
+        })
+    }
+
+    /// Cancels by transaction id, or by client order id.
+    async fn cancel_order(&self, params: CancelOrderParams) -> CancelOrderResult<Order> {
+        if self.api_key.is_none() || self.secret.is_none() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let body = match (&params.order_id, &params.client_order_id) {
+            (Some(order_id), _) => vec![("txid", order_id.as_str())],
+            (None, Some(client_order_id)) => vec![("cl_ord_id", client_order_id.as_str())],
+            (None, None) => return Err(Error::InvalidParameters("order id or client order id is required".into()).into()),
+        };
+        let response: CancelOrderResponse = self.signed_post("/0/private/CancelOrder", &body).await?;
+        if response.count == 0 {
+            return Err(Error::OrderNotFound(format!("{:?}", body)).into());
+        }
+        Ok(Order {
+            id: params.order_id.clone(),
+            client_order_id: params.client_order_id.clone(),
+            timestamp: Utc::now().timestamp_millis(),
+            status: OrderStatus::Canceled,
+            market: params.market.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Maps the first message of the `error` list of a response, `<severity><category>:<message>`,
+/// to an error.
+fn get_error(message: &str) -> Error {
+    let msg = message.to_string();
+    match message {
+        m if m.starts_with("EAPI:Invalid key") => Error::CredentialsError(msg),
+        m if m.starts_with("EAPI:Invalid signature") => Error::InvalidSignature(msg),
+        m if m.starts_with("EAPI:Invalid nonce") => Error::InvalidParameters(msg),
+        m if m.starts_with("EGeneral:Invalid arguments") => Error::InvalidParameters(msg),
+        m if m.starts_with("EQuery:Unknown asset pair") => Error::SymbolNotFound(msg),
+        m if m.starts_with("EOrder:Insufficient") => Error::InsufficientMargin(msg),
+        m if m.starts_with("EOrder:Unknown order") => Error::OrderNotFound(msg),
+        m if m.starts_with("EOrder:Order minimum not met") => Error::InvalidAmount(msg),
+        m if m.starts_with("EOrder:Invalid price") => Error::InvalidPrice(msg),
+        _ => Error::HttpError(msg),
+    }
+}
+
+/// Levels of `[price, volume, timestamp]`.
+fn to_order_book_units<T>(levels: &[(String, String, T)]) -> Result<Vec<OrderBookUnit>> {
+    levels.iter()
+        .map(|(price, amount, _)| Ok(OrderBookUnit { price: parse_number(price)?, amount: parse_number(amount)? }))
+        .collect()
+}
+
+/// Seconds with a fractional part, as sent by the exchange, to milliseconds.
+fn to_timestamp(seconds: &str) -> Result<i64> {
+    Ok((parse_float64(seconds)? * 1000.0).round() as i64)
+}
+
+fn get_book_subscription(event: &str, ws_names: &[String], depth: usize) -> String {
+    let pairs = ws_names.iter()
+        .map(|ws_name| format!("\"{}\"", ws_name))
+        .collect::<Vec<String>>()
+        .join(",");
+    format!("{{\"event\": \"{event}\", \"pair\": [{pairs}], \"subscription\": {{\"name\": \"book\", \"depth\": {depth}}}}}")
+}
+
+
+enum OrderBookItem {
+    Update(Box<(Market, WatchOrderBookResponse)>),
+    Reconnected,
+    Other,
+}
+
+impl WsItem for OrderBookItem {
+    fn reconnected() -> Self {
+        OrderBookItem::Reconnected
+    }
+}
+
+/// Book messages are arrays of the channel id, one or two payloads, the channel name and the
+/// pair. Events such as subscription statuses and heartbeats are objects.
+fn parse_order_book(message: &[u8], unifier: &Unifier) -> WatchResult<OrderBookItem> {
+    let value: serde_json::Value = serde_json::from_slice(message).map_err(|e| {
+        let message = String::from_utf8_lossy(message);
+        Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+    })?;
+    let items = match value {
+        serde_json::Value::Array(items) if items.len() >= 4 => items,
+        serde_json::Value::Object(_) => {
+            let event: WatchEventResponse = serde_json::from_value(value).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))?;
+            if event.status.as_deref() == Some("error") { // rejected subscription
+                return Err(Error::StreamError(event.error_message.unwrap_or_default()).into());
+            }
+            return Ok(OrderBookItem::Other);
+        }
+        _ => return Ok(OrderBookItem::Other),
+    };
+
+    let (channel_name, ws_name) = match (items[items.len() - 2].as_str(), items[items.len() - 1].as_str()) {
+        (Some(channel_name), Some(ws_name)) => (channel_name, ws_name.to_string()),
+        _ => return Ok(OrderBookItem::Other),
+    };
+    if !channel_name.starts_with("book") {
+        return Ok(OrderBookItem::Other);
+    }
+    let mut update = WatchOrderBookResponse::default();
+    for payload in &items[1..items.len() - 2] {
+        let payload: WatchOrderBookPayloadResponse = serde_json::from_value(payload.clone()).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))?;
+        update.merge(payload);
+    }
+    match unifier.get_market(&ws_name) {
+        Some(market) => Ok(OrderBookItem::Update(Box::new((market, update)))),
+        None => Err(WatchError::SymbolNotFound(ws_name)),
+    }
+}
+
+/// Keeps a local book per market up to date from snapshot and update events and forwards the
+/// complete books to the receiver until it is dropped.
+async fn synchronize_order_books(mut client: WsClient<OrderBookItem>, unifier: Unifier, depth: usize, tx: Sender) {
+    let mut books: HashMap<Market, LocalOrderBook> = HashMap::new();
+    while let Some(item) = client.next().await {
+        let item = match item {
+            Ok(OrderBookItem::Update(update)) => {
+                let (market, update) = *update;
+                let book = books.entry(market.clone()).or_insert_with(|| LocalOrderBook::new(market.clone()));
+                match apply_update(book, &update, depth) {
+                    Ok(true) => Ok(StreamItem::OrderBook(Ok(book.to_order_book()))),
+                    Ok(false) => continue,
+                    Err(e) => {
+                        // the book is resent as a snapshot after subscribing again
+                        book.invalidate();
+                        // a failed send ends up reconnecting, which replays the subscriptions
+                        if let Some(ws_name) = unifier.get_symbol_id(&market) {
+                            let ws_names = [ws_name];
+                            let _ = client.send(get_book_subscription("unsubscribe", &ws_names, depth)).await;
+                            let _ = client.send(get_book_subscription("subscribe", &ws_names, depth)).await;
+                        }
+                        Ok(StreamItem::OrderBook(Err(OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market)))))
+                    }
+                }
+            }
+            Ok(OrderBookItem::Reconnected) => {
+                // a snapshot follows the replayed subscriptions
+                books.values_mut().for_each(|book| book.invalidate());
+                Ok(StreamItem::Reconnected)
+            }
+            Ok(OrderBookItem::Other) => continue,
+            Err(e) => Err(e),
+        };
+        if tx.send(item).await.is_err() { // the receiver is dropped
+            return;
+        }
+    }
+}
+
+/// Applies a snapshot or update event, keeping the best `depth` levels, and verifies the
+/// checksum of updates. Returns `Ok(false)` for updates received before the first snapshot.
+fn apply_update(book: &mut LocalOrderBook, update: &WatchOrderBookResponse, depth: usize) -> Result<bool> {
+    let bids = update.bids.iter().map(|level| level.to_order_book_unit()).collect::<Result<Vec<_>>>()?;
+    let asks = update.asks.iter().map(|level| level.to_order_book_unit()).collect::<Result<Vec<_>>>()?;
+    let timestamp = update.bids.iter().chain(update.asks.iter())
+        .map(|level| to_timestamp(&level.2))
+        .collect::<Result<Vec<i64>>>()?
+        .into_iter()
+        .max();
+    if update.snapshot {
+        book.reset(&bids, &asks, 0);
+        book.update(&[], &[], 0, timestamp);
+        return Ok(true);
+    }
+    let update_id = match book.last_update_id() {
+        Some(last_update_id) => last_update_id + 1,
+        None => return Ok(false),
+    };
+    book.update(&bids, &asks, update_id, timestamp);
+    book.truncate(depth);
+    if let Some(checksum) = &update.checksum {
+        let expected = checksum.parse::<u32>()?;
+        let actual = get_checksum(&book.to_order_book())?;
+        if expected != actual {
+            return Err(Error::InvalidOrderBook(format!("checksum mismatch, expected={}, actual={}", expected, actual)));
+        }
+    }
+    Ok(true)
+}
+
+/// CRC32 of the best 10 asks from the lowest price followed by the best 10 bids from the highest
+/// price. Each level contributes its price and volume, formatted with the precisions of the
+/// market, without the decimal point and the leading zeros.
+fn get_checksum(order_book: &OrderBook) -> Result<u32> {
+    let precision = order_book.market.precision.as_ref();
+    let (price_precision, amount_precision) = match (precision.and_then(|p| p.price), precision.and_then(|p| p.amount)) {
+        (Some(price), Some(amount)) => (price.max(0) as usize, amount.max(0) as usize),
+        _ => return Err(Error::InvalidOrderBook(format!("missing precision of {}", order_book.market))),
+    };
+    let mut hasher = crc32fast::Hasher::new();
+    for unit in order_book.asks.iter().take(CHECKSUM_DEPTH).chain(order_book.bids.iter().take(CHECKSUM_DEPTH)) {
+        for (value, precision) in [(unit.price, price_precision), (unit.amount, amount_precision)] {
+            let digits = format!("{:.*}", precision, value).replace('.', "");
+            hasher.update(digits.trim_start_matches('0').as_bytes());
+        }
+    }
+    Ok(hasher.finalize())
+}
+
+
+/// Envelope of every REST response. `result` is kept raw so that errors are reported from
+/// `error` even when the result does not match the expected type.
+#[derive(Serialize, Deserialize)]
+struct Response {
+    #[serde(default)]
+    error: Vec<String>,
+    #[serde(default)]
+    result: serde_json::Value,
+}
+
+impl Response {
+    fn into_result<T: DeserializeOwned>(self) -> Result<T> {
+        if let Some(error) = self.error.first() {
+            return Err(get_error(error));
+        }
+        serde_json::from_value(self.result).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchAssetsResponse {
+    altname: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchMarketsResponse {
+    altname: String,
+    /// not set for pairs that cannot be streamed
+    wsname: Option<String>,
+    base: String,
+    quote: String,
+    pair_decimals: isize,
+    lot_decimals: isize,
+    cost_decimals: Option<isize>,
+    ordermin: Option<String>,
+    costmin: Option<String>,
+    /// leverages available for margin buys
+    #[serde(default)]
+    leverage_buy: Vec<f64>,
+    status: Option<String>,
+}
+
+impl FetchMarketsResponse {
+    fn to_market(&self, assets: &AssetCodes) -> Result<Market> {
+        let amount = match &self.ordermin {
+            Some(min) => Some(Range { min: parse_float64(min)?, max: 0.0 }),
+            None => None,
+        };
+        let cost = match &self.costmin {
+            Some(min) => Some(Range { min: parse_float64(min)?, max: 0.0 }),
+            None => None,
+        };
+        let leverage = self.leverage_buy.iter().cloned().reduce(f64::max).map(|max| Range { min: 1.0, max });
+        Ok(Market {
+            base: assets.to_unified(&self.base),
+            quote: assets.to_unified(&self.quote),
+            active: self.status.as_deref().map(util::is_active).unwrap_or(true),
+            market_type: MarketType::Spot,
+            precision: Some(Precision {
+                price: Some(self.pair_decimals),
+                amount: Some(self.lot_decimals),
+                cost: self.cost_decimals,
+            }),
+            limit: Some(MarketLimit { amount, price: None, cost, leverage }),
+            ..Default::default()
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchTickersResponse {
+    /// ask price, whole lot volume and lot volume
+    a: [String; 3],
+    /// bid price, whole lot volume and lot volume
+    b: [String; 3],
+    /// last trade price and lot volume
+    c: [String; 2],
+    /// volume of today and of the last 24 hours
+    v: [String; 2],
+    /// volume weighted average price of today and of the last 24 hours
+    p: [String; 2],
+    l: [String; 2],
+    h: [String; 2],
+    /// opening price of today
+    o: String,
+}
+
+impl FetchTickersResponse {
+    fn to_ticker(&self, market: Market, timestamp: i64) -> Result<Ticker> {
+        let last = parse_float64(&self.c[0])?;
+        let open = parse_float64(&self.o)?;
+        let base_volume = parse_float64(&self.v[1])?;
+        let vwap = parse_float64(&self.p[1])?;
+        Ok(Ticker {
+            ask: Some(parse_float64(&self.a[0])?).filter(|ask| *ask > 0.0),
+            ask_volume: parse_float64(&self.a[2])?,
+            bid: Some(parse_float64(&self.b[0])?).filter(|bid| *bid > 0.0),
+            bid_volume: parse_float64(&self.b[2])?,
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume: base_volume * vwap,
+            change: last - open,
+            close: last,
+            high: parse_float64(&self.h[1])?,
+            low: parse_float64(&self.l[1])?,
+            last,
+            open,
+            percentage: match open > 0.0 {
+                true => (last - open) / open * 100f64,
+                false => 0.0,
+            },
+            previous_close: None,
+            vwap,
+            market,
+            timestamp,
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchOrderBookResponse {
+    /// price, volume and timestamp in seconds
+    asks: Vec<(String, String, i64)>,
+    bids: Vec<(String, String, i64)>,
+}
+
+
+/// Open time in seconds, open, high, low, close, vwap, volume and number of trades.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchOhlcvResponse(i64, String, String, String, String, String, String, i64);
+
+impl FetchOhlcvResponse {
+    fn to_ohlcv(&self, market: &Market, timeframe: Timeframe) -> Result<Ohlcv> {
+        let minutes = util::get_exchange_timeframe(&timeframe)?.parse::<i64>()?;
+        Ok(Ohlcv {
+            market: market.clone(),
+            timeframe,
+            timestamp: self.0 * 1000,
+            open: parse_float64(&self.1)?,
+            high: parse_float64(&self.2)?,
+            low: parse_float64(&self.3)?,
+            close: parse_float64(&self.4)?,
+            volume: parse_float64(&self.6)?,
+            is_closed: (self.0 + minutes * 60) * 1000 <= Utc::now().timestamp_millis(),
+        })
+    }
+}
+
+
+/// Price, volume, time in seconds, side, order type, miscellaneous and trade id.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchTradesResponse(String, String, f64, String, String, String, i64);
+
+impl FetchTradesResponse {
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = parse_number(&self.0)?;
+        let amount = parse_number(&self.1)?;
+        Ok(Trade::new(
+            self.6.to_string(),
+            (self.2 * 1000.0).round() as i64,
+            market.clone(),
+            None,
+            Some(match self.4.as_str() {
+                "m" => OrderType::Market,
+                _ => OrderType::Limit,
+            }),
+            Some(util::get_unified_order_side(&self.3)?),
+            None,
+            price, amount,
+            price * amount,
+            None, None,
+        ))
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchEventResponse {
+    event: String,
+    status: Option<String>,
+    error_message: Option<String>,
+}
+
+/// Price, volume, time in seconds and, for republished updates, `r`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct WatchOrderBookLevelResponse(String, String, String, #[serde(default)] Option<String>);
+
+impl WatchOrderBookLevelResponse {
+    fn to_order_book_unit(&self) -> Result<OrderBookUnit> {
+        Ok(OrderBookUnit { price: parse_number(&self.0)?, amount: parse_number(&self.1)? })
+    }
+}
+
+/// Snapshots list levels as `as` and `bs`, updates as `a` and `b`, with the checksum `c`.
+#[derive(Default, Serialize, Deserialize)]
+struct WatchOrderBookPayloadResponse {
+    #[serde(rename = "as")]
+    snapshot_asks: Option<Vec<WatchOrderBookLevelResponse>>,
+    #[serde(rename = "bs")]
+    snapshot_bids: Option<Vec<WatchOrderBookLevelResponse>>,
+    a: Option<Vec<WatchOrderBookLevelResponse>>,
+    b: Option<Vec<WatchOrderBookLevelResponse>>,
+    c: Option<String>,
+}
+
+/// Book event, merged from its payloads.
+#[derive(Default)]
+struct WatchOrderBookResponse {
+    snapshot: bool,
+    asks: Vec<WatchOrderBookLevelResponse>,
+    bids: Vec<WatchOrderBookLevelResponse>,
+    checksum: Option<String>,
+}
+
+impl WatchOrderBookResponse {
+    fn merge(&mut self, payload: WatchOrderBookPayloadResponse) {
+        self.snapshot |= payload.snapshot_asks.is_some() || payload.snapshot_bids.is_some();
+        for levels in [payload.snapshot_asks, payload.a].into_iter().flatten() {
+            self.asks.extend(levels);
+        }
+        for levels in [payload.snapshot_bids, payload.b].into_iter().flatten() {
+            self.bids.extend(levels);
+        }
+        if payload.c.is_some() {
+            self.checksum = payload.c;
+        }
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct FetchBalanceResponse {
+    balance: String,
+    /// amount on hold for open orders
+    hold_trade: Option<String>,
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct CreateOrderResponse {
+    txid: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CancelOrderResponse {
+    count: i64,
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Kraken, PropertiesBuilder};
+    use crate::error::Error;
+    use crate::exchange::Unifier;
+    use crate::model::{Market, MarketType, Precision};
+    use crate::util::order_book::LocalOrderBook;
+
+    use super::{apply_update, AssetCodes, FetchMarketsResponse, FetchTradesResponse, OrderBookItem, parse_order_book, Response};
+
+    #[test]
+    fn test_auth() {
+        let props = PropertiesBuilder::default()
+            .api_key(Some("key".into()))
+            .secret(Some("kQH5HW/8p1uGOVjbgWA7FunAmGO8lsSUXNsu3eow76sz84Q18fWxnyRzBHCd3pd5nE9qa99HAZtuZuj6F1huXg==".into()))
+            .build()
+            .unwrap();
+        let kraken = Kraken::new(props).unwrap();
+        let body = "nonce=1616492376594&ordertype=limit&pair=XBTUSD&price=37500&type=buy&volume=1.25";
+        let signature = kraken.auth("/0/private/AddOrder", "1616492376594", body).unwrap();
+        assert_eq!(signature, "4/dpxb3iT4tp/ZCVEwSnEsLxx0bqyhLpdfOpc6fn7OR8+UClSV5n9E6aSS8MPtnRfp32bAb0nmbRn6H8ndwLUQ==");
+
+        let first = kraken.nonce();
+        assert!(kraken.nonce() > first);
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let codes = [("XXBT".to_string(), "XBT".to_string()), ("ZUSD".to_string(), "USD".to_string()), ("XXDG".to_string(), "XDG".to_string())];
+        let assets = AssetCodes::new(codes.iter().map(|(code, altname)| (code, altname)));
+        assert_eq!(assets.to_unified("XXBT"), "BTC");
+        assert_eq!(assets.to_unified("XDG"), "DOGE");
+        assert_eq!(assets.to_unified("ZUSD"), "USD");
+        // not listed
+        assert_eq!(assets.to_unified("XETH"), "ETH");
+        assert_eq!(assets.to_unified("ZEUR"), "EUR");
+        assert_eq!(assets.to_unified("DOT"), "DOT");
+
+        let message = r#"{"altname": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency", "base": "XXBT", "aclass_quote": "currency", "quote": "ZUSD", "lot": "unit", "cost_decimals": 5, "pair_decimals": 1, "lot_decimals": 8, "lot_multiplier": 1, "leverage_buy": [2, 3, 4, 5], "leverage_sell": [2, 3, 4, 5], "fees": [[0, 0.26]], "fees_maker": [[0, 0.16]], "fee_volume_currency": "ZUSD", "margin_call": 80, "margin_stop": 40, "ordermin": "0.0001", "costmin": "0.5", "tick_size": "0.1", "status": "online"}"#;
+        let resp: FetchMarketsResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(&assets).unwrap();
+        assert_eq!(market.market_type, MarketType::Spot);
+        assert!(market.active);
+        assert_eq!(market.precision.as_ref().unwrap().price, Some(1));
+        assert_eq!(market.precision.as_ref().unwrap().amount, Some(8));
+        assert_eq!(market.limit.as_ref().unwrap().amount.as_ref().unwrap().min, 0.0001);
+        assert_eq!(market.limit.as_ref().unwrap().leverage.as_ref().unwrap().max, 5.0);
+        assert_eq!(format!("{}", market), "BTC/USD");
+    }
+
+    #[test]
+    fn test_apply_order_book_update() {
+        let market = Market {
+            base: "BTC".into(),
+            quote: "USD".into(),
+            market_type: MarketType::Spot,
+            precision: Some(Precision { price: Some(5), amount: Some(8), cost: None }),
+            ..Default::default()
+        };
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"XBT/USD".to_string());
+        let parse = |message: &str| match parse_order_book(message.as_bytes(), &unifier) {
+            Ok(OrderBookItem::Update(update)) => update.1,
+            _ => panic!("unexpected message {}", message),
+        };
+        let mut book = LocalOrderBook::new(market.clone());
+
+        let update = parse(r#"[1234, {"a": [["5541.30000", "0.00000000", "1534614335.345903"]]}, {"b": [["5541.20000", "2.00000000", "1534614335.345903", "r"]], "c": "3348111575"}, "book-10", "XBT/USD"]"#);
+        assert!(!apply_update(&mut book, &update, 10).unwrap());
+
+        let snapshot = parse(r#"[1234, {"as": [["5541.30000", "2.50700000", "1534614248.123678"], ["5541.80000", "0.33000000", "1534614098.345543"], ["5542.70000", "0.64700000", "1534614244.654432"]], "bs": [["5541.20000", "1.52900000", "1534614248.765567"], ["5539.90000", "0.30000000", "1534614241.769870"], ["5539.50000", "5.00000000", "1534613831.243486"]]}, "book-10", "XBT/USD"]"#);
+        assert!(apply_update(&mut book, &snapshot, 10).unwrap());
+        assert!(apply_update(&mut book, &update, 10).unwrap());
+        let order_book = book.to_order_book();
+        assert_eq!(order_book.asks.len(), 2);
+        assert_eq!(order_book.bids.len(), 3);
+        assert_eq!(order_book.timestamp, Some(1534614335346));
+
+        // keeps the subscribed depth
+        let update = parse(r#"[1234, {"b": [["5530.00000", "1.00000000", "1534614336.000000"]], "c": "3930919166"}, "book-2", "XBT/USD"]"#);
+        assert!(apply_update(&mut book, &update, 2).unwrap());
+        assert_eq!(book.to_order_book().bids.len(), 2);
+
+        let update = parse(r#"[1234, {"a": [["5541.80000", "1.00000000", "1534614337.000000"]], "c": "3348111575"}, "book-10", "XBT/USD"]"#);
+        assert!(matches!(apply_update(&mut book, &update, 10), Err(Error::InvalidOrderBook(_))));
+
+        let status = r#"{"errorMessage": "Currency pair not supported", "event": "subscriptionStatus", "pair": "XBT/EUR", "status": "error", "subscription": {"depth": 10, "name": "book"}}"#;
+        assert!(parse_order_book(status.as_bytes(), &unifier).is_err());
+        assert!(matches!(parse_order_book(r#"{"event": "heartbeat"}"#.as_bytes(), &unifier), Ok(OrderBookItem::Other)));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let response: Response = serde_json::from_str(r#"{"error": ["EOrder:Insufficient funds"]}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::InsufficientMargin(_))));
+
+        let response: Response = serde_json::from_str(r#"{"error": ["EQuery:Unknown asset pair"]}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::SymbolNotFound(_))));
+
+        let response: Response = serde_json::from_str(r#"{"error": [], "result": {"count": 1}}"#).unwrap();
+        assert!(response.into_result::<serde_json::Value>().is_ok());
+    }
+
+    #[test]
+    fn test_to_trade() {
+        let market = Market { base: "BTC".into(), quote: "USD".into(), ..Default::default() };
+        let resp: FetchTradesResponse = serde_json::from_str(r#"["30243.40000", "0.34507674", 1688669597.8277369, "b", "m", "", 61044952]"#).unwrap();
+        let trade = resp.to_trade(&market).unwrap();
+        assert_eq!(trade.id, "61044952");
+        assert_eq!(trade.timestamp, 1688669597828);
+        assert_eq!(trade.side, Some(crate::model::OrderSide::Buy));
+    }
+}
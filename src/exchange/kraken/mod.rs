@@ -0,0 +1,5 @@
+pub use kraken::Kraken;
+
+#[allow(clippy::module_inception)]
+mod kraken;
+mod util;
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
+use crate::model::{OrderSide, OrderType, TimeInForce, Timeframe};
+
+/// Alternative names of the exchange that differ from the common asset codes.
+const COMMON_ASSET_CODES: [(&str, &str); 2] = [("XBT", "BTC"), ("XDG", "DOGE")];
+
+/// Unified asset codes by the asset codes of the exchange, e.g. `XXBT` and `XBT` to `BTC` and
+/// `ZUSD` to `USD`.
+#[derive(Clone, Debug, Default)]
+pub(in super) struct AssetCodes {
+    codes: HashMap<String, String>,
+}
+
+impl AssetCodes {
+    /// Builds the map from the asset codes and alternative names listed by the exchange.
+    pub(in super) fn new<'a>(assets: impl Iterator<Item=(&'a String, &'a String)>) -> Self {
+        let mut codes = HashMap::new();
+        for (code, altname) in assets {
+            let unified = get_common_asset_code(altname);
+            codes.insert(altname.clone(), unified.clone());
+            codes.insert(code.clone(), unified);
+        }
+        Self { codes }
+    }
+
+    /// Codes that are not listed, such as those of staked or earning balances, fall back to the
+    /// legacy convention of a four letter code prefixed with `X` for crypto and `Z` for fiat.
+    pub(in super) fn to_unified(&self, exchange_asset: &str) -> String {
+        if let Some(unified) = self.codes.get(exchange_asset) {
+            return unified.clone();
+        }
+        let asset = exchange_asset.to_uppercase();
+        let asset = match asset.len() == 4 && (asset.starts_with('X') || asset.starts_with('Z')) {
+            true => &asset[1..],
+            false => asset.as_str(),
+        };
+        get_common_asset_code(asset)
+    }
+}
+
+fn get_common_asset_code(asset: &str) -> String {
+    COMMON_ASSET_CODES.iter()
+        .find(|(code, _)| *code == asset)
+        .map(|(_, common)| common.to_string())
+        .unwrap_or_else(|| asset.to_uppercase())
+}
+
+pub(in super) fn is_active(status: &str) -> bool {
+    status == "online"
+}
+
+pub(in super) fn get_exchange_order_side(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+/// Sides of trades are abbreviated.
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "b" | "buy" => Ok(OrderSide::Buy),
+        "s" | "sell" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+pub(in super) fn get_exchange_order_type(order_type: &OrderType) -> Result<&'static str> {
+    match order_type {
+        OrderType::Market => Ok("market"),
+        OrderType::Limit => Ok("limit"),
+        _ => Err(Error::UnsupportedOrderType(order_type.to_string())),
+    }
+}
+
+/// Post-only orders are good till canceled and flagged with `oflags`.
+pub(in super) fn get_exchange_time_in_force(time_in_force: &TimeInForce) -> Result<&'static str> {
+    match time_in_force {
+        TimeInForce::GTC | TimeInForce::PO => Ok("GTC"),
+        TimeInForce::IOC => Ok("IOC"),
+        _ => Err(Error::UnsupportedTimeInForce(format!("{:?}", time_in_force))),
+    }
+}
+
+/// Candle length in minutes.
+pub(in super) fn get_exchange_timeframe(timeframe: &Timeframe) -> Result<&'static str> {
+    match timeframe {
+        Timeframe::Minute1 => Ok("1"),
+        Timeframe::Minute5 => Ok("5"),
+        Timeframe::Minute15 => Ok("15"),
+        Timeframe::Minute30 => Ok("30"),
+        Timeframe::Hour1 => Ok("60"),
+        Timeframe::Hour4 => Ok("240"),
+        Timeframe::Day1 => Ok("1440"),
+        Timeframe::Week1 => Ok("10080"),
+        _ => Err(Error::UnsupportedTimeframe(timeframe.to_string())),
+    }
+}
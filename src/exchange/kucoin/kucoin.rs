@@ -0,0 +1,1155 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use futures_util::stream::SelectAll;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+
+use crate::client::{EMPTY_BODY, EMPTY_QUERY};
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{f64_to_number, into_precision, number_to_f64, parse_float64, parse_number, ZERO};
+use crate::util::channel::{Receiver, Sender};
+use crate::util::rate_limiter::RateLimit;
+
+use super::util;
+use super::util::Product;
+
+const FUTURES_HOST: &str = "https://api-futures.kucoin.com";
+
+/// maximum number of symbols in a topic
+const SUBSCRIPTION_CHUNK_SIZE: usize = 100;
+
+/// version of the API key, whose passphrase is signed with the secret
+const API_KEY_VERSION: &str = "2";
+
+pub struct Kucoin {
+    exchange_base: ExchangeBase,
+    /// futures are served by their own host
+    futures_client: HttpClient,
+    api_key: Option<String>,
+    secret: Option<String>,
+    passphrase: Option<String>,
+}
+
+impl Kucoin {
+    /// `passphrase` is required in addition to `api_key` and `secret` for private methods.
+    ///
+    /// Streams connect to the servers returned with a token by the bullet endpoints of spot and
+    /// futures, and reconnections reuse that token.
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        if props.sandbox {
+            return Err(Error::UnsupportedInSandbox("the sandbox is discontinued".into()).into());
+        }
+        // requests are limited per resource pool over a rolling 30 seconds window
+        let rate_limits = vec![RateLimit { interval: Duration::from_secs(30), limit: 1000, header: None }];
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some("https://api.kucoin.com".to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some("wss://ws-api-spot.kucoin.com".to_string())))
+            .error_parser(Some(parse_error))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            .rate_limits(Some(rate_limits.clone()))
+            .build()?;
+        let futures_client = HttpClientBuilder::new()
+            .host(FUTURES_HOST.to_string())
+            .port(props.port.unwrap_or(443))
+            .error_parser(Some(parse_error))
+            .rate_limits(rate_limits)
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            futures_client,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            passphrase: props.passphrase.clone(),
+        })
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.api_key.is_some() && self.secret.is_some() && self.passphrase.is_some()
+    }
+
+    fn http_client(&self, product: Product) -> &HttpClient {
+        match product {
+            Product::Spot => &self.exchange_base.http_client,
+            Product::Futures => &self.futures_client,
+        }
+    }
+
+    /// Timestamp for signed requests, on the exchange clock when time sync is enabled.
+    async fn timestamp(&self) -> Result<i64> {
+        let clock = &self.exchange_base.clock;
+        if clock.needs_sync() {
+            let sent = Utc::now().timestamp_millis();
+            let server_time: i64 = self.get(Product::Spot, "/api/v1/timestamp", &vec![]).await?;
+            let received = Utc::now().timestamp_millis();
+            clock.update(sent, server_time, received);
+        }
+        Ok(clock.timestamp())
+    }
+
+    /// Signs `timestamp + method + endpoint + body` and encodes the signature in base64. The
+    /// endpoint includes the query string.
+    fn auth(&self, timestamp: &str, method: &str, endpoint: &str, body: &str) -> Result<String> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials);
+        }
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(timestamp.as_bytes());
+        signed_key.update(method.as_bytes());
+        signed_key.update(endpoint.as_bytes());
+        signed_key.update(body.as_bytes());
+        Ok(BASE64.encode(signed_key.finalize().into_bytes()))
+    }
+
+    /// The passphrase is sent signed with the secret, in base64.
+    fn signed_passphrase(&self) -> Result<String> {
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(self.passphrase.as_ref().unwrap().as_bytes());
+        Ok(BASE64.encode(signed_key.finalize().into_bytes()))
+    }
+
+    /// Product and symbol id of a loaded market.
+    fn get_symbol_id(&self, market: &Market) -> Result<(Product, String)> {
+        let product = Product::of(market)?;
+        let symbol_id = self.exchange_base.unifier.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))?;
+        Ok((product, symbol_id))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, product: Product, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let response: Response = self.http_client(product).get(path, None, Some(query)).await?;
+        response.into_result()
+    }
+
+    async fn signed_get<T: DeserializeOwned>(&self, product: Product, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let timestamp = self.timestamp().await?.to_string();
+        let endpoint = match query.is_empty() {
+            true => path.to_string(),
+            false => {
+                let query = query.iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<String>>()
+                    .join("&");
+                format!("{}?{}", path, query)
+            }
+        };
+        let signature = self.auth(&timestamp, "GET", &endpoint, "")?;
+        let passphrase = self.signed_passphrase()?;
+        let headers = vec![
+            ("KC-API-KEY", self.api_key.as_ref().unwrap().as_str()),
+            ("KC-API-SIGN", signature.as_str()),
+            ("KC-API-TIMESTAMP", timestamp.as_str()),
+            ("KC-API-PASSPHRASE", passphrase.as_str()),
+            ("KC-API-KEY-VERSION", API_KEY_VERSION),
+        ];
+        let response: Response = self.http_client(product).get(path, Some(headers), Some(query)).await?;
+        response.into_result()
+    }
+
+    async fn signed_post<T: DeserializeOwned>(&self, product: Product, path: &str, body: &String) -> Result<T> {
+        let timestamp = self.timestamp().await?.to_string();
+        let signature = self.auth(&timestamp, "POST", path, body)?;
+        let passphrase = self.signed_passphrase()?;
+        let headers = vec![
+            ("Content-Type", "application/json"),
+            ("KC-API-KEY", self.api_key.as_ref().unwrap().as_str()),
+            ("KC-API-SIGN", signature.as_str()),
+            ("KC-API-TIMESTAMP", timestamp.as_str()),
+            ("KC-API-PASSPHRASE", passphrase.as_str()),
+            ("KC-API-KEY-VERSION", API_KEY_VERSION),
+        ];
+        let response: Response = self.http_client(product).post(path, Some(headers), EMPTY_QUERY, Some(body)).await?;
+        response.into_result()
+    }
+
+    async fn fetch_contracts(&self) -> Result<Vec<FetchContractsResponse>> {
+        self.get(Product::Futures, "/api/v1/contracts/active", &vec![]).await
+    }
+
+    async fn fetch_all_markets(&self) -> Result<Vec<(String, Market)>> {
+        let mut markets = vec![];
+        let symbols: Vec<FetchSymbolsResponse> = self.get(Product::Spot, "/api/v2/symbols", &vec![]).await?;
+        for symbol in symbols {
+            markets.push((symbol.symbol.clone(), symbol.to_market()?));
+        }
+        for contract in self.fetch_contracts().await? {
+            markets.push((contract.symbol.clone(), contract.to_market()?));
+        }
+        Ok(markets)
+    }
+
+    /// Subscribes to the topics of the markets, over a connection per product bootstrapped with
+    /// a public token. The items of both connections are merged into a single receiver.
+    async fn subscribe(&self, spot_topic: &str, futures_topic: &str, markets: &[Market]) -> WatchResult<Receiver> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut symbol_ids: HashMap<Product, Vec<String>> = HashMap::new();
+        for market in markets {
+            let (product, symbol_id) = self.get_symbol_id(market).map_err(|_| WatchError::SymbolNotFound(format!("{:?}", market)))?;
+            symbol_ids.entry(product).or_default().push(symbol_id);
+        }
+
+        let mut clients = vec![];
+        let mut ping_interval = Duration::MAX;
+        for (product, symbol_ids) in symbol_ids {
+            let bullet: BulletResponse = {
+                let response: Response = self.http_client(product).post("/api/v1/bullet-public", None, EMPTY_QUERY, EMPTY_BODY).await?;
+                response.into_result()?
+            };
+            let server = bullet.instance_servers.first().ok_or_else(|| Error::InvalidResponse("no instance server".into()))?;
+            ping_interval = ping_interval.min(Duration::from_millis(server.ping_interval));
+
+            let endpoint = format!("{}?token={}&connectId={}", server.endpoint, bullet.token, random_id());
+            let mut ws_client = self.exchange_base.ws_client(endpoint.as_str(), parse_stream, self.exchange_base.unifier.clone());
+            let topic = match product {
+                Product::Spot => spot_topic,
+                Product::Futures => futures_topic,
+            };
+            for symbol_ids in symbol_ids.chunks(SUBSCRIPTION_CHUNK_SIZE) {
+                let request = SubscribeRequest {
+                    id: random_id(),
+                    message_type: "subscribe",
+                    topic: format!("{}:{}", topic, symbol_ids.join(",")),
+                    private_channel: false,
+                    response: true,
+                };
+                let message = serde_json::to_string(&request).map_err(|e| WatchError::UnknownError(format!("{}", e)))?;
+                let _ = ws_client.send(message).await?;
+            }
+            clients.push(ws_client);
+        }
+
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(forward_items(futures_util::stream::select_all(clients), ping_interval, tx));
+        Ok(receiver)
+    }
+}
+
+#[async_trait]
+impl Exchange for Kucoin {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        if self.exchange_base.markets.is_empty() {
+            let result = self.fetch_all_markets().await?;
+            self.exchange_base.unifier.reset();
+            let mut markets = vec![];
+            for (symbol_id, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &symbol_id);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.fetch_all_markets().await?;
+        Ok(result.into_iter().map(|(_, market)| market).collect())
+    }
+
+    /// Tickers of futures are the statistics of the contracts, without the best bid and ask.
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let products = match params.markets.as_ref() {
+            Some(markets) => {
+                let mut products = vec![];
+                for market in markets {
+                    let (product, _) = self.get_symbol_id(market)?;
+                    if !products.contains(&product) {
+                        products.push(product);
+                    }
+                }
+                products
+            }
+            None => Product::ALL.to_vec(),
+        };
+
+        let unifier = &self.exchange_base.unifier;
+        let is_requested = |market: &Market| params.markets.as_ref().is_none_or(|markets| markets.contains(market));
+        let mut tickers = vec![];
+        for product in products {
+            match product {
+                Product::Spot => {
+                    let result: FetchTickersResponse = self.get(product, "/api/v1/market/allTickers", &vec![]).await?;
+                    for item in result.ticker {
+                        match unifier.get_market(&item.symbol) {
+                            Some(market) if is_requested(&market) => tickers.push(item.to_ticker(market, result.time)?),
+                            _ => continue,
+                        }
+                    }
+                }
+                Product::Futures => {
+                    let timestamp = Utc::now().timestamp_millis();
+                    for contract in self.fetch_contracts().await? {
+                        match unifier.get_market(&contract.symbol) {
+                            Some(market) if is_requested(&market) => tickers.push(contract.to_ticker(market, timestamp)),
+                            _ => continue,
+                        }
+                    }
+                }
+            }
+        }
+        Ok(tickers)
+    }
+
+    async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let (product, symbol_id) = self.get_symbol_id(&params.market)?;
+        let query = vec![("symbol", symbol_id.as_str())];
+        let trades = match product {
+            Product::Spot => {
+                let result: Vec<FetchSpotTradesResponse> = self.get(product, "/api/v1/market/histories", &query).await?;
+                result.iter().map(|item| item.to_trade(&params.market)).collect::<Result<Vec<Trade>>>()?
+            }
+            Product::Futures => {
+                let result: Vec<FuturesTradeResponse> = self.get(product, "/api/v1/trade/history", &query).await?;
+                result.iter().map(|item| item.to_trade(&params.market)).collect::<Result<Vec<Trade>>>()?
+            }
+        };
+
+        // the 100 most recent trades are served, so the range and the limit are applied locally
+        let trades = trades.into_iter()
+            .filter(|trade| params.since.is_none_or(|since| trade.timestamp >= since) && params.until.is_none_or(|until| trade.timestamp <= until));
+        Ok(match params.limit {
+            Some(limit) => trades.take(limit.max(0) as usize).collect(),
+            None => trades.collect(),
+        })
+    }
+
+    /// Streams the best bid and ask. Full-depth books are not supported.
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        if params.full_depth {
+            return Err(WatchError::NotImplemented);
+        }
+        self.subscribe("/market/ticker", "/contractMarket/tickerV2", &params.markets).await
+    }
+
+    async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
+        self.subscribe("/market/match", "/contractMarket/execution", &params.markets).await
+    }
+
+    /// Fetches the spot trading account.
+    async fn fetch_balance(&self, params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if params.margin_mode.is_some() {
+            return Err(Error::InvalidParameters("margin mode is not supported".into()).into());
+        }
+
+        let query = vec![("type", "trade")];
+        let result: Vec<FetchBalanceResponse> = self.signed_get(Product::Spot, "/api/v1/accounts", &query).await?;
+        let mut balance = Balance::default();
+        for item in result {
+            balance.items.push(item.to_balance_item()?);
+        }
+        Ok(balance)
+    }
+
+    /// Amounts of futures are whole numbers of contracts, and positions are opened at a
+    /// leverage of 1.
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let (product, symbol_id) = self.get_symbol_id(&params.market)?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.exchange_base.unifier.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+
+        let is_market = order_type == OrderType::Market;
+        let time_in_force = params.time_in_force.unwrap_or(TimeInForce::GTC);
+        let client_oid = random_id();
+        let body = match product {
+            Product::Spot => serde_json::to_string(&CreateSpotOrderRequest {
+                client_oid: client_oid.clone(),
+                side: util::get_exchange_order_side(&params.order_side),
+                symbol: symbol_id,
+                order_type: util::get_exchange_order_type(&order_type)?,
+                size: amount.to_string(),
+                price: price.filter(|_| !is_market).map(|price| price.to_string()),
+                time_in_force: match is_market {
+                    true => None,
+                    false => Some(util::get_exchange_time_in_force(&time_in_force, product)?),
+                },
+                post_only: Some(true).filter(|_| time_in_force == TimeInForce::PO),
+            }),
+            Product::Futures => {
+                let contracts = number_to_f64(amount);
+                if contracts.fract() != 0.0 {
+                    return Err(Error::InvalidAmount(format!("{} is not a whole number of contracts", amount)).into());
+                }
+                serde_json::to_string(&CreateFuturesOrderRequest {
+                    client_oid: client_oid.clone(),
+                    side: util::get_exchange_order_side(&params.order_side),
+                    symbol: symbol_id,
+                    order_type: util::get_exchange_order_type(&order_type)?,
+                    leverage: 1,
+                    size: contracts as i64,
+                    price: price.filter(|_| !is_market).map(|price| price.to_string()),
+                    time_in_force: match is_market {
+                        true => None,
+                        false => Some(util::get_exchange_time_in_force(&time_in_force, product)?),
+                    },
+                    post_only: Some(true).filter(|_| time_in_force == TimeInForce::PO),
+                    reduce_only: Some(true).filter(|_| params.reduce_only),
+                    margin_mode: params.margin_mode.as_ref().map(|margin_mode| match margin_mode {
+                        MarginMode::Cross => "CROSS",
+                        MarginMode::Isolated => "ISOLATED",
+                    }),
+                })
+            }
+        }.map_err(|e| Error::InvalidParameters(format!("{}", e)))?;
+        let response: CreateOrderResponse = self.signed_post(product, "/api/v1/orders", &body).await?;
+        Ok(Order {
+            id: Some(response.order_id),
+            client_order_id: Some(client_oid),
+            timestamp: Utc::now().timestamp_millis(),
+            status: OrderStatus::Open,
+            market: params.market.clone(),
+            order_type,
+            time_in_force: match is_market {
+                true => None,
+                false => Some(time_in_force),
+            },
+            side: Some(params.order_side),
+            price: price.filter(|_| !is_market),
+            amount,
+            margin_mode: params.margin_mode.unwrap_or(MarginMode::Isolated),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Maps the error code of a response to an error.
+fn get_error(code: &str, msg: String) -> Error {
+    match code {
+        "400001" | "400003" | "400004" | "411100" => Error::CredentialsError(msg), // Any of KC-API-KEY, KC-API-SIGN, KC-API-TIMESTAMP, KC-API-PASSPHRASE is missing / KC-API-KEY not exists / KC-API-PASSPHRASE error / User is frozen
+        "400002" => Error::TimestampOutsideRecvWindow(msg), // KC-API-TIMESTAMP Invalid
+        "400005" => Error::InvalidSignature(msg), // Signature error
+        "400100" => Error::InvalidParameters(msg), // Parameter error
+        "900001" => Error::SymbolNotFound(msg), // Symbol not exists
+        "200004" | "300003" => Error::InsufficientMargin(msg), // Balance insufficient
+        "400400" | "404000" => Error::OrderNotFound(msg), // Order does not exist
+        _ => Error::HttpError(format!("code={}, msg={}", code, msg)),
+    }
+}
+
+/// Parses the body of a failed request of either host.
+fn parse_error(message: String) -> Error {
+    match serde_json::from_str::<Response>(&message) {
+        Ok(response) => get_error(&response.code, response.msg),
+        Err(_) => Error::HttpError(message),
+    }
+}
+
+/// Random 16 bytes in hex, for client order ids and the ids of stream messages.
+fn random_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Value in the quote currency of spot and linear trades, and in the base currency of inverse
+/// trades.
+fn get_cost(market: &Market, price: Number, amount: Number) -> Number {
+    let contract_size = f64_to_number(market.contract_size.unwrap_or(1.0));
+    match market.contract_type {
+        Some(ContractType::Inverse) if price == ZERO => ZERO,
+        Some(ContractType::Inverse) => amount * contract_size / price,
+        _ => amount * price * contract_size,
+    }
+}
+
+/// Book of the best bid and ask, a side being empty without a price.
+fn to_best_order_book(market: Market, bid: (&str, f64), ask: (&str, f64), timestamp: i64, sequence: i64) -> Result<OrderBook> {
+    let unit = |(price, amount): (&str, f64)| -> Result<Vec<OrderBookUnit>> {
+        match price {
+            "" => Ok(vec![]),
+            price => Ok(vec![OrderBookUnit { price: parse_number(price)?, amount: f64_to_number(amount) }]),
+        }
+    };
+    Ok(OrderBook::new(unit(bid)?, unit(ask)?, market, Some(timestamp), Some(sequence)))
+}
+
+/// Forwards the items of the connections one by one to the receiver until it is dropped, and
+/// pings the connections, which are otherwise closed by the exchange.
+async fn forward_items(mut clients: SelectAll<WsClient<Vec<StreamItem>>>, ping_interval: Duration, tx: Sender) {
+    let mut ping = tokio::time::interval(ping_interval);
+    loop {
+        let items = tokio::select! {
+            items = clients.next() => match items {
+                Some(Ok(items)) => items.into_iter().map(Ok).collect(),
+                Some(Err(e)) => vec![Err(e)],
+                None => return,
+            },
+            _ = ping.tick() => {
+                for client in clients.iter_mut() {
+                    let message = format!("{{\"id\": \"{}\", \"type\": \"ping\"}}", random_id());
+                    // a connection being re-established is skipped
+                    let _ = client.send_message(message, false).await;
+                }
+                continue;
+            }
+        };
+        for item in items {
+            if tx.send(item).await.is_err() { // the receiver is dropped
+                return;
+            }
+        }
+    }
+}
+
+/// Parses a topic message into a stream item. Pongs carry no item.
+fn parse_stream(message: &[u8], unifier: &Unifier) -> WatchResult<Vec<StreamItem>> {
+    let push = WatchPushResponse::try_from(message.to_vec())?;
+    match push.message_type.as_str() {
+        "error" => return Err(Error::StreamError(format!("code={}, data={}", push.code.unwrap_or_default(), push.data)).into()),
+        "pong" => return Ok(vec![]),
+        "message" => {}
+        _ => return Ok(vec![StreamItem::Unknown(String::from_utf8_lossy(message).to_string())]),
+    }
+    // a message is published on the topic of a single symbol
+    let (topic, symbol_id) = match push.topic.as_deref().and_then(|topic| topic.split_once(':')) {
+        Some((topic, symbol_id)) => (topic, symbol_id.to_string()),
+        None => return Ok(vec![StreamItem::Unknown(String::from_utf8_lossy(message).to_string())]),
+    };
+    let market = unifier.get_market(&symbol_id).ok_or(WatchError::SymbolNotFound(symbol_id))?;
+    let deserialize_error = |e: serde_json::Error| Error::DeserializeJsonBody(format!("{:?}", e));
+
+    let item = match topic {
+        "/market/ticker" => {
+            let data = serde_json::from_value::<WatchSpotTickerResponse>(push.data).map_err(deserialize_error)?;
+            StreamItem::OrderBook(data.to_order_book(market.clone()).map_err(|e| OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market))))
+        }
+        "/contractMarket/tickerV2" => {
+            let data = serde_json::from_value::<WatchFuturesTickerResponse>(push.data).map_err(deserialize_error)?;
+            StreamItem::OrderBook(data.to_order_book(market.clone()).map_err(|e| OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market))))
+        }
+        "/market/match" => {
+            let data = serde_json::from_value::<WatchSpotTradeResponse>(push.data).map_err(deserialize_error)?;
+            StreamItem::Trade(data.to_trade(&market).map_err(TradeError::from))
+        }
+        "/contractMarket/execution" => {
+            let data = serde_json::from_value::<FuturesTradeResponse>(push.data).map_err(deserialize_error)?;
+            StreamItem::Trade(data.to_trade(&market).map_err(TradeError::from))
+        }
+        _ => StreamItem::Unknown(String::from_utf8_lossy(message).to_string()),
+    };
+    Ok(vec![item])
+}
+
+
+/// Envelope of every REST response. `data` is kept raw so that errors are reported from `code`
+/// even when the data does not match the expected type.
+#[derive(Serialize, Deserialize)]
+struct Response {
+    code: String,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+impl Response {
+    fn into_result<T: DeserializeOwned>(self) -> Result<T> {
+        if self.code != "200000" {
+            return Err(get_error(&self.code, self.msg));
+        }
+        serde_json::from_value(self.data).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FetchSymbolsResponse {
+    symbol: String,
+    base_currency: String,
+    quote_currency: String,
+    base_min_size: String,
+    base_max_size: String,
+    quote_min_size: String,
+    quote_max_size: String,
+    base_increment: String,
+    price_increment: String,
+    enable_trading: bool,
+}
+
+impl FetchSymbolsResponse {
+    fn to_market(&self) -> Result<Market> {
+        Ok(Market {
+            base: util::to_unified_asset(&self.base_currency),
+            quote: util::to_unified_asset(&self.quote_currency),
+            active: self.enable_trading,
+            market_type: MarketType::Spot,
+            precision: Some(Precision {
+                amount: Some(into_precision(self.base_increment.clone())?),
+                price: Some(into_precision(self.price_increment.clone())?),
+                cost: None,
+            }),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: parse_float64(&self.base_min_size)?, max: parse_float64(&self.base_max_size)? }),
+                price: None,
+                cost: Some(Range { min: parse_float64(&self.quote_min_size)?, max: parse_float64(&self.quote_max_size)? }),
+                leverage: None,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+/// Active contract with the statistics of the last 24 hours. Orders are sized in lots of
+/// `multiplier`, which is negative for inverse contracts.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FetchContractsResponse {
+    symbol: String,
+    /// `FFWCSX` for perpetuals and `FFICSX` for futures
+    #[serde(rename = "type")]
+    contract_kind: String,
+    base_currency: String,
+    quote_currency: String,
+    settle_currency: String,
+    expire_date: Option<i64>,
+    lot_size: f64,
+    tick_size: f64,
+    multiplier: f64,
+    is_inverse: bool,
+    max_order_qty: f64,
+    max_leverage: f64,
+    status: String,
+    last_trade_price: Option<f64>,
+    high_price: Option<f64>,
+    low_price: Option<f64>,
+    /// ratio, not percent
+    price_chg_pct: Option<f64>,
+    volume_of24h: Option<f64>,
+    turnover_of24h: Option<f64>,
+}
+
+impl FetchContractsResponse {
+    fn to_market(&self) -> Result<Market> {
+        let (market_type, expiry) = match self.contract_kind.as_str() {
+            "FFICSX" => (MarketType::Future, self.expire_date),
+            _ => (MarketType::Swap, None),
+        };
+        Ok(Market {
+            base: util::to_unified_asset(&self.base_currency),
+            quote: util::to_unified_asset(&self.quote_currency),
+            active: self.status == "Open",
+            market_type,
+            settle: Some(util::to_unified_asset(&self.settle_currency)),
+            contract_size: Some(self.multiplier.abs()),
+            contract_type: Some(match self.is_inverse {
+                true => ContractType::Inverse,
+                false => ContractType::Linear,
+            }),
+            expiry,
+            precision: Some(Precision {
+                amount: Some(into_precision(self.lot_size.to_string())?),
+                price: Some(into_precision(self.tick_size.to_string())?),
+                cost: None,
+            }),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: self.lot_size, max: self.max_order_qty }),
+                price: None,
+                cost: None,
+                leverage: Some(Range { min: 1.0, max: self.max_leverage }),
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn to_ticker(&self, market: Market, timestamp: i64) -> Ticker {
+        let last = self.last_trade_price.unwrap_or_default();
+        let change_ratio = self.price_chg_pct.unwrap_or_default();
+        let open = match change_ratio > -1.0 {
+            true => last / (1f64 + change_ratio),
+            false => 0.0,
+        };
+        let base_volume = self.volume_of24h.unwrap_or_default();
+        let quote_volume = self.turnover_of24h.unwrap_or_default();
+        Ticker {
+            ask: None,
+            ask_volume: 0.0,
+            bid: None,
+            bid_volume: 0.0,
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume,
+            change: last - open,
+            close: last,
+            high: self.high_price.unwrap_or_default(),
+            low: self.low_price.unwrap_or_default(),
+            last,
+            open,
+            percentage: change_ratio * 100f64,
+            previous_close: None,
+            vwap: match base_volume > 0.0 {
+                true => quote_volume / base_volume,
+                false => 0.0,
+            },
+            market,
+            timestamp,
+        }
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchTickersResponse {
+    time: i64,
+    ticker: Vec<FetchTickersItemResponse>,
+}
+
+/// Fields are null for markets without trades.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FetchTickersItemResponse {
+    symbol: String,
+    buy: Option<String>,
+    best_bid_size: Option<String>,
+    sell: Option<String>,
+    best_ask_size: Option<String>,
+    /// ratio, not percent
+    change_rate: Option<String>,
+    change_price: Option<String>,
+    high: Option<String>,
+    low: Option<String>,
+    vol: Option<String>,
+    vol_value: Option<String>,
+    last: Option<String>,
+}
+
+impl FetchTickersItemResponse {
+    fn to_ticker(&self, market: Market, timestamp: i64) -> Result<Ticker> {
+        let last = util::parse_optional_float(self.last.as_deref())?;
+        let change = util::parse_optional_float(self.change_price.as_deref())?;
+        let open = last - change;
+        let base_volume = util::parse_optional_float(self.vol.as_deref())?;
+        let quote_volume = util::parse_optional_float(self.vol_value.as_deref())?;
+        Ok(Ticker {
+            ask: Some(util::parse_optional_float(self.sell.as_deref())?).filter(|ask| *ask > 0.0),
+            ask_volume: util::parse_optional_float(self.best_ask_size.as_deref())?,
+            bid: Some(util::parse_optional_float(self.buy.as_deref())?).filter(|bid| *bid > 0.0),
+            bid_volume: util::parse_optional_float(self.best_bid_size.as_deref())?,
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume,
+            change,
+            close: last,
+            high: util::parse_optional_float(self.high.as_deref())?,
+            low: util::parse_optional_float(self.low.as_deref())?,
+            last,
+            open,
+            percentage: util::parse_optional_float(self.change_rate.as_deref())? * 100f64,
+            previous_close: None,
+            vwap: match base_volume > 0.0 {
+                true => quote_volume / base_volume,
+                false => 0.0,
+            },
+            market,
+            timestamp,
+        })
+    }
+}
+
+
+/// Spot trades have no id, the sequence is used instead.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchSpotTradesResponse {
+    sequence: String,
+    price: String,
+    size: String,
+    /// taker side
+    side: String,
+    /// nanoseconds
+    time: i64,
+}
+
+impl FetchSpotTradesResponse {
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = parse_number(&self.price)?;
+        let amount = parse_number(&self.size)?;
+        Ok(Trade::new(
+            self.sequence.clone(),
+            self.time / 1_000_000,
+            market.clone(),
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.side)?),
+            None,
+            price, amount,
+            price * amount,
+            None, None,
+        ))
+    }
+}
+
+/// Trade of the REST endpoint and of the execution topic of futures.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FuturesTradeResponse {
+    trade_id: String,
+    price: String,
+    /// in lots
+    size: f64,
+    /// taker side
+    side: String,
+    /// nanoseconds
+    ts: i64,
+}
+
+impl FuturesTradeResponse {
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = parse_number(&self.price)?;
+        let amount = f64_to_number(self.size);
+        Ok(Trade::new(
+            self.trade_id.clone(),
+            self.ts / 1_000_000,
+            market.clone(),
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.side)?),
+            None,
+            price, amount,
+            get_cost(market, price, amount),
+            None, None,
+        ))
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BulletResponse {
+    token: String,
+    instance_servers: Vec<InstanceServerResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstanceServerResponse {
+    endpoint: String,
+    /// milliseconds
+    ping_interval: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SubscribeRequest {
+    id: String,
+    #[serde(rename = "type")]
+    message_type: &'static str,
+    /// topic followed by the comma separated symbols, e.g. `/market/match:BTC-USDT,ETH-USDT`
+    topic: String,
+    private_channel: bool,
+    /// acknowledge the subscription
+    response: bool,
+}
+
+/// Topic message, or one of the `welcome`, `ack`, `pong` and `error` messages of the connection.
+#[derive(Serialize, Deserialize)]
+struct WatchPushResponse {
+    #[serde(rename = "type")]
+    message_type: String,
+    topic: Option<String>,
+    code: Option<serde_json::Value>,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+impl TryFrom<Vec<u8>> for WatchPushResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct WatchSpotTickerResponse {
+    sequence: String,
+    best_bid: String,
+    best_bid_size: String,
+    best_ask: String,
+    best_ask_size: String,
+    /// milliseconds
+    time: i64,
+}
+
+impl WatchSpotTickerResponse {
+    fn to_order_book(&self, market: Market) -> Result<OrderBook> {
+        to_best_order_book(
+            market,
+            (&self.best_bid, util::parse_optional_float(Some(&self.best_bid_size))?),
+            (&self.best_ask, util::parse_optional_float(Some(&self.best_ask_size))?),
+            self.time,
+            self.sequence.parse()?,
+        )
+    }
+}
+
+/// Sizes are in lots.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct WatchFuturesTickerResponse {
+    sequence: i64,
+    best_bid_price: String,
+    best_bid_size: f64,
+    best_ask_price: String,
+    best_ask_size: f64,
+    /// nanoseconds
+    ts: i64,
+}
+
+impl WatchFuturesTickerResponse {
+    fn to_order_book(&self, market: Market) -> Result<OrderBook> {
+        to_best_order_book(market, (&self.best_bid_price, self.best_bid_size), (&self.best_ask_price, self.best_ask_size), self.ts / 1_000_000, self.sequence)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchSpotTradeResponse {
+    trade_id: String,
+    price: String,
+    size: String,
+    /// taker side
+    side: String,
+    /// nanoseconds
+    time: String,
+}
+
+impl WatchSpotTradeResponse {
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = parse_number(&self.price)?;
+        let amount = parse_number(&self.size)?;
+        Ok(Trade::new(
+            self.trade_id.clone(),
+            self.time.parse::<i64>()? / 1_000_000,
+            market.clone(),
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.side)?),
+            None,
+            price, amount,
+            price * amount,
+            None, None,
+        ))
+    }
+}
+
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FetchBalanceResponse {
+    currency: String,
+    balance: String,
+    available: String,
+    holds: String,
+}
+
+impl FetchBalanceResponse {
+    fn to_balance_item(&self) -> Result<BalanceItem> {
+        Ok(BalanceItem {
+            currency: util::to_unified_asset(&self.currency),
+            market: None,
+            total: util::parse_optional_float(Some(&self.balance))?,
+            free: util::parse_optional_float(Some(&self.available))?,
+            used: util::parse_optional_float(Some(&self.holds))?,
+            debt: 0.0,
+        })
+    }
+}
+
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateSpotOrderRequest {
+    client_oid: String,
+    side: &'static str,
+    symbol: String,
+    #[serde(rename = "type")]
+    order_type: &'static str,
+    size: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_in_force: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_only: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateFuturesOrderRequest {
+    client_oid: String,
+    side: &'static str,
+    symbol: String,
+    #[serde(rename = "type")]
+    order_type: &'static str,
+    leverage: i64,
+    /// in lots
+    size: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_in_force: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reduce_only: Option<bool>,
+    /// isolated when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    margin_mode: Option<&'static str>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderResponse {
+    order_id: String,
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Kucoin, PropertiesBuilder};
+    use crate::error::Error;
+    use crate::exchange::{StreamItem, Unifier};
+    use crate::model::{ContractType, MarketType, OrderSide};
+    use crate::util::number;
+
+    use super::{FetchContractsResponse, FetchSymbolsResponse, parse_stream, Product, Response};
+
+    #[test]
+    fn test_auth() {
+        let props = PropertiesBuilder::default()
+            .api_key(Some("key".into()))
+            .secret(Some("secret".into()))
+            .passphrase(Some("passphrase".into()))
+            .build()
+            .unwrap();
+        let kucoin = Kucoin::new(props).unwrap();
+        let signature = kucoin.auth("1547015186532", "GET", "/api/v1/accounts?type=trade", "").unwrap();
+        assert_eq!(signature, "WoIBevDveJMNfch5BTSy6p9oRsvlXvV9QzVl+Qv2llc=");
+        assert_eq!(kucoin.signed_passphrase().unwrap(), "sWd5rQWAxDzYJTY6K2sov6seA0l3uNP70anWxITg8IA=");
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"symbol": "BTC-USDT", "name": "BTC-USDT", "baseCurrency": "BTC", "quoteCurrency": "USDT", "feeCurrency": "USDT", "market": "USDS", "baseMinSize": "0.00001", "quoteMinSize": "0.1", "baseMaxSize": "10000000000", "quoteMaxSize": "99999999", "baseIncrement": "0.00000001", "quoteIncrement": "0.000001", "priceIncrement": "0.1", "priceLimitRate": "0.1", "minFunds": "0.1", "isMarginEnabled": true, "enableTrading": true}"#;
+        let resp: FetchSymbolsResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market().unwrap();
+        assert_eq!(market.market_type, MarketType::Spot);
+        assert_eq!(market.precision.as_ref().unwrap().amount, Some(8));
+        assert_eq!(market.precision.as_ref().unwrap().price, Some(1));
+        assert_eq!(format!("{}", market), "BTC/USDT");
+
+        let message = r#"{"symbol": "XBTUSDM", "rootSymbol": "XBT", "type": "FFWCSX", "baseCurrency": "XBT", "quoteCurrency": "USD", "settleCurrency": "XBT", "expireDate": null, "maxOrderQty": 10000000, "maxPrice": 1000000.0, "lotSize": 1, "tickSize": 0.1, "multiplier": -1, "isInverse": true, "maxLeverage": 75, "status": "Open", "lastTradePrice": 60000.0, "highPrice": 61000.0, "lowPrice": 50000.0, "priceChgPct": 0.2, "volumeOf24h": 100.0, "turnoverOf24h": 5800000.0}"#;
+        let resp: FetchContractsResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market().unwrap();
+        assert_eq!(market.market_type, MarketType::Swap);
+        assert_eq!(market.contract_type, Some(ContractType::Inverse));
+        assert_eq!(market.contract_size, Some(1.0));
+        assert_eq!(market.precision.as_ref().unwrap().amount, Some(0));
+        assert_eq!(Product::of(&market).unwrap(), Product::Futures);
+        assert_eq!(format!("{}", market), "BTC/USD:BTC");
+
+        let ticker = resp.to_ticker(market, 1700000000000);
+        assert_eq!(ticker.open, 50000.0);
+        assert_eq!(ticker.percentage, 20.0);
+    }
+
+    #[test]
+    fn test_parse_stream() {
+        let message = r#"{"symbol": "XBTUSDTM", "type": "FFWCSX", "baseCurrency": "XBT", "quoteCurrency": "USDT", "settleCurrency": "USDT", "lotSize": 1, "tickSize": 0.1, "multiplier": 0.001, "isInverse": false, "maxOrderQty": 1000000, "maxLeverage": 125, "status": "Open"}"#;
+        let market = serde_json::from_str::<FetchContractsResponse>(message).unwrap().to_market().unwrap();
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"XBTUSDTM".to_string());
+
+        let message = r#"{"type": "message", "topic": "/contractMarket/tickerV2:XBTUSDTM", "subject": "tickerV2", "data": {"symbol": "XBTUSDTM", "sequence": 1709134336612, "bestBidSize": 795, "bestBidPrice": "51280.0", "bestAskPrice": "51280.1", "bestAskSize": 6, "ts": 1709134336612000000}}"#;
+        match &parse_stream(message.as_bytes(), &unifier).unwrap()[..] {
+            [StreamItem::OrderBook(Ok(order_book))] => {
+                assert_eq!(order_book.bids[0].amount, number("795"));
+                assert_eq!(order_book.asks[0].price, number("51280.1"));
+                assert_eq!(order_book.timestamp, Some(1709134336612));
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+
+        let message = r#"{"type": "message", "topic": "/contractMarket/execution:XBTUSDTM", "subject": "match", "sn": 1697915257909, "data": {"symbol": "XBTUSDTM", "sequence": 1697915257909, "side": "sell", "size": 2, "price": "34000.0", "takerOrderId": "1", "makerOrderId": "2", "tradeId": "1697915257909", "ts": 1697915257909000000}}"#;
+        match &parse_stream(message.as_bytes(), &unifier).unwrap()[..] {
+            [StreamItem::Trade(Ok(trade))] => {
+                assert_eq!(trade.side, Some(OrderSide::Sell));
+                assert_eq!(trade.cost, number("68"));
+                assert_eq!(trade.timestamp, 1697915257909);
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+
+        assert!(parse_stream(r#"{"id": "1", "type": "pong"}"#.as_bytes(), &unifier).unwrap().is_empty());
+
+        let message = r#"{"id": "1", "type": "error", "code": 404, "data": "topic /contractMarket/execution:XBTUSDM is not found"}"#;
+        assert!(parse_stream(message.as_bytes(), &unifier).is_err());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let response: Response = serde_json::from_str(r#"{"code": "200004", "msg": "Balance insufficient!"}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::InsufficientMargin(_))));
+
+        let response: Response = serde_json::from_str(r#"{"code": "400005", "msg": "Invalid KC-API-SIGN"}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let props = PropertiesBuilder::default().sandbox(true).build().unwrap();
+        assert!(Kucoin::new(props).is_err());
+    }
+}
@@ -0,0 +1,5 @@
+pub use kucoin::Kucoin;
+
+#[allow(clippy::module_inception)]
+mod kucoin;
+mod util;
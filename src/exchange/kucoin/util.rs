@@ -0,0 +1,72 @@
+use crate::error::{Error, Result};
+use crate::model::{Market, MarketType, OrderSide, OrderType, TimeInForce};
+
+/// Spot and futures are separate APIs with their own hosts and streams. Symbol ids are unique
+/// across both, e.g. `BTC-USDT` and `XBTUSDTM`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub(in super) enum Product {
+    Spot,
+    Futures,
+}
+
+impl Product {
+    pub(in super) const ALL: [Product; 2] = [Product::Spot, Product::Futures];
+
+    pub(in super) fn of(market: &Market) -> Result<Product> {
+        match market.market_type {
+            MarketType::Spot => Ok(Product::Spot),
+            MarketType::Swap | MarketType::Future => Ok(Product::Futures),
+            _ => Err(Error::InvalidParameters(format!("unsupported market {}", market))),
+        }
+    }
+}
+
+/// Bitcoin is `XBT` on futures.
+pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
+    match exchange_asset.to_uppercase().as_str() {
+        "XBT" => "BTC".to_string(),
+        asset => asset.to_string(),
+    }
+}
+
+/// Parses a decimal field that the exchange leaves null or empty when it does not apply.
+pub(in super) fn parse_optional_float(s: Option<&str>) -> Result<f64> {
+    match s {
+        None | Some("") => Ok(0.0),
+        Some(s) => Ok(s.parse::<f64>()?),
+    }
+}
+
+pub(in super) fn get_exchange_order_side(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "buy" => Ok(OrderSide::Buy),
+        "sell" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+pub(in super) fn get_exchange_order_type(order_type: &OrderType) -> Result<&'static str> {
+    match order_type {
+        OrderType::Limit => Ok("limit"),
+        OrderType::Market => Ok("market"),
+        _ => Err(Error::UnsupportedOrderType(order_type.to_string())),
+    }
+}
+
+/// Post-only orders are good till canceled and flagged with `postOnly`. Futures have no
+/// fill-or-kill orders.
+pub(in super) fn get_exchange_time_in_force(time_in_force: &TimeInForce, product: Product) -> Result<&'static str> {
+    match (time_in_force, product) {
+        (TimeInForce::GTC | TimeInForce::PO, _) => Ok("GTC"),
+        (TimeInForce::IOC, _) => Ok("IOC"),
+        (TimeInForce::FOK, Product::Spot) => Ok("FOK"),
+        (TimeInForce::FOK, Product::Futures) => Err(Error::UnsupportedTimeInForce(format!("{:?}", time_in_force))),
+    }
+}
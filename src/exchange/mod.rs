@@ -2,25 +2,63 @@ use async_trait::async_trait;
 
 pub use binance::Binance;
 pub use binance::BinanceUsdm;
+pub use binance::BinanceCoinm;
+pub use binance::BinanceOptions;
+pub use bybit::Bybit;
+pub use okx::OKX;
+pub use kraken::Kraken;
+pub use coinbase::Coinbase;
+pub use deribit::Deribit;
+pub use bitget::Bitget;
+pub use gateio::Gateio;
+pub use kucoin::Kucoin;
+pub use upbit::Upbit;
+pub use bithumb::Bithumb;
 pub use params::{WatchOrderBookParams, WatchOrderBookParamsBuilder, WatchOrderBookParamsBuilderError};
 pub use params::{WatchTradesParams, WatchTradesParamsBuilder, WatchTradesParamsBuilderError};
+pub use params::{WatchTickersParams, WatchTickersParamsBuilder, WatchTickersParamsBuilderError};
 pub use params::{FetchBalanceParams, FetchBalanceParamsBuilder, FetchBalanceParamsBuilderError};
 pub use params::{FetchTickersParams, FetchTickersParamsBuilder, FetchTickersParamsBuilderError};
 pub use params::{FetchTradesParams, FetchTradesParamsBuilder, FetchTradesParamsBuilderError};
+pub use params::{FetchOrderBookParams, FetchOrderBookParamsBuilder, FetchOrderBookParamsBuilderError};
+pub use params::{FetchGreeksParams, FetchGreeksParamsBuilder, FetchGreeksParamsBuilderError};
+pub use params::{FetchOptionChainParams, FetchOptionChainParamsBuilder, FetchOptionChainParamsBuilderError};
+pub use params::{FetchOhlcvParams, FetchOhlcvParamsBuilder, FetchOhlcvParamsBuilderError};
+pub use params::{WatchOhlcvParams, WatchOhlcvParamsBuilder, WatchOhlcvParamsBuilderError};
 pub use params::{CreateOrderParams, CreateOrderParamsBuilder, CreateOrderParamsBuilderError};
+pub use params::{CancelOrderParams, CancelOrderParamsBuilder, CancelOrderParamsBuilderError};
+pub use params::{FetchOrderParams, FetchOrderParamsBuilder, FetchOrderParamsBuilderError};
+pub use params::{FetchOrdersParams, FetchOrdersParamsBuilder, FetchOrdersParamsBuilderError};
+pub use params::{WatchOrdersParams, WatchOrdersParamsBuilder, WatchOrdersParamsBuilderError};
+pub use params::{WatchMyTradesParams, WatchMyTradesParamsBuilder, WatchMyTradesParamsBuilderError};
+pub use params::{WatchBalanceParams, WatchBalanceParamsBuilder, WatchBalanceParamsBuilderError};
+pub use params::{WatchPositionsParams, WatchPositionsParamsBuilder, WatchPositionsParamsBuilderError};
 pub use params::{FetchPositionsParams, FetchPositionsParamsBuilder, FetchPositionsParamsBuilderError};
 pub use property::{Properties, PropertiesBuilder, PropertiesBuilderError};
+pub use registry::{create_exchange, exchange_ids, exchange_info, exchanges, ExchangeInfo};
 pub(crate) use property::{BaseProperties, BasePropertiesBuilder, BasePropertiesBuilderError};
 
-use crate::client::{HttpClient, HttpClientBuilder, WsClient};
+use crate::client::{Heartbeat, HttpClient, HttpClientBuilder, WsClient, WsItem};
 pub(crate) use crate::exchange::unifier::Unifier;
 use crate::error::*;
 use crate::model::*;
-use crate::util::channel::Receiver;
+use crate::util::channel::{ChannelConfig, Receiver};
+use crate::util::clock::Clock;
 
 mod binance;
+mod bybit;
+mod okx;
+mod kraken;
+mod coinbase;
+mod deribit;
+mod bitget;
+mod gateio;
+mod kucoin;
+mod upbit;
+mod bithumb;
 mod property;
 mod params;
+mod registry;
 
 mod unifier;
 
@@ -29,7 +67,20 @@ mod unifier;
 pub enum StreamItem {
     OrderBook(OrderBookResult<OrderBook>),
     Trade(TradeResult<Trade>),
+    Ticker(TickerResult<Ticker>),
+    Ohlcv(OhlcvResult<Ohlcv>),
+    /// order update from the user data stream
+    Order(OrderResult<Order>),
+    /// fill of one of the user's orders
+    MyTrade(TradeResult<Trade>),
+    /// balances changed by the update. assets that did not change are not included
+    Balance(BalanceResult<Balance>),
+    /// position changed by the update. only the fields carried by the update are set and a
+    /// closed position has zero contracts
+    Position(PositionResult<Position>),
     Subscribed(i64),
+    /// the connection was re-established and the subscriptions were sent again
+    Reconnected,
     Unknown(String),
 }
 
@@ -42,6 +93,13 @@ pub struct ExchangeBase {
     pub(super) markets: Vec<Market>,
 
     pub(super) unifier: Unifier,
+
+    pub(super) clock: Clock,
+
+    pub(super) heartbeat: Heartbeat,
+
+    /// capacity and overflow policy of the receivers
+    pub(super) channel: ChannelConfig,
 }
 
 
@@ -60,15 +118,35 @@ impl ExchangeBase {
             .host(properties.host.clone().unwrap())
             .port(properties.port.clone().unwrap())
             .error_parser(properties.error_parser)
+            .rate_limits(properties.rate_limits.clone().unwrap_or_default())
+            .weight_calculator(properties.weight_calculator)
             .build().unwrap();
         Ok(Self {
             markets: vec![],
             unifier: Unifier::new(),
+            clock: Clock::new(properties.time_sync_interval),
             ws_endpoint: properties.ws_endpoint.clone(),
             http_client,
             stream_parser: properties.stream_parser.unwrap_or(|_, _| Ok(StreamItem::Unknown("stream_parser is not set".into()))),
+            heartbeat: Heartbeat {
+                idle_timeout: properties.ws_idle_timeout,
+                ..properties.heartbeat.clone().unwrap_or_default()
+            },
+            channel: ChannelConfig::new(properties.channel_capacity, properties.overflow_policy),
         })
     }
+
+    /// Creates a stream connection keeping up the heartbeat of the exchange.
+    pub(super) fn ws_client<T: WsItem>(&self, endpoint: &str, parser: fn(&[u8], &Unifier) -> WatchResult<T>, unifier: Unifier) -> WsClient<T> {
+        WsClient::new(endpoint, parser, unifier).heartbeat(self.heartbeat.clone())
+    }
+
+    /// Creates stream connections on endpoints known only later, such as those of a listen key.
+    pub(super) fn ws_client_factory<T: WsItem + 'static>(&self, parser: fn(&[u8], &Unifier) -> WatchResult<T>) -> impl Fn(&str) -> WsClient<T> + Send + 'static {
+        let unifier = self.unifier.clone();
+        let heartbeat = self.heartbeat.clone();
+        move |endpoint| WsClient::new(endpoint, parser, unifier.clone()).heartbeat(heartbeat.clone())
+    }
 }
 
 
@@ -94,11 +172,11 @@ pub trait Exchange {
     async fn fetch_tickers(&self, _: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
         Err(FetchTickersError::NotImplemented)
     }
-    async fn fetch_order_book(&self) -> CommonResult<Vec<OrderBook>> {
-        Err(CommonError::NotImplemented)
+    async fn fetch_order_book(&self, _: FetchOrderBookParams) -> FetchOrderBookResult<OrderBook> {
+        Err(FetchOrderBookError::NotImplemented)
     }
-    async fn fetch_ohlcv(&self) -> CommonResult<()> {
-        Err(CommonError::NotImplemented)
+    async fn fetch_ohlcv(&self, _: FetchOhlcvParams) -> FetchOhlcvResult<Vec<Ohlcv>> {
+        Err(FetchOhlcvError::NotImplemented)
     }
     async fn fetch_status(&self) -> CommonResult<()> {
         Err(CommonError::NotImplemented)
@@ -106,17 +184,23 @@ pub trait Exchange {
     async fn fetch_trades(&self, _: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
         Err(FetchTradesError::NotImplemented)
     }
+    async fn fetch_greeks(&self, _: FetchGreeksParams) -> FetchGreeksResult<Greeks> {
+        Err(FetchGreeksError::NotImplemented)
+    }
+    async fn fetch_option_chain(&self, _: FetchOptionChainParams) -> FetchOptionChainResult<OptionChain> {
+        Err(FetchOptionChainError::NotImplemented)
+    }
 
     async fn watch_ticker(&self) -> CommonResult<()> {
         Err(CommonError::NotImplemented)
     }
-    async fn watch_tickers(&self) -> CommonResult<()> {
-        Err(CommonError::NotImplemented)
+    async fn watch_tickers(&self, _: WatchTickersParams) -> WatchTickersResult<Receiver> {
+        Err(WatchError::NotImplemented)
     }
     async fn watch_order_book(&self, _: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
         Err(WatchError::NotImplemented)
     }
-    async fn watch_ohlcv(&self) -> WatchResult<()> {
+    async fn watch_ohlcv(&self, _: WatchOhlcvParams) -> WatchOhlcvResult<Receiver> {
         Err(WatchError::NotImplemented)
     }
     async fn watch_status(&self) -> WatchResult<()> {
@@ -138,20 +222,20 @@ pub trait Exchange {
     async fn create_order(&self, _: CreateOrderParams) -> CreateOrderResult<Order> {
         Err(CreateOrderError::NotImplemented)
     }
-    async fn cancel_order(&self, _: Order) -> CommonResult<Order> {
-        Err(CommonError::NotImplemented)
+    async fn cancel_order(&self, _: CancelOrderParams) -> CancelOrderResult<Order> {
+        Err(CancelOrderError::NotImplemented)
     }
-    async fn fetch_order(&self) -> CommonResult<Order> {
-        Err(CommonError::NotImplemented)
+    async fn fetch_order(&self, _: FetchOrderParams) -> FetchOrdersResult<Order> {
+        Err(FetchOrdersError::NotImplemented)
     }
-    async fn fetch_orders(&self) -> CommonResult<Vec<Order>> {
-        Err(CommonError::NotImplemented)
+    async fn fetch_orders(&self, _: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        Err(FetchOrdersError::NotImplemented)
     }
-    async fn fetch_open_orders(&self) -> CommonResult<Vec<Order>> {
-        Err(CommonError::NotImplemented)
+    async fn fetch_open_orders(&self, _: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        Err(FetchOrdersError::NotImplemented)
     }
-    async fn fetch_closed_orders(&self) -> CommonResult<Vec<Order>> {
-        Err(CommonError::NotImplemented)
+    async fn fetch_closed_orders(&self, _: FetchOrdersParams) -> FetchOrdersResult<Vec<Order>> {
+        Err(FetchOrdersError::NotImplemented)
     }
     async fn fetch_my_trades(&self) -> CommonResult<Vec<Trade>> {
         Err(CommonError::NotImplemented)
@@ -163,16 +247,16 @@ pub trait Exchange {
         Err(CommonError::NotImplemented)
     }
 
-    async fn watch_balance(&self) -> WatchResult<()> {
+    async fn watch_balance(&self, _: WatchBalanceParams) -> WatchBalanceResult<Receiver> {
         Err(WatchError::NotImplemented)
     }
-    async fn watch_my_trades(&self) -> WatchResult<()> {
+    async fn watch_my_trades(&self, _: WatchMyTradesParams) -> WatchMyTradesResult<Receiver> {
         Err(WatchError::NotImplemented)
     }
-    async fn watch_orders(&self) -> WatchResult<()> {
+    async fn watch_orders(&self, _: WatchOrdersParams) -> WatchOrdersResult<Receiver> {
         Err(WatchError::NotImplemented)
     }
-    async fn watch_positions(&self) -> WatchResult<()> {
+    async fn watch_positions(&self, _: WatchPositionsParams) -> WatchPositionsResult<Receiver> {
         Err(WatchError::NotImplemented)
     }
 }
@@ -0,0 +1,5 @@
+pub use okx::OKX;
+
+#[allow(clippy::module_inception)]
+mod okx;
+mod util;
@@ -0,0 +1,1082 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+
+use crate::client::EMPTY_QUERY;
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{f64_to_number, into_precision, parse_float64, parse_number, timestamp_format, ZERO};
+use crate::util::channel::Receiver;
+use crate::util::rate_limiter::RateLimit;
+
+use super::util;
+use super::util::InstType;
+
+/// maximum number of channels in a subscription message
+const SUBSCRIPTION_CHUNK_SIZE: usize = 100;
+
+/// connections without any message for 30 seconds are closed by the exchange
+const PING_INTERVAL: Duration = Duration::from_secs(25);
+
+/// format of the `OK-ACCESS-TIMESTAMP` header
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct OKX {
+    exchange_base: ExchangeBase,
+    api_key: Option<String>,
+    secret: Option<String>,
+    passphrase: Option<String>,
+
+    /// demo trading is served by the live host and selected with a header
+    simulated: bool,
+}
+
+impl OKX {
+    /// `passphrase` is required in addition to `api_key` and `secret` for private methods.
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        let ws_endpoint = match props.sandbox {
+            true => "wss://wspap.okx.com:8443/ws/v5/public",
+            false => "wss://ws.okx.com:8443/ws/v5/public",
+        };
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some("https://www.okx.com".to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some(ws_endpoint.to_string())))
+            .error_parser(Some(|message| {
+                match serde_json::from_str::<Response>(&message) {
+                    Ok(response) => get_error(&response.code, response.msg),
+                    Err(_) => Error::HttpError(message),
+                }
+            }))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            .heartbeat(Some(Heartbeat {
+                ping_interval: Some(PING_INTERVAL),
+                ping_message: Some("ping".to_string()),
+                pong_message: Some("pong".to_string()),
+                ..Default::default()
+            }))
+            // most public and trading endpoints allow 20 requests per 2 seconds
+            .rate_limits(Some(vec![RateLimit { interval: Duration::from_secs(2), limit: 20, header: None }]))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+            passphrase: props.passphrase.clone(),
+            simulated: props.sandbox,
+        })
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.api_key.is_some() && self.secret.is_some() && self.passphrase.is_some()
+    }
+
+    /// Timestamp for signed requests, on the exchange clock when time sync is enabled.
+    async fn timestamp(&self) -> Result<i64> {
+        let clock = &self.exchange_base.clock;
+        if clock.needs_sync() {
+            let sent = Utc::now().timestamp_millis();
+            let result: Vec<ServerTimeResponse> = self.get("/api/v5/public/time", &vec![]).await?;
+            let received = Utc::now().timestamp_millis();
+            let server_time = result.first().ok_or_else(|| Error::MissingField("ts".into()))?.ts.parse()?;
+            clock.update(sent, server_time, received);
+        }
+        Ok(clock.timestamp())
+    }
+
+    /// Signs `timestamp + method + request path + body` and encodes the signature in base64. The
+    /// request path includes the query string.
+    fn auth(&self, timestamp: &str, method: &str, request_path: &str, body: &str) -> Result<String> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials);
+        }
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(timestamp.as_bytes());
+        signed_key.update(method.as_bytes());
+        signed_key.update(request_path.as_bytes());
+        signed_key.update(body.as_bytes());
+        Ok(BASE64.encode(signed_key.finalize().into_bytes()))
+    }
+
+    fn headers<'a>(&'a self, signature: Option<(&'a str, &'a str)>) -> Vec<(&'a str, &'a str)> {
+        let mut headers = vec![];
+        if let Some((timestamp, signature)) = signature {
+            headers.push(("OK-ACCESS-KEY", self.api_key.as_ref().unwrap().as_str()));
+            headers.push(("OK-ACCESS-SIGN", signature));
+            headers.push(("OK-ACCESS-TIMESTAMP", timestamp));
+            headers.push(("OK-ACCESS-PASSPHRASE", self.passphrase.as_ref().unwrap().as_str()));
+        }
+        if self.simulated {
+            headers.push(("x-simulated-trading", "1"));
+        }
+        headers
+    }
+
+    fn get_symbol_id(&self, market: &Market) -> Result<String> {
+        self.exchange_base.unifier.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let response: Response = self.exchange_base.http_client.get(path, Some(self.headers(None)), Some(query)).await?;
+        response.into_result()
+    }
+
+    async fn signed_get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let timestamp = timestamp_format(self.timestamp().await?, TIMESTAMP_FORMAT)?;
+        let request_path = match query.is_empty() {
+            true => path.to_string(),
+            false => {
+                let query = query.iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<String>>()
+                    .join("&");
+                format!("{}?{}", path, query)
+            }
+        };
+        let signature = self.auth(&timestamp, "GET", &request_path, "")?;
+        let headers = self.headers(Some((&timestamp, &signature)));
+        let response: Response = self.exchange_base.http_client.get(path, Some(headers), Some(query)).await?;
+        response.into_result()
+    }
+
+    async fn signed_post<T: DeserializeOwned>(&self, path: &str, body: &String) -> Result<T> {
+        let timestamp = timestamp_format(self.timestamp().await?, TIMESTAMP_FORMAT)?;
+        let signature = self.auth(&timestamp, "POST", path, body)?;
+        let mut headers = self.headers(Some((&timestamp, &signature)));
+        headers.push(("Content-Type", "application/json"));
+        let response: Response = self.exchange_base.http_client.post(path, Some(headers), EMPTY_QUERY, Some(body)).await?;
+        response.into_result()
+    }
+
+    async fn fetch_inst_type_markets(&self, inst_type: InstType) -> Result<Vec<(String, Market)>> {
+        // options are only listed per underlying
+        let underlyings = match inst_type {
+            InstType::Option => {
+                let result: Vec<Vec<String>> = self.get("/api/v5/public/underlying", &vec![("instType", inst_type.as_str())]).await?;
+                result.into_iter().flatten().map(Some).collect()
+            }
+            _ => vec![None],
+        };
+
+        let mut markets = vec![];
+        for underlying in underlyings {
+            let mut query = vec![("instType", inst_type.as_str())];
+            if let Some(underlying) = underlying.as_ref() {
+                query.push(("uly", underlying.as_str()));
+            }
+            let result: Vec<FetchMarketsInstrumentResponse> = self.get("/api/v5/public/instruments", &query).await?;
+            for instrument in result {
+                markets.push((instrument.inst_id.clone(), instrument.to_market(inst_type)?));
+            }
+        }
+        Ok(markets)
+    }
+
+    async fn fetch_all_markets(&self) -> Result<Vec<(String, Market)>> {
+        let mut markets = vec![];
+        for inst_type in InstType::ALL {
+            markets.append(&mut self.fetch_inst_type_markets(inst_type).await?);
+        }
+        Ok(markets)
+    }
+
+    /// Subscribes to a public channel of the markets, splitting the subscriptions over as many
+    /// messages as needed.
+    async fn subscribe(&self, channel: &str, markets: &[Market]) -> WatchResult<Receiver> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut inst_ids = vec![];
+        for market in markets {
+            match self.exchange_base.unifier.get_symbol_id(market) {
+                Some(inst_id) => inst_ids.push(inst_id),
+                None => return Err(WatchError::SymbolNotFound(format!("{:?}", market))),
+            }
+        }
+
+        let mut ws_client = self.exchange_base.ws_client(self.exchange_base.ws_endpoint.as_ref().unwrap().as_str(), parse_stream, self.exchange_base.unifier.clone());
+        for inst_ids in inst_ids.chunks(SUBSCRIPTION_CHUNK_SIZE) {
+            let request = SubscribeRequest {
+                op: "subscribe",
+                args: inst_ids.iter().map(|inst_id| ChannelArg { channel: channel.to_string(), inst_id: Some(inst_id.clone()) }).collect(),
+            };
+            let message = serde_json::to_string(&request).map_err(|e| WatchError::UnknownError(format!("{}", e)))?;
+            let _ = ws_client.send(message).await?;
+        }
+
+        // a message may carry several items, which are forwarded one by one
+        let (tx, receiver) = self.exchange_base.channel.bounded();
+        tokio::spawn(async move {
+            while let Some(items) = ws_client.next().await {
+                let items = match items {
+                    Ok(items) => items.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                for item in items {
+                    if tx.send(item).await.is_err() { // the receiver is dropped
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+#[async_trait]
+impl Exchange for OKX {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        if self.exchange_base.markets.is_empty() {
+            let result = self.fetch_all_markets().await?;
+            self.exchange_base.unifier.reset();
+            let mut markets = vec![];
+            for (inst_id, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &inst_id);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.fetch_all_markets().await?;
+        Ok(result.into_iter().map(|(_, market)| market).collect())
+    }
+
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        // tickers of margin markets are the ones of spot, and the ones of options are listed
+        // per underlying
+        let mut requests: Vec<(InstType, Option<String>)> = vec![];
+        let mut request = |inst_type: InstType, market: &Market| {
+            let request = match inst_type {
+                InstType::Margin => (InstType::Spot, None),
+                InstType::Option => (inst_type, Some(format!("{}-{}", market.base, market.quote))),
+                _ => (inst_type, None),
+            };
+            if !requests.contains(&request) {
+                requests.push(request);
+            }
+        };
+        let requested = match params.markets.as_ref() {
+            Some(markets) => {
+                let mut requested: HashMap<String, Vec<Market>> = HashMap::new();
+                for market in markets {
+                    request(InstType::of(market)?, market);
+                    requested.entry(self.get_symbol_id(market)?).or_default().push(market.clone());
+                }
+                Some(requested)
+            }
+            None => {
+                for market in &self.exchange_base.markets {
+                    request(InstType::of(market)?, market);
+                }
+                None
+            }
+        };
+
+        let mut tickers = vec![];
+        for (inst_type, underlying) in requests {
+            let mut query = vec![("instType", inst_type.as_str())];
+            if let Some(underlying) = underlying.as_ref() {
+                query.push(("uly", underlying.as_str()));
+            }
+            let result: Vec<TickerResponse> = self.get("/api/v5/market/tickers", &query).await?;
+            for item in result {
+                let markets = match requested.as_ref() {
+                    Some(requested) => requested.get(&item.inst_id).cloned().unwrap_or_default(),
+                    None => self.exchange_base.unifier.get_market(&item.inst_id).into_iter().collect(),
+                };
+                for market in markets {
+                    tickers.push(item.to_ticker(market)?);
+                }
+            }
+        }
+        Ok(tickers)
+    }
+
+    async fn fetch_order_book(&self, params: FetchOrderBookParams) -> FetchOrderBookResult<OrderBook> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let inst_id = self.get_symbol_id(&params.market)?;
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = vec![("instId", inst_id.as_str())];
+        if let Some(limit) = &limit {
+            query.push(("sz", limit.as_str()));
+        }
+        let result: Vec<OrderBookResponse> = self.get("/api/v5/market/books", &query).await?;
+        let book = result.first().ok_or_else(|| Error::InvalidResponse("empty order book".into()))?;
+        Ok(book.to_order_book(params.market)?)
+    }
+
+    async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let inst_id = self.get_symbol_id(&params.market)?;
+        let limit = params.limit.map(|limit| limit.to_string());
+        let mut query = vec![("instId", inst_id.as_str())];
+        if let Some(limit) = &limit {
+            query.push(("limit", limit.as_str()));
+        }
+        let result: Vec<TradeResponse> = self.get("/api/v5/market/trades", &query).await?;
+
+        // only the most recent trades are served, so the range is applied locally
+        let mut trades = vec![];
+        for item in result {
+            let trade = item.to_trade(&params.market)?;
+            if params.since.is_some_and(|since| trade.timestamp < since) || params.until.is_some_and(|until| trade.timestamp > until) {
+                continue;
+            }
+            trades.push(trade);
+        }
+        Ok(trades)
+    }
+
+    async fn watch_tickers(&self, params: WatchTickersParams) -> WatchTickersResult<Receiver> {
+        self.subscribe("tickers", &params.markets).await
+    }
+
+    /// Streams the best five levels of each side. Full-depth books are not supported.
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        if params.full_depth {
+            return Err(WatchError::NotImplemented);
+        }
+        self.subscribe("books5", &params.markets).await
+    }
+
+    async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
+        self.subscribe("trades", &params.markets).await
+    }
+
+    /// Fetches the trading account.
+    async fn fetch_balance(&self, _params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let result: Vec<BalanceResponse> = self.signed_get("/api/v5/account/balance", &vec![]).await?;
+        let mut balance = Balance::default();
+        for account in result {
+            balance.timestamp = Some(account.u_time.parse::<i64>().map_err(Error::from)?);
+            for detail in account.details {
+                balance.items.push(detail.to_balance_item()?);
+            }
+        }
+        Ok(balance)
+    }
+
+    async fn fetch_positions(&self, _params: FetchPositionsParams) -> FetchPositionsResult<Vec<Position>> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let result: Vec<PositionResponse> = self.signed_get("/api/v5/account/positions", &vec![]).await?;
+        let mut positions = vec![];
+        for item in result {
+            let market = match item.inst_type.as_str() {
+                // margin positions share the instrument ids of spot
+                "MARGIN" => self.exchange_base.markets.iter()
+                    .find(|market| market.market_type == MarketType::Margin && self.exchange_base.unifier.get_symbol_id(market).as_ref() == Some(&item.inst_id))
+                    .cloned(),
+                _ => self.exchange_base.unifier.get_market(&item.inst_id),
+            };
+            if let Some(market) = market {
+                if let Some(position) = item.to_position(market)? {
+                    positions.push(position);
+                }
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Amounts of derivatives orders are in contracts.
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let inst_id = self.get_symbol_id(&params.market)?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.exchange_base.markets.iter().find(|m| **m == params.market).unwrap_or(&params.market);
+                let amount = market.amount_to_precision(params.amount);
+                let price = params.price.map(|price| market.price_to_precision(price));
+                market.validate_order(amount, price.filter(|_| order_type != OrderType::Market))?;
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+
+        let inst_type = InstType::of(&params.market)?;
+        let is_market = order_type == OrderType::Market;
+        let request = CreateOrderRequest {
+            inst_id,
+            td_mode: util::get_exchange_trade_mode(inst_type, params.margin_mode.as_ref()),
+            side: util::get_exchange_order_side(&params.order_side),
+            ord_type: util::get_exchange_order_type(&order_type, params.time_in_force.as_ref())?,
+            sz: amount.to_string(),
+            px: price.filter(|_| !is_market).map(|price| price.to_string()),
+            reduce_only: Some(true).filter(|_| params.reduce_only && inst_type != InstType::Spot),
+            // spot market buys are sized in the quote currency unless told otherwise
+            tgt_ccy: Some("base_ccy").filter(|_| is_market && inst_type == InstType::Spot),
+        };
+        let body = serde_json::to_string(&request).map_err(|e| Error::InvalidParameters(format!("{}", e)))?;
+        let result: Vec<CreateOrderResponse> = self.signed_post("/api/v5/trade/order", &body).await?;
+        let response = result.first().ok_or_else(|| Error::InvalidResponse("empty order response".into()))?;
+        Ok(Order {
+            id: Some(response.ord_id.clone()),
+            client_order_id: Some(response.cl_ord_id.clone()).filter(|id| !id.is_empty()),
+            timestamp: Utc::now().timestamp_millis(),
+            status: OrderStatus::Open,
+            market: params.market.clone(),
+            order_type,
+            time_in_force: match is_market {
+                true => None,
+                false => Some(params.time_in_force.unwrap_or(TimeInForce::GTC)),
+            },
+            side: Some(params.order_side),
+            price: price.filter(|_| !is_market),
+            amount,
+            margin_mode: params.margin_mode.unwrap_or(MarginMode::Cross),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Maps the error code of a response to an error.
+fn get_error(code: &str, msg: String) -> Error {
+    match code {
+        "50102" => Error::TimestampOutsideRecvWindow(msg), // Timestamp request expired
+        "50105" | "50111" => Error::CredentialsError(msg), // Invalid OK-ACCESS-PASSPHRASE / OK-ACCESS-KEY
+        "50113" => Error::InvalidSignature(msg), // Invalid Sign
+        "50014" | "51000" => Error::InvalidParameters(msg), // Parameter {0} can not be empty / Parameter {0} error
+        "51001" => Error::SymbolNotFound(msg), // Instrument ID does not exist
+        "51008" => Error::InsufficientMargin(msg), // Order failed. Insufficient balance
+        "51603" => Error::OrderNotFound(msg), // Order does not exist
+        _ => Error::HttpError(format!("code={}, msg={}", code, msg)),
+    }
+}
+
+/// Value in the quote currency of spot and linear trades, and in the base currency of inverse
+/// trades.
+fn get_cost(market: &Market, price: Number, amount: Number) -> Number {
+    let contract_size = f64_to_number(market.contract_size.unwrap_or(1.0));
+    match market.contract_type {
+        Some(ContractType::Inverse) if price == ZERO => ZERO,
+        Some(ContractType::Inverse) => amount * contract_size / price,
+        _ => amount * price * contract_size,
+    }
+}
+
+/// Levels are `[price, amount, deprecated, number of orders]`.
+fn to_order_book_units(levels: &[Vec<String>]) -> Result<Vec<OrderBookUnit>> {
+    levels.iter()
+        .map(|level| match (level.first(), level.get(1)) {
+            (Some(price), Some(amount)) => Ok(OrderBookUnit { price: parse_number(price)?, amount: parse_number(amount)? }),
+            _ => Err(Error::InvalidOrderBook(format!("invalid level {:?}", level))),
+        })
+        .collect()
+}
+
+/// Parses a public channel push into a stream item per element of its data.
+fn parse_stream(message: &[u8], unifier: &Unifier) -> WatchResult<Vec<StreamItem>> {
+    let push = WatchPushResponse::try_from(message.to_vec())?;
+    if push.event.as_deref() == Some("error") {
+        return Err(Error::StreamError(format!("code={}, msg={}", push.code.unwrap_or_default(), push.msg.unwrap_or_default())).into());
+    }
+    let (arg, data) = match (push.arg, push.data) {
+        (Some(arg), Some(data)) => (arg, data),
+        _ => return Ok(vec![StreamItem::Unknown(String::from_utf8_lossy(message).to_string())]),
+    };
+    let inst_id = arg.inst_id.unwrap_or_default();
+    let market = match unifier.get_market(&inst_id) {
+        Some(market) => market,
+        None => return Err(WatchError::SymbolNotFound(inst_id)),
+    };
+
+    let mut items = vec![];
+    for data in data {
+        let item = match arg.channel.as_str() {
+            "books5" => StreamItem::OrderBook(
+                serde_json::from_value::<OrderBookResponse>(data)
+                    .map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+                    .and_then(|book| book.to_order_book(market.clone()))
+                    .map_err(|e| OrderBookError::InvalidOrderBook(format!("{:?}", e), Some(market.clone())))
+            ),
+            "trades" => StreamItem::Trade(
+                serde_json::from_value::<TradeResponse>(data)
+                    .map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+                    .and_then(|trade| trade.to_trade(&market))
+                    .map_err(TradeError::from)
+            ),
+            "tickers" => StreamItem::Ticker(
+                serde_json::from_value::<TickerResponse>(data)
+                    .map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+                    .and_then(|ticker| ticker.to_ticker(market.clone()))
+                    .map_err(TickerError::from)
+            ),
+            _ => StreamItem::Unknown(String::from_utf8_lossy(message).to_string()),
+        };
+        items.push(item);
+    }
+    Ok(items)
+}
+
+
+/// Envelope of every REST response. `data` is kept raw so that errors are reported from `code`
+/// even when the data does not match the expected type.
+#[derive(Serialize, Deserialize)]
+struct Response {
+    code: String,
+    msg: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+impl Response {
+    fn into_result<T: DeserializeOwned>(self) -> Result<T> {
+        if self.code != "0" {
+            // failed trading requests carry the reason in the data
+            let first = self.data.get(0);
+            if let (Some(code), Some(msg)) = (first.and_then(|d| d["sCode"].as_str()), first.and_then(|d| d["sMsg"].as_str())) {
+                if code != "0" {
+                    return Err(get_error(code, msg.to_string()));
+                }
+            }
+            return Err(get_error(&self.code, self.msg));
+        }
+        serde_json::from_value(self.data).map_err(|e| Error::DeserializeJsonBody(format!("{:?}", e)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ServerTimeResponse {
+    ts: String,
+}
+
+
+/// Fields that do not apply to an instrument type are empty strings.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FetchMarketsInstrumentResponse {
+    inst_id: String,
+    /// underlying of derivatives, e.g. `BTC-USD`
+    uly: String,
+    base_ccy: String,
+    quote_ccy: String,
+    settle_ccy: String,
+    ct_val: String,
+    ct_mult: String,
+    /// `linear` or `inverse`, empty for options
+    ct_type: String,
+    /// `C` or `P`
+    opt_type: String,
+    stk: String,
+    exp_time: String,
+    lever: String,
+    tick_sz: String,
+    lot_sz: String,
+    min_sz: String,
+    max_lmt_sz: String,
+    state: String,
+}
+
+impl FetchMarketsInstrumentResponse {
+    fn to_market(&self, inst_type: InstType) -> Result<Market> {
+        let market_type = inst_type.market_type();
+        let (base, quote) = match inst_type {
+            InstType::Spot | InstType::Margin => (self.base_ccy.as_str(), self.quote_ccy.as_str()),
+            _ => self.uly.split_once('-').ok_or_else(|| Error::InvalidResponse(format!("invalid underlying {}", self.uly)))?,
+        };
+        let is_derivative = !matches!(inst_type, InstType::Spot | InstType::Margin);
+
+        let contract_type = match (is_derivative, self.ct_type.as_str()) {
+            (false, _) => None,
+            (true, "linear") => Some(ContractType::Linear),
+            (true, "inverse") => Some(ContractType::Inverse),
+            // options settled in the base currency are inverse
+            (true, _) if self.settle_ccy == base => Some(ContractType::Inverse),
+            (true, _) => Some(ContractType::Linear),
+        };
+        let contract_size = match is_derivative {
+            true => Some(util::parse_optional_float(&self.ct_val)? * parse_float64(&self.ct_mult).unwrap_or(1.0)),
+            false => None,
+        };
+        let expiry = match inst_type {
+            InstType::Futures | InstType::Option => Some(self.exp_time.parse::<i64>()?),
+            _ => None,
+        };
+        let (strike, option_type) = match inst_type {
+            InstType::Option => (Some(parse_float64(&self.stk)?), Some(util::get_unified_option_type(&self.opt_type)?)),
+            _ => (None, None),
+        };
+
+        let precision = Precision {
+            amount: Some(into_precision(self.lot_sz.clone())?),
+            price: Some(into_precision(self.tick_sz.clone())?),
+            cost: None,
+        };
+        let leverage = match self.lever.as_str() {
+            "" => None,
+            lever => Some(Range { min: 1.0, max: parse_float64(lever)? }),
+        };
+
+        Ok(Market {
+            base: util::to_unified_asset(base),
+            quote: util::to_unified_asset(quote),
+            active: util::is_active(&self.state),
+            market_type,
+            settle: Some(util::to_unified_asset(&self.settle_ccy)).filter(|_| is_derivative),
+            contract_size,
+            contract_type,
+            expiry,
+            strike,
+            option_type,
+            precision: Some(precision),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: util::parse_optional_float(&self.min_sz)?, max: util::parse_optional_float(&self.max_lmt_sz)? }),
+                price: None,
+                cost: None,
+                leverage,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TickerResponse {
+    inst_id: String,
+    last: String,
+    ask_px: String,
+    ask_sz: String,
+    bid_px: String,
+    bid_sz: String,
+    open24h: String,
+    high24h: String,
+    low24h: String,
+    /// in the quote currency for spot, and in the base currency for derivatives
+    vol_ccy24h: String,
+    /// in the base currency for spot, and in contracts for derivatives
+    vol24h: String,
+    ts: String,
+}
+
+impl TickerResponse {
+    fn to_ticker(&self, market: Market) -> Result<Ticker> {
+        let last = util::parse_optional_float(&self.last)?;
+        let open = util::parse_optional_float(&self.open24h)?;
+        let volume = util::parse_optional_float(&self.vol24h)?;
+        let currency_volume = util::parse_optional_float(&self.vol_ccy24h)?;
+        let (base_volume, quote_volume) = match (market.market_type, market.contract_type) {
+            (MarketType::Spot | MarketType::Margin, _) => (volume, currency_volume),
+            (_, Some(ContractType::Inverse)) => (currency_volume, volume * market.contract_size.unwrap_or(1.0)),
+            _ => (currency_volume, currency_volume * last),
+        };
+        Ok(Ticker {
+            ask: Some(util::parse_optional_float(&self.ask_px)?).filter(|ask| *ask > 0.0),
+            ask_volume: util::parse_optional_float(&self.ask_sz)?,
+            bid: Some(util::parse_optional_float(&self.bid_px)?).filter(|bid| *bid > 0.0),
+            bid_volume: util::parse_optional_float(&self.bid_sz)?,
+            average: (open + last) / 2f64,
+            base_volume,
+            quote_volume,
+            change: last - open,
+            close: last,
+            high: util::parse_optional_float(&self.high24h)?,
+            low: util::parse_optional_float(&self.low24h)?,
+            last,
+            open,
+            percentage: match open > 0.0 {
+                true => (last - open) / open * 100f64,
+                false => 0.0,
+            },
+            previous_close: None,
+            vwap: match base_volume > 0.0 {
+                true => quote_volume / base_volume,
+                false => 0.0,
+            },
+            market,
+            timestamp: self.ts.parse()?,
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TradeResponse {
+    trade_id: String,
+    px: String,
+    /// in contracts for derivatives
+    sz: String,
+    /// taker side
+    side: String,
+    ts: String,
+}
+
+impl TradeResponse {
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = parse_number(&self.px)?;
+        let amount = parse_number(&self.sz)?;
+        Ok(Trade::new(
+            self.trade_id.clone(),
+            self.ts.parse()?,
+            market.clone(),
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.side)?),
+            None,
+            price, amount,
+            get_cost(market, price, amount),
+            None, None,
+        ))
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct OrderBookResponse {
+    asks: Vec<Vec<String>>,
+    bids: Vec<Vec<String>>,
+    ts: String,
+    #[serde(rename = "seqId")]
+    seq_id: Option<i64>,
+}
+
+impl OrderBookResponse {
+    fn to_order_book(&self, market: Market) -> Result<OrderBook> {
+        Ok(OrderBook::new(
+            to_order_book_units(&self.bids)?,
+            to_order_book_units(&self.asks)?,
+            market,
+            Some(self.ts.parse()?),
+            self.seq_id,
+        ))
+    }
+}
+
+
+#[derive(Serialize)]
+struct SubscribeRequest {
+    op: &'static str,
+    args: Vec<ChannelArg>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChannelArg {
+    channel: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inst_id: Option<String>,
+}
+
+/// Channel push, or the result of an operation when `event` is set.
+#[derive(Serialize, Deserialize)]
+struct WatchPushResponse {
+    event: Option<String>,
+    code: Option<String>,
+    msg: Option<String>,
+    arg: Option<ChannelArg>,
+    data: Option<Vec<serde_json::Value>>,
+}
+
+impl TryFrom<Vec<u8>> for WatchPushResponse {
+    type Error = Error;
+
+    fn try_from(message: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&message).map_err(|e| {
+            let message = String::from_utf8_lossy(&message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BalanceResponse {
+    u_time: String,
+    details: Vec<BalanceDetailResponse>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BalanceDetailResponse {
+    ccy: String,
+    /// equity of the currency
+    eq: String,
+    avail_bal: String,
+    frozen_bal: String,
+    /// borrowed amount, reported as a negative value by some account modes
+    liab: String,
+}
+
+impl BalanceDetailResponse {
+    fn to_balance_item(&self) -> Result<BalanceItem> {
+        let total = util::parse_optional_float(&self.eq)?;
+        let used = util::parse_optional_float(&self.frozen_bal)?;
+        Ok(BalanceItem {
+            currency: util::to_unified_asset(&self.ccy),
+            market: None,
+            total,
+            free: match self.avail_bal.as_str() {
+                "" => total - used,
+                avail_bal => avail_bal.parse()?,
+            },
+            used,
+            debt: util::parse_optional_float(&self.liab)?.abs(),
+        })
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct PositionResponse {
+    inst_type: String,
+    inst_id: String,
+    /// `cross` or `isolated`
+    mgn_mode: String,
+    /// `long` or `short` in hedge mode, `net` in one-way mode where `pos` is signed
+    pos_side: String,
+    pos: String,
+    avg_px: String,
+    mark_px: String,
+    liq_px: String,
+    lever: String,
+    /// margin of isolated positions
+    margin: String,
+    /// initial margin of cross positions
+    imr: String,
+    mmr: String,
+    upl: String,
+    u_time: String,
+}
+
+impl PositionResponse {
+    /// Returns `None` for empty positions.
+    fn to_position(&self, market: Market) -> Result<Option<Position>> {
+        let pos = util::parse_optional_float(&self.pos)?;
+        if pos == 0.0 {
+            return Ok(None);
+        }
+        let side = match (self.pos_side.as_str(), pos > 0.0) {
+            ("long", _) | ("net", true) => PositionSide::Long,
+            _ => PositionSide::Short,
+        };
+        let contracts = pos.abs();
+        let contract_size = market.contract_size.unwrap_or(1.0);
+        let mark_price = util::parse_optional_float(&self.mark_px)?;
+        let notional = match market.contract_type {
+            Some(ContractType::Inverse) if mark_price > 0.0 => contracts * contract_size / mark_price,
+            Some(ContractType::Inverse) => 0.0,
+            _ => contracts * contract_size * mark_price,
+        };
+        let margin_mode = match self.mgn_mode.as_str() {
+            "isolated" => MarginMode::Isolated,
+            _ => MarginMode::Cross,
+        };
+        let initial_margin = match margin_mode {
+            MarginMode::Isolated => util::parse_optional_float(&self.margin)?,
+            MarginMode::Cross => util::parse_optional_float(&self.imr)?,
+        };
+        let maintenance_margin = util::parse_optional_float(&self.mmr)?;
+        let unrealized_pnl = util::parse_optional_float(&self.upl)?;
+        let ratio = |value: f64, total: f64| if total > 0.0 { value / total } else { 0.0 };
+        Ok(Some(Position {
+            contract_size: market.contract_size,
+            market,
+            timestamp: self.u_time.parse()?,
+            is_hedged: self.pos_side != "net",
+            side,
+            contracts,
+            entry_price: util::parse_optional_float(&self.avg_px)?,
+            mark_price,
+            notional,
+            leverage: util::parse_optional_float(&self.lever)?,
+            collateral: initial_margin,
+            initial_margin,
+            maintenance_margin,
+            initial_margin_percent: ratio(initial_margin, notional),
+            maintenance_margin_percent: ratio(maintenance_margin, notional),
+            unrealized_pnl,
+            liquidation_price: util::parse_optional_float(&self.liq_px)?,
+            margin_mode,
+            margin_ratio: ratio(maintenance_margin, initial_margin),
+            percentage: ratio(unrealized_pnl, initial_margin) * 100f64,
+            ..Default::default()
+        }))
+    }
+}
+
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderRequest {
+    inst_id: String,
+    td_mode: &'static str,
+    side: &'static str,
+    ord_type: &'static str,
+    sz: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    px: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reduce_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tgt_ccy: Option<&'static str>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderResponse {
+    ord_id: String,
+    cl_ord_id: String,
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{OKX, PropertiesBuilder};
+    use crate::error::Error;
+    use crate::exchange::{StreamItem, Unifier};
+    use crate::model::{ContractType, MarginMode, MarketType, OptionType, PositionSide};
+
+    use super::{FetchMarketsInstrumentResponse, InstType, parse_stream, PositionResponse, Response};
+
+    #[test]
+    fn test_auth() {
+        let props = PropertiesBuilder::default()
+            .api_key(Some("key".into()))
+            .secret(Some("secret".into()))
+            .passphrase(Some("passphrase".into()))
+            .build()
+            .unwrap();
+        let okx = OKX::new(props).unwrap();
+        let signature = okx.auth("2020-12-08T09:08:57.715Z", "GET", "/api/v5/account/balance?ccy=BTC", "").unwrap();
+        assert_eq!(signature, "wpDvCwYCprcMQsQkxWJiWy+YADoQE4ep+OEKKLimMoY=");
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"instType": "SWAP", "instId": "BTC-USD-SWAP", "uly": "BTC-USD", "baseCcy": "", "quoteCcy": "", "settleCcy": "BTC", "ctVal": "100", "ctMult": "1", "ctValCcy": "USD", "ctType": "inverse", "optType": "", "stk": "", "listTime": "1597026383085", "expTime": "", "lever": "125", "tickSz": "0.1", "lotSz": "1", "minSz": "1", "maxLmtSz": "10000", "state": "live"}"#;
+        let resp: FetchMarketsInstrumentResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(InstType::Swap).unwrap();
+        assert_eq!(market.market_type, MarketType::Swap);
+        assert_eq!(market.contract_type, Some(ContractType::Inverse));
+        assert_eq!(market.contract_size, Some(100.0));
+        assert_eq!(format!("{}", market), "BTC/USD:BTC");
+
+        let message = r#"{"instType": "OPTION", "instId": "BTC-USD-250328-60000-C", "uly": "BTC-USD", "baseCcy": "", "quoteCcy": "", "settleCcy": "BTC", "ctVal": "0.01", "ctMult": "1", "ctValCcy": "BTC", "ctType": "", "optType": "C", "stk": "60000", "expTime": "1743148800000", "lever": "", "tickSz": "0.0005", "lotSz": "1", "minSz": "1", "maxLmtSz": "10000", "state": "live"}"#;
+        let resp: FetchMarketsInstrumentResponse = serde_json::from_str(message).unwrap();
+        let market = resp.to_market(InstType::Option).unwrap();
+        assert_eq!(market.market_type, MarketType::Option);
+        assert_eq!(market.strike, Some(60000.0));
+        assert_eq!(market.option_type, Some(OptionType::Call));
+        assert_eq!(market.expiry, Some(1743148800000));
+        assert_eq!(market.contract_type, Some(ContractType::Inverse));
+        assert_eq!(format!("{}", market), "BTC/USD:BTC-250328-60000-C");
+
+        let message = r#"{"instType": "SPOT", "instId": "BTC-USDT", "baseCcy": "BTC", "quoteCcy": "USDT", "settleCcy": "", "ctVal": "", "ctMult": "", "tickSz": "0.1", "lotSz": "0.00000001", "minSz": "0.00001", "maxLmtSz": "9999999999", "state": "live"}"#;
+        let resp: FetchMarketsInstrumentResponse = serde_json::from_str(message).unwrap();
+        let spot = resp.to_market(InstType::Spot).unwrap();
+        let margin = resp.to_market(InstType::Margin).unwrap();
+        assert_eq!(spot.precision.as_ref().unwrap().amount, Some(8));
+        assert_eq!(format!("{}", spot), "BTC/USDT");
+        assert_ne!(spot, margin);
+    }
+
+    #[test]
+    fn test_parse_stream() {
+        let message = r#"{"instType": "SPOT", "instId": "BTC-USDT", "baseCcy": "BTC", "quoteCcy": "USDT", "tickSz": "0.1", "lotSz": "0.00000001", "minSz": "0.00001", "maxLmtSz": "9999999999", "state": "live"}"#;
+        let resp: FetchMarketsInstrumentResponse = serde_json::from_str(message).unwrap();
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&resp.to_market(InstType::Spot).unwrap(), &"BTC-USDT".to_string());
+
+        let message = r#"{"arg": {"channel": "books5", "instId": "BTC-USDT"}, "data": [{"asks": [["8446", "95", "0", "3"], ["8447", "1", "0", "1"]], "bids": [["8445", "2", "0", "1"]], "instId": "BTC-USDT", "ts": "1597026383085", "seqId": 123456}]}"#;
+        let items = parse_stream(message.as_bytes(), &unifier).unwrap();
+        match &items[..] {
+            [StreamItem::OrderBook(Ok(order_book))] => {
+                assert_eq!(order_book.asks.len(), 2);
+                assert_eq!(order_book.bids.len(), 1);
+                assert_eq!(order_book.timestamp, Some(1597026383085));
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+
+        let message = r#"{"arg": {"channel": "trades", "instId": "BTC-USDT"}, "data": [{"instId": "BTC-USDT", "tradeId": "130639474", "px": "42219.9", "sz": "0.12060306", "side": "buy", "ts": "1630048897897", "count": "3"}, {"instId": "BTC-USDT", "tradeId": "130639475", "px": "42220", "sz": "0.1", "side": "sell", "ts": "1630048897898", "count": "1"}]}"#;
+        let items = parse_stream(message.as_bytes(), &unifier).unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(matches!(&items[0], StreamItem::Trade(Ok(trade)) if trade.id == "130639474"));
+
+        let message = r#"{"event": "error", "code": "60012", "msg": "Invalid request"}"#;
+        assert!(parse_stream(message.as_bytes(), &unifier).is_err());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let response: Response = serde_json::from_str(r#"{"code": "1", "msg": "Operation failed.", "data": [{"clOrdId": "", "ordId": "", "sCode": "51008", "sMsg": "Order failed. Insufficient USDT balance in account."}]}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::InsufficientMargin(_))));
+
+        let response: Response = serde_json::from_str(r#"{"code": "50113", "msg": "Invalid Sign", "data": []}"#).unwrap();
+        assert!(matches!(response.into_result::<serde_json::Value>(), Err(Error::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_to_position() {
+        let message = r#"{"instType": "SWAP", "instId": "BTC-USD-SWAP", "uly": "BTC-USD", "settleCcy": "BTC", "ctVal": "100", "ctMult": "1", "ctType": "inverse", "tickSz": "0.1", "lotSz": "1", "minSz": "1", "maxLmtSz": "10000", "lever": "125", "state": "live"}"#;
+        let market = serde_json::from_str::<FetchMarketsInstrumentResponse>(message).unwrap().to_market(InstType::Swap).unwrap();
+
+        let message = r#"{"instType": "SWAP", "instId": "BTC-USD-SWAP", "mgnMode": "isolated", "posSide": "net", "pos": "-10", "avgPx": "50000", "markPx": "40000", "liqPx": "60000", "lever": "10", "margin": "0.002", "imr": "", "mmr": "0.0001", "upl": "0.0005", "uTime": "1619507761462"}"#;
+        let resp: PositionResponse = serde_json::from_str(message).unwrap();
+        let position = resp.to_position(market.clone()).unwrap().unwrap();
+        assert_eq!(position.side, PositionSide::Short);
+        assert_eq!(position.margin_mode, MarginMode::Isolated);
+        assert_eq!(position.contracts, 10.0);
+        assert_eq!(position.notional, 0.025);
+        assert_eq!(position.collateral, 0.002);
+        assert!(!position.is_hedged);
+
+        let empty = PositionResponse { pos: "0".into(), ..resp };
+        assert!(empty.to_position(market).unwrap().is_none());
+    }
+}
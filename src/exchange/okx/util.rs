@@ -0,0 +1,110 @@
+use crate::error::{Error, Result};
+use crate::model::{MarginMode, Market, MarketType, OptionType, OrderSide, OrderType, TimeInForce};
+
+/// Instrument type of the v5 API.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub(in super) enum InstType {
+    Spot,
+    Margin,
+    Swap,
+    Futures,
+    Option,
+}
+
+impl InstType {
+    /// margin instruments share the instrument ids of spot, so they are loaded first and the
+    /// spot markets take precedence when an id is resolved
+    pub(in super) const ALL: [InstType; 5] = [InstType::Margin, InstType::Spot, InstType::Swap, InstType::Futures, InstType::Option];
+
+    pub(in super) fn as_str(&self) -> &'static str {
+        match self {
+            InstType::Spot => "SPOT",
+            InstType::Margin => "MARGIN",
+            InstType::Swap => "SWAP",
+            InstType::Futures => "FUTURES",
+            InstType::Option => "OPTION",
+        }
+    }
+
+    pub(in super) fn of(market: &Market) -> Result<InstType> {
+        match market.market_type {
+            MarketType::Spot => Ok(InstType::Spot),
+            MarketType::Margin => Ok(InstType::Margin),
+            MarketType::Swap => Ok(InstType::Swap),
+            MarketType::Future => Ok(InstType::Futures),
+            MarketType::Option => Ok(InstType::Option),
+            _ => Err(Error::InvalidParameters(format!("unsupported market {}", market))),
+        }
+    }
+
+    pub(in super) fn market_type(&self) -> MarketType {
+        match self {
+            InstType::Spot => MarketType::Spot,
+            InstType::Margin => MarketType::Margin,
+            InstType::Swap => MarketType::Swap,
+            InstType::Futures => MarketType::Future,
+            InstType::Option => MarketType::Option,
+        }
+    }
+}
+
+pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
+    exchange_asset.to_uppercase()
+}
+
+pub(in super) fn is_active(state: &str) -> bool {
+    state == "live"
+}
+
+/// Parses a decimal field that the exchange leaves empty when it does not apply.
+pub(in super) fn parse_optional_float(s: &str) -> Result<f64> {
+    match s {
+        "" => Ok(0.0),
+        s => Ok(s.parse::<f64>()?),
+    }
+}
+
+pub(in super) fn get_unified_option_type(option_type: &str) -> Result<OptionType> {
+    match option_type {
+        "C" => Ok(OptionType::Call),
+        "P" => Ok(OptionType::Put),
+        _ => Err(Error::InvalidResponse(format!("unknown option type {}", option_type))),
+    }
+}
+
+pub(in super) fn get_exchange_order_side(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "buy" => Ok(OrderSide::Buy),
+        "sell" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+/// The time in force is part of the order type of the exchange.
+pub(in super) fn get_exchange_order_type(order_type: &OrderType, time_in_force: Option<&TimeInForce>) -> Result<&'static str> {
+    match (order_type, time_in_force) {
+        (OrderType::Market, _) => Ok("market"),
+        (OrderType::Limit, None | Some(TimeInForce::GTC)) => Ok("limit"),
+        (OrderType::Limit, Some(TimeInForce::IOC)) => Ok("ioc"),
+        (OrderType::Limit, Some(TimeInForce::FOK)) => Ok("fok"),
+        (OrderType::Limit, Some(TimeInForce::PO)) => Ok("post_only"),
+        _ => Err(Error::UnsupportedOrderType(order_type.to_string())),
+    }
+}
+
+/// Trade mode of an order. Spot orders are paid in cash, every other order is placed with the
+/// margin mode, cross by default.
+pub(in super) fn get_exchange_trade_mode(inst_type: InstType, margin_mode: Option<&MarginMode>) -> &'static str {
+    match (inst_type, margin_mode) {
+        (InstType::Spot, _) => "cash",
+        (_, Some(MarginMode::Isolated)) => "isolated",
+        _ => "cross",
+    }
+}
@@ -1,12 +1,16 @@
 use derive_builder::Builder;
 
-use crate::model::{MarginMode, Market, OrderSide, OrderType, TimeInForce, WorkingType};
+use crate::model::{MarginMode, Market, Number, OrderSide, OrderType, TimeInForce, Timeframe, WorkingType};
 
 #[derive(Default, Builder, Debug)]
 #[builder(default)]
 #[non_exhaustive]
 pub struct WatchOrderBookParams {
     pub markets: Vec<Market>,
+
+    /// maintain full-depth order books locally from a snapshot and diff streams instead of
+    /// streaming the best bid and ask only
+    pub full_depth: bool,
 }
 
 
@@ -19,6 +23,15 @@ pub struct WatchTradesParams {
 }
 
 
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct WatchTickersParams {
+    /// markets to watch tickers for
+    pub markets: Vec<Market>,
+}
+
+
 #[derive(Default, Builder, Debug)]
 #[builder(default)]
 #[non_exhaustive]
@@ -38,6 +51,60 @@ pub struct FetchTradesParams {
 }
 
 
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct FetchOrderBookParams {
+    pub market: Market,
+
+    /// number of price levels on each side. the exchange default is used when it is not set
+    pub limit: Option<i64>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct FetchGreeksParams {
+    /// option market
+    pub market: Market,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct FetchOptionChainParams {
+    /// unified currency code of the underlying, e.g. `BTC`
+    pub base: String,
+
+    /// only include the options expiring at this timestamp
+    pub expiry: Option<i64>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct FetchOhlcvParams {
+    pub market: Market,
+    pub timeframe: Timeframe,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct WatchOhlcvParams {
+    /// markets to watch candles for
+    pub markets: Vec<Market>,
+    pub timeframe: Timeframe,
+}
+
+
 #[derive(Default, Builder, Debug)]
 #[builder(default)]
 #[non_exhaustive]
@@ -58,8 +125,8 @@ pub struct FetchPositionsParams {}
 #[non_exhaustive]
 pub struct CreateOrderParams {
     pub market: Market,
-    pub price: Option<f64>,
-    pub amount: f64,
+    pub price: Option<Number>,
+    pub amount: Number,
     pub order_side: OrderSide,
     pub order_type: Option<OrderType>,
     pub margin_mode: Option<MarginMode>,
@@ -67,6 +134,10 @@ pub struct CreateOrderParams {
     pub callback_rate: Option<f64>,
     pub working_type: Option<WorkingType>,
     pub reduce_only: bool,
+
+    /// round the price to the tick size and truncate the amount to the step size of the loaded
+    /// market, and reject orders outside the market limits before they are sent
+    pub normalize: bool,
 }
 
 impl Default for CreateOrderParams {
@@ -74,7 +145,7 @@ impl Default for CreateOrderParams {
         Self {
             market: Market::default(),
             price: None,
-            amount: 0.0,
+            amount: Number::default(),
             order_side: OrderSide::Buy,
             order_type: None,
             margin_mode: None,
@@ -82,6 +153,95 @@ impl Default for CreateOrderParams {
             working_type: None,
             time_in_force: None,
             reduce_only: false,
+            normalize: false,
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct CancelOrderParams {
+    pub market: Market,
+
+    /// exchange order id. takes precedence over `client_order_id` when both are set
+    pub order_id: Option<String>,
+    pub client_order_id: Option<String>,
+
+    /// margin account the order belongs to. the spot account is used when it is not set
+    pub margin_mode: Option<MarginMode>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct FetchOrderParams {
+    pub market: Market,
+
+    /// exchange order id. takes precedence over `client_order_id` when both are set
+    pub order_id: Option<String>,
+    pub client_order_id: Option<String>,
+
+    /// margin account the order belongs to. the spot account is used when it is not set
+    pub margin_mode: Option<MarginMode>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct FetchOrdersParams {
+    /// required except for open orders, which are fetched for every market when it is not set
+    pub market: Option<Market>,
+
+    /// ignored for open orders
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: Option<i64>,
+
+    /// margin account the orders belong to. the spot account is used when it is not set
+    pub margin_mode: Option<MarginMode>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct WatchOrdersParams {
+    /// account to watch. the spot account is used when it is not set
+    pub margin_mode: Option<MarginMode>,
+
+    /// isolated margin market, required when `margin_mode` is isolated
+    pub market: Option<Market>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct WatchMyTradesParams {
+    /// account to watch. the spot account is used when it is not set
+    pub margin_mode: Option<MarginMode>,
+
+    /// isolated margin market, required when `margin_mode` is isolated
+    pub market: Option<Market>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct WatchBalanceParams {
+    /// account to watch. the spot account is used when it is not set
+    pub margin_mode: Option<MarginMode>,
+
+    /// isolated margin market, required when `margin_mode` is isolated
+    pub market: Option<Market>,
+}
+
+
+#[derive(Default, Builder, Debug)]
+#[builder(default)]
+#[non_exhaustive]
+pub struct WatchPositionsParams {}
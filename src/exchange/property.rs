@@ -1,7 +1,12 @@
+use std::time::Duration;
+
 use derive_builder::Builder;
 
+use crate::client::{Heartbeat, WeightCalculator};
 use crate::error::Error;
 use crate::exchange::{StreamItem, Unifier};
+use crate::util::channel::OverflowPolicy;
+use crate::util::rate_limiter::RateLimit;
 use crate::WatchResult;
 
 #[derive(Default, Builder, Debug)]
@@ -11,8 +16,32 @@ pub struct Properties {
     pub port: Option<u16>,
     pub api_key: Option<String>,
     pub secret: Option<String>,
+
+    /// passphrase chosen when the api key was created, required by OKX
+    pub passphrase: Option<String>,
+
     pub ws_endpoint: Option<String>,
+
+    /// items buffered by each stream for its receivers. 1024 when not set
     pub channel_capacity: Option<usize>,
+
+    /// what a stream does with new items while its buffer is full. `OverflowPolicy::Block` when
+    /// not set
+    pub overflow_policy: Option<OverflowPolicy>,
+
+    /// milliseconds a signed request stays valid after its timestamp. 5000 when not set
+    pub recv_window: Option<u64>,
+
+    /// sync the timestamps of signed requests with the exchange clock, measuring the offset
+    /// again at this interval. the local clock is used as is when not set
+    pub time_sync_interval: Option<Duration>,
+
+    /// re-establish a stream connection when nothing is received for this long, yielding a
+    /// `WatchError::IdleTimeout` first. connections are not monitored when not set
+    pub ws_idle_timeout: Option<Duration>,
+
+    /// use the testnet endpoints of the exchange. `host` and `ws_endpoint` still take precedence
+    pub sandbox: bool,
 }
 
 
@@ -25,4 +54,11 @@ pub(crate) struct BaseProperties {
     pub(crate) stream_parser: Option<fn(&[u8], &Unifier) -> WatchResult<StreamItem>>,
     pub(crate) error_parser: Option<fn(String) -> Error>,
     pub(crate) channel_capacity: Option<usize>,
+    pub(crate) overflow_policy: Option<OverflowPolicy>,
+    pub(crate) rate_limits: Option<Vec<RateLimit>>,
+    pub(crate) weight_calculator: Option<WeightCalculator>,
+    pub(crate) time_sync_interval: Option<Duration>,
+    /// pings of the exchange, if it needs any
+    pub(crate) heartbeat: Option<Heartbeat>,
+    pub(crate) ws_idle_timeout: Option<Duration>,
 }
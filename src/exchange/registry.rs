@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use crate::error::{CommonError, CommonResult};
+use crate::exchange::*;
+use crate::model::MarketType;
+
+/// Static metadata of an exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeInfo {
+    /// id accepted by `create_exchange`, e.g. `binanceusdm`
+    pub id: &'static str,
+    pub name: &'static str,
+    /// types of the markets loaded by the exchange
+    pub market_types: &'static [MarketType],
+    /// ISO 3166-1 alpha-2 codes of the countries the exchange operates from
+    pub countries: &'static [&'static str],
+    /// requests, or request weight for exchanges that weigh requests, allowed per
+    /// `rate_limit_interval` by default. some exchanges update it when markets are loaded
+    pub rate_limit: u32,
+    pub rate_limit_interval: Duration,
+}
+
+const EXCHANGES: [ExchangeInfo; 14] = [
+    ExchangeInfo {
+        id: "binance",
+        name: "Binance",
+        market_types: &[MarketType::Spot, MarketType::Margin],
+        countries: &["JP", "MT"],
+        rate_limit: 6000,
+        rate_limit_interval: Duration::from_secs(60),
+    },
+    ExchangeInfo {
+        id: "binanceusdm",
+        name: "Binance USDⓈ-M",
+        market_types: &[MarketType::Swap, MarketType::Future],
+        countries: &["JP", "MT"],
+        rate_limit: 2400,
+        rate_limit_interval: Duration::from_secs(60),
+    },
+    ExchangeInfo {
+        id: "binancecoinm",
+        name: "Binance COIN-M",
+        market_types: &[MarketType::Swap, MarketType::Future],
+        countries: &["JP", "MT"],
+        rate_limit: 2400,
+        rate_limit_interval: Duration::from_secs(60),
+    },
+    ExchangeInfo {
+        id: "binanceoptions",
+        name: "Binance Options",
+        market_types: &[MarketType::Option],
+        countries: &["JP", "MT"],
+        rate_limit: 400,
+        rate_limit_interval: Duration::from_secs(60),
+    },
+    ExchangeInfo {
+        id: "bybit",
+        name: "Bybit",
+        market_types: &[MarketType::Spot, MarketType::Swap, MarketType::Future],
+        countries: &["VG"],
+        rate_limit: 600,
+        rate_limit_interval: Duration::from_secs(5),
+    },
+    ExchangeInfo {
+        id: "okx",
+        name: "OKX",
+        market_types: &[MarketType::Spot, MarketType::Margin, MarketType::Swap, MarketType::Future, MarketType::Option],
+        countries: &["CN", "US"],
+        rate_limit: 20,
+        rate_limit_interval: Duration::from_secs(2),
+    },
+    ExchangeInfo {
+        id: "kraken",
+        name: "Kraken",
+        market_types: &[MarketType::Spot],
+        countries: &["US"],
+        rate_limit: 1,
+        rate_limit_interval: Duration::from_secs(1),
+    },
+    ExchangeInfo {
+        id: "coinbase",
+        name: "Coinbase Advanced",
+        market_types: &[MarketType::Spot],
+        countries: &["US"],
+        rate_limit: 10,
+        rate_limit_interval: Duration::from_secs(1),
+    },
+    ExchangeInfo {
+        id: "deribit",
+        name: "Deribit",
+        market_types: &[MarketType::Spot, MarketType::Swap, MarketType::Future, MarketType::Option],
+        countries: &["NL"],
+        rate_limit: 20,
+        rate_limit_interval: Duration::from_secs(1),
+    },
+    ExchangeInfo {
+        id: "bitget",
+        name: "Bitget",
+        market_types: &[MarketType::Spot, MarketType::Swap, MarketType::Future],
+        countries: &["SG"],
+        rate_limit: 20,
+        rate_limit_interval: Duration::from_secs(1),
+    },
+    ExchangeInfo {
+        id: "gateio",
+        name: "Gate.io",
+        market_types: &[MarketType::Spot, MarketType::Swap],
+        countries: &["KR"],
+        rate_limit: 200,
+        rate_limit_interval: Duration::from_secs(10),
+    },
+    ExchangeInfo {
+        id: "kucoin",
+        name: "KuCoin",
+        market_types: &[MarketType::Spot, MarketType::Swap, MarketType::Future],
+        countries: &["SC"],
+        rate_limit: 1000,
+        rate_limit_interval: Duration::from_secs(30),
+    },
+    ExchangeInfo {
+        id: "upbit",
+        name: "Upbit",
+        market_types: &[MarketType::Spot],
+        countries: &["KR"],
+        rate_limit: 10,
+        rate_limit_interval: Duration::from_secs(1),
+    },
+    ExchangeInfo {
+        id: "bithumb",
+        name: "Bithumb",
+        market_types: &[MarketType::Spot],
+        countries: &["KR"],
+        rate_limit: 100,
+        rate_limit_interval: Duration::from_secs(1),
+    },
+];
+
+/// Metadata of every exchange, in the order of `exchange_ids`.
+pub fn exchanges() -> &'static [ExchangeInfo] {
+    &EXCHANGES
+}
+
+/// Ids of every exchange that `create_exchange` can build.
+pub fn exchange_ids() -> Vec<&'static str> {
+    EXCHANGES.iter().map(|info| info.id).collect()
+}
+
+/// Metadata of an exchange. Ids are case-insensitive.
+pub fn exchange_info(id: &str) -> Option<&'static ExchangeInfo> {
+    EXCHANGES.iter().find(|info| info.id.eq_ignore_ascii_case(id))
+}
+
+/// Builds an exchange from its id, e.g. `binance` or `binanceusdm`. Ids are case-insensitive.
+pub fn create_exchange(id: &str, props: Properties) -> CommonResult<Box<dyn Exchange + Send + Sync>> {
+    let info = exchange_info(id).ok_or_else(|| CommonError::UnsupportedExchange(id.to_string()))?;
+    Ok(match info.id {
+        "binance" => Box::new(Binance::new(props)?),
+        "binanceusdm" => Box::new(BinanceUsdm::new(props)?),
+        "binancecoinm" => Box::new(BinanceCoinm::new(props)?),
+        "binanceoptions" => Box::new(BinanceOptions::new(props)?),
+        "bybit" => Box::new(Bybit::new(props)?),
+        "okx" => Box::new(OKX::new(props)?),
+        "kraken" => Box::new(Kraken::new(props)?),
+        "coinbase" => Box::new(Coinbase::new(props)?),
+        "deribit" => Box::new(Deribit::new(props)?),
+        "bitget" => Box::new(Bitget::new(props)?),
+        "gateio" => Box::new(Gateio::new(props)?),
+        "kucoin" => Box::new(Kucoin::new(props)?),
+        "upbit" => Box::new(Upbit::new(props)?),
+        "bithumb" => Box::new(Bithumb::new(props)?),
+        _ => return Err(CommonError::UnsupportedExchange(id.to_string())),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{create_exchange, exchange_ids, exchange_info, CommonError, PropertiesBuilder};
+    use crate::model::MarketType;
+
+    #[test]
+    fn test_create_exchange() {
+        for id in exchange_ids() {
+            assert!(create_exchange(id, PropertiesBuilder::default().build().unwrap()).is_ok(), "failed to create {}", id);
+        }
+        assert!(create_exchange("BinanceUSDM", Default::default()).is_ok());
+        assert!(matches!(create_exchange("unknown", Default::default()), Err(CommonError::UnsupportedExchange(_))));
+    }
+
+    #[test]
+    fn test_exchange_info() {
+        let info = exchange_info("binanceusdm").unwrap();
+        assert_eq!(info.market_types, &[MarketType::Swap, MarketType::Future]);
+        assert_eq!(exchange_info("Upbit").unwrap().countries, &["KR"]);
+        assert!(exchange_info("unknown").is_none());
+    }
+}
@@ -0,0 +1,5 @@
+pub use upbit::Upbit;
+
+#[allow(clippy::module_inception)]
+mod upbit;
+mod util;
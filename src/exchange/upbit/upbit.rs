@@ -0,0 +1,903 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::client::EMPTY_QUERY;
+use crate::error::*;
+use crate::exchange::*;
+use crate::util::{f64_to_number, parse_float64, ZERO};
+use crate::util::channel::Receiver;
+use crate::util::rate_limiter::RateLimit;
+
+use super::util;
+
+/// number of markets per ticker request when no chunk size is given
+const DEFAULT_TICKER_CHUNK_SIZE: usize = 100;
+
+/// maximum number of trades per request
+const TRADE_LIMIT: i64 = 500;
+
+/// connections without any message for 120 seconds are closed by the exchange
+const PING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// amount precision of every market
+const AMOUNT_PRECISION: isize = 8;
+
+pub struct Upbit {
+    exchange_base: ExchangeBase,
+    /// access key
+    api_key: Option<String>,
+    secret: Option<String>,
+}
+
+impl Upbit {
+    pub fn new(props: Properties) -> CommonResult<Self> {
+        if props.sandbox {
+            return Err(Error::UnsupportedInSandbox("there is no testnet".into()).into());
+        }
+        let base_props = BasePropertiesBuilder::default()
+            .host(props.host.or(Some("https://api.upbit.com".to_string())))
+            .port(props.port.or(Some(443)))
+            .ws_endpoint(props.ws_endpoint.or(Some("wss://api.upbit.com/websocket/v1".to_string())))
+            .error_parser(Some(|message| {
+                match serde_json::from_str::<ErrorResponse>(&message) {
+                    Ok(response) => get_error(&response.error.name, response.error.message),
+                    Err(_) => Error::HttpError(message),
+                }
+            }))
+            .channel_capacity(props.channel_capacity)
+            .time_sync_interval(props.time_sync_interval)
+            .ws_idle_timeout(props.ws_idle_timeout)
+            .overflow_policy(props.overflow_policy)
+            .heartbeat(Some(Heartbeat { ping_interval: Some(PING_INTERVAL), ..Default::default() }))
+            // quotation endpoints allow 10 requests per second
+            .rate_limits(Some(vec![RateLimit { interval: Duration::from_secs(1), limit: 10, header: None }]))
+            .build()?;
+
+        Ok(Self {
+            exchange_base: ExchangeBase::new(&base_props)?,
+            api_key: props.api_key.clone(),
+            secret: props.secret.clone(),
+        })
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.api_key.is_some() && self.secret.is_some()
+    }
+
+    /// Creates an HS256 token for a request. Requests with parameters carry the SHA-512 hash of
+    /// their query string, which is also made of the body parameters of POST requests.
+    fn jwt(&self, query: Option<&str>) -> Result<String> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials);
+        }
+        let query_hash = query.filter(|query| !query.is_empty()).map(|query| hex::encode(Sha512::digest(query.as_bytes())));
+        let claims = JwtClaims {
+            access_key: self.api_key.as_ref().unwrap(),
+            nonce: random_id(),
+            query_hash_alg: query_hash.as_ref().map(|_| "SHA512"),
+            query_hash,
+        };
+        let header = serde_json::to_string(&JwtHeader { alg: "HS256", typ: "JWT" }).map_err(|e| Error::CredentialsError(format!("{}", e)))?;
+        let claims = serde_json::to_string(&claims).map_err(|e| Error::CredentialsError(format!("{}", e)))?;
+        let message = format!("{}.{}", BASE64_URL.encode(header), BASE64_URL.encode(claims));
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret.as_ref().unwrap().as_bytes())?;
+        signed_key.update(message.as_bytes());
+        Ok(format!("{}.{}", message, BASE64_URL.encode(signed_key.finalize().into_bytes())))
+    }
+
+    fn get_symbol_id(&self, market: &Market) -> Result<String> {
+        self.exchange_base.unifier.get_symbol_id(market).ok_or_else(|| Error::SymbolNotFound(format!("{}", market)))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        self.exchange_base.http_client.get(path, None, Some(query)).await
+    }
+
+    async fn signed_get<T: DeserializeOwned>(&self, path: &str, query: &Vec<(&str, &str)>) -> Result<T> {
+        let authorization = format!("Bearer {}", self.jwt(Some(&to_query_string(query)))?);
+        let headers = vec![("Authorization", authorization.as_str())];
+        self.exchange_base.http_client.get(path, Some(headers), Some(query)).await
+    }
+
+    /// `query` is the query string of the body parameters, in the order of the body.
+    async fn signed_post<T: DeserializeOwned>(&self, path: &str, query: &str, body: &String) -> Result<T> {
+        let authorization = format!("Bearer {}", self.jwt(Some(query))?);
+        let headers = vec![
+            ("Content-Type", "application/json"),
+            ("Authorization", authorization.as_str()),
+        ];
+        self.exchange_base.http_client.post(path, Some(headers), EMPTY_QUERY, Some(body)).await
+    }
+
+    async fn fetch_all_markets(&self) -> Result<Vec<(String, Market)>> {
+        let result: Vec<FetchMarketsResponse> = self.get("/v1/market/all", &vec![("isDetails", "true")]).await?;
+        result.iter()
+            .map(|item| Ok((item.market.clone(), item.to_market()?)))
+            .collect()
+    }
+
+    /// Subscribes to a type of stream, e.g. `orderbook` or `trade`, of the markets. Messages are
+    /// sent in binary frames.
+    async fn subscribe(&self, stream_type: &str, markets: &[Market]) -> WatchResult<Receiver> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        if markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+
+        let mut codes = vec![];
+        for market in markets {
+            match self.exchange_base.unifier.get_symbol_id(market) {
+                Some(code) => codes.push(code),
+                None => return Err(WatchError::SymbolNotFound(format!("{:?}", market))),
+            }
+        }
+        let request = vec![
+            SubscribeRequest::Ticket { ticket: random_id() },
+            SubscribeRequest::Type { stream_type, codes: &codes },
+            SubscribeRequest::Format { format: "DEFAULT" },
+        ];
+        let message = serde_json::to_string(&request).map_err(|e| WatchError::UnknownError(format!("{}", e)))?;
+        let mut ws_client = self.exchange_base.ws_client(self.exchange_base.ws_endpoint.as_ref().unwrap().as_str(), parse_stream, self.exchange_base.unifier.clone());
+        let _ = ws_client.send(message).await?;
+        Ok(Receiver::new(vec![ws_client], &self.exchange_base.channel))
+    }
+}
+
+#[async_trait]
+impl Exchange for Upbit {
+    async fn load_markets(&mut self) -> LoadMarketResult<Vec<Market>> {
+        if self.exchange_base.markets.is_empty() {
+            let result = self.fetch_all_markets().await?;
+            self.exchange_base.unifier.reset();
+            let mut markets = vec![];
+            for (symbol_id, market) in result {
+                self.exchange_base.unifier.insert_market_symbol_id(&market, &symbol_id);
+                markets.push(market);
+            }
+            self.exchange_base.markets = markets;
+        }
+        Ok(self.exchange_base.markets.clone())
+    }
+
+    async fn fetch_markets(&self) -> FetchMarketResult<Vec<Market>> {
+        let result = self.fetch_all_markets().await?;
+        Ok(result.into_iter().map(|(_, market)| market).collect())
+    }
+
+    /// Fetches the tickers of all loaded markets when no markets are given.
+    async fn fetch_tickers(&self, params: FetchTickersParams) -> FetchTickersResult<Vec<Ticker>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let markets = params.markets.as_ref().unwrap_or(&self.exchange_base.markets);
+        let mut symbol_ids = vec![];
+        for market in markets {
+            symbol_ids.push(self.get_symbol_id(market)?);
+        }
+        let mut tickers = vec![];
+        for symbol_ids in symbol_ids.chunks(params.chunk_size.unwrap_or(DEFAULT_TICKER_CHUNK_SIZE).max(1)) {
+            let symbol_ids = symbol_ids.join(",");
+            let result: Vec<FetchTickersResponse> = self.get("/v1/ticker", &vec![("markets", symbol_ids.as_str())]).await?;
+            for item in result {
+                if let Some(market) = self.exchange_base.unifier.get_market(&item.market) {
+                    tickers.push(item.to_ticker(market));
+                }
+            }
+        }
+        Ok(tickers)
+    }
+
+    /// Serves up to 30 levels.
+    async fn fetch_order_book(&self, params: FetchOrderBookParams) -> FetchOrderBookResult<OrderBook> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let result: Vec<OrderBookResponse> = self.get("/v1/orderbook", &vec![("markets", symbol_id.as_str())]).await?;
+        let book = result.into_iter().next().ok_or_else(|| Error::InvalidResponse(format!("missing order book of {}", symbol_id)))?;
+        let mut order_book = book.to_order_book(params.market);
+        if let Some(limit) = params.limit {
+            order_book.bids.truncate(limit.max(0) as usize);
+            order_book.asks.truncate(limit.max(0) as usize);
+        }
+        Ok(order_book)
+    }
+
+    /// Serves up to 500 of the most recent trades.
+    async fn fetch_trades(&self, params: FetchTradesParams) -> FetchTradesResult<Vec<Trade>> {
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let count = params.limit.unwrap_or(TRADE_LIMIT).clamp(1, TRADE_LIMIT).to_string();
+        let query = vec![("market", symbol_id.as_str()), ("count", count.as_str())];
+        let result: Vec<TradeResponse> = self.get("/v1/trades/ticks", &query).await?;
+
+        // the most recent trade comes first and the range is applied locally
+        let mut trades: Vec<Trade> = result.iter()
+            .map(|item| item.to_trade(&params.market))
+            .collect::<Result<Vec<Trade>>>()?
+            .into_iter()
+            .filter(|trade| params.since.is_none_or(|since| trade.timestamp >= since) && params.until.is_none_or(|until| trade.timestamp <= until))
+            .collect();
+        trades.sort_by_key(|trade| trade.timestamp);
+        Ok(trades)
+    }
+
+    /// Streams snapshots of the top 30 levels. Full-depth books are not supported.
+    async fn watch_order_book(&self, params: WatchOrderBookParams) -> WatchOrderBookResult<Receiver> {
+        if params.full_depth {
+            return Err(WatchError::NotImplemented);
+        }
+        self.subscribe("orderbook", &params.markets).await
+    }
+
+    async fn watch_trades(&self, params: WatchTradesParams) -> WatchTradesResult<Receiver> {
+        self.subscribe("trade", &params.markets).await
+    }
+
+    async fn fetch_balance(&self, params: FetchBalanceParams) -> FetchBalanceResult<Balance> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+        if params.margin_mode.is_some() {
+            return Err(Error::InvalidParameters("margin mode is not supported".into()).into());
+        }
+
+        let result: Vec<FetchBalanceResponse> = self.signed_get("/v1/accounts", &vec![]).await?;
+        let mut balance = Balance::default();
+        for item in result {
+            balance.items.push(item.to_balance_item()?);
+        }
+        Ok(balance)
+    }
+
+    /// Market buys are sized in the quote currency by the exchange. Amounts of `OrderType::Market`
+    /// buys are converted with the price, which is then required, and amounts of
+    /// `OrderType::MarketBuy` orders are in the quote currency. Prices of KRW markets are
+    /// normalized to the price step of their level.
+    async fn create_order(&self, params: CreateOrderParams) -> CreateOrderResult<Order> {
+        if !self.has_credentials() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let order_type = params.order_type.unwrap_or_default();
+        if params.price.is_none() && order_type == OrderType::Limit {
+            return Err(Error::InvalidPrice("price is required for limit orders".into()).into());
+        }
+        if order_type == OrderType::MarketBuy && params.order_side != OrderSide::Buy {
+            return Err(Error::InvalidParameters("market buy orders have to be buy orders".into()).into());
+        }
+        if !matches!(order_type, OrderType::Limit | OrderType::Market | OrderType::MarketBuy) {
+            return Err(Error::UnsupportedOrderType(order_type.to_string()).into());
+        }
+        if self.exchange_base.markets.is_empty() {
+            return Err(Error::MarketNotInitialized.into());
+        }
+        let symbol_id = self.get_symbol_id(&params.market)?;
+        let (amount, price) = match params.normalize {
+            true => {
+                let market = self.exchange_base.unifier.get_market(&symbol_id).unwrap_or_else(|| params.market.clone());
+                let amount = match order_type {
+                    OrderType::MarketBuy => params.amount,
+                    _ => market.amount_to_precision(params.amount),
+                };
+                let price = params.price.map(|price| util::price_to_tick(&market, price)).transpose()?;
+                if order_type != OrderType::MarketBuy {
+                    market.validate_order(amount, price)?;
+                }
+                (amount, price)
+            }
+            false => (params.amount, params.price),
+        };
+
+        let time_in_force = params.time_in_force.unwrap_or(TimeInForce::GTC);
+        let request = match (order_type, &params.order_side, price) {
+            (OrderType::Limit, _, Some(price)) => CreateOrderRequest {
+                volume: Some(amount.to_string()),
+                price: Some(price.to_string()),
+                ord_type: "limit",
+                time_in_force: util::get_exchange_time_in_force(&time_in_force),
+                ..CreateOrderRequest::new(symbol_id, &params.order_side)
+            },
+            (OrderType::Market, OrderSide::Sell, _) => CreateOrderRequest {
+                volume: Some(amount.to_string()),
+                ord_type: "market",
+                ..CreateOrderRequest::new(symbol_id, &params.order_side)
+            },
+            (OrderType::Market, OrderSide::Buy, Some(price)) => CreateOrderRequest {
+                price: Some((amount * price).to_string()),
+                ord_type: "price",
+                ..CreateOrderRequest::new(symbol_id, &params.order_side)
+            },
+            (OrderType::Market, OrderSide::Buy, None) => {
+                return Err(Error::InvalidPrice("price is required for market buys, which are sized in the quote currency".into()).into());
+            }
+            _ => CreateOrderRequest {
+                price: Some(amount.to_string()),
+                ord_type: "price",
+                ..CreateOrderRequest::new(symbol_id, &params.order_side)
+            },
+        };
+        let body = serde_json::to_string(&request).map_err(|e| Error::InvalidParameters(format!("{}", e)))?;
+        let response: OrderResponse = self.signed_post("/v1/orders", &request.to_query_string(), &body).await?;
+        let mut order = response.to_order(params.market.clone())?;
+        if order_type == OrderType::Limit {
+            order.time_in_force = Some(time_in_force);
+        }
+        Ok(order)
+    }
+}
+
+
+/// Maps the name of an error response to an error.
+fn get_error(name: &str, message: String) -> Error {
+    match name {
+        "jwt_verification" | "expired_access_key" | "nonce_used" | "no_authorization_i_p" | "no_autorization_i_p" | "out_of_scope" | "INVALID_AUTH" => Error::CredentialsError(message),
+        "invalid_query_payload" => Error::InvalidSignature(message),
+        "insufficient_funds_bid" | "insufficient_funds_ask" => Error::InsufficientMargin(message),
+        "invalid_price_bid" | "invalid_price_ask" | "invalid_price" => Error::InvalidPrice(message),
+        "under_min_total_bid" | "under_min_total_ask" | "invalid_volume_bid" | "invalid_volume_ask" | "invalid_volume" => Error::InvalidAmount(message),
+        "market_does_not_exist" | "invalid_market" => Error::SymbolNotFound(message),
+        "order_not_found" => Error::OrderNotFound(message),
+        "validation_error" | "invalid_parameter" | "INVALID_PARAM" | "WRONG_FORMAT" | "NO_TICKET" | "NO_TYPE" | "NO_CODES" => Error::InvalidParameters(message),
+        _ => Error::HttpError(format!("name={}, message={}", name, message)),
+    }
+}
+
+/// Random 16 bytes in hex, for token nonces and stream tickets.
+fn random_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Query string of parameters, as hashed in tokens. Values are not encoded.
+fn to_query_string(params: &[(&str, &str)]) -> String {
+    params.iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+fn parse_stream(message: &[u8], unifier: &Unifier) -> WatchResult<StreamItem> {
+    if message.is_empty() { // control frames
+        return Ok(StreamItem::Unknown(String::new()));
+    }
+    let response = WatchResponse::try_from(message)?;
+    if let Some(error) = response.error {
+        return Err(WatchError::ErrorResponse(format!("{:?}", get_error(&error.name, error.message))));
+    }
+    let deserialize_error = |e: serde_json::Error| Error::DeserializeJsonBody(format!("{:?}", e));
+    match response.stream_type.as_deref() {
+        Some("orderbook") => {
+            let book: OrderBookResponse = serde_json::from_slice(message).map_err(deserialize_error)?;
+            let market = unifier.get_market(&book.market).ok_or_else(|| WatchError::SymbolNotFound(book.market.clone()))?;
+            Ok(StreamItem::OrderBook(Ok(book.to_order_book(market))))
+        }
+        Some("trade") => {
+            let trade: WatchTradeResponse = serde_json::from_slice(message).map_err(deserialize_error)?;
+            let market = unifier.get_market(&trade.code).ok_or_else(|| WatchError::SymbolNotFound(trade.code.clone()))?;
+            Ok(StreamItem::Trade(TradeResponse::from(trade).to_trade(&market).map_err(TradeError::from)))
+        }
+        _ => Ok(StreamItem::Unknown(String::from_utf8_lossy(message).to_string())),
+    }
+}
+
+
+#[derive(Serialize)]
+struct JwtHeader {
+    alg: &'static str,
+    typ: &'static str,
+}
+
+#[derive(Serialize)]
+struct JwtClaims<'a> {
+    access_key: &'a str,
+    nonce: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_hash_alg: Option<&'static str>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ErrorResponse {
+    error: ErrorBodyResponse,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ErrorBodyResponse {
+    name: String,
+    #[serde(default)]
+    message: String,
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FetchMarketsResponse {
+    /// quote first, e.g. `KRW-BTC`
+    market: String,
+}
+
+impl FetchMarketsResponse {
+    fn to_market(&self) -> Result<Market> {
+        let (base, quote) = util::get_base_quote(&self.market)?;
+        // prices of KRW markets have steps that depend on their level
+        let price_precision = match quote.as_str() {
+            "KRW" => None,
+            _ => Some(AMOUNT_PRECISION),
+        };
+        let cost = util::get_min_cost(&quote).map(|min| Range { min, max: f64::MAX });
+        Ok(Market {
+            base,
+            quote,
+            // delisted markets are not listed
+            active: true,
+            market_type: MarketType::Spot,
+            precision: Some(Precision {
+                amount: Some(AMOUNT_PRECISION),
+                price: price_precision,
+                cost: None,
+            }),
+            limit: Some(MarketLimit {
+                amount: None,
+                price: None,
+                cost,
+                leverage: None,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+
+/// Prices and volumes of the current day start at midnight KST, the others cover 24 hours.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FetchTickersResponse {
+    market: String,
+    opening_price: f64,
+    high_price: f64,
+    low_price: f64,
+    trade_price: f64,
+    prev_closing_price: f64,
+    signed_change_price: f64,
+    /// fraction of the previous close
+    signed_change_rate: f64,
+    acc_trade_price_24h: f64,
+    acc_trade_volume_24h: f64,
+    timestamp: i64,
+}
+
+impl FetchTickersResponse {
+    fn to_ticker(&self, market: Market) -> Ticker {
+        let last = self.trade_price;
+        Ticker {
+            ask: None,
+            ask_volume: 0.0,
+            bid: None,
+            bid_volume: 0.0,
+            average: (self.opening_price + last) / 2f64,
+            base_volume: self.acc_trade_volume_24h,
+            quote_volume: self.acc_trade_price_24h,
+            change: self.signed_change_price,
+            close: last,
+            high: self.high_price,
+            low: self.low_price,
+            last,
+            open: self.opening_price,
+            percentage: self.signed_change_rate * 100f64,
+            previous_close: Some(self.prev_closing_price),
+            vwap: match self.acc_trade_volume_24h > 0.0 {
+                true => self.acc_trade_price_24h / self.acc_trade_volume_24h,
+                false => 0.0,
+            },
+            market,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+
+/// Book of the REST API and of the `orderbook` stream, as levels pairing a bid and an ask.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct OrderBookResponse {
+    #[serde(alias = "code")]
+    market: String,
+    timestamp: i64,
+    orderbook_units: Vec<OrderBookUnitResponse>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct OrderBookUnitResponse {
+    ask_price: f64,
+    bid_price: f64,
+    ask_size: f64,
+    bid_size: f64,
+}
+
+impl OrderBookResponse {
+    fn to_order_book(&self, market: Market) -> OrderBook {
+        let unit = |price: f64, amount: f64| OrderBookUnit { price: f64_to_number(price), amount: f64_to_number(amount) };
+        let bids = self.orderbook_units.iter()
+            .filter(|level| level.bid_size > 0.0)
+            .map(|level| unit(level.bid_price, level.bid_size))
+            .collect();
+        let asks = self.orderbook_units.iter()
+            .filter(|level| level.ask_size > 0.0)
+            .map(|level| unit(level.ask_price, level.ask_size))
+            .collect();
+        OrderBook::new(bids, asks, market, Some(self.timestamp), None)
+    }
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TradeResponse {
+    /// milliseconds
+    timestamp: i64,
+    trade_price: f64,
+    trade_volume: f64,
+    /// side of the taker, `ASK` or `BID`
+    ask_bid: String,
+    sequential_id: i64,
+}
+
+impl TradeResponse {
+    fn to_trade(&self, market: &Market) -> Result<Trade> {
+        let price = f64_to_number(self.trade_price);
+        let amount = f64_to_number(self.trade_volume);
+        Ok(Trade::new(
+            self.sequential_id.to_string(),
+            self.timestamp,
+            market.clone(),
+            None,
+            None,
+            Some(util::get_unified_order_side(&self.ask_bid)?),
+            None,
+            price, amount,
+            price * amount,
+            None, None,
+        ))
+    }
+}
+
+/// Trade of the `trade` stream, whose `timestamp` is the time of the message.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct WatchTradeResponse {
+    code: String,
+    trade_timestamp: i64,
+    trade_price: f64,
+    trade_volume: f64,
+    ask_bid: String,
+    sequential_id: i64,
+}
+
+impl From<WatchTradeResponse> for TradeResponse {
+    fn from(trade: WatchTradeResponse) -> Self {
+        Self {
+            timestamp: trade.trade_timestamp,
+            trade_price: trade.trade_price,
+            trade_volume: trade.trade_volume,
+            ask_bid: trade.ask_bid,
+            sequential_id: trade.sequential_id,
+        }
+    }
+}
+
+
+/// Item of a subscription message, which is an array of a ticket, the streams and a format.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SubscribeRequest<'a> {
+    Ticket { ticket: String },
+    Type {
+        #[serde(rename = "type")]
+        stream_type: &'a str,
+        codes: &'a [String],
+    },
+    Format { format: &'static str },
+}
+
+/// Fields shared by the messages of every stream, or an error.
+#[derive(Serialize, Deserialize)]
+struct WatchResponse {
+    #[serde(rename = "type")]
+    stream_type: Option<String>,
+    error: Option<ErrorBodyResponse>,
+}
+
+impl TryFrom<&[u8]> for WatchResponse {
+    type Error = Error;
+
+    fn try_from(message: &[u8]) -> Result<Self> {
+        serde_json::from_slice(message).map_err(|e| {
+            let message = String::from_utf8_lossy(message);
+            Error::DeserializeJsonBody(format!("Failed to deserialize json body. message={:?}, error={:?}", message, e))
+        })
+    }
+}
+
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FetchBalanceResponse {
+    currency: String,
+    balance: String,
+    locked: String,
+}
+
+impl FetchBalanceResponse {
+    fn to_balance_item(&self) -> Result<BalanceItem> {
+        let free = parse_float64(&self.balance)?;
+        let used = parse_float64(&self.locked)?;
+        Ok(BalanceItem {
+            currency: util::to_unified_asset(&self.currency),
+            market: None,
+            total: free + used,
+            free,
+            used,
+            debt: 0.0,
+        })
+    }
+}
+
+
+/// `price` is the value to spend of market buys.
+#[derive(Serialize)]
+struct CreateOrderRequest {
+    market: String,
+    side: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<String>,
+    ord_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_in_force: Option<&'static str>,
+}
+
+impl CreateOrderRequest {
+    fn new(market: String, order_side: &OrderSide) -> Self {
+        Self {
+            market,
+            side: util::get_exchange_order_side(order_side),
+            volume: None,
+            price: None,
+            ord_type: "limit",
+            time_in_force: None,
+        }
+    }
+
+    /// Query string of the body, in the order of the fields.
+    fn to_query_string(&self) -> String {
+        let mut params = vec![("market", self.market.as_str()), ("side", self.side)];
+        if let Some(volume) = &self.volume {
+            params.push(("volume", volume.as_str()));
+        }
+        if let Some(price) = &self.price {
+            params.push(("price", price.as_str()));
+        }
+        params.push(("ord_type", self.ord_type));
+        if let Some(time_in_force) = self.time_in_force {
+            params.push(("time_in_force", time_in_force));
+        }
+        to_query_string(&params)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct OrderResponse {
+    uuid: String,
+    side: String,
+    ord_type: String,
+    /// value to spend of market buys
+    price: Option<String>,
+    state: String,
+    created_at: String,
+    /// not set for market buys
+    volume: Option<String>,
+    remaining_volume: Option<String>,
+    executed_volume: String,
+    time_in_force: Option<String>,
+}
+
+impl OrderResponse {
+    fn to_order(&self, market: Market) -> Result<Order> {
+        let order_type = util::get_unified_order_type(&self.ord_type)?;
+        let status = util::get_unified_order_status(&self.state)?;
+        let filled = f64_to_number(parse_float64(&self.executed_volume)?);
+        let price = self.price.as_deref().map(parse_float64).transpose()?.map(f64_to_number);
+        let amount = match (&self.volume, order_type, price) {
+            (Some(volume), _, _) => f64_to_number(parse_float64(volume)?),
+            // in the quote currency, as sent
+            (None, OrderType::MarketBuy, Some(price)) => price,
+            (None, _, _) => filled,
+        };
+        let time_in_force = match (order_type, self.time_in_force.as_deref()) {
+            (OrderType::Limit, Some("ioc")) => Some(TimeInForce::IOC),
+            (OrderType::Limit, Some("fok")) => Some(TimeInForce::FOK),
+            (OrderType::Limit, Some("post_only")) => Some(TimeInForce::PO),
+            (OrderType::Limit, _) => Some(TimeInForce::GTC),
+            _ => Some(TimeInForce::IOC),
+        };
+        Ok(Order {
+            id: Some(self.uuid.clone()),
+            timestamp: util::parse_timestamp(&self.created_at)?,
+            status,
+            market,
+            order_type,
+            time_in_force,
+            side: Some(util::get_unified_order_side(&self.side)?),
+            price: price.filter(|_| order_type == OrderType::Limit),
+            amount,
+            filled: Some(filled),
+            remaining: self.remaining_volume.as_deref().map(parse_float64).transpose()?.map(f64_to_number).filter(|remaining| *remaining != ZERO || status == OrderStatus::Open),
+            ..Default::default()
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256, Sha512};
+
+    use crate::{PropertiesBuilder, Upbit};
+    use crate::error::Error;
+    use crate::exchange::{StreamItem, Unifier};
+    use crate::model::{MarketType, OrderSide, OrderStatus, OrderType, TimeInForce};
+    use crate::util::number;
+
+    use super::{CreateOrderRequest, FetchMarketsResponse, FetchTickersResponse, get_error, OrderResponse, parse_stream, util};
+
+    #[test]
+    fn test_jwt() {
+        let props = PropertiesBuilder::default()
+            .api_key(Some("access".into()))
+            .secret(Some("secret".into()))
+            .build()
+            .unwrap();
+        let upbit = Upbit::new(props).unwrap();
+        let mut request = CreateOrderRequest::new("KRW-BTC".into(), &OrderSide::Buy);
+        request.volume = Some("0.01".into());
+        request.price = Some("100".into());
+        let query = request.to_query_string();
+        assert_eq!(query, "market=KRW-BTC&side=bid&volume=0.01&price=100&ord_type=limit");
+
+        let token = upbit.jwt(Some(&query)).unwrap();
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        let claims: serde_json::Value = serde_json::from_slice(&BASE64_URL.decode(parts[1]).unwrap()).unwrap();
+        assert_eq!(claims["access_key"], "access");
+        assert_eq!(claims["query_hash"], hex::encode(Sha512::digest(query.as_bytes())));
+        assert_eq!(claims["query_hash_alg"], "SHA512");
+
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        signed_key.update(format!("{}.{}", parts[0], parts[1]).as_bytes());
+        assert_eq!(parts[2], BASE64_URL.encode(signed_key.finalize().into_bytes()));
+
+        let token = upbit.jwt(Some("")).unwrap();
+        let claims: serde_json::Value = serde_json::from_slice(&BASE64_URL.decode(token.split('.').nth(1).unwrap()).unwrap()).unwrap();
+        assert!(claims.get("query_hash").is_none());
+    }
+
+    #[test]
+    fn test_parse_markets() {
+        let message = r#"{"market": "KRW-BTC", "korean_name": "비트코인", "english_name": "Bitcoin", "market_warning": "NONE"}"#;
+        let market = serde_json::from_str::<FetchMarketsResponse>(message).unwrap().to_market().unwrap();
+        assert_eq!(market.market_type, MarketType::Spot);
+        assert_eq!(market.precision.as_ref().unwrap().price, None);
+        assert_eq!(market.limit.as_ref().unwrap().cost.as_ref().unwrap().min, 5000.0);
+        assert_eq!(format!("{}", market), "BTC/KRW");
+
+        let message = r#"{"market": "BTC-ETH", "korean_name": "이더리움", "english_name": "Ethereum"}"#;
+        let market = serde_json::from_str::<FetchMarketsResponse>(message).unwrap().to_market().unwrap();
+        assert_eq!(market.precision.as_ref().unwrap().price, Some(8));
+        assert_eq!(format!("{}", market), "ETH/BTC");
+    }
+
+    #[test]
+    fn test_price_to_tick() {
+        let market = serde_json::from_str::<FetchMarketsResponse>(r#"{"market": "KRW-BTC"}"#).unwrap().to_market().unwrap();
+        assert_eq!(util::price_to_tick(&market, number("95123456")).unwrap(), number("95123000"));
+        assert_eq!(util::price_to_tick(&market, number("1234567")).unwrap(), number("1234500"));
+        assert_eq!(util::price_to_tick(&market, number("123456")).unwrap(), number("123450"));
+        assert_eq!(util::price_to_tick(&market, number("5432.6")).unwrap(), number("5433"));
+        assert_eq!(util::price_to_tick(&market, number("123.456")).unwrap(), number("123.5"));
+        assert_eq!(util::price_to_tick(&market, number("0.123456")).unwrap(), number("0.1235"));
+
+        let market = serde_json::from_str::<FetchMarketsResponse>(r#"{"market": "BTC-ETH"}"#).unwrap().to_market().unwrap();
+        assert_eq!(util::price_to_tick(&market, number("0.0512345678")).unwrap(), number("0.05123457"));
+    }
+
+    #[test]
+    fn test_parse_stream() {
+        let market = serde_json::from_str::<FetchMarketsResponse>(r#"{"market": "KRW-BTC"}"#).unwrap().to_market().unwrap();
+        let mut unifier = Unifier::new();
+        unifier.insert_market_symbol_id(&market, &"KRW-BTC".to_string());
+
+        let message = r#"{"type": "orderbook", "code": "KRW-BTC", "timestamp": 1704867306396, "total_ask_size": 7.6, "total_bid_size": 4.4, "orderbook_units": [{"ask_price": 61660000, "bid_price": 61650000, "ask_size": 0.5, "bid_size": 0.25}, {"ask_price": 61670000, "bid_price": 61640000, "ask_size": 0.1, "bid_size": 0}], "stream_type": "REALTIME", "level": 0}"#;
+        match parse_stream(message.as_bytes(), &unifier).unwrap() {
+            StreamItem::OrderBook(Ok(order_book)) => {
+                assert_eq!(order_book.bids.len(), 1);
+                assert_eq!(order_book.asks.len(), 2);
+                assert_eq!(order_book.bids[0].price, number("61650000"));
+                assert_eq!(order_book.asks[0].amount, number("0.5"));
+                assert_eq!(order_book.timestamp, Some(1704867306396));
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+
+        let message = r#"{"type": "trade", "code": "KRW-BTC", "timestamp": 1704867306500, "trade_date": "2024-01-10", "trade_time": "06:15:06", "trade_timestamp": 1704867306396, "trade_price": 61650000, "trade_volume": 0.002, "ask_bid": "ASK", "prev_closing_price": 61000000, "change": "RISE", "change_price": 650000, "sequential_id": 1704867306396000, "stream_type": "REALTIME"}"#;
+        match parse_stream(message.as_bytes(), &unifier).unwrap() {
+            StreamItem::Trade(Ok(trade)) => {
+                assert_eq!(trade.id, "1704867306396000");
+                assert_eq!(trade.side, Some(OrderSide::Sell));
+                assert_eq!(trade.timestamp, 1704867306396);
+                assert_eq!(trade.cost, number("123300"));
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+
+        assert!(matches!(parse_stream(r#"{"status": "UP"}"#.as_bytes(), &unifier).unwrap(), StreamItem::Unknown(_)));
+        assert!(parse_stream(r#"{"error": {"name": "NO_CODES", "message": "No codes."}}"#.as_bytes(), &unifier).is_err());
+    }
+
+    #[test]
+    fn test_to_ticker() {
+        let message = r#"{"market": "KRW-BTC", "trade_date": "20240110", "trade_time": "061506", "trade_timestamp": 1704867306396, "opening_price": 60000000, "high_price": 62000000, "low_price": 59000000, "trade_price": 61000000, "prev_closing_price": 60000000, "change": "RISE", "change_price": 1000000, "change_rate": 0.0166666667, "signed_change_price": 1000000, "signed_change_rate": 0.0166666667, "trade_volume": 0.01, "acc_trade_price": 61000000000, "acc_trade_price_24h": 122000000000, "acc_trade_volume": 1000, "acc_trade_volume_24h": 2000, "highest_52_week_price": 70000000, "highest_52_week_date": "2023-12-01", "lowest_52_week_price": 20000000, "lowest_52_week_date": "2023-01-01", "timestamp": 1704867306500}"#;
+        let ticker = serde_json::from_str::<FetchTickersResponse>(message).unwrap().to_ticker(Default::default());
+        assert_eq!(ticker.last, 61000000.0);
+        assert_eq!(ticker.change, 1000000.0);
+        assert_eq!(ticker.previous_close, Some(60000000.0));
+        assert_eq!(ticker.vwap, 61000000.0);
+        assert_eq!(ticker.timestamp, 1704867306500);
+    }
+
+    #[test]
+    fn test_parse_order() {
+        let message = r#"{"uuid": "cdd92199-2897-4e14-9448-f923320408ad", "side": "bid", "ord_type": "limit", "price": "100.0", "state": "wait", "market": "KRW-BTC", "created_at": "2018-04-10T15:42:23+09:00", "volume": "0.01", "remaining_volume": "0.01", "reserved_fee": "0.0015", "remaining_fee": "0.0015", "paid_fee": "0.0", "locked": "1.0015", "executed_volume": "0.0", "trades_count": 0}"#;
+        let order = serde_json::from_str::<OrderResponse>(message).unwrap().to_order(Default::default()).unwrap();
+        assert_eq!(order.id.as_deref(), Some("cdd92199-2897-4e14-9448-f923320408ad"));
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.time_in_force, Some(TimeInForce::GTC));
+        assert_eq!(order.side, Some(OrderSide::Buy));
+        assert_eq!(order.timestamp, 1523342543000);
+        assert_eq!(order.amount, number("0.01"));
+        assert_eq!(order.remaining, Some(number("0.01")));
+
+        let message = r#"{"uuid": "1", "side": "bid", "ord_type": "price", "price": "10000", "state": "done", "market": "KRW-BTC", "created_at": "2018-04-10T15:42:23+09:00", "volume": null, "remaining_volume": null, "executed_volume": "0.0002", "trades_count": 1}"#;
+        let order = serde_json::from_str::<OrderResponse>(message).unwrap().to_order(Default::default()).unwrap();
+        assert_eq!(order.order_type, OrderType::MarketBuy);
+        assert_eq!(order.status, OrderStatus::Closed);
+        assert_eq!(order.amount, number("10000"));
+        assert_eq!(order.price, None);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(matches!(get_error("insufficient_funds_bid", "".into()), Error::InsufficientMargin(_)));
+        assert!(matches!(get_error("jwt_verification", "".into()), Error::CredentialsError(_)));
+        assert!(matches!(get_error("under_min_total_bid", "".into()), Error::InvalidAmount(_)));
+        assert!(matches!(get_error("server_error", "".into()), Error::HttpError(_)));
+    }
+}
@@ -0,0 +1,118 @@
+use chrono::DateTime;
+
+use crate::error::{Error, Result};
+use crate::model::{Market, Number, OrderSide, OrderStatus, OrderType, TimeInForce};
+use crate::util::{into_precision, number_to_f64, parse_number, round_to_precision};
+
+/// Price steps of the KRW markets, by the lowest price they apply to.
+const KRW_TICK_SIZES: [(f64, &str); 14] = [
+    (2_000_000.0, "1000"),
+    (1_000_000.0, "500"),
+    (500_000.0, "100"),
+    (100_000.0, "50"),
+    (10_000.0, "10"),
+    (1_000.0, "1"),
+    (100.0, "0.1"),
+    (10.0, "0.01"),
+    (1.0, "0.001"),
+    (0.1, "0.0001"),
+    (0.01, "0.00001"),
+    (0.001, "0.000001"),
+    (0.0001, "0.0000001"),
+    (0.0, "0.00000001"),
+];
+
+pub(in super) fn to_unified_asset(exchange_asset: &str) -> String {
+    exchange_asset.to_uppercase()
+}
+
+/// Base and quote of a market id, which is quote first, e.g. `KRW-BTC`.
+pub(in super) fn get_base_quote(symbol_id: &str) -> Result<(String, String)> {
+    match symbol_id.split_once('-') {
+        Some((quote, base)) => Ok((to_unified_asset(base), to_unified_asset(quote))),
+        None => Err(Error::InvalidResponse(format!("invalid market {}", symbol_id))),
+    }
+}
+
+/// Minimum order value of a quote currency.
+pub(in super) fn get_min_cost(quote: &str) -> Option<f64> {
+    match quote {
+        "KRW" => Some(5000.0),
+        "BTC" => Some(0.00005),
+        "USDT" => Some(0.5),
+        _ => None,
+    }
+}
+
+/// Price step of a KRW market at the given price.
+pub(in super) fn get_krw_tick_size(price: f64) -> &'static str {
+    KRW_TICK_SIZES.iter()
+        .find(|(min, _)| price >= *min)
+        .map(|(_, tick_size)| *tick_size)
+        .unwrap_or(KRW_TICK_SIZES[KRW_TICK_SIZES.len() - 1].1)
+}
+
+/// Rounds the price to the nearest step of the market. The step of KRW markets depends on the
+/// price level, the other markets having a fixed precision.
+pub(in super) fn price_to_tick(market: &Market, price: Number) -> Result<Number> {
+    if market.quote != "KRW" {
+        return Ok(market.price_to_precision(price));
+    }
+    let tick_size = get_krw_tick_size(number_to_f64(price));
+    let digits = into_precision(tick_size.to_string())?;
+    let tick_size = parse_number(tick_size)?;
+    Ok(round_to_precision(round_to_precision(price / tick_size, 0, false) * tick_size, digits, false))
+}
+
+/// Milliseconds of an ISO 8601 time with an offset, e.g. `2018-04-10T15:42:23+09:00`.
+pub(in super) fn parse_timestamp(time: &str) -> Result<i64> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.timestamp_millis())
+        .map_err(|e| Error::ParseError(format!("{}: {}", time, e)))
+}
+
+pub(in super) fn get_exchange_order_side(order_side: &OrderSide) -> &'static str {
+    match order_side {
+        OrderSide::Buy => "bid",
+        OrderSide::Sell => "ask",
+    }
+}
+
+/// Sides are `bid` and `ask` in orders and `BID` and `ASK`, the side of the taker, in trades.
+pub(in super) fn get_unified_order_side(exchange_order_side: &str) -> Result<OrderSide> {
+    match exchange_order_side {
+        "bid" | "BID" => Ok(OrderSide::Buy),
+        "ask" | "ASK" => Ok(OrderSide::Sell),
+        _ => Err(Error::UnsupportedOrderSide(exchange_order_side.to_string())),
+    }
+}
+
+/// Limit orders are good till canceled unless a time in force is sent.
+pub(in super) fn get_exchange_time_in_force(time_in_force: &TimeInForce) -> Option<&'static str> {
+    match time_in_force {
+        TimeInForce::GTC => None,
+        TimeInForce::IOC => Some("ioc"),
+        TimeInForce::FOK => Some("fok"),
+        TimeInForce::PO => Some("post_only"),
+    }
+}
+
+/// Reserved orders are `watch` until they are triggered.
+pub(in super) fn get_unified_order_status(exchange_order_status: &str) -> Result<OrderStatus> {
+    match exchange_order_status {
+        "wait" | "watch" => Ok(OrderStatus::Open),
+        "done" => Ok(OrderStatus::Closed),
+        "cancel" => Ok(OrderStatus::Canceled),
+        _ => Err(Error::UnsupportedOrderStatus(exchange_order_status.to_string())),
+    }
+}
+
+/// Market buys are `price` orders, sized in the quote currency.
+pub(in super) fn get_unified_order_type(exchange_order_type: &str) -> Result<OrderType> {
+    match exchange_order_type {
+        "limit" => Ok(OrderType::Limit),
+        "market" => Ok(OrderType::Market),
+        "price" => Ok(OrderType::MarketBuy),
+        _ => Err(Error::UnsupportedOrderType(exchange_order_type.to_string())),
+    }
+}
@@ -1,30 +1,74 @@
 pub use exchange::Binance;
 pub use exchange::BinanceUsdm;
+pub use exchange::BinanceCoinm;
+pub use exchange::BinanceOptions;
+pub use exchange::Bybit;
+pub use exchange::OKX;
+pub use exchange::Kraken;
+pub use exchange::Coinbase;
+pub use exchange::Deribit;
+pub use exchange::Bitget;
+pub use exchange::Gateio;
+pub use exchange::Kucoin;
+pub use exchange::Upbit;
+pub use exchange::Bithumb;
 pub use exchange::Exchange;
+pub use exchange::{create_exchange, exchange_ids, exchange_info, exchanges, ExchangeInfo};
 
 pub use exchange::{WatchOrderBookParams, WatchOrderBookParamsBuilder, WatchOrderBookParamsBuilderError};
 pub use exchange::{WatchTradesParams, WatchTradesParamsBuilder, WatchTradesParamsBuilderError};
+pub use exchange::{WatchTickersParams, WatchTickersParamsBuilder, WatchTickersParamsBuilderError};
 pub use exchange::{FetchBalanceParams, FetchBalanceParamsBuilder, FetchBalanceParamsBuilderError};
 pub use exchange::{FetchTickersParams, FetchTickersParamsBuilder, FetchTickersParamsBuilderError};
 pub use exchange::{FetchTradesParams, FetchTradesParamsBuilder, FetchTradesParamsBuilderError};
+pub use exchange::{FetchOrderBookParams, FetchOrderBookParamsBuilder, FetchOrderBookParamsBuilderError};
+pub use exchange::{FetchGreeksParams, FetchGreeksParamsBuilder, FetchGreeksParamsBuilderError};
+pub use exchange::{FetchOptionChainParams, FetchOptionChainParamsBuilder, FetchOptionChainParamsBuilderError};
+pub use exchange::{FetchOhlcvParams, FetchOhlcvParamsBuilder, FetchOhlcvParamsBuilderError};
+pub use exchange::{WatchOhlcvParams, WatchOhlcvParamsBuilder, WatchOhlcvParamsBuilderError};
 pub use exchange::{CreateOrderParams, CreateOrderParamsBuilder, CreateOrderParamsBuilderError};
+pub use exchange::{CancelOrderParams, CancelOrderParamsBuilder, CancelOrderParamsBuilderError};
+pub use exchange::{FetchOrderParams, FetchOrderParamsBuilder, FetchOrderParamsBuilderError};
+pub use exchange::{FetchOrdersParams, FetchOrdersParamsBuilder, FetchOrdersParamsBuilderError};
+pub use exchange::{WatchOrdersParams, WatchOrdersParamsBuilder, WatchOrdersParamsBuilderError};
+pub use exchange::{WatchMyTradesParams, WatchMyTradesParamsBuilder, WatchMyTradesParamsBuilderError};
+pub use exchange::{WatchBalanceParams, WatchBalanceParamsBuilder, WatchBalanceParamsBuilderError};
+pub use exchange::{WatchPositionsParams, WatchPositionsParamsBuilder, WatchPositionsParamsBuilderError};
 pub use exchange::{FetchPositionsParams, FetchPositionsParamsBuilder, FetchPositionsParamsBuilderError};
 pub use exchange::{Properties, PropertiesBuilder, PropertiesBuilderError};
 
 pub use crate::error::{ConnectError, ConnectResult};
 pub use crate::error::{WatchOrderBookError, WatchOrderBookResult};
 pub use crate::error::{WatchTradesError, WatchTradesResult};
+pub use crate::error::{WatchTickersError, WatchTickersResult};
 pub use crate::error::{FetchMarketError, FetchMarketResult};
 pub use crate::error::{FetchPositionsError, FetchPositionsResult};
 pub use crate::error::{FetchBalanceError, FetchBalanceResult};
 pub use crate::error::{FetchTickersError, FetchTickersResult};
+pub use crate::error::{FetchOrderBookError, FetchOrderBookResult};
+pub use crate::error::{FetchGreeksError, FetchGreeksResult};
+pub use crate::error::{FetchOptionChainError, FetchOptionChainResult};
+pub use crate::error::{FetchOhlcvError, FetchOhlcvResult};
+pub use crate::error::{WatchOhlcvError, WatchOhlcvResult};
+pub use crate::error::{WatchOrdersError, WatchOrdersResult};
+pub use crate::error::{WatchMyTradesError, WatchMyTradesResult};
+pub use crate::error::{WatchBalanceError, WatchBalanceResult};
+pub use crate::error::{WatchPositionsError, WatchPositionsResult};
 
 pub use crate::error::{LoadMarketError, LoadMarketResult};
 pub use crate::error::{CommonError, CommonResult};
 pub use crate::error::{WatchError, WatchResult};
 pub use crate::error::{OrderBookError, OrderBookResult};
+pub use crate::error::{OhlcvError, OhlcvResult};
+pub use crate::error::{OrderError, OrderResult};
+pub use crate::error::{TradeError, TradeResult};
+pub use crate::error::{TickerError, TickerResult};
+pub use crate::error::{BalanceError, BalanceResult};
+pub use crate::error::{PositionError, PositionResult};
 pub use crate::error::{CreateOrderError, CreateOrderResult};
-pub use crate::util::channel::Receiver;
+pub use crate::error::{CancelOrderError, CancelOrderResult};
+pub use crate::error::{FetchOrdersError, FetchOrdersResult};
+pub use crate::util::channel::{OverflowPolicy, Receiver, TypedReceiver};
 
 pub use exchange::StreamItem;
 mod exchange;
@@ -3,10 +3,20 @@ use std::hash::{Hash, Hasher};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{OrderBookError, OrderBookResult};
-use crate::util::timestamp_format;
+use crate::{CreateOrderError, CreateOrderResult, OrderBookError, OrderBookResult};
+use crate::util::{number_to_f64, parse_number, round_to_precision, round_to_step, timestamp_format, ZERO};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+/// Number type of prices and amounts. It is `rust_decimal::Decimal` with the `decimal` feature
+/// enabled and `f64` otherwise.
+#[cfg(feature = "decimal")]
+pub type Number = rust_decimal::Decimal;
+
+/// Number type of prices and amounts. It is `rust_decimal::Decimal` with the `decimal` feature
+/// enabled and `f64` otherwise.
+#[cfg(not(feature = "decimal"))]
+pub type Number = f64;
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum OptionType {
     Call,
     Put,
@@ -120,6 +130,12 @@ pub struct Market {
     /// precision for price, amount and cost
     pub precision: Option<Precision>,
 
+    /// minimum price increment, which may not be a power of ten such as 0.05
+    pub tick_size: Option<Number>,
+
+    /// minimum amount increment
+    pub step_size: Option<Number>,
+
     /// market limits for amount, price, cost and leverage
     pub limit: Option<MarketLimit>,
 }
@@ -145,6 +161,11 @@ impl Display for Market {
             } else {
                 write!(f, "-{}", self.strike.unwrap())?;
             }
+            match self.option_type {
+                Some(OptionType::Call) => write!(f, "-C")?,
+                Some(OptionType::Put) => write!(f, "-P")?,
+                None => {}
+            }
         }
 
         Ok(())
@@ -157,6 +178,8 @@ impl Hash for Market {
         self.quote.hash(state);
         self.market_type.hash(state);
         self.expiry.hash(state);
+        self.strike.map(f64::to_bits).hash(state);
+        self.option_type.hash(state);
     }
 }
 
@@ -166,11 +189,70 @@ impl PartialEq<Self> for Market {
             && self.quote == other.quote
             && self.market_type == other.market_type
             && self.expiry == other.expiry
+            && self.strike == other.strike
+            && self.option_type == other.option_type
     }
 }
 
 impl Eq for Market {}
 
+impl Market {
+    /// Rounds the amount down to a multiple of the step size, or truncates it to the amount
+    /// precision when the step size is unknown. The amount is returned as is when both are
+    /// unknown.
+    pub fn amount_to_precision(&self, amount: Number) -> Number {
+        if let Some(step_size) = self.step_size {
+            return round_to_step(amount, step_size);
+        }
+        match self.precision.and_then(|precision| precision.amount) {
+            Some(digits) => round_to_precision(amount, digits, true),
+            None => amount,
+        }
+    }
+
+    /// Rounds the price down to a multiple of the tick size, or rounds it to the price precision
+    /// when the tick size is unknown. The price is returned as is when both are unknown.
+    pub fn price_to_precision(&self, price: Number) -> Number {
+        if let Some(tick_size) = self.tick_size {
+            return round_to_step(price, tick_size);
+        }
+        match self.precision.and_then(|precision| precision.price) {
+            Some(digits) => round_to_precision(price, digits, false),
+            None => price,
+        }
+    }
+
+    /// Checks an order against the amount, price and cost limits of the market. A zero bound
+    /// means that there is no limit. The cost is checked only when the price is given.
+    pub fn validate_order(&self, amount: Number, price: Option<Number>) -> CreateOrderResult<()> {
+        if amount <= ZERO {
+            return Err(CreateOrderError::InvalidAmount(format!("amount must be positive. amount={}", amount)));
+        }
+        let limit = match &self.limit {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        if let Some(range) = &limit.amount {
+            if !range.contains(number_to_f64(amount)) {
+                return Err(CreateOrderError::InvalidAmount(format!("amount is out of range. amount={}, min={}, max={}", amount, range.min, range.max)));
+            }
+        }
+        if let (Some(range), Some(price)) = (&limit.price, price) {
+            if !range.contains(number_to_f64(price)) {
+                return Err(CreateOrderError::InvalidPrice(format!("price is out of range. price={}, min={}, max={}", price, range.min, range.max)));
+            }
+        }
+        if let (Some(range), Some(price)) = (&limit.cost, price) {
+            let cost = price * amount;
+            if !range.contains(number_to_f64(cost)) {
+                return Err(CreateOrderError::InvalidCost(format!("cost is out of range. cost={}, min={}, max={}", cost, range.min, range.max)));
+            }
+        }
+        Ok(())
+    }
+}
+
 
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -240,6 +322,13 @@ pub struct Range {
     pub max: f64,
 }
 
+impl Range {
+    /// Zero bounds are treated as unbounded.
+    pub fn contains(&self, value: f64) -> bool {
+        (self.min <= 0.0 || value >= self.min) && (self.max <= 0.0 || value <= self.max)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct CurrencyLimit {
@@ -417,22 +506,22 @@ pub struct Order {
     pub side: Option<OrderSide>,
 
     /// float price in quote currency (may be empty for market orders)
-    pub price: Option<f64>,
+    pub price: Option<Number>,
 
     /// float average filling price
-    pub average: Option<f64>,
+    pub average: Option<Number>,
 
     /// ordered amount of base currency
-    pub amount: f64,
+    pub amount: Number,
 
     /// filled amount of base currency
-    pub filled: Option<f64>,
+    pub filled: Option<Number>,
 
     /// remaining amount to fill
-    pub remaining: Option<f64>,
+    pub remaining: Option<Number>,
 
     /// 'filled' * 'price' (filling price used where available)
-    pub cost: Option<f64>,
+    pub cost: Option<Number>,
 
     /// a list of order trades/executions
     pub trades: Option<Vec<Trade>>,
@@ -468,13 +557,13 @@ pub struct Trade {
     pub is_maker: Option<bool>,
 
     /// float price in quote currency
-    pub price: f64,
+    pub price: Number,
 
     /// amount of base currency
-    pub amount: f64,
+    pub amount: Number,
 
     /// total cost, `price * amount`,
-    pub cost: f64,
+    pub cost: Number,
 
     /// provided by exchange or calculated by ccxtr
     pub fee: Option<OrderFee>,
@@ -484,7 +573,7 @@ pub struct Trade {
 }
 
 impl Trade {
-    pub fn new(id: String, timestamp: i64, market: Market, order_id: Option<String>, order_type: Option<OrderType>, side: Option<OrderSide>, is_maker: Option<bool>, price: f64, amount: f64, cost: f64, fee: Option<OrderFee>, fees: Option<Vec<OrderFee>>) -> Self {
+    pub fn new(id: String, timestamp: i64, market: Market, order_id: Option<String>, order_type: Option<OrderType>, side: Option<OrderSide>, is_maker: Option<bool>, price: Number, amount: Number, cost: Number, fee: Option<OrderFee>, fees: Option<Vec<OrderFee>>) -> Self {
         Self {
             id,
             timestamp,
@@ -508,13 +597,13 @@ impl Trade {
 #[non_exhaustive]
 pub struct OrderFee {
     /// which currency the fee is (usually quote)
-    currency: String,
+    pub currency: String,
 
     /// the fee amount in that currency
-    cost: f64,
+    pub cost: Number,
 
     /// the fee rate (if available)
-    rate: Option<f64>,
+    pub rate: Option<f64>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -723,18 +812,18 @@ impl From<String> for OrderBook {
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct OrderBookUnit {
-    pub price: f64,
-    pub amount: f64,
+    pub price: Number,
+    pub amount: Number,
 }
 
-impl Into<(f64, f64)> for OrderBookUnit {
-    fn into(self) -> (f64, f64) {
+impl Into<(Number, Number)> for OrderBookUnit {
+    fn into(self) -> (Number, Number) {
         (self.price, self.amount)
     }
 }
 
-impl From<(f64, f64)> for OrderBookUnit {
-    fn from(value: (f64, f64)) -> Self {
+impl From<(Number, Number)> for OrderBookUnit {
+    fn from(value: (Number, Number)) -> Self {
         Self {
             price: value.0,
             amount: value.1,
@@ -748,8 +837,8 @@ impl TryFrom<&Vec<String>> for OrderBookUnit {
 
     fn try_from(value: &Vec<String>) -> OrderBookResult<Self> {
         Ok(OrderBookUnit {
-            price: value[0].parse::<f64>()?,
-            amount: value[1].parse::<f64>()?,
+            price: parse_number(&value[0])?,
+            amount: parse_number(&value[1])?,
         })
     }
 }
@@ -759,12 +848,88 @@ impl TryFrom<&[String; 2]> for OrderBookUnit {
 
     fn try_from(value: &[String; 2]) -> OrderBookResult<Self> {
         Ok(OrderBookUnit {
-            price: value[0].parse::<f64>()?,
-            amount: value[1].parse::<f64>()?,
+            price: parse_number(&value[0])?,
+            amount: parse_number(&value[1])?,
         })
     }
 }
 
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Timeframe {
+    #[default]
+    Minute1,
+    Minute3,
+    Minute5,
+    Minute15,
+    Minute30,
+    Hour1,
+    Hour2,
+    Hour4,
+    Hour6,
+    Hour8,
+    Hour12,
+    Day1,
+    Day3,
+    Week1,
+    Month1,
+}
+
+impl Display for Timeframe {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Timeframe::Minute1 => write!(f, "1m"),
+            Timeframe::Minute3 => write!(f, "3m"),
+            Timeframe::Minute5 => write!(f, "5m"),
+            Timeframe::Minute15 => write!(f, "15m"),
+            Timeframe::Minute30 => write!(f, "30m"),
+            Timeframe::Hour1 => write!(f, "1h"),
+            Timeframe::Hour2 => write!(f, "2h"),
+            Timeframe::Hour4 => write!(f, "4h"),
+            Timeframe::Hour6 => write!(f, "6h"),
+            Timeframe::Hour8 => write!(f, "8h"),
+            Timeframe::Hour12 => write!(f, "12h"),
+            Timeframe::Day1 => write!(f, "1d"),
+            Timeframe::Day3 => write!(f, "3d"),
+            Timeframe::Week1 => write!(f, "1w"),
+            Timeframe::Month1 => write!(f, "1M"),
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Ohlcv {
+    /// market
+    pub market: Market,
+
+    /// candle length
+    pub timeframe: Timeframe,
+
+    /// Unix timestamp in milliseconds of the candle open
+    pub timestamp: i64,
+
+    /// float price in quote currency
+    pub open: f64,
+
+    /// float price in quote currency
+    pub high: f64,
+
+    /// float price in quote currency
+    pub low: f64,
+
+    /// float price in quote currency
+    pub close: f64,
+
+    /// traded amount of base currency
+    pub volume: f64,
+
+    /// false while the candle is still in progress
+    pub is_closed: bool,
+}
+
+
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct MarginLoan {
@@ -820,7 +985,7 @@ pub struct BalanceItem {
 }
 
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[non_exhaustive]
 pub struct Ticker {
     pub ask: Option<f64>,
@@ -846,4 +1011,108 @@ pub struct Ticker {
     pub market: Market,
     pub timestamp: i64,
     pub vwap: f64,
-}
\ No newline at end of file
+}
+
+
+/// Mark price and sensitivities of an option. Implied volatilities are annualized fractions.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Greeks {
+    pub market: Market,
+    pub timestamp: i64,
+
+    /// change of the option price for a change of 1 in the underlying price
+    pub delta: f64,
+
+    /// change of the delta for a change of 1 in the underlying price
+    pub gamma: f64,
+
+    /// change of the option price per day
+    pub theta: f64,
+
+    /// change of the option price for a change of 1 percentage point in the volatility
+    pub vega: f64,
+
+    #[serde(rename = "markPrice")]
+    pub mark_price: f64,
+    #[serde(rename = "markImpliedVolatility")]
+    pub mark_implied_volatility: f64,
+    #[serde(rename = "bidImpliedVolatility")]
+    pub bid_implied_volatility: Option<f64>,
+    #[serde(rename = "askImpliedVolatility")]
+    pub ask_implied_volatility: Option<f64>,
+
+    /// index price of the underlying
+    #[serde(rename = "underlyingPrice")]
+    pub underlying_price: Option<f64>,
+}
+
+
+/// Greeks of the listed options of an underlying, sorted by expiry, strike and option type.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct OptionChain {
+    /// uppercase string, unified currency code of the underlying
+    pub base: String,
+    pub timestamp: i64,
+
+    /// index price of the underlying
+    #[serde(rename = "underlyingPrice")]
+    pub underlying_price: Option<f64>,
+
+    pub options: Vec<Greeks>,
+}
+
+impl OptionChain {
+    /// Expiries of the options in the chain, in ascending order.
+    pub fn expiries(&self) -> Vec<i64> {
+        let mut expiries: Vec<i64> = self.options.iter().filter_map(|option| option.market.expiry).collect();
+        expiries.dedup();
+        expiries
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::CreateOrderError;
+    use crate::util::number;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_order() {
+        let market = Market {
+            precision: Some(Precision { price: Some(2), amount: Some(3), cost: None }),
+            limit: Some(MarketLimit {
+                amount: Some(Range { min: 0.001, max: 100.0 }),
+                price: Some(Range { min: 0.01, max: 0.0 }),
+                cost: Some(Range { min: 10.0, max: 0.0 }),
+                leverage: None,
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(market.amount_to_precision(number("0.12345")), number("0.123"));
+        assert_eq!(market.price_to_precision(number("101.005")), number("101.01"));
+        assert!(market.validate_order(number("0.1"), Some(number("101"))).is_ok());
+        assert!(market.validate_order(number("0.1"), None).is_ok());
+        assert!(matches!(market.validate_order(number("0.0001"), None), Err(CreateOrderError::InvalidAmount(_))));
+        assert!(matches!(market.validate_order(number("0.05"), Some(number("101"))), Err(CreateOrderError::InvalidCost(_))));
+    }
+
+    #[test]
+    fn test_round_to_tick_size() {
+        let market = Market {
+            precision: Some(Precision { price: Some(2), amount: Some(1), cost: None }),
+            tick_size: Some(number("0.05")),
+            step_size: Some(number("0.5")),
+            ..Default::default()
+        };
+
+        assert_eq!(market.price_to_precision(number("101.07")), number("101.05"));
+        assert_eq!(market.price_to_precision(number("1.15")), number("1.15"));
+        assert_eq!(market.price_to_precision(number("1.149")), number("1.1"));
+        assert_eq!(market.amount_to_precision(number("2.9")), number("2.5"));
+        assert_eq!(market.amount_to_precision(number("3")), number("3"));
+    }
+}
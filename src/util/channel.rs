@@ -1,27 +1,451 @@
-use futures_util::stream::SelectAll;
-use futures_util::StreamExt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
 
-use crate::client::WsClient;
-use crate::exchange::StreamItem;
+use async_broadcast::TrySendError;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::oneshot;
+
+use crate::client::{SubscriptionCommand, SubscriptionDispatcher, SubscriptionSpec, WsClient};
+use crate::error::{BalanceError, Error, OhlcvError, OrderBookError, OrderError, PositionError, Result, TickerError, TradeError};
+use crate::exchange::{StreamItem, Unifier};
+use crate::model::{Balance, Market, Ohlcv, Order, OrderBook, Position, Ticker, Trade};
 use crate::{WatchError, WatchResult};
 
-pub struct Receiver {
-    clients: SelectAll<WsClient>,
+/// time to wait for the exchange to acknowledge a change of the subscriptions
+const SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// items buffered by a receiver when `Properties::channel_capacity` is not set
+const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+/// What a stream does with new items while its channel is full.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// stop reading the connections until the slowest receiver catches up
+    #[default]
+    Block,
+    /// drop the oldest buffered item to make room for the new one
+    DropOldest,
+    /// drop the new item
+    DropNewest,
 }
 
+/// Capacity and overflow policy of the channels of the receivers of an exchange.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ChannelConfig {
+    pub(crate) capacity: usize,
+    pub(crate) overflow_policy: OverflowPolicy,
+}
 
-impl Receiver {
-    pub(crate) fn new(clients: Vec<WsClient>) -> Self {
-        let clients = futures_util::stream::select_all(clients);
+impl ChannelConfig {
+    pub(crate) fn new(capacity: Option<usize>, overflow_policy: Option<OverflowPolicy>) -> Self {
         Self {
-            clients
+            capacity: capacity.unwrap_or(DEFAULT_CHANNEL_CAPACITY).max(1),
+            overflow_policy: overflow_policy.unwrap_or_default(),
         }
     }
+
+    /// Channel between a background task of an exchange and a receiver. The items are buffered
+    /// by the receiver only, so that the capacity and the overflow policy apply as set.
+    pub(crate) fn bounded(&self) -> (Sender, Receiver) {
+        let (mut items, rx) = async_broadcast::broadcast(self.capacity);
+        items.set_overflow(self.overflow_policy == OverflowPolicy::DropOldest);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let sender = Sender { items, dropped: dropped.clone(), overflow_policy: self.overflow_policy };
+        (sender, Receiver { items: rx, dropped, subscriptions: None })
+    }
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+/// Sending half of the channel of a `Receiver`, fed by a background task.
+#[derive(Clone)]
+pub(crate) struct Sender {
+    items: async_broadcast::Sender<WatchResult<StreamItem>>,
+    dropped: Arc<AtomicU64>,
+    overflow_policy: OverflowPolicy,
+}
+
+impl Sender {
+    /// Sends an item, applying the overflow policy while the channel is full. Fails with
+    /// `WatchError::Disconnected` once every clone of the receiver is dropped.
+    pub(crate) async fn send(&self, item: WatchResult<StreamItem>) -> WatchResult<()> {
+        let sent = match self.overflow_policy {
+            OverflowPolicy::Block => self.items.broadcast(item).await.is_ok(),
+            OverflowPolicy::DropOldest | OverflowPolicy::DropNewest => match self.items.try_broadcast(item) {
+                Ok(displaced) => {
+                    if displaced.is_some() {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    true
+                }
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(_) => false,
+            },
+        };
+        match sent {
+            true => Ok(()),
+            false => Err(WatchError::Disconnected),
+        }
+    }
+
+    /// Whether every clone of the receiver is dropped.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.items.is_closed()
+    }
+}
+
+
+/// Items of a stream, read from the connections by a background task.
+///
+/// Clones receive every item from the point they were cloned at, so that several consumers can
+/// share the connections. The connections are closed once every clone is dropped.
+#[derive(Clone)]
+pub struct Receiver {
+    items: async_broadcast::Receiver<WatchResult<StreamItem>>,
+    /// items dropped by the overflow policy
+    dropped: Arc<AtomicU64>,
+    subscriptions: Option<Arc<Subscriptions>>,
+}
+
+/// Changes the topics of the connections of a receiver.
+struct Subscriptions {
+    commands: flume::Sender<SubscriptionCommand>,
+    unifier: Unifier,
+    topic: Arc<dyn Fn(&str) -> String + Send + Sync>,
+}
+
+
+impl Receiver {
+    /// Creates a receiver of the items of the connections, read by a background task until the
+    /// connections are closed or every clone of the receiver is dropped.
+    pub(crate) fn new(clients: Vec<WsClient>, config: &ChannelConfig) -> Self {
+        let (tx, receiver) = config.bounded();
+        let mut clients = futures_util::stream::select_all(clients);
+        tokio::spawn(async move {
+            while let Some(item) = clients.next().await {
+                if tx.send(item).await.is_err() { // the receiver is dropped
+                    return;
+                }
+            }
+        });
+        receiver
+    }
+
+    /// Creates a receiver subscribed to the topics of the symbol ids, whose markets can be changed
+    /// afterwards.
+    pub(crate) async fn subscribed(spec: SubscriptionSpec, symbol_ids: &[String], config: &ChannelConfig) -> Result<Self> {
+        let topics = symbol_ids.iter().map(|symbol_id| (spec.topic)(symbol_id)).collect();
+        let (unifier, topic) = (spec.unifier.clone(), spec.topic.clone());
+        let (tx, mut receiver) = config.bounded();
+        let commands = SubscriptionDispatcher::start(spec, topics, tx).await?;
+        receiver.subscriptions = Some(Arc::new(Subscriptions { commands, unifier, topic }));
+        Ok(receiver)
+    }
+
     pub async fn receive(&mut self) -> WatchResult<StreamItem> {
-        let option = self.clients.next().await;
-        if option.is_none() {
-            return Err(WatchError::Disconnected);
+        self.next().await.unwrap_or(Err(WatchError::Disconnected))
+    }
+
+    /// Number of items dropped so far by the overflow policy, across every clone of the receiver.
+    /// Always zero with `OverflowPolicy::Block`.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Adds markets to the stream, on the open connections while they have room for them and on
+    /// new connections otherwise. Resolves once the exchange has acknowledged them.
+    ///
+    /// Only the receivers of streams with a subscription per market support it, and the others
+    /// fail with `WatchError::NotImplemented`.
+    pub async fn subscribe(&self, markets: &[Market]) -> WatchResult<()> {
+        self.send(markets, SubscriptionCommand::Subscribe).await
+    }
+
+    /// Drops markets from the stream. Resolves once the exchange has acknowledged it, although
+    /// items of the markets that were already sent may still be received.
+    pub async fn unsubscribe(&self, markets: &[Market]) -> WatchResult<()> {
+        self.send(markets, SubscriptionCommand::Unsubscribe).await
+    }
+
+    /// Order books of the stream. Other items are skipped.
+    pub fn order_books(self) -> TypedReceiver<OrderBook, OrderBookError> {
+        TypedReceiver::new(self, |item| match item {
+            StreamItem::OrderBook(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    /// Public trades of the stream. Other items are skipped.
+    pub fn trades(self) -> TypedReceiver<Trade, TradeError> {
+        TypedReceiver::new(self, |item| match item {
+            StreamItem::Trade(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    /// Tickers of the stream. Other items are skipped.
+    pub fn tickers(self) -> TypedReceiver<Ticker, TickerError> {
+        TypedReceiver::new(self, |item| match item {
+            StreamItem::Ticker(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    /// Candles of the stream. Other items are skipped.
+    pub fn ohlcvs(self) -> TypedReceiver<Ohlcv, OhlcvError> {
+        TypedReceiver::new(self, |item| match item {
+            StreamItem::Ohlcv(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    /// Order updates of the stream. Other items are skipped.
+    pub fn orders(self) -> TypedReceiver<Order, OrderError> {
+        TypedReceiver::new(self, |item| match item {
+            StreamItem::Order(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    /// Fills of the user's orders. Other items are skipped.
+    pub fn my_trades(self) -> TypedReceiver<Trade, TradeError> {
+        TypedReceiver::new(self, |item| match item {
+            StreamItem::MyTrade(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    /// Balance updates of the stream. Other items are skipped.
+    pub fn balances(self) -> TypedReceiver<Balance, BalanceError> {
+        TypedReceiver::new(self, |item| match item {
+            StreamItem::Balance(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    /// Position updates of the stream. Other items are skipped.
+    pub fn positions(self) -> TypedReceiver<Position, PositionError> {
+        TypedReceiver::new(self, |item| match item {
+            StreamItem::Position(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    async fn send(&self, markets: &[Market], command: fn(Vec<String>, oneshot::Sender<Result<()>>) -> SubscriptionCommand) -> WatchResult<()> {
+        let subscriptions = self.subscriptions.as_ref().ok_or(WatchError::NotImplemented)?;
+        if markets.is_empty() {
+            return Err(Error::MissingMarkets.into());
+        }
+        let mut topics = vec![];
+        for market in markets {
+            match subscriptions.unifier.get_symbol_id(market) {
+                Some(symbol_id) => topics.push((subscriptions.topic)(&symbol_id)),
+                None => return Err(WatchError::SymbolNotFound(format!("{:?}", market))),
+            }
+        }
+
+        let (ack, ack_rx) = oneshot::channel();
+        subscriptions.commands.send_async(command(topics, ack)).await.map_err(|_| WatchError::Disconnected)?;
+        match tokio::time::timeout(SUBSCRIPTION_TIMEOUT, ack_rx).await {
+            Ok(Ok(result)) => Ok(result?),
+            Ok(Err(_)) => Err(WatchError::Disconnected),
+            Err(_) => Err(WatchError::WebsocketError("the subscription was not acknowledged in time".into())),
+        }
+    }
+}
+
+
+/// Ends once the connections are closed for good, where `receive` returns
+/// `WatchError::Disconnected`.
+impl Stream for Receiver {
+    type Item = WatchResult<StreamItem>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // items dropped by the overflow policy are skipped, and counted by `dropped` already
+        self.items.poll_next_unpin(cx)
+    }
+}
+
+
+/// Items of a single kind of a `Receiver`, e.g. its order books.
+///
+/// Errors of the stream itself, like `WatchError::IdleTimeout`, are yielded as the `WatchError`
+/// variant of the error of the item, and so are reconnections, as `WatchError::Reconnected`.
+pub struct TypedReceiver<T, E> {
+    receiver: Receiver,
+    select: fn(StreamItem) -> Option<std::result::Result<T, E>>,
+}
+
+impl<T, E> TypedReceiver<T, E> {
+    fn new(receiver: Receiver, select: fn(StreamItem) -> Option<std::result::Result<T, E>>) -> Self {
+        Self { receiver, select }
+    }
+
+    /// The receiver of all the items, e.g. to change its markets.
+    pub fn receiver(&self) -> &Receiver {
+        &self.receiver
+    }
+}
+
+impl<T, E: From<WatchError>> Stream for TypedReceiver<T, E> {
+    type Item = std::result::Result<T, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match futures_util::ready!(self.receiver.poll_next_unpin(cx)) {
+                Some(Ok(StreamItem::Reconnected)) => return Poll::Ready(Some(Err(WatchError::Reconnected.into()))),
+                Some(Ok(item)) => {
+                    if let Some(item) = (self.select)(item) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use tokio_stream::StreamMap;
+
+    use crate::error::OrderBookError;
+    use crate::exchange::StreamItem;
+    use crate::model::{OrderBook, Trade};
+    use crate::util::channel::{ChannelConfig, OverflowPolicy, Receiver};
+    use crate::WatchError;
+
+    /// ids of the items received until the channel is closed
+    async fn receive_all(receiver: &mut Receiver) -> Vec<i64> {
+        let mut ids = vec![];
+        loop {
+            match receiver.receive().await {
+                Ok(StreamItem::Subscribed(id)) => ids.push(id),
+                Err(WatchError::Disconnected) => return ids,
+                item => panic!("unexpected item: {:?}", item),
+            }
+        }
+    }
+
+    /// sends 5 items to a receiver of capacity 2 that does not read them until 3 are dropped
+    async fn overflow(policy: OverflowPolicy) -> Receiver {
+        let config = ChannelConfig::new(Some(2), Some(policy));
+        let (tx, receiver) = config.bounded();
+        for id in 0..5 {
+            tx.send(Ok(StreamItem::Subscribed(id))).await.unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while receiver.dropped() < 3 {
+                tokio::task::yield_now().await;
+            }
+        }).await.unwrap();
+        receiver
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest() {
+        let mut receiver = overflow(OverflowPolicy::DropNewest).await;
+        assert_eq!(receive_all(&mut receiver).await, vec![0, 1]);
+        assert_eq!(receiver.dropped(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let mut receiver = overflow(OverflowPolicy::DropOldest).await;
+        assert_eq!(receive_all(&mut receiver).await, vec![3, 4]);
+        assert_eq!(receiver.dropped(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_block() {
+        let config = ChannelConfig::new(Some(2), None);
+        let (tx, mut receiver) = config.bounded();
+        tokio::spawn(async move {
+            for id in 0..10 {
+                tx.send(Ok(StreamItem::Subscribed(id))).await.unwrap();
+            }
+        });
+        assert_eq!(receive_all(&mut receiver).await, (0..10).collect::<Vec<_>>());
+        assert_eq!(receiver.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_clone() {
+        let config = ChannelConfig::new(Some(2), None);
+        let (tx, mut receiver) = config.bounded();
+        let mut clone = receiver.clone();
+        let consumer = tokio::spawn(async move { receive_all(&mut clone).await });
+        tokio::spawn(async move {
+            for id in 0..5 {
+                tx.send(Ok(StreamItem::Subscribed(id))).await.unwrap();
+            }
+        });
+        assert_eq!(receive_all(&mut receiver).await, vec![0, 1, 2, 3, 4]);
+        assert_eq!(consumer.await.unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let config = ChannelConfig::default();
+        let (tx, receiver) = config.bounded();
+        for id in 0..3 {
+            tx.send(Ok(StreamItem::Subscribed(id))).await.unwrap();
+        }
+        tx.send(Ok(StreamItem::Reconnected)).await.unwrap();
+        drop(tx);
+        let ids: Vec<i64> = receiver
+            .filter_map(|item| async move { match item {
+                Ok(StreamItem::Subscribed(id)) => Some(id),
+                _ => None,
+            }})
+            .collect().await;
+        assert_eq!(ids, vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_typed_receiver() {
+        let config = ChannelConfig::default();
+        let (tx, receiver) = config.bounded();
+        let mut order_books = receiver.order_books();
+        tx.send(Ok(StreamItem::Trade(Ok(Trade::default())))).await.unwrap();
+        tx.send(Ok(StreamItem::OrderBook(Ok(OrderBook::default())))).await.unwrap();
+        tx.send(Err(WatchError::IdleTimeout(Duration::from_secs(1)))).await.unwrap();
+        tx.send(Ok(StreamItem::Reconnected)).await.unwrap();
+        drop(tx);
+        assert_eq!(order_books.next().await.unwrap().unwrap(), OrderBook::default());
+        assert!(matches!(order_books.next().await, Some(Err(OrderBookError::WatchError(WatchError::IdleTimeout(_))))));
+        assert!(matches!(order_books.next().await, Some(Err(OrderBookError::WatchError(WatchError::Reconnected)))));
+        assert!(order_books.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_map() {
+        let config = ChannelConfig::default();
+        let mut streams = StreamMap::new();
+        let mut senders = vec![];
+        for name in ["binance", "okx"] {
+            let (tx, receiver) = config.bounded();
+            streams.insert(name, receiver.trades());
+            senders.push(tx);
         }
-        option.unwrap()
+        senders[1].send(Ok(StreamItem::Trade(Ok(Trade::default())))).await.unwrap();
+        let (name, trade) = streams.next().await.unwrap();
+        assert_eq!(name, "okx");
+        assert!(trade.is_ok());
     }
 }
@@ -0,0 +1,193 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::time::Instant;
+
+use crate::error::Result;
+
+/// Offset of the exchange clock from the local one, applied to the timestamps of signed requests.
+#[derive(Clone)]
+pub(crate) struct Clock {
+    /// the offset is measured again once it is older than this. it stays zero when not set
+    sync_interval: Option<Duration>,
+    state: Arc<Mutex<ClockState>>,
+}
+
+#[derive(Default)]
+struct ClockState {
+    offset: i64,
+    synced_at: Option<Instant>,
+    /// whether a background task keeps the offset up to date
+    syncing: bool,
+}
+
+impl ClockState {
+    fn update(&mut self, sent: i64, server_time: i64, received: i64) {
+        let rtt = received - sent;
+        self.offset = server_time + rtt / 2 - received;
+        self.synced_at = Some(Instant::now());
+    }
+}
+
+impl Clock {
+    pub(crate) fn new(sync_interval: Option<Duration>) -> Self {
+        Self {
+            sync_interval,
+            state: Arc::new(Mutex::new(ClockState::default())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ClockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether the offset should be measured before the next timestamp is taken.
+    pub(crate) fn needs_sync(&self) -> bool {
+        match (self.sync_interval, self.lock().synced_at) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(interval), Some(synced_at)) => synced_at.elapsed() >= interval,
+        }
+    }
+
+    /// Records a measurement of the server time. `sent` and `received` are the local times in
+    /// milliseconds around the request, and the server is assumed to answer halfway through.
+    pub(crate) fn update(&self, sent: i64, server_time: i64, received: i64) {
+        self.lock().update(sent, server_time, received);
+    }
+
+    /// Takes a first measurement with `measure`, then keeps taking one every sync interval in a
+    /// background task that ends with the last clone of the clock, so that timestamps never wait
+    /// for the exchange. `measure` returns the `sent`, server and `received` times of `update`.
+    /// Does nothing without a sync interval or when the task is already running.
+    pub(crate) async fn start_sync<F, Fut>(&self, measure: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(i64, i64, i64)>> + Send + 'static,
+    {
+        let interval = match self.sync_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        if self.lock().syncing {
+            return;
+        }
+        let measurement = measure().await;
+        {
+            let mut state = self.lock();
+            // a failed first measurement leaves the offset at zero until the next one
+            if let Ok((sent, server_time, received)) = measurement {
+                state.update(sent, server_time, received);
+            }
+            if state.syncing {
+                return;
+            }
+            state.syncing = true;
+        }
+
+        let state = Arc::downgrade(&self.state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+            loop {
+                ticker.tick().await;
+                if state.strong_count() == 0 {
+                    return;
+                }
+                // a failed measurement keeps the last offset until the next one
+                if let Ok((sent, server_time, received)) = measure().await {
+                    match state.upgrade() {
+                        Some(state) => state.lock().unwrap_or_else(|e| e.into_inner()).update(sent, server_time, received),
+                        None => return,
+                    }
+                }
+            }
+        });
+    }
+
+    /// milliseconds to add to the local time to get the exchange time
+    pub(crate) fn offset(&self) -> i64 {
+        self.lock().offset
+    }
+
+    /// Current exchange time in milliseconds.
+    pub(crate) fn timestamp(&self) -> i64 {
+        Utc::now().timestamp_millis() + self.offset()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::time::Duration;
+
+    use crate::error::Error;
+
+    use super::Clock;
+
+    #[test]
+    fn test_clock() {
+        let clock = Clock::new(None);
+        assert!(!clock.needs_sync());
+        assert_eq!(clock.offset(), 0);
+
+        let clock = Clock::new(Some(Duration::from_secs(60)));
+        assert!(clock.needs_sync());
+        clock.update(1_000, 1_600, 1_200);
+        assert!(!clock.needs_sync());
+        assert_eq!(clock.offset(), 500);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_start_sync() {
+        let calls = Arc::new(AtomicI64::new(0));
+        let measure = {
+            let calls = calls.clone();
+            move || {
+                let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                async move { Ok((0, call * 1_000, 0)) }
+            }
+        };
+        let clock = Clock::new(Some(Duration::from_secs(60)));
+        clock.start_sync(measure.clone()).await;
+        clock.start_sync(measure).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(clock.offset(), 1_000);
+
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(clock.offset(), 2_000);
+
+        // the task ends with the clock
+        drop(clock);
+        tokio::time::sleep(Duration::from_secs(120)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_start_sync_failure() {
+        let calls = Arc::new(AtomicI64::new(0));
+        let measure = {
+            let calls = calls.clone();
+            move || {
+                let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    match call {
+                        1 => Err(Error::HttpError("unreachable".into())),
+                        _ => Ok((0, call * 1_000, 0)),
+                    }
+                }
+            }
+        };
+        let clock = Clock::new(Some(Duration::from_secs(60)));
+        clock.start_sync(measure).await;
+        assert_eq!(clock.offset(), 0);
+
+        // the background task measures the offset again
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        assert_eq!(clock.offset(), 2_000);
+    }
+}
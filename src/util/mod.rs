@@ -4,9 +4,13 @@ use std::str::FromStr;
 use chrono::{TimeZone, Utc};
 
 use crate::error::{Error, Result};
+use crate::model::Number;
 
 mod collections;
 pub(crate) mod channel;
+pub(crate) mod clock;
+pub(crate) mod order_book;
+pub(crate) mod rate_limiter;
 
 pub(crate) fn into_precision(s: String) -> Result<isize> {
     if s.contains('e') {
@@ -43,6 +47,108 @@ pub(crate) fn parse_float64(s: &str) -> Result<f64> {
     Ok(d)
 }
 
+#[cfg(feature = "decimal")]
+pub(crate) const ZERO: Number = rust_decimal::Decimal::ZERO;
+
+#[cfg(not(feature = "decimal"))]
+pub(crate) const ZERO: Number = 0.0;
+
+/// Parses a price or amount without going through `f64` when the `decimal` feature is enabled.
+pub(crate) fn parse_number(s: &str) -> Result<Number> {
+    let d = Number::from_str(s)?;
+    Ok(d)
+}
+
+/// Rounds to the given number of decimal digits. Negative digits round to tens, hundreds and so
+/// on. Truncation rounds toward zero.
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn round_to_precision(value: Number, digits: isize, truncate: bool) -> Number {
+    let factor = 10f64.powi(digits as i32);
+    let scaled = value * factor;
+    let rounded = scaled.round();
+    // 0.3 * 10 is 2.9999999999999996, which must not be truncated to 2
+    if !truncate || (scaled - rounded).abs() < scaled.abs().max(1.0) * 1e-9 {
+        return rounded / factor;
+    }
+    scaled.trunc() / factor
+}
+
+/// Rounds to the given number of decimal digits. Negative digits round to tens, hundreds and so
+/// on. Truncation rounds toward zero.
+#[cfg(feature = "decimal")]
+pub(crate) fn round_to_precision(value: Number, digits: isize, truncate: bool) -> Number {
+    use rust_decimal::RoundingStrategy;
+
+    let strategy = match truncate {
+        true => RoundingStrategy::ToZero,
+        false => RoundingStrategy::MidpointAwayFromZero,
+    };
+    if digits >= 0 {
+        return value.round_dp_with_strategy(digits as u32, strategy);
+    }
+    let factor = Number::from(10i64.pow(digits.unsigned_abs() as u32));
+    (value / factor).round_dp_with_strategy(0, strategy) * factor
+}
+
+/// Rounds down to a multiple of `step`, such as a tick size of 0.05. A non-positive step leaves
+/// the value as is.
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn round_to_step(value: Number, step: Number) -> Number {
+    if step <= ZERO {
+        return value;
+    }
+    let steps = value / step;
+    let rounded = steps.round();
+    // 1.15 / 0.05 is 22.999999999999996, which must not be rounded down to 22
+    let steps = match (steps - rounded).abs() < steps.abs().max(1.0) * 1e-9 {
+        true => rounded,
+        false => steps.floor(),
+    };
+    // 22 * 0.05 is 1.1000000000000001, so round to the digits of the step
+    let digits = step.to_string().split_once('.').map_or(0, |(_, fraction)| fraction.len());
+    round_to_precision(steps * step, digits as isize, false)
+}
+
+/// Rounds down to a multiple of `step`, such as a tick size of 0.05. A non-positive step leaves
+/// the value as is.
+#[cfg(feature = "decimal")]
+pub(crate) fn round_to_step(value: Number, step: Number) -> Number {
+    if step <= ZERO {
+        return value;
+    }
+    (value / step).floor() * step
+}
+
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn number_to_f64(value: Number) -> f64 {
+    value
+}
+
+#[cfg(feature = "decimal")]
+pub(crate) fn number_to_f64(value: Number) -> f64 {
+    use rust_decimal::prelude::ToPrimitive;
+
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn f64_to_number(value: f64) -> Number {
+    value
+}
+
+#[cfg(feature = "decimal")]
+pub(crate) fn f64_to_number(value: f64) -> Number {
+    use rust_decimal::prelude::FromPrimitive;
+
+    Number::from_f64(value).unwrap_or_default()
+}
+
+/// Price or amount literal for tests that have to build with and without the `decimal` feature.
+#[cfg(test)]
+pub(crate) fn number(s: &str) -> Number {
+    parse_number(s).unwrap()
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -53,6 +159,24 @@ mod test {
         assert_eq!(super::into_precision("1".to_string()).unwrap(), 0);
         assert_eq!(super::into_precision("1e-7".to_string()).unwrap(), 7);
     }
+
+    #[test]
+    fn test_round_to_precision() {
+        use super::{number, round_to_precision};
+
+        assert_eq!(round_to_precision(number("0.3"), 1, true), number("0.3"));
+        assert_eq!(round_to_precision(number("1.23456"), 3, true), number("1.234"));
+        assert_eq!(round_to_precision(number("1.23456"), 3, false), number("1.235"));
+        assert_eq!(round_to_precision(number("1234"), -2, true), number("1200"));
+        assert_eq!(round_to_precision(number("1250"), -2, false), number("1300"));
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_parse_number() {
+        let amount = super::number("0.1") + super::number("0.2");
+        assert_eq!(amount.to_string(), "0.3");
+    }
 }
 
 
@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::model::{Market, Number, OrderBook, OrderBookUnit};
+use crate::util::ZERO;
+
+/// Price wrapper with a total order so that price levels can be kept sorted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct PriceKey(Number);
+
+impl Eq for PriceKey {}
+
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey {
+    #[cfg(not(feature = "decimal"))]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+
+    #[cfg(feature = "decimal")]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+
+/// Full-depth order book maintained locally from a snapshot and subsequent diffs.
+pub(crate) struct LocalOrderBook {
+    market: Market,
+    bids: BTreeMap<PriceKey, Number>,
+    asks: BTreeMap<PriceKey, Number>,
+    timestamp: Option<i64>,
+
+    /// None until the book is initialized from a snapshot
+    last_update_id: Option<i64>,
+}
+
+impl LocalOrderBook {
+    pub(crate) fn new(market: Market) -> Self {
+        Self {
+            market,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            timestamp: None,
+            last_update_id: None,
+        }
+    }
+
+    pub(crate) fn market(&self) -> &Market {
+        &self.market
+    }
+
+    pub(crate) fn last_update_id(&self) -> Option<i64> {
+        self.last_update_id
+    }
+
+    pub(crate) fn is_synchronized(&self) -> bool {
+        self.last_update_id.is_some()
+    }
+
+    /// Replaces every level with the given snapshot.
+    pub(crate) fn reset(&mut self, bids: &[OrderBookUnit], asks: &[OrderBookUnit], last_update_id: i64) {
+        self.bids.clear();
+        self.asks.clear();
+        self.timestamp = None;
+        self.last_update_id = Some(last_update_id);
+        self.update(bids, asks, last_update_id, None);
+    }
+
+    /// Drops every level. The book has to be reset from a new snapshot before it is used again.
+    pub(crate) fn invalidate(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.timestamp = None;
+        self.last_update_id = None;
+    }
+
+    /// Applies absolute quantities for the given price levels. A zero amount removes the level.
+    pub(crate) fn update(&mut self, bids: &[OrderBookUnit], asks: &[OrderBookUnit], last_update_id: i64, timestamp: Option<i64>) {
+        for unit in bids {
+            Self::update_level(&mut self.bids, unit);
+        }
+        for unit in asks {
+            Self::update_level(&mut self.asks, unit);
+        }
+        self.last_update_id = Some(last_update_id);
+        self.timestamp = timestamp.or(self.timestamp);
+    }
+
+    /// Drops the levels beyond the best `depth` of each side, for streams that only maintain the
+    /// top of the book.
+    pub(crate) fn truncate(&mut self, depth: usize) {
+        while self.bids.len() > depth {
+            self.bids.pop_first();
+        }
+        while self.asks.len() > depth {
+            self.asks.pop_last();
+        }
+    }
+
+    fn update_level(levels: &mut BTreeMap<PriceKey, Number>, unit: &OrderBookUnit) {
+        if unit.amount == ZERO {
+            levels.remove(&PriceKey(unit.price));
+        } else {
+            levels.insert(PriceKey(unit.price), unit.amount);
+        }
+    }
+
+    /// Bids sorted from the highest price and asks sorted from the lowest price.
+    pub(crate) fn to_order_book(&self) -> OrderBook {
+        let bids = self.bids.iter().rev().map(|(price, amount)| (price.0, *amount).into()).collect();
+        let asks = self.asks.iter().map(|(price, amount)| (price.0, *amount).into()).collect();
+        OrderBook::new(bids, asks, self.market.clone(), self.timestamp, self.last_update_id)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::model::{Market, OrderBookUnit};
+    use crate::util::number;
+
+    use super::LocalOrderBook;
+
+    #[test]
+    fn test_local_order_book() {
+        let mut book = LocalOrderBook::new(Market::default());
+        assert!(!book.is_synchronized());
+
+        let bids: Vec<OrderBookUnit> = vec![(number("99"), number("1")).into(), (number("100"), number("2")).into()];
+        let asks: Vec<OrderBookUnit> = vec![(number("102"), number("1")).into(), (number("101"), number("3")).into()];
+        book.reset(&bids, &asks, 10);
+        assert_eq!(book.last_update_id(), Some(10));
+
+        book.update(&[(number("100"), number("0")).into(), (number("98"), number("5")).into()], &[(number("101"), number("4")).into()], 11, Some(1000));
+        let order_book = book.to_order_book();
+        assert_eq!(order_book.bids, vec![(number("99"), number("1")).into(), (number("98"), number("5")).into()]);
+        assert_eq!(order_book.asks, vec![(number("101"), number("4")).into(), (number("102"), number("1")).into()]);
+        assert_eq!(order_book.last_update_id, Some(11));
+        assert_eq!(order_book.timestamp, Some(1000));
+
+        book.truncate(1);
+        let order_book = book.to_order_book();
+        assert_eq!(order_book.bids, vec![(number("99"), number("1")).into()]);
+        assert_eq!(order_book.asks, vec![(number("101"), number("4")).into()]);
+
+        book.invalidate();
+        assert!(!book.is_synchronized());
+        assert!(book.to_order_book().bids.is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::header::HeaderMap;
+use tokio::time::Instant;
+
+/// Request weight allowed per interval.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RateLimit {
+    pub(crate) interval: Duration,
+    pub(crate) limit: u32,
+
+    /// response header carrying the weight the exchange has counted in the current interval
+    pub(crate) header: Option<String>,
+}
+
+
+/// Token bucket refilled at `limit / interval`. Exchanges count the weight in fixed windows
+/// aligned to the clock, so the weight reported by the exchange for the current window caps the
+/// tokens as well.
+struct Bucket {
+    rate_limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+
+    /// index of the window `used` belongs to
+    window: u128,
+    used: u32,
+}
+
+impl Bucket {
+    fn new(rate_limit: RateLimit, now: Instant, epoch: Duration) -> Self {
+        Self {
+            tokens: rate_limit.limit as f64,
+            window: window_index(&rate_limit, epoch),
+            rate_limit,
+            updated: now,
+            used: 0,
+        }
+    }
+
+    fn refill(&mut self, now: Instant, epoch: Duration) {
+        let limit = self.rate_limit.limit as f64;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit / self.rate_limit.interval.as_secs_f64()).min(limit);
+        self.updated = now;
+
+        let window = window_index(&self.rate_limit, epoch);
+        if window != self.window {
+            self.window = window;
+            self.used = 0;
+        }
+    }
+
+    /// Time to wait until `weight` can be spent. A weight above the limit waits for a full bucket.
+    fn wait(&self, weight: u32, epoch: Duration) -> Duration {
+        let weight = weight.min(self.rate_limit.limit);
+        let interval = self.rate_limit.interval;
+        let mut wait = Duration::ZERO;
+        if self.tokens < weight as f64 {
+            let missing = weight as f64 - self.tokens;
+            wait = Duration::from_secs_f64(missing * interval.as_secs_f64() / self.rate_limit.limit as f64);
+        }
+        if self.used + weight > self.rate_limit.limit {
+            let next_window = Duration::from_millis(((self.window + 1) * interval.as_millis()) as u64);
+            wait = wait.max(next_window.saturating_sub(epoch));
+        }
+        wait
+    }
+
+    fn spend(&mut self, weight: u32) {
+        self.tokens -= weight.min(self.rate_limit.limit) as f64;
+        self.used += weight;
+    }
+
+    fn observe(&mut self, used: u32) {
+        self.used = self.used.max(used);
+        self.tokens = self.tokens.min(self.rate_limit.limit.saturating_sub(self.used) as f64);
+    }
+}
+
+fn window_index(rate_limit: &RateLimit, epoch: Duration) -> u128 {
+    epoch.as_millis() / rate_limit.interval.as_millis().max(1)
+}
+
+
+struct State {
+    buckets: Vec<Bucket>,
+
+    /// requests are held back until then after the exchange rejected one for exceeding its limits
+    blocked_until: Option<Instant>,
+
+    /// wall clock time of an instant, used to find the window other instants fall into
+    anchor: (Instant, Duration),
+}
+
+impl State {
+    fn epoch(&self, now: Instant) -> Duration {
+        self.anchor.1 + now.saturating_duration_since(self.anchor.0)
+    }
+}
+
+
+/// Client side rate limiter shared by the clones of an `HttpClient`.
+#[derive(Clone)]
+pub(crate) struct RateLimiter {
+    state: Arc<Mutex<State>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate_limits: Vec<RateLimit>) -> Self {
+        let now = Instant::now();
+        let epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let limiter = Self {
+            state: Arc::new(Mutex::new(State {
+                buckets: vec![],
+                blocked_until: None,
+                anchor: (now, epoch),
+            })),
+        };
+        limiter.set_rate_limits(rate_limits);
+        limiter
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // the state is valid after any panic, so a poisoned lock can be used as is
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replaces the limits, e.g. with the ones announced by the exchange. The weight already spent
+    /// is carried over to the new limit with the same interval.
+    pub(crate) fn set_rate_limits(&self, rate_limits: Vec<RateLimit>) {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let epoch = state.epoch(now);
+        let mut buckets = vec![];
+        for rate_limit in rate_limits {
+            let mut bucket = Bucket::new(rate_limit, now, epoch);
+            if let Some(old) = state.buckets.iter_mut().find(|b| b.rate_limit.interval == bucket.rate_limit.interval) {
+                old.refill(now, epoch);
+                bucket.observe(old.used);
+                bucket.tokens = bucket.tokens.min(old.tokens);
+            }
+            buckets.push(bucket);
+        }
+        state.buckets = buckets;
+    }
+
+    /// Waits until `weight` can be spent without exceeding any limit and spends it.
+    pub(crate) async fn acquire(&self, weight: u32) {
+        loop {
+            let wait = {
+                let mut state = self.lock();
+                let now = Instant::now();
+                match state.blocked_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        state.blocked_until = None;
+                        let epoch = state.epoch(now);
+                        let mut wait = Duration::ZERO;
+                        for bucket in state.buckets.iter_mut() {
+                            bucket.refill(now, epoch);
+                            wait = wait.max(bucket.wait(weight, epoch));
+                        }
+                        if wait.is_zero() {
+                            state.buckets.iter_mut().for_each(|bucket| bucket.spend(weight));
+                            return;
+                        }
+                        wait
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Syncs the spent weight with the one reported in the response headers.
+    pub(crate) fn update(&self, headers: &HeaderMap) {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let epoch = state.epoch(now);
+        for bucket in state.buckets.iter_mut() {
+            let used = bucket.rate_limit.header.as_ref()
+                .and_then(|header| headers.get(header.as_str()))
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u32>().ok());
+            if let Some(used) = used {
+                bucket.refill(now, epoch);
+                bucket.observe(used);
+            }
+        }
+    }
+
+    /// Holds back every request for `duration`.
+    pub(crate) fn block(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut state = self.lock();
+        state.blocked_until = Some(state.blocked_until.map_or(until, |blocked| blocked.max(until)));
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use tokio::time::Instant;
+
+    use super::{RateLimit, RateLimiter};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(vec![RateLimit {
+            interval: Duration::from_secs(60),
+            limit: 10,
+            header: Some("x-used-weight".to_string()),
+        }]);
+
+        let start = Instant::now();
+        limiter.acquire(5).await;
+        limiter.acquire(5).await;
+        assert_eq!(Instant::now(), start);
+
+        // the bucket is empty, so the next request waits for the window to end at the latest
+        limiter.acquire(1).await;
+        assert!(Instant::now() > start);
+        assert!(Instant::now() - start <= Duration::from_secs(60));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-used-weight", HeaderValue::from_static("10"));
+        limiter.update(&headers);
+        let blocked = Instant::now();
+        limiter.acquire(1).await;
+        assert!(Instant::now() > blocked);
+
+        limiter.block(Duration::from_secs(120));
+        let blocked = Instant::now();
+        limiter.acquire(1).await;
+        assert!(Instant::now() - blocked >= Duration::from_secs(120));
+    }
+}